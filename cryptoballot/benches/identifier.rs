@@ -0,0 +1,34 @@
+//! Compares the time taken to compare equal and unequal identifiers.
+//!
+//! Identifier equality is constant-time, so all three cases should take the same time. Run with `cargo bench`.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use cryptoballot::*;
+
+fn identifier_eq(c: &mut Criterion) {
+    let election_id = ElectionTransaction::build_id([7; 15]);
+    let id = Identifier::new(election_id, TransactionType::Vote, Some([9; 16]));
+
+    let equal = id;
+
+    // Differs in the first byte of the election-id
+    let mut differs_first = id;
+    differs_first.election_id[0] ^= 1;
+
+    // Differs in the last byte of the unique-info
+    let mut differs_last = id;
+    differs_last.unique_info[15] ^= 1;
+
+    let mut group = c.benchmark_group("identifier_eq");
+    group.bench_function("equal", |b| b.iter(|| black_box(&id) == black_box(&equal)));
+    group.bench_function("differs_first_byte", |b| {
+        b.iter(|| black_box(&id) == black_box(&differs_first))
+    });
+    group.bench_function("differs_last_byte", |b| {
+        b.iter(|| black_box(&id) == black_box(&differs_last))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, identifier_eq);
+criterion_main!(benches);
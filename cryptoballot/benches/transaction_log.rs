@@ -0,0 +1,60 @@
+//! Compares sequential write throughput of a `TransactionLog` against a `MemStore`.
+//!
+//! Every `TransactionLog` write is synced to disk, so expect it to be bound by the disk rather than by encoding.
+//! Run with `cargo bench`.
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use cryptoballot::*;
+
+const NUM_TRANSACTIONS: usize = 100;
+
+fn transactions() -> Vec<SignedTransaction> {
+    let (authority_secret, authority_public) = generate_keypair();
+    (0..NUM_TRANSACTIONS)
+        .map(|_| {
+            let election = ElectionTransaction::new(authority_public);
+            Signed::sign(&authority_secret, election).unwrap().into()
+        })
+        .collect()
+}
+
+fn sequential_writes(c: &mut Criterion) {
+    let txs = transactions();
+    let path = std::env::temp_dir().join("cryptoballot_bench_transaction_log");
+
+    let mut group = c.benchmark_group("sequential_writes");
+    group.throughput(Throughput::Elements(NUM_TRANSACTIONS as u64));
+
+    group.bench_function("mem_store", |b| {
+        b.iter_batched(
+            || (MemStore::default(), txs.clone()),
+            |(mut store, txs)| {
+                for tx in txs {
+                    store.set(tx);
+                }
+            },
+            BatchSize::SmallInput,
+        )
+    });
+
+    group.bench_function("transaction_log", |b| {
+        b.iter_batched(
+            || {
+                let _ = std::fs::remove_file(&path);
+                (TransactionLog::open(&path).unwrap(), txs.clone())
+            },
+            |(mut log, txs)| {
+                for tx in txs {
+                    log.set(tx).unwrap();
+                }
+            },
+            BatchSize::PerIteration,
+        )
+    });
+
+    group.finish();
+    let _ = std::fs::remove_file(&path);
+}
+
+criterion_group!(benches, sequential_writes);
+criterion_main!(benches);
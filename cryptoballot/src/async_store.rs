@@ -0,0 +1,108 @@
+use crate::*;
+use std::future::Future;
+use std::pin::Pin;
+
+/// A future returned by an `AsyncStore`
+pub type StoreFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// A transaction store that is read asynchronously, such as one backed by a remote bulletin-board API.
+///
+/// This mirrors the methods of `Store` that a store implements itself. Wrap an `AsyncStore` in a `BlockingStore` to
+/// use the rest of the `Store` API, including validation.
+pub trait AsyncStore {
+    /// Get a transaction of an unknown type
+    fn get_transaction(&self, id: Identifier) -> StoreFuture<'_, Option<SignedTransaction>>;
+
+    /// Check whether a transaction is in the store.
+    ///
+    /// The default implementation fetches the whole transaction, see `Store::contains`.
+    fn contains(&self, id: Identifier) -> StoreFuture<'_, bool> {
+        let tx = self.get_transaction(id);
+        Box::pin(async move { tx.await.is_some() })
+    }
+
+    /// Get all transactions with identifiers in `[start, end_inclusive]`, in ascending identifier order
+    fn range(
+        &self,
+        start: Identifier,
+        end_inclusive: Identifier,
+    ) -> StoreFuture<'_, Vec<SignedTransaction>>;
+
+    /// Get the identifiers of every election in the store, in ascending order
+    fn list_elections(&self) -> StoreFuture<'_, Vec<Identifier>> {
+        let txs = self.range(Identifier::first(), Identifier::last());
+        Box::pin(async move {
+            txs.await
+                .iter()
+                .map(|tx| tx.id())
+                .filter(|id| id.transaction_type == TransactionType::Election)
+                .collect()
+        })
+    }
+
+    /// Validate a transaction and store it as a single atomic operation, see `Store::apply_transaction`
+    fn apply_transaction(&self, _tx: SignedTransaction) -> StoreFuture<'_, Result<(), ApplyError>> {
+        Box::pin(async { Err(StoreError::ReadOnly.into()) })
+    }
+}
+
+/// Runs a future to completion, blocking the current thread
+pub trait BlockOn {
+    fn block_on<F: Future>(&self, future: F) -> F::Output;
+}
+
+#[cfg(feature = "tokio")]
+impl BlockOn for tokio::runtime::Handle {
+    fn block_on<F: Future>(&self, future: F) -> F::Output {
+        tokio::runtime::Handle::block_on(self, future)
+    }
+}
+
+/// A `Store` that reads from an `AsyncStore`, blocking on each read.
+///
+/// This lets the synchronous validation code run against an async store. Every read blocks the calling thread, so it
+/// must not be used from a task running on the same runtime: with tokio, use it from `spawn_blocking` instead.
+pub struct BlockingStore<A: AsyncStore, B: BlockOn> {
+    store: A,
+    runtime: B,
+}
+
+impl<A: AsyncStore, B: BlockOn> BlockingStore<A, B> {
+    /// Wrap an async store, running its futures with `runtime` (such as a `tokio::runtime::Handle`)
+    pub fn new(store: A, runtime: B) -> Self {
+        BlockingStore { store, runtime }
+    }
+
+    /// Get the wrapped store
+    pub fn inner(&self) -> &A {
+        &self.store
+    }
+
+    /// Unwrap the store
+    pub fn into_inner(self) -> A {
+        self.store
+    }
+}
+
+impl<A: AsyncStore, B: BlockOn> Store for BlockingStore<A, B> {
+    fn get_transaction(&self, id: Identifier) -> Option<SignedTransaction> {
+        self.runtime.block_on(self.store.get_transaction(id))
+    }
+
+    fn contains(&self, id: Identifier) -> bool {
+        self.runtime.block_on(self.store.contains(id))
+    }
+
+    fn range(&self, start: Identifier, end_inclusive: Identifier) -> Vec<SignedTransaction> {
+        self.runtime
+            .block_on(self.store.range(start, end_inclusive))
+    }
+
+    fn list_elections(&self) -> Vec<Identifier> {
+        self.runtime.block_on(self.store.list_elections())
+    }
+
+    fn apply_transaction(&self, tx: SignedTransaction) -> Result<(), ApplyError> {
+        self.runtime.block_on(self.store.apply_transaction(tx))
+    }
+}
@@ -0,0 +1,1315 @@
+use crate::*;
+use ed25519_dalek::{SecretKey, Signature};
+use indexmap::IndexMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+/// The checks performed by `audit_election`, in the order they are run
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditCheckKind {
+    /// Every transaction is well formed and belongs to the same election, with no duplicates
+    Integrity,
+
+    /// Every transaction validates, in dependency order
+    Validation,
+
+    /// The transactions present are consistent with the phase the election is in
+    Phase,
+
+    /// Every vote has been fully decrypted
+    DecryptionCompleteness,
+
+    /// Re-tallying the decrypted votes reproduces the certified result
+    Tally,
+}
+
+impl AuditCheckKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AuditCheckKind::Integrity => "integrity",
+            AuditCheckKind::Validation => "validation",
+            AuditCheckKind::Phase => "phase",
+            AuditCheckKind::DecryptionCompleteness => "decryption_completeness",
+            AuditCheckKind::Tally => "tally",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditStatus {
+    Pass,
+    Fail,
+
+    /// The check could not be run, for example because voting has not yet ended
+    Skipped,
+}
+
+/// The result of a single audit check
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AuditCheck {
+    pub check: AuditCheckKind,
+    pub status: AuditStatus,
+
+    /// Human-readable details, one per problem found (or a note explaining why the check was skipped)
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub details: Vec<String>,
+}
+
+impl AuditCheck {
+    fn new(check: AuditCheckKind, details: Vec<String>) -> Self {
+        let status = if details.is_empty() {
+            AuditStatus::Pass
+        } else {
+            AuditStatus::Fail
+        };
+        AuditCheck {
+            check,
+            status,
+            details,
+        }
+    }
+
+    fn skipped(check: AuditCheckKind, reason: &str) -> Self {
+        AuditCheck {
+            check,
+            status: AuditStatus::Skipped,
+            details: vec![reason.to_string()],
+        }
+    }
+}
+
+/// The phase an election is in, as determined from the transactions posted so far
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ElectionPhase {
+    /// Trustees are generating the encryption key
+    KeyGeneration,
+
+    /// The encryption key is posted and votes are being cast
+    Voting,
+
+    /// Voting has ended and votes are being mixed and decrypted
+    Decryption,
+
+    /// Every vote has been decrypted
+    Complete,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditSeverity {
+    /// Worth investigating, but does not by itself make the result wrong
+    Warning,
+
+    /// The election cannot be correctly completed or tallied as it stands
+    Error,
+}
+
+/// A single problem found by `audit_findings`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AuditFinding {
+    pub severity: AuditSeverity,
+
+    /// The transaction the finding is about, or the election transaction for election-wide findings
+    pub id: Identifier,
+    pub description: String,
+}
+
+impl AuditFinding {
+    fn new(severity: AuditSeverity, id: Identifier, description: String) -> Self {
+        AuditFinding {
+            severity,
+            id,
+            description,
+        }
+    }
+}
+
+/// A full audit of an election, as produced by `audit_election`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AuditReport {
+    /// The audited election, or None if no election transaction was found
+    pub election_id: Option<Identifier>,
+    pub phase: Option<ElectionPhase>,
+    pub num_transactions: usize,
+    pub checks: Vec<AuditCheck>,
+
+    /// Cross-transaction findings, as found by `audit_findings`
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub findings: Vec<AuditFinding>,
+}
+
+impl AuditReport {
+    /// Returns true if no check failed and there are no error findings. Skipped checks do not count as failures.
+    pub fn passed(&self) -> bool {
+        self.checks
+            .iter()
+            .all(|check| check.status != AuditStatus::Fail)
+            && self
+                .findings
+                .iter()
+                .all(|finding| finding.severity != AuditSeverity::Error)
+    }
+
+    /// Get the result of a single check
+    pub fn check(&self, kind: AuditCheckKind) -> Option<&AuditCheck> {
+        self.checks.iter().find(|check| check.check == kind)
+    }
+
+    /// Render a human-readable summary of the report, with pass / fail for each check
+    pub fn render_summary(&self) -> String {
+        let mut summary = String::new();
+
+        summary.push_str("ELECTION AUDIT REPORT\n");
+        summary.push_str("=====================\n");
+        match self.election_id {
+            Some(election_id) => summary.push_str(&format!("Election:     {}\n", election_id)),
+            None => summary.push_str("Election:     unknown\n"),
+        }
+        match self.phase {
+            Some(phase) => summary.push_str(&format!("Phase:        {:?}\n", phase)),
+            None => summary.push_str("Phase:        unknown\n"),
+        }
+        summary.push_str(&format!("Transactions: {}\n\n", self.num_transactions));
+
+        for check in self.checks.iter() {
+            let status = match check.status {
+                AuditStatus::Pass => "PASS",
+                AuditStatus::Fail => "FAIL",
+                AuditStatus::Skipped => "SKIP",
+            };
+            summary.push_str(&format!("[{}] {}\n", status, check.check.name()));
+            for detail in check.details.iter() {
+                summary.push_str(&format!("       {}\n", detail));
+            }
+        }
+
+        if !self.findings.is_empty() {
+            summary.push_str("\nFindings:\n");
+            for finding in self.findings.iter() {
+                let severity = match finding.severity {
+                    AuditSeverity::Warning => "WARN",
+                    AuditSeverity::Error => "ERROR",
+                };
+                summary.push_str(&format!(
+                    "[{}] {}: {}\n",
+                    severity, finding.id, finding.description
+                ));
+            }
+        }
+
+        let result = if self.passed() { "PASS" } else { "FAIL" };
+        summary.push_str(&format!("\nResult: {}\n", result));
+
+        summary
+    }
+}
+
+/// Audit an election from its full transaction log.
+///
+/// Runs every `AuditCheckKind` check over the transactions. If a certified result is given, the decrypted votes are
+/// re-tallied and compared against it, otherwise the tally check is skipped.
+pub fn audit_election(
+    transactions: &[SignedTransaction],
+    certified: Option<&TallyTransaction>,
+) -> AuditReport {
+    let mut report = AuditReport {
+        election_id: None,
+        phase: None,
+        num_transactions: transactions.len(),
+        checks: Vec::with_capacity(5),
+        findings: vec![],
+    };
+
+    let elections: Vec<&SignedTransaction> = transactions
+        .iter()
+        .filter(|tx| tx.transaction_type() == TransactionType::Election)
+        .collect();
+    let election: ElectionTransaction = match elections.as_slice() {
+        [election] => (*election).clone().into(),
+        _ => {
+            let detail = format!("expected 1 election transaction, found {}", elections.len());
+            report
+                .checks
+                .push(AuditCheck::new(AuditCheckKind::Integrity, vec![detail]));
+            for kind in &[
+                AuditCheckKind::Validation,
+                AuditCheckKind::Phase,
+                AuditCheckKind::DecryptionCompleteness,
+                AuditCheckKind::Tally,
+            ] {
+                report
+                    .checks
+                    .push(AuditCheck::skipped(*kind, "no election to audit"));
+            }
+            return report;
+        }
+    };
+    report.election_id = Some(election.id);
+
+    report.checks.push(AuditCheck::new(
+        AuditCheckKind::Integrity,
+        check_integrity(&election, transactions),
+    ));
+
+    let store = MemStore::from(transactions.to_vec());
+    let validation_errors = store
+        .validate_all()
+        .into_iter()
+        .map(|(id, e)| format!("{} {}: {}", id.transaction_type.name(), id, e))
+        .collect();
+    report.checks.push(AuditCheck::new(
+        AuditCheckKind::Validation,
+        validation_errors,
+    ));
+
+    let has = |tx_type: TransactionType| !store.get_multiple(election.id, tx_type).is_empty();
+    let has_encryption_key = has(TransactionType::EncryptionKey);
+    let has_voting_end = has(TransactionType::VotingEnd);
+
+    let mut phase_errors = Vec::new();
+    if !has_encryption_key && has(TransactionType::Vote) {
+        phase_errors.push("votes were cast before the encryption key was posted".to_string());
+    }
+    if !has_voting_end {
+        for tx_type in &[
+            TransactionType::Mix,
+            TransactionType::PartialDecryption,
+            TransactionType::PartialDecryptionBatch,
+            TransactionType::Decryption,
+        ] {
+            if has(*tx_type) {
+                phase_errors.push(format!(
+                    "{} transactions were posted before voting ended",
+                    tx_type.name()
+                ));
+            }
+        }
+    }
+    report
+        .checks
+        .push(AuditCheck::new(AuditCheckKind::Phase, phase_errors));
+
+    let undecrypted = if has_voting_end {
+        let undecrypted: Vec<String> = check_decryption_completeness(&election, &store)
+            .into_iter()
+            .map(|(_, detail)| detail)
+            .collect();
+        report.checks.push(AuditCheck::new(
+            AuditCheckKind::DecryptionCompleteness,
+            undecrypted.clone(),
+        ));
+        undecrypted
+    } else {
+        report.checks.push(AuditCheck::skipped(
+            AuditCheckKind::DecryptionCompleteness,
+            "voting has not ended",
+        ));
+        vec![]
+    };
+
+    report.phase = Some(if !has_encryption_key {
+        ElectionPhase::KeyGeneration
+    } else if !has_voting_end {
+        ElectionPhase::Voting
+    } else if !undecrypted.is_empty() {
+        ElectionPhase::Decryption
+    } else {
+        ElectionPhase::Complete
+    });
+
+    let tally_check = match certified {
+        None => AuditCheck::skipped(AuditCheckKind::Tally, "no certified result provided"),
+        Some(_) if report.phase != Some(ElectionPhase::Complete) => AuditCheck::new(
+            AuditCheckKind::Tally,
+            vec!["a result was certified before every vote was decrypted".to_string()],
+        ),
+        Some(certified) => AuditCheck::new(
+            AuditCheckKind::Tally,
+            check_tally(&election, &store, certified),
+        ),
+    };
+    report.checks.push(tally_check);
+
+    report.findings = audit_findings(&store, election.id);
+
+    report
+}
+
+/// Check the invariants that hold across an election's transactions, rather than within any one of them.
+///
+/// Once voting has ended, this reports:
+///  - votes (or final mix outputs when using a mixnet) without a decryption
+///  - decryptions without partial decryptions from at least `trustees_threshold` trustees
+///  - gaps in each contest's mix chain, and mix chains that have not reached the final mix
+///  - trustees who have not posted any partial decryptions
+///
+/// Findings are sorted with errors first. Nothing is reported before voting has ended, since none of these are
+/// problems while voting is still underway.
+pub fn audit_findings<S: Store>(store: &S, election_id: Identifier) -> Vec<AuditFinding> {
+    let election = match store.get_election(election_id) {
+        Ok(election) => election.tx,
+        Err(e) => {
+            return vec![AuditFinding::new(
+                AuditSeverity::Error,
+                election_id,
+                e.to_string(),
+            )]
+        }
+    };
+    if store
+        .get_multiple(election.id, TransactionType::VotingEnd)
+        .is_empty()
+    {
+        return vec![];
+    }
+
+    let mut findings: Vec<AuditFinding> = check_decryption_completeness(&election, store)
+        .into_iter()
+        .map(|(id, detail)| AuditFinding::new(AuditSeverity::Error, id, detail))
+        .collect();
+
+    // Group the partial decryptions by the ciphertext they decrypt
+    let partials = store.get_all_partial_decryptions(election.id);
+    let mut trustees_by_upstream: HashMap<(Identifier, u32, u16), HashSet<u8>> = HashMap::new();
+    for partial in partials.iter() {
+        trustees_by_upstream
+            .entry((
+                partial.upstream_id,
+                partial.contest_index,
+                partial.upstream_index,
+            ))
+            .or_default()
+            .insert(partial.trustee_index);
+    }
+
+    for tx in store.get_multiple(election.id, TransactionType::Decryption) {
+        let decryption: DecryptionTransaction = tx.into();
+        let upstream = (
+            decryption.upstream_id,
+            decryption.contest_index,
+            decryption.upstream_index,
+        );
+        let num_partials = trustees_by_upstream.get(&upstream).map_or(0, HashSet::len);
+        if num_partials < election.trustees_threshold as usize {
+            findings.push(AuditFinding::new(
+                AuditSeverity::Error,
+                decryption.id,
+                format!(
+                    "decryption has partial decryptions from {} trustees, but {} are required",
+                    num_partials, election.trustees_threshold
+                ),
+            ));
+        }
+    }
+
+    // A zero threshold is reported by check_decryption_completeness, since then no mix is the final one
+    let final_mix_index = election.trustees_threshold.checked_sub(1);
+    if let (Some(_), Some(final_mix_index)) = (&election.mix_config, final_mix_index) {
+        let mut mixes_by_contest: BTreeMap<u32, Vec<MixTransaction>> = BTreeMap::new();
+        for tx in store.get_multiple(election.id, TransactionType::Mix) {
+            let mix: MixTransaction = tx.into();
+            mixes_by_contest
+                .entry(mix.contest_index)
+                .or_default()
+                .push(mix);
+        }
+
+        for (contest_index, mixes) in mixes_by_contest.iter() {
+            let indexes: BTreeSet<u8> = mixes.iter().map(|mix| mix.mix_index).collect();
+            for mix in mixes.iter() {
+                if mix.mix_index > 0 && !indexes.contains(&(mix.mix_index - 1)) {
+                    findings.push(AuditFinding::new(
+                        AuditSeverity::Error,
+                        mix.id,
+                        format!(
+                            "mix chain for contest {} has a gap before mix {}",
+                            contest_index, mix.mix_index
+                        ),
+                    ));
+                }
+            }
+            if !indexes.contains(&final_mix_index) {
+                findings.push(AuditFinding::new(
+                    AuditSeverity::Warning,
+                    election.id,
+                    format!(
+                        "mix chain for contest {} has not reached the final mix {}",
+                        contest_index, final_mix_index
+                    ),
+                ));
+            }
+        }
+    }
+
+    for trustee_index in non_participating_trustees(&election, &partials) {
+        findings.push(AuditFinding::new(
+            AuditSeverity::Warning,
+            election.id,
+            format!(
+                "trustee {} has not posted any partial decryptions",
+                trustee_index
+            ),
+        ));
+    }
+
+    findings.sort_by(|a, b| b.severity.cmp(&a.severity));
+    findings
+}
+
+// Trustees who have not posted any of `partials`
+fn non_participating_trustees(
+    election: &ElectionTransaction,
+    partials: &[PartialDecryptionTransaction],
+) -> Vec<u8> {
+    let participating: HashSet<u8> = partials
+        .iter()
+        .map(|partial| partial.trustee_index)
+        .collect();
+    election
+        .trustees
+        .iter()
+        .map(|trustee| trustee.index)
+        .filter(|index| !participating.contains(index))
+        .collect()
+}
+
+fn check_integrity(
+    election: &ElectionTransaction,
+    transactions: &[SignedTransaction],
+) -> Vec<String> {
+    let mut errors = Vec::new();
+    let mut seen = HashSet::with_capacity(transactions.len());
+
+    for tx in transactions {
+        let id = tx.id();
+        if !seen.insert(id) {
+            errors.push(format!("duplicate transaction {}", id));
+        }
+        if id.election_id != election.id.election_id {
+            errors.push(format!(
+                "transaction {} belongs to a different election",
+                id
+            ));
+        }
+        if id.transaction_type != tx.transaction_type() {
+            errors.push(format!(
+                "transaction {} has the id of a {} transaction but is a {} transaction",
+                id,
+                id.transaction_type.name(),
+                tx.type_name()
+            ));
+        }
+    }
+
+    errors
+}
+
+/// The cross-transaction consistency of an election, as reported by `verify_election_integrity`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct IntegrityReport {
+    pub election_id: Identifier,
+
+    /// The number of transactions of each type posted for the election
+    pub transaction_counts: IndexMap<TransactionType, usize>,
+
+    /// Declared trustees that have not posted a keygen_public_key transaction
+    pub missing_public_keys: Vec<u8>,
+
+    /// Whether enough trustees have posted public keys to meet the election's `trustees_threshold`
+    pub threshold_met: bool,
+
+    /// Votes, or final mixes when using a mixnet, that have not been fully decrypted. Only checked once voting has ended.
+    pub missing_decryptions: Vec<Identifier>,
+
+    /// Trustees that did not post any partial decryptions. Only checked once voting has ended.
+    pub non_participating_trustees: Vec<u8>,
+}
+
+impl IntegrityReport {
+    /// Returns true if every invariant holds.
+    ///
+    /// Trustees that did not take part in decryption do not count against this, as long as the votes were decrypted.
+    pub fn is_consistent(&self) -> bool {
+        self.missing_public_keys.is_empty()
+            && self.threshold_met
+            && self.missing_decryptions.is_empty()
+    }
+}
+
+/// Check an election's cross-transaction invariants: that every declared trustee has posted a public key, that the
+/// trustee threshold can be met, and (once voting has ended) that every vote has been decrypted.
+///
+/// The invariants are reported rather than returned as errors, since an election part way through is expected to
+/// fail some of them. Individual transactions are not validated, use `Store::validate_all` for that.
+pub fn verify_election_integrity<S: Store>(
+    store: &S,
+    election_id: Identifier,
+) -> Result<IntegrityReport, IntegrityError> {
+    let election = store.get_election(election_id)?.tx;
+
+    let start = Identifier::start(election.id, TransactionType::Election, None);
+    let end = Identifier::end(election.id, TransactionType::MAX, None);
+    let mut transaction_counts = IndexMap::new();
+    for tx in store.range(start, end) {
+        *transaction_counts.entry(tx.transaction_type()).or_insert(0) += 1;
+    }
+
+    let public_keys: HashSet<u8> = store
+        .get_current_keygen_public_keys(election.id)
+        .iter()
+        .map(|tx| tx.trustee_index)
+        .collect();
+    let missing_public_keys: Vec<u8> = election
+        .trustees
+        .iter()
+        .map(|trustee| trustee.index)
+        .filter(|index| !public_keys.contains(index))
+        .collect();
+    let threshold_met = public_keys.len() >= election.trustees_threshold as usize;
+
+    // The same checks as `audit_findings`, which are only made once voting has ended
+    let mut missing_decryptions = Vec::new();
+    let mut non_participating = Vec::new();
+    if transaction_counts.contains_key(&TransactionType::VotingEnd) {
+        for (id, _) in check_decryption_completeness(&election, store) {
+            if !missing_decryptions.contains(&id) {
+                missing_decryptions.push(id);
+            }
+        }
+
+        let partials = store.get_all_partial_decryptions(election.id);
+        non_participating = non_participating_trustees(&election, &partials);
+    }
+
+    Ok(IntegrityReport {
+        election_id: election.id,
+        transaction_counts,
+        missing_public_keys,
+        threshold_met,
+        missing_decryptions,
+        non_participating_trustees: non_participating,
+    })
+}
+
+/// A single trustee's partial decryptions, as reported by `verify_trustee_participation`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TrusteeParticipationReport {
+    pub election_id: Identifier,
+    pub trustee_index: u8,
+
+    /// Ciphertexts the trustee has partially decrypted, with a valid proof
+    pub accepted: Vec<DecryptionTarget>,
+
+    /// Ciphertexts the trustee has not partially decrypted
+    pub missing: Vec<DecryptionTarget>,
+
+    /// Ciphertexts whose partial decryption by the trustee does not verify, with the reason
+    pub failed: Vec<(DecryptionTarget, String)>,
+}
+
+impl TrusteeParticipationReport {
+    /// Returns true if the trustee has a valid partial decryption of every ciphertext
+    pub fn is_complete(&self) -> bool {
+        self.missing.is_empty() && self.failed.is_empty()
+    }
+}
+
+/// Check a trustee's partial decryptions of every ciphertext in the election that must be decrypted (see
+/// `decryption_targets`), verifying the proof of each one found, whether posted on its own or in a batch.
+pub fn verify_trustee_participation<S: Store>(
+    store: &S,
+    election_id: Identifier,
+    trustee_index: u8,
+) -> Result<TrusteeParticipationReport, TransactionNotFound> {
+    let election = store.get_election(election_id)?.tx;
+
+    let mut report = TrusteeParticipationReport {
+        election_id,
+        trustee_index,
+        accepted: Vec::new(),
+        missing: Vec::new(),
+        failed: Vec::new(),
+    };
+    for target in decryption_targets(store, &election) {
+        let partial_id = target.partial_decryption_id(election_id, trustee_index);
+        match store.find_partial_decryption(partial_id) {
+            None => report.missing.push(target),
+            Some(partial) => match partial.verify_proof(store, &election) {
+                Ok(()) => report.accepted.push(target),
+                Err(e) => report.failed.push((target, e.to_string())),
+            },
+        }
+    }
+
+    Ok(report)
+}
+
+// Find every vote (or final mix output when using a mixnet) for each contest that has no decryption
+fn check_decryption_completeness<S: Store>(
+    election: &ElectionTransaction,
+    store: &S,
+) -> Vec<(Identifier, String)> {
+    let mut errors = Vec::new();
+
+    // Every vote must have made it through the mixnet before it can be decrypted. Challenged votes are never mixed.
+    if election.mix_config.is_some() {
+        let final_mix_index = match election.trustees_threshold.checked_sub(1) {
+            Some(final_mix_index) => final_mix_index,
+            None => {
+                let detail = "election has a trustees threshold of 0, so no mix is final";
+                errors.push((election.id, detail.to_string()));
+                return errors;
+            }
+        };
+        let mixed: HashSet<(u32, Identifier)> = store
+            .get_multiple(election.id, TransactionType::Mix)
+            .into_iter()
+            .map(MixTransaction::from)
+            .filter(|mix| mix.mix_index == final_mix_index)
+            .flat_map(|mix| {
+                let contest_index = mix.contest_index;
+                mix.vote_ids.into_iter().map(move |id| (contest_index, id))
+            })
+            .collect();
+        for vote in store.get_cast_votes(election.id) {
+            for encrypted_vote in vote.encrypted_votes.iter() {
+                if !mixed.contains(&(encrypted_vote.contest_index, vote.id)) {
+                    let detail = format!(
+                        "vote {} has not been mixed for contest {}",
+                        vote.id, encrypted_vote.contest_index
+                    );
+                    errors.push((vote.id, detail));
+                }
+            }
+        }
+    }
+
+    for target in decryption_targets(store, election) {
+        if !store.contains(target.decryption_id(election.id)) {
+            errors.push((
+                target.upstream_id,
+                format!("{} has not been decrypted", target),
+            ));
+        }
+    }
+
+    errors
+}
+
+// Re-tally every contest and compare it against the certified result
+fn check_tally<S: Store>(
+    election: &ElectionTransaction,
+    store: &S,
+    certified: &TallyTransaction,
+) -> Vec<String> {
+    let mut errors = Vec::new();
+
+    if certified.election_id != election.id {
+        errors.push(format!(
+            "certified result is for election {}",
+            certified.election_id
+        ));
+        return errors;
+    }
+
+    let decryptions: Vec<DecryptionTransaction> = store
+        .get_multiple(election.id, TransactionType::Decryption)
+        .into_iter()
+        .map(|tx| tx.into())
+        .collect();
+
+    for contest in election.contests.iter() {
+        let certified_result = match certified.tally.get(&contest.id) {
+            Some(result) => result,
+            None => {
+                errors.push(format!("contest {} has no certified result", contest.id));
+                continue;
+            }
+        };
+
+        let mut tally = match contest.contest_type.new_tally(contest.num_winners) {
+            Ok(tally) => tally,
+            Err(e) => {
+                errors.push(format!("contest {}: {}", contest.id, e));
+                continue;
+            }
+        };
+        let result = match tally_decryptions(contest, tally.as_mut(), &decryptions) {
+            Ok(result) => result,
+            Err(e) => {
+                errors.push(format!("contest {}: {}", contest.id, e));
+                continue;
+            }
+        };
+
+        if result.num_votes != certified_result.num_votes {
+            errors.push(format!(
+                "contest {}: {} votes were tallied but {} were certified",
+                contest.id, result.num_votes, certified_result.num_votes
+            ));
+        }
+        if result.totals != certified_result.totals {
+            errors.push(format!(
+                "contest {}: recomputed totals do not match the certified totals",
+                contest.id
+            ));
+        }
+
+        let mut winners = result.winners.into_unranked();
+        let mut certified_winners = certified_result.winners.clone().into_unranked();
+        winners.sort();
+        certified_winners.sort();
+        if winners != certified_winners {
+            errors.push(format!(
+                "contest {}: recomputed winners {} do not match the certified winners {}",
+                contest.id,
+                winners.join(", "),
+                certified_winners.join(", ")
+            ));
+        }
+    }
+
+    for contest_id in certified.tally.keys() {
+        if !election
+            .contests
+            .iter()
+            .any(|contest| &contest.id == contest_id)
+        {
+            errors.push(format!(
+                "certified result contains unknown contest {}",
+                contest_id
+            ));
+        }
+    }
+
+    errors
+}
+
+/// Everything a voter needs to check that their own vote was correctly decrypted, signed by the election authority.
+///
+/// The package holds the election, the vote, the trustees' public keys and re-shares, and every partial decryption and
+/// decryption of the vote. `verify` re-does the decryption from these alone, so a voter can check it without
+/// access to the full election. Votes in elections using a mixnet cannot be traced to their decryption, so they
+/// have no audit package.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct AuditPackage {
+    pub election: Signed<ElectionTransaction>,
+    pub vote: Signed<VoteTransaction>,
+    pub public_keys: Vec<Signed<KeyGenPublicKeyTransaction>>,
+
+    /// The re-shares of revoked trustees' key shares, which the partial decryptions are verified against along with
+    /// `public_keys`
+    #[serde(default)]
+    pub reshares: Vec<Signed<TrusteeReshareTransaction>>,
+
+    pub partial_decryptions: Vec<Signed<PartialDecryptionTransaction>>,
+    pub decryptions: Vec<Signed<DecryptionTransaction>>,
+
+    /// The election authority's signature over the JSON serialization of all the above transactions
+    #[serde(with = "EdSignatureHex")]
+    pub signature: Signature,
+}
+
+/// The decrypted contents of a vote, as confirmed by `AuditPackage::verify`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DecryptionSummary {
+    pub election_id: Identifier,
+    pub vote_id: Identifier,
+
+    /// The decrypted vote for each contest, keyed by contest index
+    pub contests: IndexMap<u32, Vec<Selection>>,
+}
+
+impl AuditPackage {
+    /// Collect the audit package for a vote and sign it with the election authority's secret key
+    pub fn for_vote<S: Store>(
+        store: &S,
+        election_id: Identifier,
+        vote_id: Identifier,
+        authority_secret: &SecretKey,
+    ) -> Result<AuditPackage, AuditError> {
+        let election = store.get_election(election_id)?;
+        if election.mix_config.is_some() {
+            return Err(AuditError::MixnetElection);
+        }
+        if ed25519_dalek::PublicKey::from(authority_secret) != election.authority_public {
+            return Err(AuditError::InvalidSignature);
+        }
+
+        let vote = store.get_vote(vote_id)?;
+        if vote.election != election_id {
+            return Err(AuditError::Mismatch(vote_id));
+        }
+
+        let public_keys = store.get_current_keygen_public_keys(election_id);
+        let reshares = store.get_trustee_reshares(election_id);
+
+        let mut partial_decryptions = Vec::new();
+        let mut decryptions = Vec::with_capacity(vote.encrypted_votes.len());
+        for encrypted_vote in vote.encrypted_votes.iter() {
+            let contest_index = encrypted_vote.contest_index;
+            let decryption_id =
+                DecryptionTransaction::build_id(election_id, vote_id, contest_index, 0);
+            let decryption = store
+                .get_decryption(decryption_id)
+                .map_err(|_| AuditError::NotDecrypted(vote_id, contest_index))?;
+
+            for trustee_index in decryption.trustees.iter() {
+                let partial_id = PartialDecryptionTransaction::build_id(
+                    election_id,
+                    vote_id,
+                    contest_index,
+                    0,
+                    *trustee_index,
+                );
+                partial_decryptions.push(store.get_partial_decryption(partial_id)?);
+            }
+
+            decryptions.push(decryption);
+        }
+
+        let signature = sign_detached(
+            authority_secret,
+            &signed_bytes(
+                &election,
+                &vote,
+                &public_keys,
+                &reshares,
+                &partial_decryptions,
+                &decryptions,
+            ),
+        );
+
+        Ok(AuditPackage {
+            election,
+            vote,
+            public_keys,
+            reshares,
+            partial_decryptions,
+            decryptions,
+            signature,
+        })
+    }
+
+    /// Verify the package, returning the decrypted vote.
+    ///
+    /// Checks the authority's signature over the package and the signature on every transaction in it, that every
+    /// transaction belongs to the election and the vote, and that combining the partial decryptions decrypts the
+    /// vote to exactly what each decryption transaction records.
+    pub fn verify(&self) -> Result<DecryptionSummary, AuditError> {
+        let election = self.election.inner();
+        let vote = self.vote.inner();
+
+        let message = signed_bytes(
+            &self.election,
+            &self.vote,
+            &self.public_keys,
+            &self.reshares,
+            &self.partial_decryptions,
+            &self.decryptions,
+        );
+        verify_detached(&election.authority_public, &message, &self.signature)
+            .map_err(|_| AuditError::InvalidSignature)?;
+
+        self.election.verify_signature()?;
+        self.vote.verify_signature()?;
+        if vote.election != election.id {
+            return Err(AuditError::Mismatch(vote.id));
+        }
+
+        let is_trustee = |trustee_index: u8, public_key: &ed25519_dalek::PublicKey| {
+            election
+                .get_trustee(trustee_index)
+                .map(|trustee| &trustee.public_key == public_key)
+                .unwrap_or(false)
+        };
+
+        for public_key in self.public_keys.iter() {
+            public_key.verify_signature()?;
+            if public_key.election != election.id
+                || !is_trustee(public_key.trustee_index, &public_key.trustee_public_key)
+            {
+                return Err(AuditError::Mismatch(public_key.id));
+            }
+        }
+        for reshare in self.reshares.iter() {
+            reshare.verify_signature()?;
+            if reshare.election != election.id
+                || !is_trustee(reshare.trustee_index, &reshare.trustee_public_key)
+            {
+                return Err(AuditError::Mismatch(reshare.id));
+            }
+        }
+        for partial in self.partial_decryptions.iter() {
+            partial.verify_signature()?;
+            if partial.election_id != election.id
+                || partial.upstream_id != vote.id
+                || !is_trustee(partial.trustee_index, &partial.trustee_public_key)
+            {
+                return Err(AuditError::Mismatch(partial.id));
+            }
+        }
+
+        let public_keys: Vec<KeyGenPublicKeyTransaction> =
+            self.public_keys.iter().map(|tx| tx.tx.clone()).collect();
+        let reshares: Vec<TrusteeReshareTransaction> =
+            self.reshares.iter().map(|tx| tx.tx.clone()).collect();
+        let public_keys = refresh_public_keys(public_keys, &reshares)?;
+        let trustees = election.get_full_trustees();
+
+        let mut contests = IndexMap::with_capacity(vote.encrypted_votes.len());
+        for encrypted_vote in vote.encrypted_votes.iter() {
+            let contest_index = encrypted_vote.contest_index;
+
+            let decryption = self
+                .decryptions
+                .iter()
+                .find(|decryption| decryption.contest_index == contest_index)
+                .ok_or(AuditError::NotDecrypted(vote.id, contest_index))?;
+            if decryption.election_id != election.id
+                || decryption.upstream_id != vote.id
+                || decryption.upstream_index != 0
+            {
+                return Err(AuditError::Mismatch(decryption.id));
+            }
+
+            let partials: Vec<PartialDecryptionTransaction> = self
+                .partial_decryptions
+                .iter()
+                .filter(|partial| {
+                    partial.contest_index == contest_index
+                        && decryption.trustees.contains(&partial.trustee_index)
+                })
+                .map(|partial| partial.tx.clone())
+                .collect();
+            if partials.len() < election.trustees_threshold as usize {
+                return Err(ValidationError::NotEnoughShares(
+                    election.trustees_threshold as usize,
+                    partials.len(),
+                )
+                .into());
+            }
+
+            let decrypted_vote = decrypt_vote(
+                &encrypted_vote.selections,
+                election.trustees_threshold,
+                &trustees,
+                &public_keys,
+                &partials,
+            );
+
+            // A spoiled ballot is summarized as an empty vote
+            let decrypted_vote = match decrypted_vote {
+                Err(ValidationError::VoteDecodingError(_)) if decryption.spoiled => vec![],
+                Ok(_) if decryption.spoiled => {
+                    return Err(ValidationError::VoteDecryptionMismatch.into());
+                }
+                decrypted_vote => decrypted_vote?,
+            };
+            if !decrypted_vote_eq(&decrypted_vote, &decryption.decrypted_vote) {
+                return Err(ValidationError::VoteDecryptionMismatch.into());
+            }
+
+            contests.insert(contest_index, decrypted_vote);
+        }
+
+        Ok(DecryptionSummary {
+            election_id: election.id,
+            vote_id: vote.id,
+            contests,
+        })
+    }
+}
+
+// The bytes covered by the authority's signature on an audit package
+fn signed_bytes(
+    election: &Signed<ElectionTransaction>,
+    vote: &Signed<VoteTransaction>,
+    public_keys: &[Signed<KeyGenPublicKeyTransaction>],
+    reshares: &[Signed<TrusteeReshareTransaction>],
+    partial_decryptions: &[Signed<PartialDecryptionTransaction>],
+    decryptions: &[Signed<DecryptionTransaction>],
+) -> Vec<u8> {
+    serde_json::to_vec(&(
+        election,
+        vote,
+        public_keys,
+        reshares,
+        partial_decryptions,
+        decryptions,
+    ))
+    .expect("cryptoballot: unexpected error serializing JSON")
+}
+
+/// Collect the transactions needed to verify the decryption of a single vote on their own, in dependency order.
+///
+/// `Store::validate_all` succeeds on the bundle with no other transactions. Starting from the decryptions and partial
+/// decryptions of the vote (or, when using a mixnet, of the output of every final mix the vote went into), the
+/// bundle follows each transaction's `inputs` back through the mixes to the vote. It also holds the election's setup
+/// and every other vote, since voting_end commits to the full set of votes. Mixes, partial decryptions and
+/// decryptions of other votes are left out, unless they were posted in the same PartialDecryptionBatch.
+pub fn vote_bundle<S: Store>(
+    store: &S,
+    vote_id: Identifier,
+) -> Result<Vec<SignedTransaction>, TransactionNotFound> {
+    let vote = store.get_vote(vote_id)?;
+    let election = store.get_election(vote.election)?.tx;
+
+    let mut bundle: BTreeMap<Identifier, SignedTransaction> = BTreeMap::new();
+    let start = Identifier::start(election.id, TransactionType::Election, None);
+    let end = Identifier::end(election.id, TransactionType::MAX, None);
+    for tx in store.range(start, end) {
+        let decryption_phase = matches!(
+            tx.transaction_type(),
+            TransactionType::Mix
+                | TransactionType::PartialDecryption
+                | TransactionType::PartialDecryptionBatch
+                | TransactionType::Decryption
+        );
+        if !decryption_phase {
+            bundle.insert(tx.id(), tx);
+        }
+    }
+
+    let mut pending = Vec::new();
+    for target in decryption_targets(store, &election) {
+        let includes_vote = match target.upstream_id.transaction_type {
+            TransactionType::Mix => store
+                .get_mix(target.upstream_id)?
+                .vote_ids
+                .contains(&vote_id),
+            _ => target.upstream_id == vote_id,
+        };
+        if includes_vote {
+            pending.push(target.upstream_id);
+            pending.push(target.decryption_id(election.id));
+            for trustee in election.trustees.iter() {
+                pending.push(target.partial_decryption_id(election.id, trustee.index));
+            }
+        }
+    }
+
+    while let Some(id) = pending.pop() {
+        if bundle.contains_key(&id) {
+            continue;
+        }
+        let tx = match store.get_transaction(id) {
+            Some(tx) => tx,
+            None if id.transaction_type == TransactionType::PartialDecryption => {
+                match store.find_partial_decryption_batch(id) {
+                    Some(batch) => batch.into(),
+                    None => continue,
+                }
+            }
+            None => continue,
+        };
+        pending.extend(tx.inputs());
+        bundle.insert(tx.id(), tx);
+    }
+
+    let mut bundle: Vec<SignedTransaction> = bundle.into_values().collect();
+    sort_by_dependency_order(&mut bundle);
+    Ok(bundle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_audit_election() {
+        let txs = crate::tests::test_election_2();
+        let election: ElectionTransaction = txs[0].clone().into();
+
+        // Certify the results by tallying the decrypted votes
+        let decryptions: Vec<DecryptionTransaction> = txs
+            .iter()
+            .filter(|tx| tx.transaction_type() == TransactionType::Decryption)
+            .map(|tx| tx.clone().into())
+            .collect();
+        let mut tally = indexmap::IndexMap::new();
+        for contest in election.contests.iter() {
+            let mut method = contest.contest_type.new_tally(contest.num_winners).unwrap();
+            let result = tally_decryptions(contest, method.as_mut(), &decryptions).unwrap();
+            tally.insert(contest.id.clone(), result);
+        }
+        let certified = TallyTransaction {
+            id: election.id,
+            election_id: election.id,
+            tally,
+            trustees: vec![1, 2],
+            created_at: None,
+        };
+
+        let report = audit_election(&txs, Some(&certified));
+        assert!(report.passed(), "{}", report.render_summary());
+        assert_eq!(report.phase, Some(ElectionPhase::Complete));
+        for check in report.checks.iter() {
+            assert_eq!(check.status, AuditStatus::Pass);
+        }
+
+        // Without a certified result the tally can't be checked
+        let report = audit_election(&txs, None);
+        assert!(report.passed());
+        assert_eq!(
+            report.check(AuditCheckKind::Tally).unwrap().status,
+            AuditStatus::Skipped
+        );
+
+        // Only trustees 1 and 2 took part in the decryption, which is enough to meet the threshold
+        assert_eq!(report.findings.len(), 1);
+        assert_eq!(report.findings[0].severity, AuditSeverity::Warning);
+        assert_eq!(report.findings[0].id, election.id);
+
+        // Dropping a partial decryption leaves the decryption short of the threshold
+        let store = MemStore::from(txs.clone());
+        let partial_id = txs
+            .iter()
+            .find(|tx| tx.transaction_type() == TransactionType::PartialDecryption)
+            .unwrap()
+            .id();
+        store.remove(partial_id).unwrap().unwrap();
+        let findings = audit_findings(&store, election.id);
+        assert_eq!(findings[0].severity, AuditSeverity::Error);
+        assert_eq!(findings[0].id.transaction_type, TransactionType::Decryption);
+    }
+
+    #[test]
+    fn test_audit_tampered_election() {
+        let txs = crate::tests::test_election_2();
+        let election: ElectionTransaction = txs[0].clone().into();
+
+        // Certify a result in which the only vote went to someone else
+        let certified_votes = vec![vec![Selection {
+            write_in: true,
+            score: 0,
+            selection: "Somebody Else".to_string(),
+            weight: None,
+        }]];
+        let mut tally = indexmap::IndexMap::new();
+        for contest in election.contests.iter() {
+            let result = TallyResult::tally(
+                contest.id.clone(),
+                contest.index,
+                contest.num_winners,
+                contest.contest_type.clone(),
+                certified_votes.clone(),
+            )
+            .unwrap();
+            tally.insert(contest.id.clone(), result);
+        }
+        let certified = TallyTransaction {
+            id: election.id,
+            election_id: election.id,
+            tally,
+            trustees: vec![1, 2],
+            created_at: None,
+        };
+
+        let report = audit_election(&txs, Some(&certified));
+        assert!(!report.passed());
+        assert_eq!(
+            report.check(AuditCheckKind::Validation).unwrap().status,
+            AuditStatus::Pass
+        );
+        let tally_check = report.check(AuditCheckKind::Tally).unwrap();
+        assert_eq!(tally_check.status, AuditStatus::Fail);
+        assert!(tally_check
+            .details
+            .iter()
+            .any(|detail| detail.contains("do not match the certified winners")));
+
+        // Dropping the decryption leaves the election incomplete
+        let undecrypted: Vec<SignedTransaction> = txs
+            .iter()
+            .filter(|tx| tx.transaction_type() != TransactionType::Decryption)
+            .cloned()
+            .collect();
+        let report = audit_election(&undecrypted, None);
+        assert!(!report.passed());
+        assert_eq!(report.phase, Some(ElectionPhase::Decryption));
+        assert_eq!(
+            report.check(AuditCheckKind::Validation).unwrap().status,
+            AuditStatus::Pass
+        );
+        assert_eq!(
+            report
+                .check(AuditCheckKind::DecryptionCompleteness)
+                .unwrap()
+                .status,
+            AuditStatus::Fail
+        );
+
+        // An election that can't be tallied, or that has no final mix, fails the audit rather than panicking
+        let with_election =
+            |configure: &dyn Fn(&mut ElectionTransaction)| -> Vec<SignedTransaction> {
+                let mut txs = txs.clone();
+                if let SignedTransaction::Election(election) = &mut txs[0] {
+                    configure(&mut election.tx);
+                }
+                txs
+            };
+        let unsupported = with_election(&|election| {
+            election.contests[0].contest_type = ContestType::SchulzeRatio;
+        });
+        let report = audit_election(&unsupported, Some(&certified));
+        let tally_check = report.check(AuditCheckKind::Tally).unwrap();
+        assert_eq!(tally_check.status, AuditStatus::Fail);
+        assert!(tally_check
+            .details
+            .iter()
+            .any(|detail| detail.contains("can't be tallied")));
+
+        let no_final_mix = with_election(&|election| {
+            election.trustees_threshold = 0;
+            election.mix_config = Some(MixConfig {
+                timeout_secs: 60,
+                batch_size: None,
+            });
+        });
+        let report = audit_election(&no_final_mix, Some(&certified));
+        assert!(!report.passed());
+        assert!(report
+            .check(AuditCheckKind::DecryptionCompleteness)
+            .unwrap()
+            .details
+            .iter()
+            .any(|detail| detail.contains("trustees threshold of 0")));
+    }
+
+    #[test]
+    fn test_verify_election_integrity() {
+        let txs = crate::tests::test_election_2();
+        let election_id = txs[0].id();
+
+        let store = MemStore::from(txs.clone());
+        let report = verify_election_integrity(&store, election_id).unwrap();
+        assert!(report.is_consistent());
+        assert_eq!(report.transaction_counts[&TransactionType::KeyGenShare], 3);
+        assert_eq!(
+            report.transaction_counts[&TransactionType::PartialDecryption],
+            2
+        );
+        assert_eq!(report.non_participating_trustees, vec![3]);
+
+        let decryption_id = txs
+            .iter()
+            .find(|tx| tx.transaction_type() == TransactionType::Decryption)
+            .unwrap()
+            .id();
+        store.remove(decryption_id).unwrap().unwrap();
+        let report = verify_election_integrity(&store, election_id).unwrap();
+        assert!(!report.is_consistent());
+        assert_eq!(report.missing_decryptions.len(), 1);
+        assert_eq!(
+            report.missing_decryptions[0].transaction_type,
+            TransactionType::Vote
+        );
+
+        assert!(verify_election_integrity(&store, ElectionTransaction::build_id([9; 15])).is_err());
+    }
+}
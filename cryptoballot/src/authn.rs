@@ -1,11 +1,25 @@
 use crate::*;
 use ed25519_dalek::PublicKey;
+use ed25519_dalek::SecretKey;
+use ed25519_dalek::Signature;
+use hmac::{Hmac, Mac, NewMac};
 use indexmap::IndexMap;
-use rsa::{RSAPrivateKey, RSAPublicKey};
+use rand::Rng;
+use rsa::{BigUint, PublicKeyParts, RSAPrivateKey, RSAPublicKey};
 use rsa_fdh::blind;
 use sha2::Sha256;
+use std::collections::HashMap;
 use uuid::Uuid;
 
+/// Minimum authenticator key size for production elections
+pub const MIN_PRODUCTION_AUTHENTICATOR_KEY_BITS: usize = 2048;
+
+/// Minimum authenticator key size for test and demo elections
+pub const MIN_AUTHENTICATOR_KEY_BITS: usize = 256;
+
+/// The only public exponent accepted for authenticator keys (F4)
+pub const AUTHENTICATOR_KEY_EXPONENT: u32 = 65537;
+
 /// RSA Public Key for blind signing
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct AuthPublicKey(#[serde(with = "RSAPublicKeyHex")] RSAPublicKey);
@@ -46,6 +60,8 @@ impl Authenticator {
     ///
     /// WARNING: The secret keys generated here must NOT be used for any other purpose.
     /// Doing so can result in secret key disclosure.
+    ///
+    /// The returned secret keys are zeroed from memory when dropped, and no other copy of them is made.
     pub fn new(
         keysize: usize,
         ballot_ids: &[String],
@@ -64,7 +80,8 @@ impl Authenticator {
 
         for ballot_id in ballot_ids {
             let secret = RSAPrivateKey::new(&mut rng, keysize)?;
-            let public: RSAPublicKey = secret.clone().into();
+            // Build the public key from its parts, rather than from a clone of the secret key
+            let public = RSAPublicKey::new(secret.n().clone(), secret.e().clone())?;
 
             public_keys.insert(ballot_id.clone(), AuthPublicKey(public));
             secret_keys.insert(ballot_id.clone(), secret);
@@ -78,6 +95,28 @@ impl Authenticator {
         Ok((authenticator, secret_keys))
     }
 
+    /// Check that every public key is a structurally valid RSA key with a modulus of at least `min_bits` and the standard exponent.
+    ///
+    /// A small modulus or an unusual exponent would let the authenticator (or anyone else) forge authentications.
+    pub fn validate_keys(&self, min_bits: usize) -> Result<(), ValidationError> {
+        for (ballot_id, public_key) in self.public_keys.iter() {
+            let invalid = || ValidationError::InvalidAuthenticatorKey(self.id, ballot_id.clone());
+            let key = &public_key.0;
+
+            // Re-run the checks done by the rsa crate when constructing a key, since deserializing skips them
+            RSAPublicKey::new(key.n().clone(), key.e().clone()).map_err(|_| invalid())?;
+
+            if key.n().bits() < min_bits {
+                return Err(invalid());
+            }
+            if *key.e() != BigUint::from(AUTHENTICATOR_KEY_EXPONENT) {
+                return Err(invalid());
+            }
+        }
+
+        Ok(())
+    }
+
     /// Sign the blinded (`election-id`, `ballot-id`, `voter-public-key`) auth-package triplet.
     ///
     /// This should only be called after verifying the voter's bonefides (eg government-id, security-code, password etc)
@@ -105,14 +144,19 @@ impl Authenticator {
         anonymous_key: &PublicKey,
         signature: &[u8],
     ) -> Result<(), ValidationError> {
-        let package = AuthPackage {
-            election_id,
-            ballot_id: ballot_id.to_string(),
-            anonymous_key: anonymous_key.clone(),
-        };
+        let package = AuthPackage::new(election_id, ballot_id.to_string(), anonymous_key.clone());
+        self.verify_package(&package, signature)
+    }
+
+    /// Verify that the authenticator signature covers exactly this auth package
+    pub fn verify_package(
+        &self,
+        package: &AuthPackage,
+        signature: &[u8],
+    ) -> Result<(), ValidationError> {
         let public_key = self
             .public_keys
-            .get(ballot_id)
+            .get(&package.ballot_id)
             .ok_or(ValidationError::BallotDoesNotExist)?;
 
         let digest = package.digest(&public_key.0);
@@ -127,12 +171,19 @@ impl Authenticator {
 ///
 /// Make sure this package is blinded before being sent to the authenticator to keep the voter's
 /// public-key secret from the authenticator.
+///
+/// For weighted votes the package also carries the voter's weight, so that the authenticators certify it.
 // TODO: Be smarter about lifetimes here so we don't need to clone PublicKey
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct AuthPackage {
     election_id: Identifier,
     ballot_id: String,
     anonymous_key: PublicKey,
+
+    // Skipped when unset so that unweighted packages pack exactly as they did before weights existed
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    weight: Option<u32>,
 }
 
 impl AuthPackage {
@@ -142,9 +193,18 @@ impl AuthPackage {
             election_id,
             ballot_id,
             anonymous_key,
+            weight: None,
         }
     }
 
+    /// Set the voting weight being certified, which must match the vote's `weight`.
+    ///
+    /// The authenticator must check that the voter is entitled to this weight before signing the package.
+    pub fn with_weight(mut self, weight: Option<u32>) -> Self {
+        self.weight = weight;
+        self
+    }
+
     /// Blind the authentication package, readiying it to be send to the authenticator
     pub fn blind(&self, signer_pub_key: &RSAPublicKey) -> (Vec<u8>, Vec<u8>) {
         let mut csprng = rand::rngs::OsRng {};
@@ -195,12 +255,233 @@ impl Authentication {
     }
 }
 
+/// A challenge-response front-end for an `Authenticator`, for interactive voter authentication.
+///
+/// The voter first requests a challenge, which is a random nonce signed by the authenticator that expires after `ttl_secs`.
+/// The voter answers it with an HMAC-SHA256 of the challenge keyed by their credential (a password, security-code etc).
+/// If the response checks out against the credential the authenticator has on file for that voter, the voter's blinded
+/// auth-package is blind-signed exactly as with `Authenticator::authenticate`.
+///
+/// Each challenge may only be used once, and only for the ballot it was issued for. Used nonces are remembered until
+/// their challenge expires, after which the challenge is rejected as expired anyway.
+pub struct ChallengeResponseAuthenticator {
+    pub authenticator: Authenticator,
+    pub challenge_public: PublicKey,
+    challenge_secret: SecretKey,
+    ttl_secs: u64,
+    /// Used nonces, mapped to the expiry time of their challenge
+    seen_nonces: HashMap<Vec<u8>, u64>,
+}
+
+/// A time-limited authentication challenge
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Challenge {
+    pub authenticator: Uuid,
+    pub ballot_id: String,
+
+    #[serde(with = "hex_serde")]
+    pub nonce: Vec<u8>,
+
+    /// Expiry time, in seconds since the UNIX epoch
+    pub expires_at: u64,
+}
+
+impl Challenge {
+    fn pack(&self) -> Vec<u8> {
+        serde_cbor::to_vec(self).expect("cryptoballot: error packing auth challenge")
+    }
+}
+
+/// A challenge signed by the authenticator that issued it
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SignedChallenge {
+    pub challenge: Challenge,
+
+    #[serde(with = "EdSignatureHex")]
+    pub sig: Signature,
+}
+
+/// A voter's response to a `SignedChallenge`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ChallengeResponse {
+    pub challenge: SignedChallenge,
+
+    #[serde(with = "hex_serde")]
+    pub response: Vec<u8>,
+}
+
+impl ChallengeResponseAuthenticator {
+    /// Wrap an authenticator. Challenges are signed with a fresh ed25519 key and are valid for `ttl_secs`.
+    pub fn new(authenticator: Authenticator, ttl_secs: u64) -> Self {
+        let (challenge_secret, challenge_public) = generate_keypair();
+
+        ChallengeResponseAuthenticator {
+            authenticator,
+            challenge_public,
+            challenge_secret,
+            ttl_secs,
+            seen_nonces: HashMap::new(),
+        }
+    }
+
+    /// Issue a new challenge for a voter wishing to vote the given ballot
+    pub fn challenge(&self, ballot_id: &str) -> SignedChallenge {
+        self.challenge_at(ballot_id, SystemClock.now())
+    }
+
+    /// Issue a new challenge as if the current time were `now`
+    pub fn challenge_at(&self, ballot_id: &str, now: u64) -> SignedChallenge {
+        let challenge = Challenge {
+            authenticator: self.authenticator.id,
+            ballot_id: ballot_id.to_string(),
+            nonce: rand::thread_rng().gen::<[u8; 32]>().to_vec(),
+            expires_at: now + self.ttl_secs,
+        };
+
+        let sig = sign_detached(&self.challenge_secret, &challenge.pack());
+
+        SignedChallenge { challenge, sig }
+    }
+
+    /// Answer a challenge using the voter's credential
+    pub fn respond(credential: &[u8], challenge: &SignedChallenge) -> ChallengeResponse {
+        let mut mac = Hmac::<Sha256>::new_from_slice(credential)
+            .expect("cryptoballot: HMAC accepts keys of any length");
+        mac.update(&challenge.challenge.pack());
+
+        ChallengeResponse {
+            challenge: challenge.clone(),
+            response: mac.finalize().into_bytes().to_vec(),
+        }
+    }
+
+    /// Check the response against the voter's `credential` and, if valid, blind-sign the blinded auth-package.
+    ///
+    /// `ballot_id` is the ballot being authenticated, and `secret` the authenticator's secret key for that ballot.
+    /// `credential` is the credential the authenticator has on file for the voter - looking it up is implementation specific.
+    pub fn authenticate(
+        &mut self,
+        ballot_id: &str,
+        secret: &RSAPrivateKey,
+        credential: &[u8],
+        response: &ChallengeResponse,
+        blinded_auth_package: &[u8],
+    ) -> Result<Authentication, ValidationError> {
+        self.authenticate_at(
+            ballot_id,
+            secret,
+            credential,
+            response,
+            blinded_auth_package,
+            SystemClock.now(),
+        )
+    }
+
+    /// Same as `authenticate`, as if the current time were `now`
+    pub fn authenticate_at(
+        &mut self,
+        ballot_id: &str,
+        secret: &RSAPrivateKey,
+        credential: &[u8],
+        response: &ChallengeResponse,
+        blinded_auth_package: &[u8],
+        now: u64,
+    ) -> Result<Authentication, ValidationError> {
+        let signed = &response.challenge;
+        let challenge = &signed.challenge;
+        let packed = challenge.pack();
+
+        if challenge.authenticator != self.authenticator.id {
+            return Err(ValidationError::AuthChallengeWrongAuthenticator);
+        }
+        verify_detached(&self.challenge_public, &packed, &signed.sig)?;
+
+        // A challenge issued for one ballot must not unlock a signature for another
+        if challenge.ballot_id != ballot_id {
+            return Err(ValidationError::AuthChallengeWrongBallot(
+                challenge.ballot_id.clone(),
+            ));
+        }
+
+        if now > challenge.expires_at {
+            return Err(ValidationError::AuthChallengeExpired(challenge.expires_at));
+        }
+
+        // Expired challenges are rejected above, so their nonces no longer need to be remembered
+        self.seen_nonces.retain(|_, expires_at| *expires_at >= now);
+        if self.seen_nonces.contains_key(&challenge.nonce) {
+            return Err(ValidationError::AuthChallengeReplayed);
+        }
+
+        let mut mac = Hmac::<Sha256>::new_from_slice(credential)
+            .expect("cryptoballot: HMAC accepts keys of any length");
+        mac.update(&packed);
+        mac.verify(&response.response)
+            .map_err(|_| ValidationError::AuthChallengeResponseInvalid)?;
+
+        // Only burn the nonce once the voter has proven knowledge of the credential
+        self.seen_nonces
+            .insert(challenge.nonce.clone(), challenge.expires_at);
+
+        Ok(self
+            .authenticator
+            .authenticate(secret, blinded_auth_package))
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
     use crate::*;
     use rand::Rng;
 
+    #[test]
+    fn test_invalid_authenticator_key() {
+        use rsa::{BigUint, PublicKeyParts, RSAPublicKey};
+
+        let (_authority_secret, authority_public) = generate_keypair();
+        let (trustee, _trustee_secret) = Trustee::new(1, 1, 1);
+        let (mut authenticator, _auth_secrets) =
+            Authenticator::new(256, &vec!["TEST".to_string()]).unwrap();
+
+        let mut election = ElectionTransaction::new(authority_public);
+        election.trustees = vec![trustee];
+        election.authenticators = vec![authenticator.clone()];
+        election.validate_tx(&MemStore::default()).unwrap();
+
+        // Production elections need full-size keys
+        election.is_production = true;
+        assert!(matches!(
+            election.validate_tx(&MemStore::default()),
+            Err(ValidationError::InvalidAuthenticatorKey(_, _))
+        ));
+        election.is_production = false;
+
+        // Same modulus, but with a non-standard exponent
+        let key = authenticator.public_keys.get_mut("TEST").unwrap();
+        let n = key.as_ref().n().clone();
+        *key = AuthPublicKey(RSAPublicKey::new(n, BigUint::from(3u32)).unwrap());
+        election.authenticators = vec![authenticator];
+        assert!(matches!(
+            election.validate_tx(&MemStore::default()),
+            Err(ValidationError::InvalidAuthenticatorKey(_, ballot_id)) if ballot_id == "TEST"
+        ));
+    }
+
+    #[test]
+    fn test_authenticator_secrets_zeroize() {
+        use zeroize::Zeroize;
+        fn assert_zeroize<T: Zeroize>() {}
+        assert_zeroize::<rsa::RSAPrivateKey>();
+
+        let (_authenticator, mut auth_secrets) =
+            Authenticator::new(256, &vec!["TEST".to_string()]).unwrap();
+        let mut secret = auth_secrets.remove("TEST").unwrap();
+        assert!(secret.d().to_bytes_le().iter().any(|b| *b != 0));
+        secret.zeroize();
+        assert!(secret.d().to_bytes_le().iter().all(|b| *b == 0));
+    }
+
     #[test]
     fn test_blind_signing() {
         let mut rng = rand::thread_rng();
@@ -229,4 +510,113 @@ mod tests {
             .verify(election_id, ballot_id, &voter_public, &auth.signature)
             .unwrap();
     }
+
+    #[test]
+    fn test_challenge_response() {
+        let mut rng = rand::thread_rng();
+        let election_id = ElectionTransaction::build_id(rng.gen());
+        let ballot_id = "TEST";
+        let (_voter_secret, voter_public) = generate_keypair();
+        let credential = b"correct horse battery staple";
+
+        let (authenticator, auth_secrets) =
+            Authenticator::new(256, &vec![ballot_id.to_string()]).unwrap();
+        let auth_secret = auth_secrets.get(ballot_id).unwrap();
+        let public_key = authenticator
+            .public_keys
+            .get(ballot_id)
+            .unwrap()
+            .as_ref()
+            .clone();
+        let mut cr_authenticator = ChallengeResponseAuthenticator::new(authenticator, 60);
+
+        let auth_package = AuthPackage::new(election_id, ballot_id.to_string(), voter_public);
+        let (blinded, unblinder) = auth_package.blind(&public_key);
+
+        // Wrong credential
+        let challenge = cr_authenticator.challenge_at(ballot_id, 1000);
+        let response = ChallengeResponseAuthenticator::respond(b"wrong", &challenge);
+        assert!(matches!(
+            cr_authenticator.authenticate_at(
+                ballot_id,
+                auth_secret,
+                credential,
+                &response,
+                &blinded,
+                1010
+            ),
+            Err(ValidationError::AuthChallengeResponseInvalid)
+        ));
+
+        // Expired
+        let response = ChallengeResponseAuthenticator::respond(credential, &challenge);
+        assert!(matches!(
+            cr_authenticator.authenticate_at(
+                ballot_id,
+                auth_secret,
+                credential,
+                &response,
+                &blinded,
+                1061
+            ),
+            Err(ValidationError::AuthChallengeExpired(1060))
+        ));
+
+        // Valid
+        let auth = cr_authenticator
+            .authenticate_at(
+                ballot_id,
+                auth_secret,
+                credential,
+                &response,
+                &blinded,
+                1010,
+            )
+            .unwrap();
+        let auth = auth.unblind(&public_key, unblinder);
+        cr_authenticator
+            .authenticator
+            .verify(election_id, ballot_id, &voter_public, &auth.signature)
+            .unwrap();
+
+        // Replayed
+        assert!(matches!(
+            cr_authenticator.authenticate_at(
+                ballot_id,
+                auth_secret,
+                credential,
+                &response,
+                &blinded,
+                1010
+            ),
+            Err(ValidationError::AuthChallengeReplayed)
+        ));
+        assert_eq!(cr_authenticator.seen_nonces.len(), 1);
+
+        // Issued for a different ballot
+        let other_challenge = cr_authenticator.challenge_at("OTHER", 1000);
+        let response = ChallengeResponseAuthenticator::respond(credential, &other_challenge);
+        assert!(matches!(
+            cr_authenticator.authenticate_at(ballot_id, auth_secret, credential, &response, &blinded, 1010),
+            Err(ValidationError::AuthChallengeWrongBallot(other)) if other == "OTHER"
+        ));
+
+        // Used nonces are forgotten once their challenge has expired
+        let challenge = cr_authenticator.challenge_at(ballot_id, 1100);
+        let response = ChallengeResponseAuthenticator::respond(credential, &challenge);
+        cr_authenticator
+            .authenticate_at(
+                ballot_id,
+                auth_secret,
+                credential,
+                &response,
+                &blinded,
+                1110,
+            )
+            .unwrap();
+        assert_eq!(cr_authenticator.seen_nonces.len(), 1);
+        assert!(cr_authenticator
+            .seen_nonces
+            .contains_key(&challenge.challenge.nonce));
+    }
 }
@@ -1,5 +1,7 @@
+use crate::*;
 use indexmap::IndexMap;
 use prost::Message;
+use uuid::Uuid;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Ballot {
@@ -24,6 +26,11 @@ pub struct Contest {
     pub write_in: bool,
     pub candidates: Vec<Candidate>,
 
+    /// If set, this contest is a referendum question rather than a candidate race
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub question: Option<BallotQuestion>,
+
     /// Application specific properties.
     ///
     /// Hashmaps are not allowed because their unstable ordering leads to non-determinism.
@@ -44,6 +51,113 @@ pub struct Candidate {
     pub properties: IndexMap<String, serde_json::Value>,
 }
 
+impl Contest {
+    /// Work out which candidate a selection in this contest is for.
+    ///
+    /// A selection is a write-in if it is marked as one, or if it doesn't match any of the contest's registered
+    /// candidates. Contests without a list of registered candidates accept any unmarked selection as registered.
+    /// If the contest doesn't allow write-ins (see `write_in`), a write-in spoils the ballot.
+    pub fn selected_candidate(
+        &self,
+        selection: &Selection,
+    ) -> Result<SelectedCandidate, SpoiledBallotError> {
+        let registered = !selection.write_in
+            && (self.candidates.is_empty()
+                || self
+                    .candidates
+                    .iter()
+                    .any(|candidate| candidate.id == selection.selection));
+
+        if registered {
+            Ok(SelectedCandidate::Registered(selection.selection.clone()))
+        } else if self.write_in {
+            Ok(SelectedCandidate::WriteIn(selection.selection.clone()))
+        } else if selection.write_in {
+            Err(SpoiledBallotError::WriteInNotAllowed)
+        } else {
+            Err(SpoiledBallotError::CandidateNotFound)
+        }
+    }
+}
+
+/// The candidate a decrypted selection is for (see `Contest::selected_candidate`)
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SelectedCandidate {
+    /// One of the contest's registered candidates, by candidate id
+    Registered(String),
+
+    /// A free-form write-in, which may also be a misspelling of a registered candidate
+    WriteIn(String),
+}
+
+/// The name all write-ins are counted under in a contest's tally.
+///
+/// The individual write-ins are counted separately in `TallyResult::write_ins`.
+pub const WRITE_IN_CANDIDATE: &str = "write-in";
+
+/// A referendum question put to voters in place of a candidate race.
+///
+/// For a `YesNo` question each vote is a single selection of `REFERENDUM_YES` or `REFERENDUM_NO`. For a `Ranked`
+/// question the options are the contest's candidates, and votes are tallied using the contest's `ContestType`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BallotQuestion {
+    pub id: Uuid,
+    pub question: String,
+    pub ballot_type: ReferendumType,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReferendumType {
+    YesNo,
+    Ranked,
+}
+
+/// The selection for a "yes" vote on a `ReferendumType::YesNo` question
+pub const REFERENDUM_YES: &str = "yes";
+
+/// The selection for a "no" vote on a `ReferendumType::YesNo` question
+pub const REFERENDUM_NO: &str = "no";
+
+/// Check that a decrypted vote is a valid answer to a `ReferendumType::YesNo` question:
+/// a single selection of either `REFERENDUM_YES` or `REFERENDUM_NO`, and not a write-in.
+pub fn validate_yes_no_vote(vote: &[Selection]) -> Result<(), ValidationError> {
+    match vote {
+        [selection]
+            if !selection.write_in
+                && (selection.selection == REFERENDUM_YES
+                    || selection.selection == REFERENDUM_NO) =>
+        {
+            Ok(())
+        }
+        _ => Err(ValidationError::InvalidReferendumVote),
+    }
+}
+
+/// Count the yes and no votes for a `ReferendumType::YesNo` question, returning `(yes_count, no_count)`.
+///
+/// Only pass the decryptions for the question's contest. Votes are counted by weight, and votes that are not a valid
+/// yes / no answer are not counted. A vote with an invalid weight is an error.
+pub fn tally_referendum(votes: &[DecryptionTransaction]) -> Result<(u64, u64), ValidationError> {
+    let mut yes = 0;
+    let mut no = 0;
+
+    for vote in votes {
+        if validate_yes_no_vote(&vote.decrypted_vote).is_err() {
+            continue;
+        }
+        let weight = vote_weight(&vote.decrypted_vote)?;
+        if vote.decrypted_vote[0].selection == REFERENDUM_YES {
+            yes += weight;
+        } else {
+            no += weight;
+        }
+    }
+
+    Ok((yes, no))
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "snake_case")]
 pub enum ContestType {
@@ -51,6 +165,8 @@ pub enum ContestType {
     /// who polls the most among their counterparts (a plurality) is elected. It may be called first-past-the-post (FPTP),
     /// single-choice voting, simple plurality, or relative/simple majority.
     ///
+    /// With more than one winner, each voter may select up to `num_winners` candidates.
+    ///
     ///  For Plurality tally, `Selection.score` has no meaning.
     Plurality,
 
@@ -164,6 +280,15 @@ pub enum ContestType {
     /// For SchulzeMargin tally, `Selection.score` is interpreted as the candidate rank, where the best ranked candidate has a rank of zero.
     /// Candidates that have the same rank are considered to be of equal preference.
     SchulzeMargin,
+
+    /// Single Transferable Vote is a proportional, multi-winner, ranked voting system. Any candidate reaching the Droop quota
+    /// is elected and their surplus votes are transferred (at a fractional value) to the next preference on each of their ballots.
+    /// When no candidate reaches the quota, the lowest-polling candidate is eliminated and their votes transferred at full value.
+    /// The number of seats is the contest's `num_winners`.
+    ///
+    /// For SingleTransferableVote tally, `Selection.score` is interpreted as the candidate rank, where the best ranked candidate has a rank of zero.
+    /// Candidates must be ranked in strict order; candidates with the same rank are ordered as they appear on the ballot.
+    SingleTransferableVote,
 }
 
 #[derive(Serialize, Deserialize, Clone, Message, PartialEq, Eq)]
@@ -184,6 +309,36 @@ pub struct Selection {
     /// Known candidate-id or free-form text, depending on the value of the `write_in` field.
     #[prost(string)]
     pub selection: String,
+
+    /// Optional voting weight (eg. number of shares held), defaults to 1 if not set.
+    ///
+    /// Since the weight is encrypted along with the selection, it cannot be altered after the vote is cast.
+    /// All selections in a vote must carry the same weight, which must match the weight the authenticators
+    /// certified on the vote (see `VoteTransaction::weight`). A weight of zero is invalid.
+    #[prost(uint32, optional)]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub weight: Option<u32>,
+}
+
+/// Get the weight of a vote, checking that it is valid.
+///
+/// An empty vote or a vote without a weight has a weight of 1.
+pub fn vote_weight(vote: &[Selection]) -> Result<u64, ValidationError> {
+    let weight = match vote.first() {
+        Some(selection) => selection.weight,
+        None => return Ok(1),
+    };
+
+    if vote.iter().any(|selection| selection.weight != weight) {
+        return Err(ValidationError::MismatchedVoteWeight);
+    }
+
+    match weight {
+        Some(0) => Err(ValidationError::InvalidVoteWeight),
+        Some(weight) => Ok(weight as u64),
+        None => Ok(1),
+    }
 }
 
 impl Into<(String, u64)> for Selection {
@@ -197,3 +352,50 @@ impl Into<(String, u32)> for Selection {
         (self.selection, self.score)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tally_referendum() {
+        let election_id = ElectionTransaction::build_id([3; 15]);
+        let vote_id = Identifier::new(election_id, TransactionType::Vote, Some([4; 16]));
+
+        let answer = |selection: &str, weight: Option<u32>| {
+            vec![Selection {
+                write_in: false,
+                score: 0,
+                selection: selection.to_string(),
+                weight,
+            }]
+        };
+
+        assert!(validate_yes_no_vote(&answer(REFERENDUM_YES, None)).is_ok());
+        assert!(validate_yes_no_vote(&answer(REFERENDUM_NO, None)).is_ok());
+        assert!(matches!(
+            validate_yes_no_vote(&answer("maybe", None)),
+            Err(ValidationError::InvalidReferendumVote)
+        ));
+        assert!(matches!(
+            validate_yes_no_vote(&[]),
+            Err(ValidationError::InvalidReferendumVote)
+        ));
+
+        let mut both = answer(REFERENDUM_YES, None);
+        both.extend(answer(REFERENDUM_NO, None));
+        assert!(validate_yes_no_vote(&both).is_err());
+
+        let votes: Vec<DecryptionTransaction> = vec![
+            answer(REFERENDUM_YES, None),
+            answer(REFERENDUM_YES, Some(3)),
+            answer(REFERENDUM_NO, None),
+            answer("maybe", None),
+        ]
+        .into_iter()
+        .map(|vote| DecryptionTransaction::new(election_id, vote_id, 0, 0, vec![1], vote))
+        .collect();
+
+        assert_eq!(tally_referendum(&votes).unwrap(), (4, 1));
+    }
+}
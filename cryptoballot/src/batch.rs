@@ -0,0 +1,151 @@
+use crate::*;
+use std::collections::HashSet;
+
+/// A set of related transactions that are validated and stored together, or not at all.
+///
+/// Transactions in a batch may depend on each other (for example a mix and the mix it follows), and may be given in
+/// any order.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct TransactionBatch {
+    pub transactions: Vec<SignedTransaction>,
+}
+
+impl TransactionBatch {
+    pub fn new(transactions: Vec<SignedTransaction>) -> Self {
+        TransactionBatch { transactions }
+    }
+}
+
+/// Validate every transaction in a batch against the store, without storing any of them.
+///
+/// The transactions are validated in dependency order, each against the store plus the transactions in the batch
+/// before it.
+pub fn validate_batch<S: Store>(batch: &TransactionBatch, store: &S) -> Result<(), BatchError> {
+    let mut seen = HashSet::with_capacity(batch.transactions.len());
+    for tx in batch.transactions.iter() {
+        if !seen.insert(tx.id()) {
+            return Err(BatchError::DuplicateTransaction(tx.id()));
+        }
+    }
+
+    let mut txs = batch.transactions.clone();
+    sort_by_dependency_order(&mut txs);
+
+    let mut validated = MemStore::default();
+    for tx in txs {
+        let view = BatchView {
+            store,
+            batch: &validated,
+        };
+        tx.validate(&view)
+            .map_err(|e| BatchError::InvalidTransaction(tx.id(), e))?;
+        validated.set(tx);
+    }
+
+    Ok(())
+}
+
+/// Validate a batch and then store every transaction in it.
+///
+/// Nothing is stored unless every transaction validates. Once validated, the transactions are stored in dependency
+/// order, so the store can only be left with part of the batch if the store itself fails to write.
+pub fn commit_batch<S: Store>(batch: TransactionBatch, store: &mut S) -> Result<(), BatchError> {
+    validate_batch(&batch, &*store)?;
+
+    let mut txs = batch.transactions;
+    sort_by_dependency_order(&mut txs);
+    for tx in txs {
+        let id = tx.id();
+        store
+            .apply_transaction(tx)
+            .map_err(|e| BatchError::ApplyFailed(id, e))?;
+    }
+
+    Ok(())
+}
+
+// A store overlaid with the batch transactions validated so far
+struct BatchView<'a, S: Store> {
+    store: &'a S,
+    batch: &'a MemStore,
+}
+
+impl<'a, S: Store> Store for BatchView<'a, S> {
+    fn get_transaction(&self, id: Identifier) -> Option<SignedTransaction> {
+        self.batch
+            .get_transaction(id)
+            .or_else(|| self.store.get_transaction(id))
+    }
+
+    fn contains(&self, id: Identifier) -> bool {
+        self.batch.contains(id) || self.store.contains(id)
+    }
+
+    fn range(&self, start: Identifier, end_inclusive: Identifier) -> Vec<SignedTransaction> {
+        let mut txs = self.store.range(start, end_inclusive);
+        txs.extend(self.batch.range(start, end_inclusive));
+        txs.sort_by_key(|tx| tx.id());
+        txs.dedup_by_key(|tx| tx.id());
+        txs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transaction_batch() {
+        let txs = crate::tests::test_election_2();
+        let election_id = txs[0].id();
+
+        // Store everything up to voting end, then submit the rest as a batch in reverse order
+        let split = txs
+            .iter()
+            .position(|tx| tx.transaction_type() == TransactionType::VotingEnd)
+            .unwrap();
+        let mut store = MemStore::default();
+        for tx in txs[..split].iter().cloned() {
+            store.apply_transaction(tx).unwrap();
+        }
+        let batch_txs: Vec<SignedTransaction> = txs[split..].iter().rev().cloned().collect();
+
+        // Without one of the partial decryptions the decryption fails, and nothing is stored
+        let partial_id = batch_txs
+            .iter()
+            .find(|tx| tx.transaction_type() == TransactionType::PartialDecryption)
+            .unwrap()
+            .id();
+        let incomplete = batch_txs
+            .iter()
+            .filter(|tx| tx.id() != partial_id)
+            .cloned()
+            .collect();
+        match commit_batch(TransactionBatch::new(incomplete), &mut store) {
+            Err(BatchError::InvalidTransaction(id, _)) => {
+                assert_eq!(id.transaction_type, TransactionType::Decryption)
+            }
+            _ => panic!("expected the decryption to fail validation"),
+        }
+        assert!(store
+            .get_multiple(election_id, TransactionType::VotingEnd)
+            .is_empty());
+        assert!(store
+            .get_multiple(election_id, TransactionType::PartialDecryption)
+            .is_empty());
+
+        let mut duplicated = batch_txs.clone();
+        duplicated.push(batch_txs[0].clone());
+        assert!(matches!(
+            validate_batch(&TransactionBatch::new(duplicated), &store),
+            Err(BatchError::DuplicateTransaction(_))
+        ));
+
+        commit_batch(TransactionBatch::new(batch_txs), &mut store).unwrap();
+        assert_eq!(
+            store.range(Identifier::first(), Identifier::last()).len(),
+            txs.len()
+        );
+        assert!(store.validate_all().is_empty());
+    }
+}
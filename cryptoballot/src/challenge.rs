@@ -0,0 +1,118 @@
+use crate::*;
+use ed25519_dalek::PublicKey;
+
+/// Transaction 13: Challenge
+///
+/// A Benaloh challenge of a vote. The voter reveals the plaintext of their vote along with the seed the vote was
+/// encrypted with (see `encrypt_vote_with_seed`), so anyone can re-encrypt the plaintext and confirm that the posted
+/// vote is an honest encryption of it. A voter who doubts their voting device can challenge a vote and re-cast with
+/// a fresh anonymous key, and a device that cannot tell which votes will be challenged cannot safely cheat on any.
+///
+/// A challenged vote reveals how it was voted, so it is spoiled: it is left out of the mixnet and cannot be decrypted
+/// or tallied. Votes can only be challenged before voting ends.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ChallengeTransaction {
+    pub id: Identifier,
+    pub election: Identifier,
+    pub vote_id: Identifier,
+
+    /// The challenged vote's anonymous key, so only the voter can challenge their own vote
+    #[serde(with = "EdPublicKeyHex")]
+    pub anonymous_key: PublicKey,
+
+    /// The plaintext of every contest in the vote, in the same order as the vote's `encrypted_votes`
+    pub votes: Vec<PlaintextVote>,
+
+    /// The seed the vote was encrypted with
+    #[serde(with = "hex_serde")]
+    pub seed: [u8; 32],
+
+    /// Optional creation time, in seconds since the UNIX epoch
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<u64>,
+}
+
+impl ChallengeTransaction {
+    /// Create a new ChallengeTransaction revealing the plaintext and seed of the given vote
+    pub fn new(vote: &VoteTransaction, votes: Vec<PlaintextVote>, seed: [u8; 32]) -> Self {
+        ChallengeTransaction {
+            id: Self::build_id(vote.election, vote.id),
+            election: vote.election,
+            vote_id: vote.id,
+            anonymous_key: vote.anonymous_key,
+            votes,
+            seed,
+            created_at: None,
+        }
+    }
+
+    pub fn build_id(election_id: Identifier, vote_id: Identifier) -> Identifier {
+        Identifier::new(
+            election_id,
+            TransactionType::Challenge,
+            Some(vote_id.unique_info),
+        )
+    }
+}
+
+impl CryptoBallotTransaction for ChallengeTransaction {
+    #[inline(always)]
+    fn id(&self) -> Identifier {
+        self.id
+    }
+
+    #[inline(always)]
+    fn public(&self) -> Option<PublicKey> {
+        Some(self.anonymous_key)
+    }
+
+    #[inline(always)]
+    fn election_id(&self) -> Identifier {
+        self.election
+    }
+
+    #[inline(always)]
+    fn tx_type() -> TransactionType {
+        TransactionType::Challenge
+    }
+
+    /// Validate the transaction
+    ///
+    /// The validation does the following:
+    ///  - Validates that this transaction has been signed by the challenged vote's anonymous key
+    ///  - Validates that voting has not ended
+    ///  - Validates that re-encrypting the revealed plaintext with the revealed seed reproduces the vote exactly
+    fn validate_tx<S: Store>(&self, store: &S) -> Result<(), ValidationError> {
+        if Self::build_id(self.election, self.vote_id) != self.id {
+            return Err(ValidationError::IdentifierBadComposition);
+        }
+
+        let vote = store.get_vote(self.vote_id)?;
+        if vote.election != self.election || vote.anonymous_key != self.anonymous_key {
+            return Err(ValidationError::ChallengeKeyMismatch);
+        }
+
+        let voting_end_id = Identifier::new(self.election, TransactionType::VotingEnd, None);
+        if store.contains(voting_end_id) {
+            return Err(ValidationError::VotingHasEnded);
+        }
+
+        let encryption_key = store
+            .get_encryption_key(self.election)
+            .map_err(|_| ValidationError::EncryptionKeyTransactionDoesNotExist)?;
+        let reencrypted =
+            encrypt_vote_with_seed(&encryption_key.encryption_key, &self.votes, self.seed)
+                .map_err(|_| ValidationError::ChallengeEncryptionMismatch)?;
+
+        let expected = serde_cbor::to_vec(&vote.encrypted_votes)
+            .expect("cryptoballot: unexpected error serializing vote");
+        let reencrypted = serde_cbor::to_vec(&reencrypted)
+            .expect("cryptoballot: unexpected error serializing vote");
+        if expected != reencrypted {
+            return Err(ValidationError::ChallengeEncryptionMismatch);
+        }
+
+        Ok(())
+    }
+}
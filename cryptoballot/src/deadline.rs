@@ -0,0 +1,82 @@
+use crate::*;
+
+/// A store that refuses partial decryptions received after the election's `decryption_deadline`.
+///
+/// The deadline is checked against when a partial decryption reaches the store, not against its signed `created_at`,
+/// which the trustee chooses and could backdate. That receipt time is not part of the election record, so partial
+/// decryptions are not checked against the deadline when they are validated: re-validating the stored partial
+/// decryptions after the deadline does not reject the ones that were received in time.
+pub struct DeadlineStore<S: Store, C: Clock = SystemClock> {
+    store: S,
+    clock: C,
+}
+
+impl<S: Store> DeadlineStore<S> {
+    /// Wrap a store, using the system clock
+    pub fn new(store: S) -> Self {
+        Self::with_clock(store, SystemClock)
+    }
+}
+
+impl<S: Store, C: Clock> DeadlineStore<S, C> {
+    /// Wrap a store, using `clock` to time submissions
+    pub fn with_clock(store: S, clock: C) -> Self {
+        DeadlineStore { store, clock }
+    }
+
+    /// Get the wrapped store
+    pub fn inner(&self) -> &S {
+        &self.store
+    }
+
+    /// Unwrap the store
+    pub fn into_inner(self) -> S {
+        self.store
+    }
+}
+
+impl<S: Store, C: Clock> Store for DeadlineStore<S, C> {
+    fn get_transaction(&self, id: Identifier) -> Option<SignedTransaction> {
+        self.store.get_transaction(id)
+    }
+
+    fn contains(&self, id: Identifier) -> bool {
+        self.store.contains(id)
+    }
+
+    fn range(&self, start: Identifier, end_inclusive: Identifier) -> Vec<SignedTransaction> {
+        self.store.range(start, end_inclusive)
+    }
+
+    fn list_elections(&self) -> Vec<Identifier> {
+        self.store.list_elections()
+    }
+
+    fn set_checked(&self, tx: SignedTransaction) -> Result<(), StoreError> {
+        self.store.set_checked(tx)
+    }
+
+    fn remove(&self, id: Identifier) -> Result<Option<SignedTransaction>, StoreError> {
+        self.store.remove(id)
+    }
+
+    fn apply_transaction(&self, tx: SignedTransaction) -> Result<(), ApplyError> {
+        let election_id = match &tx {
+            SignedTransaction::PartialDecryption(signed) => signed.tx.election_id,
+            SignedTransaction::PartialDecryptionBatch(signed) => signed.tx.election_id,
+            _ => return self.store.apply_transaction(tx),
+        };
+        let deadline = match self.store.get_election(election_id) {
+            Ok(election) => election.decryption_deadline,
+            Err(_) => None,
+        };
+
+        if let Some(deadline) = deadline {
+            if self.clock.now() > deadline {
+                return Err(ValidationError::DecryptionDeadlineExpired(deadline).into());
+            }
+        }
+
+        self.store.apply_transaction(tx)
+    }
+}
@@ -2,13 +2,23 @@ use crate::*;
 use cryptid::elgamal::Ciphertext;
 use cryptid::threshold::DecryptShare;
 use cryptid::threshold::Threshold;
+use cryptid::threshold::{PubkeyProof, ThresholdParty};
 use ed25519_dalek::PublicKey;
 use prost::Message;
+use rand::{CryptoRng, RngCore};
 use std::collections::HashMap;
+use subtle::ConstantTimeEq;
 
 /// Transaction 9: Partial Decryption
 ///
+/// Shares are serialized as hex (see `DecryptShareHex`). Transactions signed before that change serialized each
+/// share as a structure, so those are still accepted, and are re-serialized the way they were signed so that their
+/// signatures still verify.
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(
+    from = "PartialDecryptionTransactionRepr",
+    into = "PartialDecryptionTransactionRepr"
+)]
 pub struct PartialDecryptionTransaction {
     pub id: Identifier,
     pub election_id: Identifier,
@@ -24,10 +34,100 @@ pub struct PartialDecryptionTransaction {
 
     pub trustee_index: u8,
 
-    #[serde(with = "EdPublicKeyHex")]
     pub trustee_public_key: PublicKey,
 
     pub partial_decryption: Vec<DecryptShare>,
+
+    /// Optional creation time, in seconds since the UNIX epoch
+    pub created_at: Option<u64>,
+
+    // Set when the shares were deserialized in the legacy structured form
+    legacy_share_encoding: bool,
+}
+
+// The serialized form of PartialDecryptionTransaction
+#[derive(Serialize, Deserialize)]
+struct PartialDecryptionTransactionRepr {
+    id: Identifier,
+    election_id: Identifier,
+    upstream_id: Identifier,
+    upstream_index: u16,
+    contest_index: u32,
+    trustee_index: u8,
+
+    #[serde(with = "EdPublicKeyHex")]
+    trustee_public_key: PublicKey,
+
+    partial_decryption: Vec<DecryptShareRepr>,
+
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    created_at: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum DecryptShareRepr {
+    Hex(#[serde(with = "DecryptShareHex")] DecryptShare),
+    Legacy(DecryptShare),
+}
+
+impl From<PartialDecryptionTransactionRepr> for PartialDecryptionTransaction {
+    fn from(repr: PartialDecryptionTransactionRepr) -> Self {
+        let legacy_share_encoding = repr
+            .partial_decryption
+            .iter()
+            .any(|share| matches!(share, DecryptShareRepr::Legacy(_)));
+        let partial_decryption = repr
+            .partial_decryption
+            .into_iter()
+            .map(|share| match share {
+                DecryptShareRepr::Hex(share) | DecryptShareRepr::Legacy(share) => share,
+            })
+            .collect();
+
+        PartialDecryptionTransaction {
+            id: repr.id,
+            election_id: repr.election_id,
+            upstream_id: repr.upstream_id,
+            upstream_index: repr.upstream_index,
+            contest_index: repr.contest_index,
+            trustee_index: repr.trustee_index,
+            trustee_public_key: repr.trustee_public_key,
+            partial_decryption,
+            created_at: repr.created_at,
+            legacy_share_encoding,
+        }
+    }
+}
+
+impl From<PartialDecryptionTransaction> for PartialDecryptionTransactionRepr {
+    fn from(tx: PartialDecryptionTransaction) -> Self {
+        let legacy_share_encoding = tx.legacy_share_encoding;
+        let partial_decryption = tx
+            .partial_decryption
+            .into_iter()
+            .map(|share| {
+                if legacy_share_encoding {
+                    DecryptShareRepr::Legacy(share)
+                } else {
+                    DecryptShareRepr::Hex(share)
+                }
+            })
+            .collect();
+
+        PartialDecryptionTransactionRepr {
+            id: tx.id,
+            election_id: tx.election_id,
+            upstream_id: tx.upstream_id,
+            upstream_index: tx.upstream_index,
+            contest_index: tx.contest_index,
+            trustee_index: tx.trustee_index,
+            trustee_public_key: tx.trustee_public_key,
+            partial_decryption,
+            created_at: tx.created_at,
+        }
+    }
 }
 
 impl PartialDecryptionTransaction {
@@ -56,6 +156,8 @@ impl PartialDecryptionTransaction {
             contest_index,
             trustee_public_key,
             partial_decryption,
+            created_at: None,
+            legacy_share_encoding: false,
         }
     }
 
@@ -76,6 +178,60 @@ impl PartialDecryptionTransaction {
             Some(unique_info),
         )
     }
+
+    /// Verify the partial decryption against the upstream ciphertexts and the trustee's public key.
+    ///
+    /// Unlike `validate_tx` this can be used on a partial decryption that is already in the store.
+    pub fn verify_proof<S: Store>(
+        &self,
+        store: &S,
+        election: &ElectionTransaction,
+    ) -> Result<(), ValidationError> {
+        trace_span!("partial_decryption.verify", id = %self.id);
+
+        // Get the ciphertext either from the vote or the mix
+        let encrypted_vote: Vec<Ciphertext> = encrypted_vote_from_upstream_tx(
+            store,
+            self.upstream_id,
+            self.upstream_index,
+            self.contest_index,
+            &election.mix_config,
+        )?;
+        election
+            .crypto_params
+            .validate_ciphertexts(&encrypted_vote)?;
+
+        // Get the public key transaction for this trustee, with its public key share refreshed by any re-shares
+        let public_key = store
+            .get_decryption_public_keys(self.election_id)?
+            .into_iter()
+            .find(|public_key| public_key.trustee_index == self.trustee_index)
+            .ok_or(TransactionNotFound {
+                id: KeyGenPublicKeyTransaction::build_id(self.election_id, self.trustee_index),
+                tx_type: TransactionType::KeyGenPublicKey,
+            })?;
+
+        // Validate that the public_key transaction matches
+        if self.trustee_public_key != public_key.trustee_public_key {
+            return Err(ValidationError::TrusteePublicKeyMismatch(
+                self.trustee_index,
+            ));
+        }
+
+        if encrypted_vote.len() != self.partial_decryption.len() {
+            // TODO: Use a dedicated errror
+            return Err(ValidationError::PartialDecryptionProofFailed);
+        }
+
+        // Verify the partial decryption proof
+        for (i, partial) in self.partial_decryption.iter().enumerate() {
+            if !partial.verify(&public_key.public_key_proof, &encrypted_vote[i]) {
+                return Err(ValidationError::PartialDecryptionProofFailed);
+            }
+        }
+
+        Ok(())
+    }
 }
 
 impl CryptoBallotTransaction for PartialDecryptionTransaction {
@@ -99,7 +255,10 @@ impl CryptoBallotTransaction for PartialDecryptionTransaction {
         TransactionType::Decryption
     }
 
-    /// Validate the transaction
+    /// Validate the transaction.
+    ///
+    /// The election's `decryption_deadline` is not checked here, since it depends on when the partial decryption
+    /// was received rather than anything signed into it. It is enforced on submission by `DeadlineStore`.
     fn validate_tx<S: Store>(&self, store: &S) -> Result<(), ValidationError> {
         let election = store.get_election(self.election_id)?;
 
@@ -114,6 +273,9 @@ impl CryptoBallotTransaction for PartialDecryptionTransaction {
             }
         }
         let trustee = trustee.ok_or(ValidationError::TrusteeDoesNotExist(self.trustee_index))?;
+        if store.is_trustee_revoked(self.election_id, trustee.index) {
+            return Err(ValidationError::TrusteeRevoked(trustee.index));
+        }
 
         // Check the ID
         if Self::build_id(
@@ -126,49 +288,32 @@ impl CryptoBallotTransaction for PartialDecryptionTransaction {
         {
             return Err(ValidationError::IdentifierBadComposition);
         }
-        // Make sure the mix index is equal to the minimum number of mixes
-
-        // Make sure voting end exists
-        let voting_end_id = Identifier::new(self.election_id, TransactionType::VotingEnd, None);
-        if store.get_transaction(voting_end_id).is_none() {
-            return Err(ValidationError::MisingVotingEndTransaction);
-        }
-
-        // Get the ciphertext either from the vote or the mix
-        let encrypted_vote: Vec<Ciphertext> = encrypted_vote_from_upstream_tx(
-            store,
-            self.upstream_id,
-            self.upstream_index,
-            self.contest_index,
-            &election.mix_config,
-        )?;
-
-        // Get the public key transaction for this trustee
-        let pkey_tx_id = KeyGenPublicKeyTransaction::build_id(self.election_id, self.trustee_index);
-        let public_key = store.get_keygen_public_key(pkey_tx_id)?;
-
-        // Validate that the public_key transaction matches
-        if self.trustee_index != public_key.inner().trustee_index
-            || self.trustee_public_key != public_key.inner().trustee_public_key
-        {
-            return Err(ValidationError::TrusteePublicKeyMismatch(
-                self.trustee_index,
-            ));
-        }
 
-        if encrypted_vote.len() != self.partial_decryption.len() {
-            // TODO: Use a dedicated errror
-            return Err(ValidationError::PartialDecryptionProofFailed);
+        // A trustee may only partially decrypt each ciphertext once, whether on its own or in a batch
+        if store.find_partial_decryption(self.id).is_some() {
+            return Err(ValidationError::DuplicatePartialDecryption(self.id));
         }
 
-        // Verify the partial decryption proof
-        for (i, partial) in self.partial_decryption.iter().enumerate() {
-            if !partial.verify(&public_key.inner().public_key_proof, &encrypted_vote[i]) {
-                return Err(ValidationError::PartialDecryptionProofFailed);
+        // With a mixnet, only the output of the final mix may be decrypted: that is, after every shuffle
+        if election.mix_config.is_some() {
+            if self.upstream_id.transaction_type != TransactionType::Mix {
+                return Err(ValidationError::MixRequired);
+            }
+            let mix = store.get_mix(self.upstream_id)?;
+            if mix.mix_index != election.trustees_threshold - 1 {
+                return Err(ValidationError::WrongMixSelected);
             }
         }
 
-        Ok(())
+        // Make sure voting end exists
+        let voting_end_id = Identifier::new(self.election_id, TransactionType::VotingEnd, None);
+        let voting_end: VotingEndTransaction = store
+            .get_transaction(voting_end_id)
+            .ok_or(ValidationError::MisingVotingEndTransaction)?
+            .into();
+        validate_timestamp_order(voting_end.created_at, self.created_at)?;
+
+        self.verify_proof(store, &election)
     }
 }
 
@@ -196,6 +341,27 @@ pub struct DecryptionTransaction {
 
     /// The decrypted vote
     pub decrypted_vote: Vec<Selection>,
+
+    /// True if the vote decrypted to something that isn't a valid vote (for example, text that isn't UTF-8).
+    /// Spoiled ballots have an empty `decrypted_vote`, and are reported but not counted.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_false")]
+    pub spoiled: bool,
+
+    /// If true, some trustees did not post a partial decryption, and `absent_trustees` formally records who they are
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_false")]
+    pub recover_decryption_without_quorum: bool,
+
+    /// The trustees (as defined by index) who abstained from this decryption, see `record_trustee_absence`
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub absent_trustees: Vec<u8>,
+
+    /// Optional creation time, in seconds since the UNIX epoch
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<u64>,
 }
 
 impl DecryptionTransaction {
@@ -219,9 +385,48 @@ impl DecryptionTransaction {
             upstream_index,
             trustees,
             decrypted_vote,
+            spoiled: false,
+            recover_decryption_without_quorum: false,
+            absent_trustees: vec![],
+            created_at: None,
         }
     }
 
+    /// Create a new DecryptionTransaction for a vote that doesn't decrypt to a valid vote
+    pub fn new_spoiled(
+        election_id: Identifier,
+        upstream_id: Identifier,
+        contest_index: u32,
+        upstream_index: u16,
+        trustees: Vec<u8>,
+    ) -> DecryptionTransaction {
+        let mut decryption = Self::new(
+            election_id,
+            upstream_id,
+            contest_index,
+            upstream_index,
+            trustees,
+            vec![],
+        );
+        decryption.spoiled = true;
+        decryption
+    }
+
+    /// Record that every trustee in the election but not in `trustees` abstained from this decryption.
+    ///
+    /// The decryption is still only valid if at least `trustees_threshold` trustees took part. Validation then also
+    /// checks that every trustee either participated or is recorded as absent, and that no absent trustee has in fact
+    /// posted a partial decryption.
+    pub fn record_trustee_absence(&mut self, election: &ElectionTransaction) {
+        self.recover_decryption_without_quorum = true;
+        self.absent_trustees = election
+            .trustees
+            .iter()
+            .map(|trustee| trustee.index)
+            .filter(|index| !self.trustees.contains(index))
+            .collect();
+    }
+
     pub fn build_id(
         election_id: Identifier,
         upstream_id: Identifier,
@@ -232,6 +437,69 @@ impl DecryptionTransaction {
         let unique_info = build_unique_info(upstream_id, contest_index, upstream_index, 0);
         Identifier::new(election_id, TransactionType::Decryption, Some(unique_info))
     }
+
+    /// Check the decrypted vote against the ciphertext and the trustees' partial decryptions of it.
+    ///
+    /// This is the part of `validate` that doesn't read from a store. `partials` are the partial decryptions of
+    /// `encrypted_vote` by each of `self.trustees`, and `pubkeys` are the trustees' current public keys. Callers that
+    /// fetch these some other way (such as from an `AsyncStore`) must also check the rest of what `validate` does.
+    pub fn verify_decryption(
+        &self,
+        election: &ElectionTransaction,
+        encrypted_vote: &[Ciphertext],
+        pubkeys: &[KeyGenPublicKeyTransaction],
+        partials: &[PartialDecryptionTransaction],
+    ) -> Result<(), ValidationError> {
+        trace_span!("decryption.verify", id = %self.id);
+
+        // Make sure we have enough shares
+        let required_shares = election.trustees_threshold as usize;
+        if partials.len() < required_shares {
+            return Err(ValidationError::NotEnoughShares(
+                required_shares,
+                partials.len(),
+            ));
+        }
+
+        // Decrypt the vote. A vote that doesn't decode must be marked as spoiled.
+        let decrypted_vote = decrypt_vote(
+            encrypted_vote,
+            election.trustees_threshold,
+            &election.trustees,
+            pubkeys,
+            partials,
+        );
+        let decrypted_vote = match decrypted_vote {
+            Err(ValidationError::VoteDecodingError(_)) if self.spoiled => {
+                if !self.decrypted_vote.is_empty() {
+                    return Err(ValidationError::VoteDecryptionMismatch);
+                }
+                return Ok(());
+            }
+            decrypted_vote => decrypted_vote?,
+        };
+
+        if self.spoiled || !decrypted_vote_eq(&decrypted_vote, &self.decrypted_vote) {
+            return Err(ValidationError::VoteDecryptionMismatch);
+        }
+
+        vote_weight(&self.decrypted_vote)?;
+
+        let question = election
+            .contests
+            .iter()
+            .find(|contest| contest.index == self.contest_index)
+            .and_then(|contest| contest.question.as_ref());
+        if let Some(question) = question {
+            if question.ballot_type == ReferendumType::YesNo {
+                validate_yes_no_vote(&self.decrypted_vote)?;
+            }
+        }
+
+        // TODO: Check that the selections match the ballot-style settings
+
+        Ok(())
+    }
 }
 
 impl CryptoBallotTransaction for DecryptionTransaction {
@@ -271,6 +539,17 @@ impl CryptoBallotTransaction for DecryptionTransaction {
 
         let election = store.get_election(self.election_id)?;
 
+        // Make sure voting end exists and precedes this decryption
+        let voting_end_id = Identifier::new(self.election_id, TransactionType::VotingEnd, None);
+        let voting_end: VotingEndTransaction = store
+            .get_transaction(voting_end_id)
+            .ok_or(ValidationError::MisingVotingEndTransaction)?
+            .into();
+        validate_timestamp_order(voting_end.created_at, self.created_at)?;
+
+        // No vote may have been added since voting ended
+        voting_end.verify_vote_commitment(store)?;
+
         // Get the ciphertext either from the vote or the mix
         let encrypted_vote: Vec<Ciphertext> = encrypted_vote_from_upstream_tx(
             store,
@@ -280,13 +559,21 @@ impl CryptoBallotTransaction for DecryptionTransaction {
             &election.mix_config,
         )?;
 
-        // Get all pubkeys mapped by trustee ID
-        let pubkeys: Vec<KeyGenPublicKeyTransaction> = store
-            .get_multiple(self.election_id, TransactionType::KeyGenPublicKey)
-            .into_iter()
-            .map(|tx| tx.into())
-            .map(|tx: Signed<KeyGenPublicKeyTransaction>| tx.tx)
-            .collect();
+        // Get all pubkeys, with their public key shares refreshed by any re-shares
+        let pubkeys = store.get_decryption_public_keys(self.election_id)?;
+
+        // Every trustee must have a public key, otherwise their partial decryptions can't be used
+        for trustee in election.trustees.iter() {
+            if !pubkeys
+                .iter()
+                .any(|pubkey| pubkey.trustee_index == trustee.index)
+            {
+                return Err(ValidationError::MissingTrusteePublicKey(trustee.id));
+            }
+        }
+        if pubkeys.len() != election.trustees.len() {
+            return Err(ValidationError::WrongNumberOfPublicKeyTransactions);
+        }
 
         // Get all partial decryptions mapped by trustee ID
         let mut partials = Vec::with_capacity(self.trustees.len());
@@ -296,6 +583,9 @@ impl CryptoBallotTransaction for DecryptionTransaction {
                 .inner()
                 .get_trustee(*trustee_index)
                 .ok_or(ValidationError::TrusteeDoesNotExist(*trustee_index))?;
+            if store.is_trustee_revoked(self.election_id, trustee.index) {
+                return Err(ValidationError::TrusteeRevoked(trustee.index));
+            }
             let partial_id = PartialDecryptionTransaction::build_id(
                 self.election_id,
                 self.upstream_id,
@@ -303,40 +593,327 @@ impl CryptoBallotTransaction for DecryptionTransaction {
                 self.upstream_index,
                 trustee.index,
             );
-            let partial = store.get_partial_decryption(partial_id)?;
+            let partial = store
+                .find_partial_decryption(partial_id)
+                .ok_or(TransactionNotFound {
+                    id: partial_id,
+                    tx_type: TransactionType::PartialDecryption,
+                })?;
+            validate_timestamp_order(partial.created_at, self.created_at)?;
+
+            partials.push(partial);
+        }
+
+        // Check the trustee absence record against the partial decryptions that were actually posted
+        if self.recover_decryption_without_quorum {
+            for absent_index in self.absent_trustees.iter() {
+                if election.inner().get_trustee(*absent_index).is_none() {
+                    return Err(ValidationError::TrusteeDoesNotExist(*absent_index));
+                }
+            }
+            for trustee in election.trustees.iter() {
+                let participated = self.trustees.contains(&trustee.index);
+                let absent = self.absent_trustees.contains(&trustee.index);
+                if participated == absent {
+                    return Err(ValidationError::TrusteeAbsenceMismatch(trustee.index));
+                }
 
-            partials.push(partial.tx);
+                let partial_id = PartialDecryptionTransaction::build_id(
+                    self.election_id,
+                    self.upstream_id,
+                    self.contest_index,
+                    self.upstream_index,
+                    trustee.index,
+                );
+                if absent && store.find_partial_decryption(partial_id).is_some() {
+                    return Err(ValidationError::TrusteeAbsenceMismatch(trustee.index));
+                }
+            }
+        } else if let Some(absent_index) = self.absent_trustees.first() {
+            return Err(ValidationError::TrusteeAbsenceMismatch(*absent_index));
         }
 
-        // Make sure we have enough shares
-        let required_shares = election.trustees_threshold as usize;
-        if partials.len() < required_shares {
-            return Err(ValidationError::NotEnoughShares(
-                required_shares,
-                partials.len(),
-            ));
+        self.verify_decryption(&election, &encrypted_vote, &pubkeys, &partials)?;
+
+        // The weight encrypted in the selections must be the one the authenticators certified on the vote
+        if !self.spoiled {
+            let certified_weight = match self.upstream_id.transaction_type {
+                TransactionType::Vote => store.get_vote(self.upstream_id)?.tx.weight.unwrap_or(1),
+                _ => 1,
+            } as u64;
+            let weight = vote_weight(&self.decrypted_vote)?;
+            if weight != certified_weight {
+                return Err(ValidationError::VoteWeightMismatch(
+                    certified_weight,
+                    weight,
+                ));
+            }
         }
 
-        // Decrypt the vote
-        let decrypted_vote = decrypt_vote(
-            &encrypted_vote,
-            election.inner().trustees_threshold,
-            &election.inner().trustees,
-            &pubkeys,
-            &partials,
-        )?;
+        Ok(())
+    }
+}
 
-        if decrypted_vote != self.decrypted_vote {
-            return Err(ValidationError::VoteDecryptionMismatch);
+/// A ciphertext that must be decrypted, identified the same way as in `DecryptionTransaction::build_id`
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DecryptionTarget {
+    /// The vote transaction ID, or the final mix transaction ID when using a mixnet
+    pub upstream_id: Identifier,
+
+    pub contest_index: u32,
+
+    /// The index of the ciphertext in the mix's `mixed_ciphertexts`, or `0` for a vote
+    pub upstream_index: u16,
+}
+
+impl DecryptionTarget {
+    /// The ID of a trustee's partial decryption of this ciphertext
+    pub fn partial_decryption_id(&self, election_id: Identifier, trustee_index: u8) -> Identifier {
+        PartialDecryptionTransaction::build_id(
+            election_id,
+            self.upstream_id,
+            self.contest_index,
+            self.upstream_index,
+            trustee_index,
+        )
+    }
+
+    /// The ID of the decryption of this ciphertext
+    pub fn decryption_id(&self, election_id: Identifier) -> Identifier {
+        DecryptionTransaction::build_id(
+            election_id,
+            self.upstream_id,
+            self.contest_index,
+            self.upstream_index,
+        )
+    }
+}
+
+impl std::fmt::Display for DecryptionTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.upstream_id.transaction_type {
+            TransactionType::Mix => write!(
+                f,
+                "mix {} contest {} index {}",
+                self.upstream_id, self.contest_index, self.upstream_index
+            ),
+            _ => write!(
+                f,
+                "vote {} contest {}",
+                self.upstream_id, self.contest_index
+            ),
         }
+    }
+}
 
-        // TODO: Check that the selections match the ballot-style settings
+/// Every ciphertext in an election that must be decrypted: each contest of each vote, or each ciphertext output by
+/// the final mixes when using a mixnet.
+///
+/// Challenged votes are never decrypted, so are left out. When using a mixnet only the final mixes posted so far are
+/// included, so this is only complete once mixing has finished.
+pub fn decryption_targets<S: Store>(
+    store: &S,
+    election: &ElectionTransaction,
+) -> Vec<DecryptionTarget> {
+    let mut targets = Vec::new();
+    match &election.mix_config {
+        None => {
+            for vote in store.get_cast_votes(election.id) {
+                for encrypted_vote in vote.encrypted_votes.iter() {
+                    targets.push(DecryptionTarget {
+                        upstream_id: vote.id,
+                        contest_index: encrypted_vote.contest_index,
+                        upstream_index: 0,
+                    });
+                }
+            }
+        }
+        Some(_) => {
+            // With a threshold of 0 no mix is the final one, so there is nothing to decrypt
+            let final_mix_index = match election.trustees_threshold.checked_sub(1) {
+                Some(final_mix_index) => final_mix_index,
+                None => return targets,
+            };
+            for tx in store.get_multiple(election.id, TransactionType::Mix) {
+                let mix: MixTransaction = tx.into();
+                if mix.mix_index != final_mix_index {
+                    continue;
+                }
+                for upstream_index in 0..mix.mixed_ciphertexts.len() as u16 {
+                    targets.push(DecryptionTarget {
+                        upstream_id: mix.id,
+                        contest_index: mix.contest_index,
+                        upstream_index,
+                    });
+                }
+            }
+        }
+    }
 
-        Ok(())
+    targets
+}
+
+/// The number of decryption transactions needed to finish decrypting an election: one for each contest of each
+/// counted vote.
+///
+/// This is the final number of `decryption_targets`. Each mix outputs exactly one ciphertext for each ciphertext it
+/// shuffles, so with a mixnet the count is known before mixing has finished.
+pub fn expected_decryptions<S: Store>(
+    store: &S,
+    election_id: Identifier,
+) -> Result<usize, ValidationError> {
+    store.get_election(election_id)?;
+
+    Ok(store
+        .get_cast_votes(election_id)
+        .iter()
+        .map(|vote| vote.encrypted_votes.len())
+        .sum())
+}
+
+/// Count the decryption transactions posted so far, returning `(completed, expected)`
+pub fn decryption_progress<S: Store>(
+    store: &S,
+    election_id: Identifier,
+) -> Result<(usize, usize), ValidationError> {
+    let expected = expected_decryptions(store, election_id)?;
+    let completed = store
+        .get_multiple(election_id, TransactionType::Decryption)
+        .len();
+
+    Ok((completed, expected))
+}
+
+/// The IDs of trustees who have not yet partially decrypted every ciphertext in the election (see
+/// `decryption_targets`).
+///
+/// Only `trustees_threshold` trustees need to partially decrypt each ciphertext, but every trustee is expected to, so
+/// that decryption doesn't depend on any one trustee. Trustees revoked by a TrusteeRevocation transaction are never
+/// pending. Returns no trustees if the election does not exist.
+pub fn pending_partial_decryptions<S: Store>(
+    store: &S,
+    election_id: Identifier,
+) -> Vec<uuid::Uuid> {
+    let election = match store.get_election(election_id) {
+        Ok(election) => election,
+        Err(_) => return vec![],
+    };
+    let targets = decryption_targets(store, &election);
+
+    election
+        .trustees
+        .iter()
+        .filter(|trustee| !store.is_trustee_revoked(election_id, trustee.index))
+        .filter(|trustee| {
+            targets.iter().any(|target| {
+                let partial_id = PartialDecryptionTransaction::build_id(
+                    election_id,
+                    target.upstream_id,
+                    target.contest_index,
+                    target.upstream_index,
+                    trustee.index,
+                );
+                store.find_partial_decryption(partial_id).is_none()
+            })
+        })
+        .map(|trustee| trustee.id)
+        .collect()
+}
+
+/// Check whether the election's `decryption_deadline` has passed with trustees still pending (see
+/// `pending_partial_decryptions`)
+pub fn partial_decryptions_overdue<S: Store, C: Clock>(
+    store: &S,
+    election_id: Identifier,
+    clock: &C,
+) -> bool {
+    let deadline = match store.get_election(election_id) {
+        Ok(election) => election.decryption_deadline,
+        Err(_) => return false,
+    };
+
+    match deadline {
+        Some(deadline) if clock.now() > deadline => {
+            !pending_partial_decryptions(store, election_id).is_empty()
+        }
+        _ => false,
     }
 }
 
+/// Count the trustees that have partially decrypted a ciphertext, returning `(collected, threshold)`.
+///
+/// The ciphertext is identified the same way as in `DecryptionTransaction::build_id`. Partial decryptions are found
+/// whether they were posted on their own or in a batch. They are counted, not validated, since the store only holds
+/// partial decryptions that already passed validation.
+pub fn partials_available<S: Store>(
+    store: &S,
+    election_id: Identifier,
+    upstream_id: Identifier,
+    contest_index: u32,
+    upstream_index: u16,
+) -> Result<(usize, usize), TransactionNotFound> {
+    let election = store.get_election(election_id)?;
+
+    let collected = election
+        .trustees
+        .iter()
+        .filter(|trustee| {
+            let partial_id = PartialDecryptionTransaction::build_id(
+                election_id,
+                upstream_id,
+                contest_index,
+                upstream_index,
+                trustee.index,
+            );
+            store.find_partial_decryption(partial_id).is_some()
+        })
+        .count();
+
+    Ok((collected, election.trustees_threshold as usize))
+}
+
+/// Check whether enough trustees have partially decrypted a ciphertext for a DecryptionTransaction to be built.
+///
+/// Returns false if the election does not exist.
+pub fn can_decrypt<S: Store>(
+    store: &S,
+    election_id: Identifier,
+    upstream_id: Identifier,
+    contest_index: u32,
+    upstream_index: u16,
+) -> bool {
+    match partials_available(
+        store,
+        election_id,
+        upstream_id,
+        contest_index,
+        upstream_index,
+    ) {
+        Ok((collected, threshold)) => collected >= threshold,
+        Err(_) => false,
+    }
+}
+
+/// Compare two decrypted votes in constant time.
+///
+/// Validators compare the vote they decrypt against the one claimed in a decryption transaction. Anyone who can
+/// submit decryption transactions and time their validation could otherwise use an early-exit comparison to learn
+/// how much of a claimed vote is correct, and so recover the vote one byte at a time. The votes are compared by their
+/// CBOR encoding, which only leaks its length.
+pub fn decrypted_vote_eq(a: &[Selection], b: &[Selection]) -> bool {
+    let a = serde_cbor::to_vec(a).expect("cryptoballot: unexpected error serializing vote");
+    let b = serde_cbor::to_vec(b).expect("cryptoballot: unexpected error serializing vote");
+    a.ct_eq(&b).into()
+}
+
 /// Decrypt the vote from the given partial decryptions.
+///
+/// This is the threshold decryption path: the vote is decrypted by combining at least `trustees_threshold` partial
+/// decryptions posted by the trustees, so no single party ever holds the election secret-key. See
+/// `decrypt_with_combined_key` for the trusted-dealer path.
+///
+/// Each partial decryption's proof is checked against `ciphertexts` before it is used, so partials computed for a
+/// different vote are rejected with `ValidationError::PartialDecryptionProofFailed`.
 pub fn decrypt_vote(
     ciphertexts: &[Ciphertext],
     trustees_threshold: u8,
@@ -356,27 +933,94 @@ pub fn decrypt_vote(
         .map(|tx| (tx.trustee_index, tx))
         .collect();
 
-    // Decrypt the vote
-    let mut results = Vec::with_capacity(ciphertexts.len());
-    for (i, ciphertext) in ciphertexts.iter().enumerate() {
-        let mut decrypt =
-            cryptid::threshold::Decryption::new(trustees_threshold as usize, ciphertext);
+    // Every partial must decrypt exactly these ciphertexts
+    if partials
+        .values()
+        .any(|partial| partial.partial_decryption.len() != ciphertexts.len())
+    {
+        return Err(ValidationError::PartialDecryptionProofFailed);
+    }
 
+    // Decrypt the vote
+    let mut shares = Vec::with_capacity(ciphertexts.len());
+    for i in 0..ciphertexts.len() {
+        let mut ciphertext_shares = Vec::with_capacity(trustees.len());
         for trustee in trustees {
             if let Some(partial) = partials.get(&trustee.index) {
                 if let Some(pubkey) = pubkeys.get(&trustee.index) {
-                    decrypt.add_share(
-                        trustee.index as usize,
+                    // Check the share was computed against this ciphertext before combining it
+                    if !partial.partial_decryption[i]
+                        .verify(&pubkey.public_key_proof, &ciphertexts[i])
+                    {
+                        return Err(ValidationError::PartialDecryptionProofFailed);
+                    }
+                    ciphertext_shares.push((
+                        trustee.index,
                         &pubkey.public_key_proof,
                         &partial.partial_decryption[i],
-                    );
+                    ));
                 }
             };
         }
+        shares.push(ciphertext_shares);
+    }
 
-        let raw_selection = decrypt
-            .finish()
-            .map_err(|e| ValidationError::VoteDecryptionFailed(e))?;
+    decrypt_vote_with::<DefaultEncryptionScheme>(ciphertexts, trustees_threshold, &shares)
+}
+
+/// Decrypt a vote with the combined election secret-key, held by a single trusted dealer.
+///
+/// `combined_key` holds the key shares of at least `trustees_threshold` trustees, keyed by trustee index. The dealer
+/// computes every partial decryption itself, so unlike `decrypt_vote` nothing is posted or verified on the
+/// election's log. This is only suitable for testing and for elections where a trusted dealer is acceptable.
+#[deprecated = "Use threshold decryption in production elections"]
+pub fn decrypt_with_combined_key<R: CryptoRng + RngCore>(
+    rng: &mut R,
+    ciphertexts: &[Ciphertext],
+    trustees_threshold: u8,
+    combined_key: &[(u8, ThresholdParty)],
+) -> Result<Vec<Selection>, ValidationError> {
+    let proofs: Vec<PubkeyProof> = combined_key
+        .iter()
+        .map(|(_, party)| party.pubkey_proof())
+        .collect();
+    let partials: Vec<Vec<DecryptShare>> = ciphertexts
+        .iter()
+        .map(|ciphertext| {
+            combined_key
+                .iter()
+                .map(|(_, party)| DefaultEncryptionScheme::partial_decrypt(party, ciphertext, rng))
+                .collect()
+        })
+        .collect();
+
+    let shares: Vec<Vec<_>> = partials
+        .iter()
+        .map(|ciphertext_partials| {
+            combined_key
+                .iter()
+                .zip(proofs.iter())
+                .zip(ciphertext_partials.iter())
+                .map(|(((trustee_index, _), proof), partial)| (*trustee_index, proof, partial))
+                .collect()
+        })
+        .collect();
+
+    decrypt_vote_with::<DefaultEncryptionScheme>(ciphertexts, trustees_threshold, &shares)
+}
+
+/// Decrypt a vote encrypted with the given encryption scheme.
+///
+/// `shares` holds, for each ciphertext, the trustee index, share verification key and decryption share of each
+/// trustee that partially decrypted it.
+pub fn decrypt_vote_with<E: EncryptionScheme>(
+    ciphertexts: &[E::Ciphertext],
+    trustees_threshold: u8,
+    shares: &[Vec<(u8, &E::ShareVerificationKey, &E::DecryptShare)>],
+) -> Result<Vec<Selection>, ValidationError> {
+    let mut results = Vec::with_capacity(ciphertexts.len());
+    for (ciphertext, ciphertext_shares) in ciphertexts.iter().zip(shares) {
+        let raw_selection = E::combine(ciphertext, trustees_threshold, ciphertext_shares)?;
 
         let selection = Selection::decode(raw_selection.as_slice())?;
         results.push(selection);
@@ -406,12 +1050,15 @@ pub fn encrypted_vote_from_upstream_tx<S: Store>(
 
             let vote = store.get_vote(upstream_id)?.tx;
 
-            for encrypted_vote in vote.encrypted_votes {
-                if encrypted_vote.contest_index == contest_index {
-                    return Ok(encrypted_vote.selections);
-                }
+            let challenge_id = ChallengeTransaction::build_id(vote.election, vote.id);
+            if store.contains(challenge_id) {
+                return Err(ValidationError::VoteChallenged(vote.id));
             }
-            return Err(ValidationError::InvalidUpstreamContestIndex);
+
+            return vote
+                .get_contest(contest_index)
+                .map(|encrypted_vote| encrypted_vote.selections.clone())
+                .ok_or(ValidationError::InvalidUpstreamContestIndex);
         }
         TransactionType::Mix => {
             let mix = store.get_mix(upstream_id)?.tx;
@@ -474,3 +1121,119 @@ fn build_unique_info(
 
     unique_info
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decrypt_share_serde_roundtrip() {
+        use cryptid::threshold::DecryptShare;
+
+        #[derive(Serialize, Deserialize)]
+        struct HexShare(#[serde(with = "DecryptShareHex")] DecryptShare);
+
+        let txs = crate::tests::test_election_2();
+
+        let partial: PartialDecryptionTransaction = txs
+            .iter()
+            .find(|tx| tx.transaction_type() == TransactionType::PartialDecryption)
+            .unwrap()
+            .clone()
+            .into();
+
+        // JSON round-trip of the whole transaction
+        let json = serde_json::to_string(&partial).unwrap();
+        let roundtrip: PartialDecryptionTransaction = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            partial.partial_decryption.len(),
+            roundtrip.partial_decryption.len()
+        );
+        for (a, b) in partial
+            .partial_decryption
+            .iter()
+            .zip(roundtrip.partial_decryption.iter())
+        {
+            assert_eq!(
+                serde_cbor::to_vec(a).unwrap(),
+                serde_cbor::to_vec(b).unwrap()
+            );
+        }
+
+        // Partial decryptions signed with structured shares still re-serialize to the bytes that were signed
+        let signed: Signed<PartialDecryptionTransaction> = txs
+            .iter()
+            .find(|tx| tx.transaction_type() == TransactionType::PartialDecryption)
+            .unwrap()
+            .clone()
+            .into();
+        signed.verify_signature().unwrap();
+        let json = serde_json::to_string(&signed).unwrap();
+        let roundtrip: Signed<PartialDecryptionTransaction> = serde_json::from_str(&json).unwrap();
+        roundtrip.verify_signature().unwrap();
+
+        // New partial decryptions use hex shares, in both JSON and CBOR
+        let hex_partial = PartialDecryptionTransaction::new(
+            partial.election_id,
+            partial.upstream_id,
+            partial.upstream_index,
+            partial.trustee_index,
+            partial.contest_index,
+            partial.trustee_public_key,
+            partial.partial_decryption.clone(),
+        );
+        let json = serde_json::to_value(&hex_partial).unwrap();
+        assert!(json["partial_decryption"][0].is_string());
+        let roundtrip: PartialDecryptionTransaction = serde_json::from_value(json).unwrap();
+        assert_eq!(roundtrip.as_bytes(), hex_partial.as_bytes());
+        let cbor = hex_partial.as_bytes();
+        let roundtrip: PartialDecryptionTransaction = serde_cbor::from_slice(&cbor).unwrap();
+        assert_eq!(roundtrip.as_bytes(), cbor);
+
+        // Hex round-trip of a single share
+        let share = partial.partial_decryption[0].clone();
+        let json = serde_json::to_string(&HexShare(share.clone())).unwrap();
+        let HexShare(roundtrip) = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            serde_cbor::to_vec(&share).unwrap(),
+            serde_cbor::to_vec(&roundtrip).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_decryption_mismatch() {
+        let txs = crate::tests::test_election_2();
+
+        let (decryption, rest): (Vec<SignedTransaction>, Vec<SignedTransaction>) = txs
+            .into_iter()
+            .partition(|tx| tx.transaction_type() == TransactionType::Decryption);
+        let store = MemStore::from(rest);
+        let decryption: Signed<DecryptionTransaction> = decryption[0].clone().into();
+
+        assert!(decrypted_vote_eq(
+            &decryption.decrypted_vote,
+            &decryption.decrypted_vote.clone()
+        ));
+        decryption.validate(&store).unwrap();
+
+        // A different selection, and a vote with an extra selection, are both rejected
+        let mut changed = decryption.clone();
+        changed.tx.decrypted_vote[0].selection = "Somebody Else".to_string();
+        assert!(!decrypted_vote_eq(
+            &decryption.decrypted_vote,
+            &changed.decrypted_vote
+        ));
+        assert!(matches!(
+            changed.validate(&store),
+            Err(ValidationError::VoteDecryptionMismatch)
+        ));
+
+        let mut extended = decryption.clone();
+        let extra = extended.decrypted_vote[0].clone();
+        extended.tx.decrypted_vote.push(extra);
+        assert!(matches!(
+            extended.validate(&store),
+            Err(ValidationError::VoteDecryptionMismatch)
+        ));
+    }
+}
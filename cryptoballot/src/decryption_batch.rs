@@ -0,0 +1,164 @@
+use crate::*;
+use cryptid::threshold::DecryptShare;
+use ed25519_dalek::PublicKey;
+use std::collections::HashSet;
+
+/// Transaction 14: PartialDecryptionBatch
+///
+/// A trustee's partial decryptions of many ciphertexts in a single transaction. In a large election, posting one
+/// PartialDecryption transaction per vote (or per mixed ciphertext) produces an enormous number of transactions.
+///
+/// Each entry is validated exactly as the equivalent PartialDecryption transaction would be, and a trustee may only
+/// partially decrypt each ciphertext once, whether on its own or in a batch. DecryptionTransaction validation finds
+/// partial decryptions in either form (see `Store::find_partial_decryption`).
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PartialDecryptionBatchTransaction {
+    pub id: Identifier,
+    pub election_id: Identifier,
+    pub trustee_index: u8,
+
+    #[serde(with = "EdPublicKeyHex")]
+    pub trustee_public_key: PublicKey,
+
+    /// Distinguishes a trustee's batches from each other
+    pub batch_index: u16,
+
+    pub decryptions: Vec<BatchedPartialDecryption>,
+
+    /// Optional creation time, in seconds since the UNIX epoch
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<u64>,
+}
+
+/// A single partial decryption within a PartialDecryptionBatch transaction
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BatchedPartialDecryption {
+    /// The upstream transaction ID, either the vote transaction ID or the mix transaction ID
+    pub upstream_id: Identifier,
+
+    /// If this is from a mix, the index of the ciphertext in the `mixed_ciphertexts` field, or `0` if from a vote transaction
+    pub upstream_index: u16,
+
+    /// The contest index this decryption is for
+    pub contest_index: u32,
+
+    pub partial_decryption: Vec<DecryptShare>,
+}
+
+impl PartialDecryptionBatchTransaction {
+    /// Create a new PartialDecryptionBatchTransaction
+    pub fn new(
+        election_id: Identifier,
+        trustee_index: u8,
+        trustee_public_key: PublicKey,
+        batch_index: u16,
+        decryptions: Vec<BatchedPartialDecryption>,
+    ) -> Self {
+        PartialDecryptionBatchTransaction {
+            id: Self::build_id(election_id, trustee_index, batch_index),
+            election_id,
+            trustee_index,
+            trustee_public_key,
+            batch_index,
+            decryptions,
+            created_at: None,
+        }
+    }
+
+    pub fn build_id(election_id: Identifier, trustee_index: u8, batch_index: u16) -> Identifier {
+        let mut unique_info = [0; 16];
+        unique_info[0] = trustee_index;
+        unique_info[1..3].copy_from_slice(&batch_index.to_be_bytes());
+        Identifier::new(
+            election_id,
+            TransactionType::PartialDecryptionBatch,
+            Some(unique_info),
+        )
+    }
+
+    /// Get each entry as the equivalent PartialDecryption transaction
+    pub fn partial_decryptions(&self) -> Vec<PartialDecryptionTransaction> {
+        self.decryptions
+            .iter()
+            .map(|decryption| self.to_partial_decryption(decryption))
+            .collect()
+    }
+
+    /// Get the partial decryption of a single ciphertext, if it is in this batch
+    pub fn get(
+        &self,
+        upstream_id: Identifier,
+        contest_index: u32,
+        upstream_index: u16,
+    ) -> Option<PartialDecryptionTransaction> {
+        self.decryptions
+            .iter()
+            .find(|decryption| {
+                decryption.upstream_id == upstream_id
+                    && decryption.contest_index == contest_index
+                    && decryption.upstream_index == upstream_index
+            })
+            .map(|decryption| self.to_partial_decryption(decryption))
+    }
+
+    fn to_partial_decryption(
+        &self,
+        decryption: &BatchedPartialDecryption,
+    ) -> PartialDecryptionTransaction {
+        let mut partial = PartialDecryptionTransaction::new(
+            self.election_id,
+            decryption.upstream_id,
+            decryption.upstream_index,
+            self.trustee_index,
+            decryption.contest_index,
+            self.trustee_public_key,
+            decryption.partial_decryption.clone(),
+        );
+        partial.created_at = self.created_at;
+        partial
+    }
+}
+
+impl CryptoBallotTransaction for PartialDecryptionBatchTransaction {
+    #[inline(always)]
+    fn id(&self) -> Identifier {
+        self.id
+    }
+
+    #[inline(always)]
+    fn public(&self) -> Option<PublicKey> {
+        Some(self.trustee_public_key)
+    }
+
+    #[inline(always)]
+    fn election_id(&self) -> Identifier {
+        self.election_id
+    }
+
+    #[inline(always)]
+    fn tx_type() -> TransactionType {
+        TransactionType::PartialDecryptionBatch
+    }
+
+    fn validate_tx<S: Store>(&self, store: &S) -> Result<(), ValidationError> {
+        if Self::build_id(self.election_id, self.trustee_index, self.batch_index) != self.id {
+            return Err(ValidationError::IdentifierBadComposition);
+        }
+
+        if self.decryptions.is_empty() {
+            return Err(ValidationError::EmptyPartialDecryptionBatch);
+        }
+
+        // Each entry is validated as its own partial decryption, which also rejects any that were already posted
+        let mut seen = HashSet::with_capacity(self.decryptions.len());
+        for partial in self.partial_decryptions() {
+            if !seen.insert(partial.id) {
+                return Err(ValidationError::DuplicatePartialDecryption(partial.id));
+            }
+            partial.validate_tx(store)?;
+        }
+
+        Ok(())
+    }
+}
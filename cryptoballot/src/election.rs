@@ -1,7 +1,13 @@
 use crate::*;
+use cryptid::curve::CurveElem;
+use cryptid::elgamal::PublicKey as ElGamalPublicKey;
+use cryptid::threshold::PubkeyProof;
+use curve25519_dalek::scalar::Scalar as DalekScalar;
 use ed25519_dalek::PublicKey;
+use ed25519_dalek::SecretKey;
 use indexmap::IndexMap;
 use rand::Rng;
+use std::collections::BTreeMap;
 use uuid::Uuid;
 
 /// Transaction 1: Election
@@ -41,11 +47,81 @@ pub struct ElectionTransaction {
     /// List of contests in this election
     pub contests: Vec<Contest>,
 
+    /// Official observers who receive all transactions but cannot vote or decrypt
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub observers: Vec<Observer>,
+
+    /// If true, every observer must post an ObserverAcknowledgment transaction before votes are accepted
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_false")]
+    pub require_observer_acknowledgments: bool,
+
+    /// If true, every vote must prove that each of its ciphertexts is correctly encrypted (see `EncryptionProof`)
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_false")]
+    pub require_encryption_proofs: bool,
+
+    /// If true, every vote must prove that its closed plurality contests are valid (see `VoteValidityProof`)
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_false")]
+    pub require_vote_validity_proofs: bool,
+
+    /// If true, trustees may revoke and replace their KeyGenPublicKey transaction before the EncryptionKey transaction is posted
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_false")]
+    pub revocations_allowed: bool,
+
+    /// The largest weight a vote may carry (eg. for shareholder votes), votes are unweighted if not set.
+    ///
+    /// Each vote's weight is certified by its authenticators (see `VoteTransaction::weight`). Weighted votes can't be
+    /// mixed, since a mix unlinks each decrypted vote from the vote its weight was certified on.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_vote_weight: Option<u32>,
+
+    /// Optional limit on how many votes each authenticator may authenticate within a window of time, enforced by
+    /// `RateLimitingStore`
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auth_rate_limit: Option<RateLimit>,
+
     /// Application specific properties.
     ///
     /// Hashmaps are not allowed because their unstable ordering leads to non-determinism.
     #[serde(default)]
     pub properties: IndexMap<String, serde_json::Value>,
+
+    /// Human readable description of who authorized this election (eg "Springfield County Board of Elections")
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signing_authority_description: Option<String>,
+
+    /// URL where the authority's public key and description can be confirmed. Must be an `https://` URL.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signing_authority_url: Option<String>,
+
+    /// True if this is a real (production) election, as opposed to a test or demo election.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_false")]
+    pub is_production: bool,
+
+    /// The group that votes are encrypted in. Votes and partial decryptions with ciphertexts from any other group are rejected.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "CryptoParams::is_default")]
+    pub crypto_params: CryptoParams,
+
+    /// Time after which partial decryptions are no longer accepted, in seconds since the UNIX epoch, enforced by
+    /// `DeadlineStore` against when each partial decryption is received
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decryption_deadline: Option<u64>,
+
+    /// Optional creation time, in seconds since the UNIX epoch
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<u64>,
 }
 
 impl ElectionTransaction {
@@ -65,7 +141,20 @@ impl ElectionTransaction {
             mix_config: None,
             ballots: vec![],
             contests: vec![],
+            observers: vec![],
+            require_observer_acknowledgments: false,
+            require_encryption_proofs: false,
+            require_vote_validity_proofs: false,
+            revocations_allowed: false,
+            max_vote_weight: None,
+            auth_rate_limit: None,
             properties: IndexMap::new(),
+            signing_authority_description: None,
+            signing_authority_url: None,
+            is_production: false,
+            crypto_params: CryptoParams::default(),
+            decryption_deadline: None,
+            created_at: None,
         }
     }
 
@@ -88,6 +177,13 @@ impl ElectionTransaction {
         None
     }
 
+    /// Get an observer with the given ID
+    pub fn get_observer(&self, observer_id: Uuid) -> Option<&Observer> {
+        self.observers
+            .iter()
+            .find(|observer| observer.id == observer_id)
+    }
+
     /// Get a trustee with the given ID
     pub fn get_trustee(&self, trustee_index: u8) -> Option<&Trustee> {
         for trustee in self.trustees.iter() {
@@ -98,6 +194,43 @@ impl ElectionTransaction {
         None
     }
 
+    /// Get a trustee by their stable ID (see `Trustee::id`)
+    pub fn get_trustee_by_id(&self, trustee_id: Uuid) -> Option<&Trustee> {
+        self.trustees
+            .iter()
+            .find(|trustee| !trustee.id.is_nil() && trustee.id == trustee_id)
+    }
+
+    /// Add a trustee, such as one created with `Trustee::from_public_key`, returning their index.
+    ///
+    /// A trustee without an index (zero) is given the index after the highest one in the election. Fails if that
+    /// would be past 255, the highest possible index.
+    pub fn add_trustee(&mut self, mut trustee: Trustee) -> Result<u8, ValidationError> {
+        if trustee.index == 0 {
+            let max_index = self.trustees.iter().map(|t| t.index).max().unwrap_or(0);
+            trustee.index = max_index
+                .checked_add(1)
+                .ok_or(ValidationError::InvalidTrusteeIndex(max_index))?;
+        }
+        let index = trustee.index;
+        self.trustees.push(trustee);
+        Ok(index)
+    }
+
+    /// Get a contest by its index. Votes, partial decryptions and decryptions all refer to contests by index.
+    pub fn get_contest(&self, contest_index: u32) -> Option<&Contest> {
+        self.contests
+            .iter()
+            .find(|contest| contest.index == contest_index)
+    }
+
+    /// Get a contest by its ID
+    pub fn get_contest_by_id(&self, contest_id: &str) -> Option<&Contest> {
+        self.contests
+            .iter()
+            .find(|contest| contest.id == contest_id)
+    }
+
     pub fn get_ballot(&self, ballot_id: &str) -> Option<&Ballot> {
         for ballot in &self.ballots {
             if ballot.id == ballot_id {
@@ -107,6 +240,79 @@ impl ElectionTransaction {
         None
     }
 
+    /// Issue a receipt for a recorded vote, signed with the election authority's secret key.
+    ///
+    /// The receipt will only verify if `authority_secret` is the secret key for this election's `authority_public`.
+    pub fn issue_receipt(
+        &self,
+        vote: &Signed<VoteTransaction>,
+        authority_secret: &SecretKey,
+    ) -> VoteReceipt {
+        VoteReceipt::issue(vote, authority_secret)
+    }
+
+    /// Derive the election encryption key from the trustees' KeyGenPublicKey transactions.
+    ///
+    /// Each transaction carries its trustee's public share of the election key, `g^x_i`, as its `public_key_proof`.
+    /// The election key `g^x` is interpolated in the exponent from the shares of the `trustees_threshold` trustees
+    /// with the lowest indexes, so it is derived from the trustees' own contributions instead of being trusted as
+    /// posted. This lets a verifier confirm that the key in the EncryptionKey transaction is the product of the
+    /// distributed key generation, and not a key that the election authority secretly controls. The joint key each
+    /// trustee computed for itself must match the derived key.
+    pub fn derive_election_public_key(
+        &self,
+        pubkeys: &[KeyGenPublicKeyTransaction],
+    ) -> Result<ElGamalPublicKey, ValidationError> {
+        let mut shares = BTreeMap::new();
+        for tx in pubkeys {
+            if self.get_trustee(tx.trustee_index).is_none() {
+                return Err(ValidationError::TrusteeDoesNotExist(tx.trustee_index));
+            }
+            if shares
+                .insert(tx.trustee_index, &tx.public_key_proof)
+                .is_some()
+            {
+                return Err(ValidationError::InvalidTrusteeIndex(tx.trustee_index));
+            }
+        }
+
+        let threshold = self.trustees_threshold as usize;
+        if threshold == 0 || shares.len() < threshold {
+            return Err(ValidationError::WrongNumberOfPublicKeyTransactions);
+        }
+        let indexes: Vec<u8> = shares.keys().take(threshold).copied().collect();
+
+        let mut derived: Option<CurveElem> = None;
+        for index in indexes.iter() {
+            let share = public_key_share(*index, shares[index])?;
+            let term = share.scaled(&to_cryptid_scalar(&lagrange_coefficient(*index, &indexes)));
+            derived = Some(match derived {
+                Some(derived) => derived + term,
+                None => term,
+            });
+        }
+        let derived = election_public_key(derived.expect("threshold is at least 1"))?;
+
+        for tx in pubkeys {
+            if tx.public_key != derived {
+                return Err(ValidationError::MismatchedEncryptionKey(tx.trustee_index));
+            }
+        }
+
+        Ok(derived)
+    }
+
+    /// Get non-fatal validation warnings for this election
+    pub fn validation_warnings(&self) -> Vec<ValidationWarning> {
+        let mut warnings = Vec::new();
+
+        if self.is_production && self.signing_authority_description.is_none() {
+            warnings.push(ValidationWarning::MissingAuthorityDescription);
+        }
+
+        warnings
+    }
+
     /// Get all trustees with all info
     pub fn get_full_trustees(&self) -> Vec<Trustee> {
         let mut trustees = Vec::with_capacity(self.trustees.len());
@@ -120,6 +326,12 @@ impl ElectionTransaction {
     }
 }
 
+impl AuthorityTransaction for ElectionTransaction {
+    fn set_authority_public(&mut self, public: PublicKey) {
+        self.authority_public = public;
+    }
+}
+
 impl CryptoBallotTransaction for ElectionTransaction {
     #[inline(always)]
     fn id(&self) -> Identifier {
@@ -147,9 +359,50 @@ impl CryptoBallotTransaction for ElectionTransaction {
             return Err(ValidationError::IdentifierBadComposition);
         }
 
-        // Make sure trustees settings are sane
-        if self.trustees_threshold as usize > self.trustees.len() {
-            return Err(ValidationError::InvalidTrusteeThreshold);
+        // Make sure the signing authority URL is well-formed
+        if let Some(url) = &self.signing_authority_url {
+            if !url.starts_with("https://") || url.len() <= "https://".len() {
+                return Err(ValidationError::InvalidAuthorityUrl(url.clone()));
+            }
+        }
+
+        // Make sure trustees settings are sane. A threshold of zero would make any decryption valid, and one above the
+        // number of trustees would make decryption impossible. Together these also require at least one trustee.
+        if self.trustees_threshold == 0 || self.trustees_threshold as usize > self.trustees.len() {
+            return Err(ValidationError::InvalidThreshold(
+                self.trustees_threshold,
+                self.trustees.len(),
+            ));
+        }
+
+        // Trustee indices are used as secret-share x-coordinates and written into IDs as a single byte, so they must
+        // be unique and run from 1 to the number of trustees. This also caps an election at 255 trustees.
+        for (i, trustee) in self.trustees.iter().enumerate() {
+            let out_of_range = trustee.index == 0 || trustee.index as usize > self.trustees.len();
+            let duplicate = self.trustees[..i]
+                .iter()
+                .any(|other| other.index == trustee.index);
+            if out_of_range || duplicate {
+                return Err(ValidationError::InvalidTrusteeIndex(trustee.index));
+            }
+        }
+
+        // Make sure mixnet settings are sane
+        if let Some(mix_config) = &self.mix_config {
+            mix_config.validate(self.trustees_threshold)?;
+        }
+
+        // Make sure vote weight settings are sane
+        if let Some(max_vote_weight) = self.max_vote_weight {
+            if max_vote_weight == 0 {
+                return Err(ValidationError::InvalidVoteWeight);
+            }
+            if self.mix_config.is_some() {
+                return Err(ValidationError::WeightedVotesMixed);
+            }
+            if self.authenticators_threshold == 0 {
+                return Err(ValidationError::WeightedVotesUnauthenticated);
+            }
         }
 
         // Make sure authenticator settings are sane
@@ -157,24 +410,74 @@ impl CryptoBallotTransaction for ElectionTransaction {
             return Err(ValidationError::InvalidAuthThreshold);
         }
 
+        // Unauthenticated votes aren't counted against any authenticator, so they would get around the rate limit
+        if self.auth_rate_limit.is_some() && self.authenticators_threshold == 0 {
+            return Err(ValidationError::RateLimitUnauthenticated);
+        }
+
+        // Make sure authenticator keys are well-formed
+        let min_key_bits = if self.is_production {
+            MIN_PRODUCTION_AUTHENTICATOR_KEY_BITS
+        } else {
+            MIN_AUTHENTICATOR_KEY_BITS
+        };
+        for authenticator in self.authenticators.iter() {
+            authenticator.validate_keys(min_key_bits)?;
+        }
+
+        // Contests are looked up by both ID and index, so both must be unique
+        for (i, contest) in self.contests.iter().enumerate() {
+            let earlier = &self.contests[..i];
+            if earlier.iter().any(|other| other.id == contest.id) {
+                return Err(ValidationError::DuplicateContestId(contest.id.clone()));
+            }
+            if earlier.iter().any(|other| other.index == contest.index) {
+                return Err(ValidationError::DuplicateContestIndex(contest.index));
+            }
+        }
+
         // TODO: Make sure the encryption public-key is well-formed
         // TODO: check parsing of public key
-        // TODO: check that we have at least 1 trustee
-        // TODO: Hard Maximum of 255 trustees (index needs to fit in a non-zero u8)
         // TODO: Sanity check ballot-ids in authenticators
-        // TODO: MixConfig validation: non-zero on all three params
         // TODO: Check that properties do not contain hashmaps (due to unstable ordering) (including in ballots, contests, and candidates)
         // TODO: Check that ballots and contests are consistent and well formed
         //       All contests must exist in at least one ballot
         //       All ballot contests must exist
-        //       All contest and ballots have a unique ID
-        //       All contests have a unique index
+        //       All ballots have a unique ID
         //       All candidates have a unique index within their contest
 
         Ok(())
     }
 }
 
+// The Lagrange coefficient of the trustee at `index` for interpolating, at zero, the polynomial through `indexes`
+fn lagrange_coefficient(index: u8, indexes: &[u8]) -> DalekScalar {
+    let i = DalekScalar::from(index as u64);
+    indexes
+        .iter()
+        .filter(|j| **j != index)
+        .fold(DalekScalar::one(), |coefficient, j| {
+            let j = DalekScalar::from(*j as u64);
+            coefficient * j * (j - i).invert()
+        })
+}
+
+// cryptid doesn't expose the group elements inside its key types, so they are read and built through their serialized
+// forms: a PubkeyProof serializes as just the trustee's public key share, and a PublicKey as `{"y": <element>}`.
+pub(crate) fn public_key_share(
+    trustee_index: u8,
+    proof: &PubkeyProof,
+) -> Result<CurveElem, ValidationError> {
+    serde_json::to_value(proof)
+        .and_then(serde_json::from_value)
+        .map_err(|_| ValidationError::InvalidPublicKeyShare(trustee_index))
+}
+
+fn election_public_key(y: CurveElem) -> Result<ElGamalPublicKey, ValidationError> {
+    serde_json::from_value(serde_json::json!({ "y": y }))
+        .map_err(|_| ValidationError::InvalidPublicKey)
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -204,6 +507,7 @@ mod tests {
             num_winners: 1,
             write_in: true,
             candidates: vec![],
+            question: None,
             properties: IndexMap::new(),
         };
 
@@ -258,5 +562,149 @@ mod tests {
         assert!(election.get_authenticator(some_uuid).is_none());
         assert!(election.get_trustee(0).is_none());
         assert!(election.get_trustee(2).is_none());
+        assert!(election.get_contest(1).is_none());
+        assert!(election.get_contest_by_id("NOPE").is_none());
+    }
+
+    #[test]
+    fn contests_are_unique() {
+        let store = MemStore::default();
+        let (_authority_secret, authority_public) = generate_keypair();
+        let (trustee, _trustee_secret) = Trustee::new(1, 1, 1);
+
+        let contest = |id: &str, index: u32| Contest {
+            id: id.to_string(),
+            index,
+            contest_type: ContestType::Plurality,
+            num_winners: 1,
+            write_in: true,
+            candidates: vec![],
+            question: None,
+            properties: IndexMap::new(),
+        };
+
+        let mut election = ElectionTransaction::new(authority_public);
+        election.trustees = vec![trustee];
+        election.authenticators_threshold = 0;
+        election.contests = vec![contest("MAYOR", 0), contest("COUNCIL", 1)];
+        election.validate_tx(&store).unwrap();
+        assert_eq!(election.get_contest(1).unwrap().id, "COUNCIL");
+        assert_eq!(election.get_contest_by_id("MAYOR").unwrap().index, 0);
+
+        election.contests.push(contest("MAYOR", 2));
+        assert!(matches!(
+            election.validate_tx(&store),
+            Err(ValidationError::DuplicateContestId(id)) if id == "MAYOR"
+        ));
+
+        election.contests[2] = contest("SCHOOL BOARD", 1);
+        assert!(matches!(
+            election.validate_tx(&store),
+            Err(ValidationError::DuplicateContestIndex(1))
+        ));
+    }
+
+    #[test]
+    fn trustee_indices_are_contiguous() {
+        let store = MemStore::default();
+        let (_authority_secret, authority_public) = generate_keypair();
+        let trustee = |index: u8| Trustee::new(index, 3, 2).0;
+
+        let mut election = ElectionTransaction::new(authority_public);
+        election.authenticators_threshold = 0;
+        election.trustees_threshold = 2;
+        election.trustees = vec![trustee(2), trustee(3), trustee(1)];
+        election.validate_tx(&store).unwrap();
+
+        election.trustees[2] = trustee(0);
+        assert!(matches!(
+            election.validate_tx(&store),
+            Err(ValidationError::InvalidTrusteeIndex(0))
+        ));
+
+        election.trustees[2] = trustee(4);
+        assert!(matches!(
+            election.validate_tx(&store),
+            Err(ValidationError::InvalidTrusteeIndex(4))
+        ));
+
+        election.trustees[2] = trustee(2);
+        assert!(matches!(
+            election.validate_tx(&store),
+            Err(ValidationError::InvalidTrusteeIndex(2))
+        ));
+    }
+
+    #[test]
+    fn add_trustee_assigns_next_index() {
+        let (_authority_secret, authority_public) = generate_keypair();
+        let mut election = ElectionTransaction::new(authority_public);
+
+        let (registered, _) = Trustee::register(uuid::Uuid::new_v4());
+        assert_eq!(election.add_trustee(registered.clone()).unwrap(), 1);
+        assert_eq!(
+            election.add_trustee(Trustee::new(255, 3, 2).0).unwrap(),
+            255
+        );
+
+        // There is no index after 255
+        assert!(matches!(
+            election.add_trustee(registered),
+            Err(ValidationError::InvalidTrusteeIndex(255))
+        ));
+        assert_eq!(election.trustees.len(), 2);
+    }
+
+    #[test]
+    fn trustees_threshold_bounds() {
+        let store = MemStore::default();
+        let (_authority_secret, authority_public) = generate_keypair();
+
+        let mut election = ElectionTransaction::new(authority_public);
+        election.authenticators_threshold = 0;
+
+        // No trustees at all
+        assert!(matches!(
+            election.validate_tx(&store),
+            Err(ValidationError::InvalidThreshold(1, 0))
+        ));
+
+        election.trustees = (1..=3).map(|index| Trustee::new(index, 3, 2).0).collect();
+
+        election.trustees_threshold = 0;
+        assert!(matches!(
+            election.validate_tx(&store),
+            Err(ValidationError::InvalidThreshold(0, 3))
+        ));
+
+        election.trustees_threshold = 1;
+        election.validate_tx(&store).unwrap();
+
+        election.trustees_threshold = 3;
+        election.validate_tx(&store).unwrap();
+
+        election.trustees_threshold = 4;
+        assert!(matches!(
+            election.validate_tx(&store),
+            Err(ValidationError::InvalidThreshold(4, 3))
+        ));
+    }
+
+    #[test]
+    fn crypto_params_default() {
+        let (_authority_secret, authority_public) = generate_keypair();
+        let election = ElectionTransaction::new(authority_public);
+        assert_eq!(election.crypto_params, CryptoParams::Ristretto255);
+
+        // Elections using the default group serialize as they did before crypto_params was added
+        let mut json = serde_json::to_value(&election).unwrap();
+        assert!(json.get("crypto_params").is_none());
+
+        json["crypto_params"] = "ristretto255".into();
+        let parsed: ElectionTransaction = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(parsed.crypto_params, CryptoParams::Ristretto255);
+
+        json["crypto_params"] = "secp256k1".into();
+        assert!(serde_json::from_value::<ElectionTransaction>(json).is_err());
     }
 }
@@ -0,0 +1,236 @@
+use crate::*;
+use cryptid::curve::CurveElem;
+use cryptid::elgamal::{Ciphertext, PublicKey as EncryptionPublicKey};
+use curve25519_dalek::scalar::Scalar as DalekScalar;
+use ed25519_dalek::PublicKey;
+use prost::Message;
+use rand::{CryptoRng, RngCore};
+use rand_chacha::rand_core::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use sha2::{Digest, Sha512};
+use std::convert::TryFrom;
+
+/// A non-interactive Schnorr proof of knowledge of the randomness in an ElGamal ciphertext.
+///
+/// The voter proves knowledge of the randomness `r` in the ciphertext's first component `c1 = g^r`, so a voter can't
+/// copy another voter's ciphertext without knowing its `r`. The proof says nothing about the second component: it
+/// doesn't show that the ciphertext encrypts a well-formed selection, which `VoteValidityProof` shows for closed
+/// plurality contests, and is otherwise only checked once the vote is decrypted. The Fiat-Shamir challenge binds the
+/// proof to the election key, the vote's anonymous key and the ciphertext's position in the vote.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CiphertextProof {
+    /// g^w for the prover's random nonce w
+    pub commitment: CurveElem,
+
+    /// w + c·r, where c is the Fiat-Shamir challenge
+    #[serde(with = "hex_serde")]
+    pub response: [u8; 32],
+}
+
+/// Proofs of knowledge of the randomness in every ciphertext in a vote.
+///
+/// There is one list of proofs for each `EncryptedVote` in `VoteTransaction::encrypted_votes`, in the same order,
+/// with one proof per selection.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct EncryptionProof(pub Vec<Vec<CiphertextProof>>);
+
+impl EncryptionProof {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Verify the proof for every ciphertext in the vote against the election's encryption key
+    pub fn verify(
+        &self,
+        vote: &VoteTransaction,
+        encryption_key: &EncryptionPublicKey,
+    ) -> Result<(), ValidationError> {
+        if self.0.len() != vote.encrypted_votes.len() {
+            return Err(ValidationError::MissingEncryptionProof);
+        }
+
+        for (encrypted_vote, proofs) in vote.encrypted_votes.iter().zip(self.0.iter()) {
+            let contest_index = encrypted_vote.contest_index;
+            if proofs.len() != encrypted_vote.selections.len() {
+                return Err(ValidationError::InvalidEncryptionProof(contest_index));
+            }
+
+            for (selection_index, (ciphertext, proof)) in encrypted_vote
+                .selections
+                .iter()
+                .zip(proofs.iter())
+                .enumerate()
+            {
+                let context = ProofContext {
+                    election_id: vote.election,
+                    anonymous_key: &vote.anonymous_key,
+                    contest_index,
+                    selection_index,
+                };
+                if !proof.verify(encryption_key, ciphertext, &context) {
+                    return Err(ValidationError::InvalidEncryptionProof(contest_index));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl CiphertextProof {
+    // Prove knowledge of `r`, the randomness the ciphertext was encrypted with, drawing the nonce from `rng`
+    fn prove<R: CryptoRng + RngCore>(
+        encryption_key: &EncryptionPublicKey,
+        ciphertext: &Ciphertext,
+        r: &DalekScalar,
+        context: &ProofContext,
+        rng: &mut R,
+    ) -> Self {
+        let w = DalekScalar::random(rng);
+        let commitment = CurveElem::generator().scaled(&to_cryptid_scalar(&w));
+        let challenge = challenge(encryption_key, ciphertext, &commitment, context);
+        let response = w + challenge * r;
+
+        CiphertextProof {
+            commitment,
+            response: response.to_bytes(),
+        }
+    }
+
+    // Check that g^response == commitment · c1^challenge
+    fn verify(
+        &self,
+        encryption_key: &EncryptionPublicKey,
+        ciphertext: &Ciphertext,
+        context: &ProofContext,
+    ) -> bool {
+        let response = match DalekScalar::from_canonical_bytes(self.response) {
+            Some(response) => response,
+            None => return false,
+        };
+        let challenge = challenge(encryption_key, ciphertext, &self.commitment, context);
+
+        let lhs = CurveElem::generator().scaled(&to_cryptid_scalar(&response));
+        let rhs = self.commitment.clone() + ciphertext.c1.scaled(&to_cryptid_scalar(&challenge));
+
+        lhs == rhs
+    }
+}
+
+/// Encrypt every contest in a vote with randomness drawn from `seed`, with a proof of knowledge of the randomness in
+/// each ciphertext.
+///
+/// The encrypted votes are exactly those that `encrypt_vote_with_seed` produces for the same plaintext and seed, so
+/// the vote can still be challenged by revealing the seed (see `ChallengeTransaction`). Only the proofs' nonces are
+/// drawn from `rng`. The proofs are bound to the vote's anonymous key, so it must be generated before the vote is
+/// encrypted. The returned encrypted votes and proof go in the vote's `encrypted_votes` and `encryption_proof`.
+pub fn encrypt_vote_with_proof<R: CryptoRng + RngCore>(
+    encryption_key: &EncryptionPublicKey,
+    election_id: Identifier,
+    anonymous_key: &PublicKey,
+    votes: &[PlaintextVote],
+    seed: [u8; 32],
+    rng: &mut R,
+) -> Result<(Vec<EncryptedVote>, EncryptionProof), Error> {
+    let (encrypted_votes, encryption_proof, _) =
+        encrypt_vote_with_randomness(encryption_key, election_id, anonymous_key, votes, seed, rng)?;
+
+    Ok((encrypted_votes, encryption_proof))
+}
+
+// As `encrypt_vote_with_proof`, also returning the randomness each selection was encrypted with
+pub(crate) fn encrypt_vote_with_randomness<R: CryptoRng + RngCore>(
+    encryption_key: &EncryptionPublicKey,
+    election_id: Identifier,
+    anonymous_key: &PublicKey,
+    votes: &[PlaintextVote],
+    seed: [u8; 32],
+    rng: &mut R,
+) -> Result<(Vec<EncryptedVote>, EncryptionProof, Vec<Vec<DalekScalar>>), Error> {
+    let mut seed_rng = ChaCha20Rng::from_seed(seed);
+    let mut encrypted_votes = Vec::with_capacity(votes.len());
+    let mut encryption_proof = EncryptionProof::default();
+    let mut randomness = Vec::with_capacity(votes.len());
+    for vote in votes {
+        let mut selections = Vec::with_capacity(vote.selections.len());
+        let mut proofs = Vec::with_capacity(vote.selections.len());
+        let mut selection_randomness = Vec::with_capacity(vote.selections.len());
+        for (selection_index, selection) in vote.selections.iter().enumerate() {
+            let (ciphertext, r) =
+                encrypt_selection_with_seed(encryption_key, selection, &mut seed_rng)?;
+
+            let context = ProofContext {
+                election_id,
+                anonymous_key,
+                contest_index: vote.contest_index,
+                selection_index,
+            };
+            proofs.push(CiphertextProof::prove(
+                encryption_key,
+                &ciphertext,
+                &r,
+                &context,
+                rng,
+            ));
+            selections.push(ciphertext);
+            selection_randomness.push(r);
+        }
+
+        encrypted_votes.push(EncryptedVote {
+            contest_index: vote.contest_index,
+            selections,
+        });
+        encryption_proof.0.push(proofs);
+        randomness.push(selection_randomness);
+    }
+
+    Ok((encrypted_votes, encryption_proof, randomness))
+}
+
+// Encrypt a selection with randomness drawn from `seed_rng`, returning the randomness along with the ciphertext
+pub(crate) fn encrypt_selection_with_seed(
+    encryption_key: &EncryptionPublicKey,
+    selection: &Selection,
+    seed_rng: &mut ChaCha20Rng,
+) -> Result<(Ciphertext, DalekScalar), Error> {
+    let mut buf = Vec::with_capacity(selection.encoded_len());
+    selection.encode(&mut buf)?;
+
+    let r = DalekScalar::random(seed_rng);
+    let ciphertext = encryption_key.encrypt_with_r(&buf, &to_cryptid_scalar(&r));
+
+    Ok((ciphertext, r))
+}
+
+// Where a ciphertext sits, which the proof is bound to so that it can't be replayed elsewhere
+struct ProofContext<'a> {
+    election_id: Identifier,
+    anonymous_key: &'a PublicKey,
+    contest_index: u32,
+    selection_index: usize,
+}
+
+fn challenge(
+    encryption_key: &EncryptionPublicKey,
+    ciphertext: &Ciphertext,
+    commitment: &CurveElem,
+    context: &ProofContext,
+) -> DalekScalar {
+    // Unwraps OK - these are all plain serializable values
+    let mut hasher = Sha512::new();
+    hasher.update(b"cryptoballot_encryption_proof");
+    hasher.update(context.election_id.to_array());
+    hasher.update(context.anonymous_key.as_bytes());
+    hasher.update(context.contest_index.to_le_bytes());
+    hasher.update((context.selection_index as u64).to_le_bytes());
+    hasher.update(serde_cbor::to_vec(encryption_key).unwrap());
+    hasher.update(serde_cbor::to_vec(ciphertext).unwrap());
+    hasher.update(serde_cbor::to_vec(commitment).unwrap());
+
+    DalekScalar::from_hash(hasher)
+}
+
+pub(crate) fn to_cryptid_scalar(scalar: &DalekScalar) -> cryptid::Scalar {
+    // Unwrap OK - a reduced dalek scalar is always a canonical cryptid scalar
+    cryptid::Scalar::try_from(scalar.to_bytes().to_vec()).unwrap()
+}
@@ -23,9 +23,15 @@ pub enum Error {
     #[error("cryptoballot: invalid identifier - wrong length")]
     IdentifierBadLen,
 
+    #[error("cryptoballot: invalid identifier - unknown transaction type {0}")]
+    IdentifierBadType(u8),
+
     #[error("cryptoballot: CBOR error deserializing transaction: {0}")]
     CBORDeserialization(#[from] serde_cbor::Error),
 
+    #[error("cryptoballot: MessagePack error deserializing transaction: {0}")]
+    MessagePackDeserialization(#[from] rmp_serde::decode::Error),
+
     #[error("cryptoballot: JSON error deserializing transaction: {0}")]
     JSONDeserialization(#[from] serde_json::Error),
 
@@ -58,6 +64,130 @@ pub enum Error {
 
     #[error("cryptoballot: could not encode vote selection: {0}")]
     VoteEncodingError(#[from] prost::EncodeError),
+
+    #[error(
+        "cryptoballot: contest {0} must select distinct candidates it declares, with no score and the vote's weight"
+    )]
+    InvalidPluralitySelection(u32),
+
+    #[error("cryptoballot: tie between {}", .0.join(", "))]
+    Tie(Vec<String>),
+
+    #[error("cryptoballot: unknown tie-break strategy {0}")]
+    UnknownTieBreak(String),
+}
+
+/// Errors building or verifying an `AuditPackage`
+#[derive(Debug, Error)]
+pub enum AuditError {
+    #[error("cryptoballot audit: {0}")]
+    TransactionNotFound(#[from] TransactionNotFound),
+
+    #[error("cryptoballot audit: {0}")]
+    ValidationError(#[from] ValidationError),
+
+    #[error("cryptoballot audit: votes in an election using a mixnet cannot be traced to their decryption")]
+    MixnetElection,
+
+    #[error("cryptoballot audit: vote {0} has not been decrypted for contest {1}")]
+    NotDecrypted(Identifier, u32),
+
+    #[error("cryptoballot audit: transaction {0} does not belong to the audited election or vote")]
+    Mismatch(Identifier),
+
+    #[error("cryptoballot audit: audit package is not signed by the election authority")]
+    InvalidSignature,
+}
+
+/// Invalid election configuration
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("cryptoballot: mixnet must have at least one shuffle (trustees_threshold is 0)")]
+    NoShuffles,
+
+    #[error("cryptoballot: mixnet has {0} shuffles, more than the maximum of 10")]
+    TooManyShuffles(u8),
+
+    #[error("cryptoballot: mixnet batch_size must not be 0")]
+    ZeroBatchSize,
+}
+
+/// Errors verifying a `VoteReceipt`
+#[derive(Debug, Error)]
+pub enum ReceiptError {
+    #[error("cryptoballot: vote receipt is not signed by the election authority")]
+    InvalidSignature,
+
+    #[error("cryptoballot: vote receipt is not for vote {0}")]
+    WrongVote(Identifier),
+
+    #[error("cryptoballot: recorded vote does not match the vote receipt")]
+    CommitmentMismatch,
+}
+
+/// Errors re-signing a transaction with `Signed::reauthorize`
+#[derive(Debug, Error)]
+pub enum ReauthorizeError {
+    #[error("cryptoballot: cannot reauthorize a transaction whose signature is invalid: {0}")]
+    InvalidSignature(#[from] ValidationError),
+
+    #[error("cryptoballot: old secret key is not the transaction's authority key")]
+    WrongAuthorityKey,
+
+    #[error("cryptoballot: new secret key does not match the new public key")]
+    MismatchedPublicKeys,
+
+    #[error("{0}")]
+    Error(#[from] Error),
+}
+
+/// Errors validating or committing a `TransactionBatch`
+#[derive(Debug, Error)]
+pub enum BatchError {
+    #[error("cryptoballot: transaction {0} appears more than once in the batch")]
+    DuplicateTransaction(Identifier),
+
+    #[error("cryptoballot: batch transaction {0} is invalid: {1}")]
+    InvalidTransaction(Identifier, ValidationError),
+
+    #[error("cryptoballot: failed to store batch transaction {0}: {1}")]
+    ApplyFailed(Identifier, ApplyError),
+}
+
+/// Errors checking an election with `verify_election_integrity`
+#[derive(Debug, Error)]
+pub enum IntegrityError {
+    #[error("cryptoballot: cannot verify election integrity: {0}")]
+    TransactionNotFound(#[from] TransactionNotFound),
+}
+
+/// Errors summarizing an election with `summarize_election`
+#[derive(Debug, Error)]
+pub enum SummaryError {
+    #[error("cryptoballot: cannot summarize election: {0}")]
+    TransactionNotFound(#[from] TransactionNotFound),
+}
+
+/// Errors casting a vote with `cast_vote`
+#[derive(Debug, Error)]
+pub enum CastVoteError {
+    #[error("cryptoballot: ballot {0} does not exist in this election")]
+    BallotDoesNotExist(String),
+
+    #[error("cryptoballot: authenticator {0} has no public key for this ballot")]
+    MissingAuthenticatorKey(uuid::Uuid),
+
+    #[error("cryptoballot: not enough authentications: need {0}, have {1}")]
+    NotEnoughAuthentications(usize, usize),
+
+    #[error("{0}")]
+    TransactionNotFound(#[from] TransactionNotFound),
+
+    #[error("{0}")]
+    Error(#[from] Error),
+
+    #[error("{0}")]
+    ValidationError(#[from] ValidationError),
 }
 
 /// Transaction Validation errors
@@ -75,8 +205,17 @@ pub enum ValidationError {
     #[error("cryptoballot validation: mismatched encryption_key for trustee {0})")]
     MismatchedEncryptionKey(u8),
 
-    #[error("cryptoballot validation: threshold is invalid for number of trustees")]
-    InvalidTrusteeThreshold,
+    #[error("cryptoballot validation: encryption_key does not match key derived from keygen_public_key transactions")]
+    EncryptionKeyDerivationMismatch,
+
+    #[error("cryptoballot validation: invalid signing authority url {0}, must be an https:// url")]
+    InvalidAuthorityUrl(String),
+
+    #[error("cryptoballot validation: trustee threshold {0} is invalid for {1} trustees")]
+    InvalidThreshold(u8, usize),
+
+    #[error("cryptoballot validation: trustee index {0} is out of range or duplicated")]
+    InvalidTrusteeIndex(u8),
 
     #[error("cryptoballot validation: threshold is invalid for number of authenticators")]
     InvalidAuthThreshold,
@@ -84,6 +223,9 @@ pub enum ValidationError {
     #[error("cryptoballot validation: invalid public key")]
     InvalidPublicKey,
 
+    #[error("cryptoballot validation: invalid public key share for trustee {0}")]
+    InvalidPublicKeyShare(u8),
+
     #[error("cryptoballot validation: election mismatch")]
     ElectionMismatch,
 
@@ -98,12 +240,81 @@ pub enum ValidationError {
     )]
     TrusteeDoesNotExist(u8),
 
+    #[error("cryptoballot validation: trustee absence record is wrong for trustee {0}")]
+    TrusteeAbsenceMismatch(u8),
+
+    #[error("cryptoballot validation: observer {0} does not exist in election")]
+    ObserverDoesNotExist(uuid::Uuid),
+
+    #[error("cryptoballot validation: observer public key mismatch for observer {0}")]
+    ObserverPublicKeyMismatch(uuid::Uuid),
+
+    #[error(
+        "cryptoballot validation: missing observer_acknowledgment transaction for observer {0}"
+    )]
+    MissingObserverAcknowledgment(uuid::Uuid),
+
+    #[error("cryptoballot validation: election does not allow trustee key revocations")]
+    RevocationsNotAllowed,
+
+    #[error("cryptoballot validation: revoked public key does not match trustee {0}'s keygen_public_key transaction")]
+    RevokedKeyMismatch(u8),
+
+    #[error("cryptoballot validation: trustee keys cannot be revoked or replaced after the encryption_key transaction")]
+    RevocationAfterEncryptionKey,
+
+    #[error("cryptoballot validation: trustee {0} has been revoked")]
+    TrusteeRevoked(u8),
+
+    #[error("cryptoballot validation: not enough active trustees: need {0}, found {1}")]
+    NotEnoughActiveTrustees(usize, usize),
+
+    #[error("cryptoballot validation: trustee {0} has already posted partial decryptions, so it cannot be revoked")]
+    TrusteeAlreadyDecrypted(u8),
+
+    #[error("cryptoballot validation: trustee {0} has not revoked their previous keygen_public_key transaction")]
+    KeyGenPublicKeyNotRevoked(u8),
+
+    #[error("cryptoballot validation: trustee {0} has not replaced their revoked keygen_public_key transaction")]
+    RevokedKeyNotReplaced(u8),
+
+    #[error("cryptoballot validation: trustee {0} has not been revoked")]
+    TrusteeNotRevoked(u8),
+
+    #[error(
+        "cryptoballot validation: trustee {0} has not re-shared revoked trustee {1}'s key share"
+    )]
+    MissingTrusteeReshare(u8, u8),
+
+    #[error("cryptoballot validation: invalid re-share commitment for trustee {0}")]
+    InvalidReshareCommitment(u8),
+
+    #[error("cryptoballot validation: trustees cannot be revoked or re-shared after partial decryptions are posted")]
+    PartialDecryptionsAlreadyPosted,
+
+    #[error(
+        "cryptoballot validation: challenge must be signed by the challenged vote's anonymous key"
+    )]
+    ChallengeKeyMismatch,
+
+    #[error("cryptoballot validation: re-encrypting the challenged vote does not reproduce it")]
+    ChallengeEncryptionMismatch,
+
+    #[error("cryptoballot validation: vote {0} has been challenged and cannot be counted")]
+    VoteChallenged(Identifier),
+
+    #[error("{0}")]
+    InvalidReceipt(#[from] ReceiptError),
+
     #[error("cryptoballot validation: missing keygen_public_key transaction for trustee {0})")]
     MissingKeyGenPublicKeyTransaction(u8),
 
     #[error("cryptoballot validation: wrong number of keygen_public_key transactions)")]
     WrongNumberOfPublicKeyTransactions,
 
+    #[error("cryptoballot validation: trustee {0} has not posted a keygen_public_key transaction")]
+    MissingTrusteePublicKey(uuid::Uuid),
+
     #[error("cryptoballot validation: trustee {0} share is missing)")]
     TrusteeShareMissing(u8),
 
@@ -116,6 +327,15 @@ pub enum ValidationError {
     #[error("cryptoballot validation: authentication failed")]
     AuthFailed,
 
+    #[error("cryptoballot validation: authenticator {0} has an invalid public key for ballot {1}")]
+    InvalidAuthenticatorKey(uuid::Uuid, String),
+
+    #[error("cryptoballot validation: authentication does not match the vote's election, ballot, and anonymous key")]
+    AuthenticationMismatch,
+
+    #[error("cryptoballot validation: not enough authentications: need {0}, have {1}")]
+    NotEnoughAuthentications(usize, usize),
+
     #[error("cryptoballot: encryption_key transaction not does yet exist")]
     EncryptionKeyTransactionDoesNotExist,
 
@@ -128,24 +348,94 @@ pub enum ValidationError {
     #[error("cryptoballot: vote decryption failed: decrypted vote mismatch")]
     VoteDecryptionMismatch,
 
+    #[error("cryptoballot validation: vote weight must be greater than zero")]
+    InvalidVoteWeight,
+
+    #[error("cryptoballot validation: all selections in a vote must have the same weight")]
+    MismatchedVoteWeight,
+
+    #[error("cryptoballot validation: vote weight {0} exceeds the election's maximum vote weight of {1}")]
+    VoteWeightExceedsMax(u32, u32),
+
+    #[error("cryptoballot validation: decrypted vote weight {1} does not match the certified vote weight {0}")]
+    VoteWeightMismatch(u64, u64),
+
+    #[error("cryptoballot validation: weighted votes cannot be used with a mixnet")]
+    WeightedVotesMixed,
+
+    #[error("cryptoballot validation: weighted votes require at least one authenticator to certify the weights")]
+    WeightedVotesUnauthenticated,
+
+    #[error("cryptoballot validation: an authenticator rate limit requires at least one authenticator per vote")]
+    RateLimitUnauthenticated,
+
+    #[error("cryptoballot validation: a yes / no referendum vote must be a single \"yes\" or \"no\" selection")]
+    InvalidReferendumVote,
+
     #[error("cryptoballot: auth signature verification failed")]
     AuthSignatureVerificationFailed,
 
+    #[error("cryptoballot: auth challenge was issued by a different authenticator")]
+    AuthChallengeWrongAuthenticator,
+
+    #[error("cryptoballot: auth challenge was issued for ballot {0}")]
+    AuthChallengeWrongBallot(String),
+
+    #[error("cryptoballot: auth challenge expired at {0}")]
+    AuthChallengeExpired(u64),
+
+    #[error("cryptoballot: auth challenge has already been used")]
+    AuthChallengeReplayed,
+
+    #[error("cryptoballot: auth challenge response is invalid")]
+    AuthChallengeResponseInvalid,
+
     #[error("cryptoballot: not enough secret shares: need {0}, found {1}")]
     NotEnoughShares(usize, usize),
 
     #[error("cryptoballot: transaction not found: {0}")]
     TransactionNotFound(#[from] TransactionNotFound),
 
+    #[error("cryptoballot: decryption deadline passed at {0}")]
+    DecryptionDeadlineExpired(u64),
+
+    #[error("cryptoballot validation: partial decryption {0} has already been posted")]
+    DuplicatePartialDecryption(Identifier),
+
+    #[error("cryptoballot validation: partial decryption batch is empty")]
+    EmptyPartialDecryptionBatch,
+
     #[error("cryptoballot: Missing voting_end transaction")]
     MisingVotingEndTransaction,
 
     #[error("cryptoballot validation: signature error: {0}")]
     SignatureError(#[from] ed25519_dalek::SignatureError),
 
+    #[error(
+        "cryptoballot validation: transaction does not re-serialize to the bytes that were signed"
+    )]
+    SignedBytesMismatch,
+
     #[error("cryptoballot validation: share decryption error")]
     ShareDecryptionError,
 
+    #[error("cryptoballot validation: vote is missing proofs of correct encryption")]
+    MissingEncryptionProof,
+
+    #[error("cryptoballot validation: invalid proof of correct encryption for contest {0}")]
+    InvalidEncryptionProof(u32),
+
+    #[error("cryptoballot validation: vote is missing proofs that its selections are valid")]
+    MissingVoteValidityProof,
+
+    #[error("cryptoballot validation: invalid proof that the selections in contest {0} are valid")]
+    InvalidVoteValidityProof(u32),
+
+    #[error(
+        "cryptoballot validation: key share from trustee {0} does not match its keygen commitment"
+    )]
+    SecretShareCommitmentMismatch(u8),
+
     #[error("cryptoballot: partial decryption proof failed to verify")]
     PartialDecryptionProofFailed,
 
@@ -188,17 +478,201 @@ pub enum ValidationError {
     #[error("cryptoballot: invalid upstream contest index")]
     InvalidUpstreamContestIndex,
 
-    #[error("cryptoballot: wrong mix selected for decryption")]
+    #[error(
+        "cryptoballot: wrong mix selected for decryption, only the final mix may be decrypted"
+    )]
     WrongMixSelected,
 
+    #[error("cryptoballot validation: election uses a mixnet, votes must be decrypted from the final mix")]
+    MixRequired,
+
     #[error("cryptoballot: voted in wrong contest")]
     VotedInWrongContest,
 
+    #[error("cryptoballot validation: vote contains more than one encryption of contest {0}")]
+    DuplicateContestVote(u32),
+
+    #[error(
+        "cryptoballot validation: vote selects more candidates than there are winners in plurality contest {0}"
+    )]
+    Overvote(u32),
+
+    #[error("cryptoballot validation: more than one contest has id {0}")]
+    DuplicateContestId(String),
+
+    #[error("cryptoballot validation: more than one contest has index {0}")]
+    DuplicateContestIndex(u32),
+
     #[error("cryptoballot: vote anonymous_key collides with existing vote")]
     VoteAnonymousKeyCollision,
 
+    #[error("cryptoballot: transaction created at {1} precedes a transaction it depends on created at {0}")]
+    TimestampOrderViolation(u64, u64),
+
+    #[error("{0}")]
+    InvalidMixConfig(#[from] ConfigError),
+
+    #[error("cryptoballot: vote {0} was created after voting ended")]
+    VoteAfterVotingEnd(Identifier),
+
+    #[error("cryptoballot validation: transaction created at {0} is in the future (now {1})")]
+    CreatedInFuture(u64, u64),
+
+    #[error("cryptoballot validation: voting_end commits to {0} votes, found {1}")]
+    VoteCountMismatch(u64, u64),
+
+    #[error("cryptoballot validation: votes do not match the voting_end vote root")]
+    VoteRootMismatch,
+
+    #[error("cryptoballot: authenticator {0} has authenticated too many votes, try again later")]
+    RateLimitExceeded(uuid::Uuid),
+
     #[error("cryptoballot: could not decode vote selection: {0}")]
     VoteDecodingError(#[from] prost::DecodeError),
+
+    #[error("{0}")]
+    Tally(#[from] TallyError),
+}
+
+impl ValidationError {
+    /// The HTTP status code a REST API should respond with when rejecting a posted transaction
+    pub fn http_status_code(&self) -> u16 {
+        use ValidationError::*;
+
+        match self {
+            // A transaction this one depends on hasn't been posted (or found) yet
+            TransactionNotFound(_)
+            | EncryptionKeyTransactionDoesNotExist
+            | MisingVotingEndTransaction
+            | MissingKeyGenPublicKeyTransaction(_)
+            | MissingPrevMixTransaction
+            | MissingObserverAcknowledgment(_)
+            | TrusteeNotRevoked(_)
+            | MissingTrusteeReshare(_, _)
+            | NotEnoughShares(_, _) => 404,
+
+            // The transaction is well formed, but conflicts with one already posted or with the election's phase
+            DuplicatePartialDecryption(_)
+            | VoteAnonymousKeyCollision
+            | VotingHasEnded
+            | VoteAfterVotingEnd(_)
+            | VoteChallenged(_)
+            | RevocationAfterEncryptionKey
+            | TrusteeRevoked(_)
+            | NotEnoughActiveTrustees(_, _)
+            | TrusteeAlreadyDecrypted(_)
+            | PartialDecryptionsAlreadyPosted
+            | AuthChallengeReplayed
+            | DecryptionDeadlineExpired(_) => 409,
+
+            // The authenticator is throttling votes, the client should back off
+            RateLimitExceeded(_) => 429,
+
+            // Cryptographic operations failed on values that had already passed validation
+            SecretRecoveryFailed | VoteDecryptionFailed(_) | ShareDecryptionError => 500,
+
+            // Everything else is a malformed or invalid transaction, which will never be accepted as-is
+            _ => 400,
+        }
+    }
+
+    /// Whether posting the same transaction again might succeed later, such as once a transaction it depends on has
+    /// been posted
+    pub fn is_retryable(&self) -> bool {
+        matches!(self.http_status_code(), 404 | 429)
+    }
+}
+
+/// Errors writing to a transaction store
+#[derive(Debug, Error)]
+pub enum StoreError {
+    #[error("cryptoballot: store does not support writing transactions")]
+    ReadOnly,
+
+    #[error("{0}")]
+    Log(#[from] LogError),
+
+    #[error("{0}")]
+    Conflict(#[from] ConflictDetected),
+}
+
+/// Two different transactions were stored under the same identifier.
+///
+/// Identifiers are derived from what a transaction is for, so two transactions with the same identifier but different
+/// content mean the signer equivocated, such as a trustee posting two different partial decryptions of the same vote.
+/// The hashes (see `SignedTransaction::content_hash`) identify both versions, so that they can be reported as evidence.
+#[derive(Debug, Clone, Error)]
+#[error(
+    "cryptoballot: conflicting transactions for {id}: stored {} but got {}",
+    hex::encode(.existing_hash),
+    hex::encode(.new_hash)
+)]
+pub struct ConflictDetected {
+    pub id: Identifier,
+    pub existing_hash: [u8; 32],
+    pub new_hash: [u8; 32],
+}
+
+impl ConflictDetected {
+    /// The index of the trustee who equivocated, if the conflicting transactions are partial decryptions
+    pub fn equivocating_trustee(&self) -> Option<u8> {
+        match self.id.transaction_type {
+            // The last byte of a partial decryption's unique-info is always the trustee index
+            TransactionType::PartialDecryption => Some(self.id.unique_info[15]),
+            _ => None,
+        }
+    }
+}
+
+/// Errors reading or writing a `TransactionLog`
+#[derive(Debug, Error)]
+pub enum LogError {
+    #[error("cryptoballot: transaction log I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("cryptoballot: not a transaction log (bad magic bytes)")]
+    BadMagic,
+
+    #[error("cryptoballot: unsupported transaction log version {0}")]
+    UnsupportedVersion(u8),
+
+    #[error("cryptoballot: transaction log entry at offset {0} is corrupt")]
+    Corrupt(u64),
+
+    #[error("cryptoballot: transaction log entry at offset {0} is truncated")]
+    Truncated(u64),
+
+    #[error("cryptoballot: CBOR error in transaction log: {0}")]
+    CBORError(#[from] serde_cbor::Error),
+}
+
+/// Errors applying (validating and storing) a transaction
+#[derive(Debug, Error)]
+pub enum ApplyError {
+    #[error("{0}")]
+    Validation(#[from] ValidationError),
+
+    #[error("{0}")]
+    Store(#[from] StoreError),
+}
+
+/// Non-fatal issues found while validating a transaction
+#[derive(Debug, Error, Serialize, Deserialize, Clone, PartialEq)]
+pub enum ValidationWarning {
+    #[error(
+        "cryptoballot warning: production election is missing a signing authority description"
+    )]
+    MissingAuthorityDescription,
+}
+
+/// Errors creating a tally method or counting a vote with it (see `Tally`)
+#[derive(Debug, Error, Clone)]
+pub enum TallyError {
+    #[error("cryptoballot tally: a vote selects the same candidate more than once")]
+    DuplicateCandidate,
+
+    #[error("cryptoballot tally: {0:?} contests can't be tallied")]
+    UnsupportedContestType(ContestType),
 }
 
 /// SpoiledBallotError represent the various ways a ballot can be spoiled
@@ -228,3 +702,36 @@ pub enum SpoiledBallotError {
     #[error("cryptoballot: spoiled ballot: candidate ID not found in this contest")]
     CandidateNotFound,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validation_error_http_status() {
+        // A transaction that depends on one not yet posted can succeed later
+        let election_id = ElectionTransaction::build_id([1; 15]);
+        let vote_id = Identifier::new(election_id, TransactionType::Vote, None);
+        let err: ValidationError = MemStore::default()
+            .get_vote(vote_id)
+            .map(|_| ())
+            .unwrap_err()
+            .into();
+        assert_eq!(err.http_status_code(), 404);
+        assert!(err.is_retryable());
+
+        let err = ValidationError::VotingHasEnded;
+        assert_eq!(err.http_status_code(), 409);
+        assert!(!err.is_retryable());
+
+        assert_eq!(
+            ValidationError::IdentifierBadComposition.http_status_code(),
+            400
+        );
+        assert_eq!(
+            ValidationError::SecretRecoveryFailed.http_status_code(),
+            500
+        );
+        assert!(ValidationError::RateLimitExceeded(uuid::Uuid::nil()).is_retryable());
+    }
+}
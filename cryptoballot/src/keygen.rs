@@ -16,6 +16,11 @@ pub struct KeyGenCommitmentTransaction {
     #[serde(with = "X25519PublicKeyHex")]
     pub x25519_public_key: x25519::PublicKey,
     pub commitment: KeygenCommitment,
+
+    /// Optional creation time, in seconds since the UNIX epoch
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<u64>,
 }
 
 /// Transaction 3: KeyGenShare
@@ -29,6 +34,11 @@ pub struct KeyGenShareTransaction {
 
     #[serde(with = "indexmap::serde_seq")]
     pub shares: IndexMap<u8, EncryptedShare>,
+
+    /// Optional creation time, in seconds since the UNIX epoch
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<u64>,
 }
 
 /// Transaction 4: KeyGenPublicKey
@@ -41,9 +51,23 @@ pub struct KeyGenPublicKeyTransaction {
     pub trustee_public_key: PublicKey,
     pub public_key: cryptid::elgamal::PublicKey,
     pub public_key_proof: cryptid::threshold::PubkeyProof,
+
+    /// Zero for the trustee's first key. Each replacement of a revoked key increments the revision.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_zero")]
+    pub revision: u8,
+
+    /// Optional creation time, in seconds since the UNIX epoch
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<u64>,
 }
 
 /// Transaction 5: EncryptionKey
+///
+/// Aggregates the trustees' KeyGenPublicKey transactions into the joint ElGamal public key that votes are encrypted to.
+/// It can only be posted once there is a KeyGenPublicKey transaction for every trustee in the election, and the
+/// key it records must be the one derived from them (see `ElectionTransaction::derive_election_public_key`).
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct EncryptionKeyTransaction {
     pub id: Identifier,
@@ -51,6 +75,11 @@ pub struct EncryptionKeyTransaction {
     #[serde(with = "EdPublicKeyHex")]
     pub authority_public_key: PublicKey,
     pub encryption_key: cryptid::elgamal::PublicKey,
+
+    /// Optional creation time, in seconds since the UNIX epoch
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<u64>,
 }
 
 impl KeyGenCommitmentTransaction {
@@ -69,6 +98,7 @@ impl KeyGenCommitmentTransaction {
             trustee_public_key,
             x25519_public_key,
             commitment,
+            created_at: None,
         }
     }
 
@@ -143,6 +173,7 @@ impl KeyGenShareTransaction {
             trustee_index,
             trustee_public_key,
             shares,
+            created_at: None,
         }
     }
 
@@ -179,6 +210,9 @@ impl CryptoBallotTransaction for KeyGenShareTransaction {
     /// The validation does the following:
     ///  - Validates that this transaction has been signed by a valid trustee
     ///  - Validates that there is one share per trustee in the election
+    ///
+    /// The shares are encrypted to their recipients, so their consistency with the sender's KeyGenCommitment can only
+    /// be checked by each recipient, see `Trustee::verify_key_shares`.
     fn validate_tx<S: Store>(&self, store: &S) -> Result<(), ValidationError> {
         let election = store.get_election(self.election)?;
 
@@ -226,12 +260,31 @@ impl KeyGenPublicKeyTransaction {
             trustee_public_key,
             public_key,
             public_key_proof,
+            revision: 0,
+            created_at: None,
         }
     }
 
+    /// Turn this into the replacement for the trustee's revoked key at `revision - 1`
+    pub fn with_revision(mut self, revision: u8) -> Self {
+        self.revision = revision;
+        self.id = Self::build_revision_id(self.election, self.trustee_index, revision);
+        self
+    }
+
+    /// Build the ID of a trustee's first KeyGenPublicKey transaction
     pub fn build_id(election_id: Identifier, trustee_index: u8) -> Identifier {
+        Self::build_revision_id(election_id, trustee_index, 0)
+    }
+
+    pub fn build_revision_id(
+        election_id: Identifier,
+        trustee_index: u8,
+        revision: u8,
+    ) -> Identifier {
         let mut unique_info = [0; 16];
         unique_info[0] = trustee_index;
+        unique_info[1] = revision;
         Identifier::new(
             election_id,
             TransactionType::KeyGenPublicKey,
@@ -280,6 +333,23 @@ impl CryptoBallotTransaction for KeyGenPublicKeyTransaction {
             return Err(ValidationError::TrusteeDoesNotExist(self.trustee_index));
         }
 
+        // A replacement key must follow the revocation of the previous one
+        if self.revision > 0 {
+            let revocation_id = TrusteeKeyRevocationTransaction::build_id(
+                self.election,
+                self.trustee_index,
+                self.revision - 1,
+            );
+            if !store.contains(revocation_id) {
+                return Err(ValidationError::KeyGenPublicKeyNotRevoked(
+                    self.trustee_index,
+                ));
+            }
+            if store.get_encryption_key(self.election).is_ok() {
+                return Err(ValidationError::RevocationAfterEncryptionKey);
+            }
+        }
+
         Ok(())
     }
 }
@@ -296,6 +366,7 @@ impl EncryptionKeyTransaction {
             election: election_id,
             authority_public_key,
             encryption_key,
+            created_at: None,
         }
     }
 
@@ -304,6 +375,12 @@ impl EncryptionKeyTransaction {
     }
 }
 
+impl AuthorityTransaction for EncryptionKeyTransaction {
+    fn set_authority_public(&mut self, public: PublicKey) {
+        self.authority_public_key = public;
+    }
+}
+
 impl CryptoBallotTransaction for EncryptionKeyTransaction {
     #[inline(always)]
     fn id(&self) -> Identifier {
@@ -337,10 +414,20 @@ impl CryptoBallotTransaction for EncryptionKeyTransaction {
             return Err(ValidationError::AuthorityPublicKeyMismatch);
         }
 
-        // Get all keygen_public_key transactions
-        let pk_txs = store.get_multiple(self.election, TransactionType::KeyGenPublicKey);
-        let pk_txs: Vec<Signed<KeyGenPublicKeyTransaction>> =
-            pk_txs.into_iter().map(|tx| tx.into()).collect();
+        // Get the current keygen_public_key transaction for each trustee
+        let pk_txs = store.get_current_keygen_public_keys(self.election);
+
+        // A revoked key must be replaced before the encryption key can be posted
+        for tx in &pk_txs {
+            let revocation_id = TrusteeKeyRevocationTransaction::build_id(
+                self.election,
+                tx.trustee_index,
+                tx.revision,
+            );
+            if store.contains(revocation_id) {
+                return Err(ValidationError::RevokedKeyNotReplaced(tx.trustee_index));
+            }
+        }
 
         // Validate that the number of public key transactions match
         if pk_txs.len() != election.trustees.len() {
@@ -365,13 +452,11 @@ impl CryptoBallotTransaction for EncryptionKeyTransaction {
             }
         }
 
-        // Validate that all the encryption keys match
-        for tx in &pk_txs {
-            if tx.inner().public_key != self.encryption_key {
-                return Err(ValidationError::MismatchedEncryptionKey(
-                    tx.inner().trustee_index,
-                ));
-            }
+        // Validate that the encryption key is the one derived from the trustees' contributions
+        let pk_txs: Vec<KeyGenPublicKeyTransaction> = pk_txs.into_iter().map(|tx| tx.tx).collect();
+        let derived_key = election.derive_election_public_key(&pk_txs)?;
+        if derived_key != self.encryption_key {
+            return Err(ValidationError::EncryptionKeyDerivationMismatch);
         }
 
         Ok(())
@@ -11,15 +11,30 @@
 //!  - **Transaction 4: KeyGenPublicKey Transaction** - Trustee's computation of the Election Encryption Key.
 //!  - **Transaction 5: EncryptionKey Transaction** - The Encryption Key that will be used by voters to encrypt their vote.
 //!  - **Transaction 6: Vote Transaction** - Voter's encrypted vote.
-//!  - **Transaction 7: VotingEnd Transaction** - Denotes the end of voting.
+//!  - **Transaction 7: VotingEnd Transaction** - Denotes the end of voting, committing to the votes that were cast.
 //!  - **Transaction 8: Mix Transaction** - Shuffled and mixed vote for a single contest, created by a trustee.
 //!  - **Transaction 9: PartialDecryption Transaction** - A partially decrypted vote from a trustee.
 //!  - **Transaction 10: Decryption Transaction** - A fully decrypted vote .
+//!  - **Transaction 11: ObserverAcknowledgment Transaction** - An observer's confirmation that they have received the election.
+//!  - **Transaction 12: TrusteeKeyRevocation Transaction** - A trustee's revocation of their KeyGenPublicKey transaction, which must then be replaced.
+//!  - **Transaction 13: Challenge Transaction** - A voter's challenge of their own vote, revealing its plaintext to prove it was encrypted honestly. Challenged votes are not counted.
+//!  - **Transaction 14: PartialDecryptionBatch Transaction** - A trustee's partial decryptions of many ciphertexts at once, equivalent to posting each as a PartialDecryption transaction.
+//!  - **Transaction 15: TrusteeRevocation Transaction** - The election authority's revocation of a compromised trustee, whose partial decryptions are then rejected.
+//!  - **Transaction 16: TrusteeReshare Transaction** - A remaining trustee's re-share of a revoked trustee's key share, refreshing the remaining trustees' shares so that the revoked share can no longer decrypt.
 //!  - **Election Authority** - Creates an Election Transaction.
 //!  - **Trustee** - A group of trustees collectively create the encryption-key, decrypt votes, and run the mixnet. Generally ⅔ of trustees are required to be honest for the CryptoBallot protocol to function.
 //!  - **Authenticator** - Certifies that a voter can vote an election and ballot.
+//!  - **Observer** - An official non-voting participant who receives all transactions, but cannot vote or decrypt.
 //!  - **Contest** - A single question that voters are voting on.
 //!  - **Ballot** - A set of contests, usually restricted to a geographic area. A single contest can exist across multiple ballots.
+//!
+//! ## Cargo features:
+//!  - **tokio** - Lets a `tokio::runtime::Handle` run a `BlockingStore`, for validating against an `AsyncStore`.
+//!  - **tracing** - Emits a `tracing` span for each transaction validated, with its ID, type and outcome, and
+//!    sub-spans for verifying signatures, shuffle proofs, partial decryption proofs and decryptions.
+//!
+//! CryptoBallot requires `std`. Besides the file-backed `TransactionLog`, `MemStore` relies on `std::sync::RwLock`,
+//! and several dependencies (cryptid, rsa, tallystick and serde_json with `preserve_order`) require `std`.
 
 #![feature(is_sorted)]
 
@@ -34,34 +49,91 @@ pub extern crate rsa;
 pub extern crate uuid;
 pub extern crate x25519_dalek;
 
+// Enter a tracing span until the end of the enclosing block, if the `tracing` feature is enabled
+macro_rules! trace_span {
+    ($($span:tt)*) => {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::info_span!($($span)*).entered();
+    };
+}
+
+// Emit the outcome of validating a transaction as a tracing event, if the `tracing` feature is enabled
+macro_rules! trace_outcome {
+    ($result:expr) => {
+        #[cfg(feature = "tracing")]
+        match &$result {
+            Ok(_) => tracing::debug!("valid"),
+            Err(e) => tracing::debug!(error = %e, "invalid"),
+        }
+    };
+}
+
+mod async_store;
+mod audit;
 mod authn;
 mod ballot;
+mod batch;
+mod challenge;
+mod clock;
+mod deadline;
 mod decryption;
+mod decryption_batch;
 mod election;
+mod encryption_proof;
 mod error;
 mod keygen;
+mod merkle;
 mod mix;
+mod observer;
+mod rate_limit;
+mod receipt;
+mod revocation;
+mod scheme;
 mod serde_hex;
 mod store;
+mod stv;
+mod summary;
 mod tally;
 mod transaction;
+mod transaction_log;
 mod trustee;
 mod util;
+mod validity_proof;
+mod viz;
 mod vote;
 mod voting_end;
 
+pub use async_store::*;
+pub use audit::*;
 pub use authn::*;
 pub use ballot::*;
+pub use batch::*;
+pub use challenge::*;
+pub use clock::*;
+pub use deadline::*;
 pub use decryption::*;
+pub use decryption_batch::*;
 pub use election::*;
+pub use encryption_proof::*;
 pub use error::*;
 pub use keygen::*;
+pub use merkle::*;
 pub use mix::*;
+pub use observer::*;
+pub use rate_limit::*;
+pub use receipt::*;
+pub use revocation::*;
+pub use scheme::*;
 pub use store::*;
+pub use stv::*;
+pub use summary::*;
 pub use tally::*;
 pub use transaction::*;
+pub use transaction_log::*;
 pub use trustee::*;
 pub use util::*;
+pub use validity_proof::*;
+pub use viz::*;
 pub use vote::*;
 pub use voting_end::*;
 
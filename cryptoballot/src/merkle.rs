@@ -0,0 +1,167 @@
+use crate::*;
+use sha2::{Digest, Sha256};
+
+/// A merkle tree over an election's vote IDs, for proving that a vote is in the published set.
+///
+/// This is the same tree whose root is committed to in the `VotingEndTransaction` (see `vote_root`), so an
+/// `InclusionProof` can be checked against the signed `vote_root` without trusting whoever produced the proof.
+#[derive(Debug, Clone)]
+pub struct MerkleAuditTree {
+    vote_ids: Vec<Identifier>,
+
+    // Every level of the tree, from the leaves up to the root
+    levels: Vec<Vec<[u8; 32]>>,
+}
+
+/// A sibling hash on the path from a leaf to the merkle root
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProofNode {
+    /// The sibling is on the left
+    Left(#[serde(with = "hex_serde")] [u8; 32]),
+
+    /// The sibling is on the right
+    Right(#[serde(with = "hex_serde")] [u8; 32]),
+}
+
+/// Proof that a vote is included in a `MerkleAuditTree`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct InclusionProof {
+    pub vote_id: Identifier,
+
+    /// Sibling hashes from the leaf up to the root. A node without a sibling is carried up unchanged, so has no entry.
+    pub path: Vec<ProofNode>,
+}
+
+impl MerkleAuditTree {
+    /// Build the tree over every vote in the election
+    pub fn from_votes(votes: &[Signed<VoteTransaction>]) -> Self {
+        let vote_ids: Vec<Identifier> = votes.iter().map(|vote| vote.id()).collect();
+        Self::from_vote_ids(&vote_ids)
+    }
+
+    /// Build the tree over a set of vote IDs, in any order
+    pub fn from_vote_ids(vote_ids: &[Identifier]) -> Self {
+        let mut vote_ids = vote_ids.to_vec();
+        vote_ids.sort();
+
+        let mut levels = vec![vote_ids.iter().map(leaf_hash).collect::<Vec<_>>()];
+        while levels[levels.len() - 1].len() > 1 {
+            let level = levels[levels.len() - 1]
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => node_hash(left, right),
+                    _ => pair[0],
+                })
+                .collect();
+            levels.push(level);
+        }
+
+        MerkleAuditTree { vote_ids, levels }
+    }
+
+    /// The merkle root, which is all zeros for a tree with no votes
+    pub fn root(&self) -> [u8; 32] {
+        self.levels[self.levels.len() - 1]
+            .first()
+            .copied()
+            .unwrap_or([0; 32])
+    }
+
+    /// Prove that a vote is in the tree, or None if it isn't
+    pub fn inclusion_proof(&self, vote_id: Identifier) -> Option<InclusionProof> {
+        let mut index = self.vote_ids.binary_search(&vote_id).ok()?;
+
+        let mut path = Vec::new();
+        for level in &self.levels[..self.levels.len() - 1] {
+            if index % 2 == 1 {
+                path.push(ProofNode::Left(level[index - 1]));
+            } else if let Some(sibling) = level.get(index + 1) {
+                path.push(ProofNode::Right(*sibling));
+            }
+            index /= 2;
+        }
+
+        Some(InclusionProof { vote_id, path })
+    }
+}
+
+impl InclusionProof {
+    /// Check that `vote` is included in the tree with the given root, such as a VotingEnd's `vote_root`
+    pub fn verify(&self, root: [u8; 32], vote: &Signed<VoteTransaction>) -> bool {
+        if vote.id() != self.vote_id {
+            return false;
+        }
+
+        let hash = self
+            .path
+            .iter()
+            .fold(leaf_hash(&self.vote_id), |hash, node| match node {
+                ProofNode::Left(sibling) => node_hash(sibling, &hash),
+                ProofNode::Right(sibling) => node_hash(&hash, sibling),
+            });
+
+        hash == root
+    }
+}
+
+// Leaves and internal nodes are hashed with different prefixes, so that a node can't be passed off as a leaf
+fn leaf_hash(vote_id: &Identifier) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update([0x00]);
+    hasher.update(vote_id.to_array());
+    hasher.finalize().into()
+}
+
+fn node_hash(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update([0x01]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merkle_audit_tree() {
+        // An odd number of votes, so that a node is carried up without a sibling
+        let election_id = ElectionTransaction::build_id([1; 15]);
+        let votes: Vec<Signed<VoteTransaction>> = (0..5)
+            .map(|_| {
+                let (vote, voter_secret) =
+                    VoteTransaction::new(election_id, "TEST".to_string(), vec![]);
+                Signed::sign(&voter_secret, vote).unwrap()
+            })
+            .collect();
+
+        // The tree's root is the one a VotingEnd transaction commits to
+        let vote_ids: Vec<Identifier> = votes.iter().map(|vote| vote.id()).collect();
+        let root = vote_root(&vote_ids);
+        let tree = MerkleAuditTree::from_votes(&votes);
+        assert_eq!(tree.root(), root);
+
+        for vote in votes.iter() {
+            let proof = tree.inclusion_proof(vote.id()).unwrap();
+            let proof: InclusionProof =
+                serde_json::from_str(&serde_json::to_string(&proof).unwrap()).unwrap();
+            assert!(proof.verify(root, vote));
+            assert!(!proof.verify([0; 32], vote));
+        }
+
+        // A proof for one vote doesn't prove another
+        let proof = tree.inclusion_proof(votes[0].id()).unwrap();
+        assert!(!proof.verify(root, &votes[1]));
+
+        // Votes not in the tree have no proof
+        let smaller = MerkleAuditTree::from_votes(&votes[1..]);
+        assert!(smaller.inclusion_proof(votes[0].id()).is_none());
+        assert!(!smaller
+            .inclusion_proof(votes[1].id())
+            .unwrap()
+            .verify(root, &votes[1]));
+        assert_eq!(MerkleAuditTree::from_votes(&[]).root(), [0; 32]);
+    }
+}
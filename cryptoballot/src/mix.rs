@@ -7,12 +7,38 @@ use ed25519_dalek::PublicKey;
 use rand::{CryptoRng, Rng};
 use std::collections::HashSet;
 
+/// The most shuffles a mixnet may be configured with.
+///
+/// Every shuffle adds a mix transaction (and its proof) per contest that every verifier must check, so long chains
+/// make elections slow to verify while adding little once a few independent trustees have shuffled.
+pub const MAX_MIX_SHUFFLES: u8 = 10;
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct MixConfig {
     pub timeout_secs: u64,
     pub batch_size: Option<u16>,
 }
 
+impl MixConfig {
+    /// Validate the mixnet configuration for an election with the given number of shuffles.
+    ///
+    /// Votes are shuffled once by each of the first `trustees_threshold` trustees, so `num_shuffles` is the election's
+    /// `trustees_threshold`. It must be between 1 and `MAX_MIX_SHUFFLES`: with no shuffles, votes would be
+    /// decrypted directly despite the election being configured to use a mixnet.
+    pub fn validate(&self, num_shuffles: u8) -> Result<(), ConfigError> {
+        if num_shuffles == 0 {
+            return Err(ConfigError::NoShuffles);
+        }
+        if num_shuffles > MAX_MIX_SHUFFLES {
+            return Err(ConfigError::TooManyShuffles(num_shuffles));
+        }
+        if self.batch_size == Some(0) {
+            return Err(ConfigError::ZeroBatchSize);
+        }
+        Ok(())
+    }
+}
+
 /// Transaction 8: Mix
 #[derive(Serialize, Deserialize, Clone)]
 pub struct MixTransaction {
@@ -51,6 +77,11 @@ pub struct MixTransaction {
 
     /// Proof of correct shuffle and re-encryption
     pub proof: ShuffleProof,
+
+    /// Optional creation time, in seconds since the UNIX epoch
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<u64>,
 }
 
 impl MixTransaction {
@@ -84,6 +115,7 @@ impl MixTransaction {
             vote_ids,
             mixed_ciphertexts,
             proof,
+            created_at: None,
         }
     }
 
@@ -109,6 +141,126 @@ impl MixTransaction {
 
         Identifier::new(election_id, TransactionType::Mix, Some(unique_info))
     }
+
+    /// Verify the shuffle proof against the ciphertexts that were shuffled.
+    ///
+    /// For the first mix these are the contest's ciphertexts from the cast votes, in ascending order of vote ID, and
+    /// for later mixes they are the previous mix's output. Unlike `validate`, this checks only the proof, not where
+    /// `original_ciphertexts` came from.
+    pub fn verify_shuffle_proof(
+        &self,
+        original_ciphertexts: &[Vec<Ciphertext>],
+        encryption_key: &EncryptionPublicKey,
+    ) -> bool {
+        if original_ciphertexts.len() != self.mixed_ciphertexts.len() {
+            return false;
+        }
+
+        verify_mix(
+            original_ciphertexts.to_vec(),
+            self.mixed_ciphertexts.clone(),
+            encryption_key,
+            &self.proof,
+            self.trustee_index,
+            self.mix_index,
+            self.contest_index,
+            self.batch,
+        )
+        .is_ok()
+    }
+}
+
+/// Builds a mix transaction by shuffling and re-encrypting ciphertexts, and proving the shuffle correct.
+///
+/// Start the first mix of a contest with `new`, passing the cast votes' ciphertexts in ascending order of vote ID, and
+/// every later mix with `after`, which picks up where the previous mix left off.
+pub struct MixTransactionBuilder {
+    election_id: Identifier,
+    ciphertexts: Vec<Vec<Ciphertext>>,
+    mix_index: u8,
+    prev_mix_id: Option<Identifier>,
+    contest_index: u32,
+    batch: u32,
+    vote_ids: Vec<Identifier>,
+}
+
+impl MixTransactionBuilder {
+    /// Start building a mix of `ciphertexts` for contest 0, batch 0, with no previous mix
+    pub fn new(election_id: Identifier, ciphertexts: Vec<Vec<Ciphertext>>, mix_index: u8) -> Self {
+        MixTransactionBuilder {
+            election_id,
+            ciphertexts,
+            mix_index,
+            prev_mix_id: None,
+            contest_index: 0,
+            batch: 0,
+            vote_ids: vec![],
+        }
+    }
+
+    /// Start building the mix that follows `prev_mix`, shuffling its output
+    pub fn after(prev_mix: &MixTransaction) -> Self {
+        MixTransactionBuilder {
+            election_id: prev_mix.election_id,
+            ciphertexts: prev_mix.mixed_ciphertexts.clone(),
+            mix_index: prev_mix.mix_index + 1,
+            prev_mix_id: Some(prev_mix.id),
+            contest_index: prev_mix.contest_index,
+            batch: prev_mix.batch,
+            vote_ids: prev_mix.vote_ids.clone(),
+        }
+    }
+
+    /// Set the contest and batch being mixed
+    pub fn with_contest(mut self, contest_index: u32, batch: u32) -> Self {
+        self.contest_index = contest_index;
+        self.batch = batch;
+        self
+    }
+
+    /// Set the previous mix, whose output is being shuffled
+    pub fn with_prev_mix(mut self, prev_mix_id: Identifier) -> Self {
+        self.prev_mix_id = Some(prev_mix_id);
+        self
+    }
+
+    /// Set the IDs of the votes being mixed, in ascending order
+    pub fn with_vote_ids(mut self, vote_ids: Vec<Identifier>) -> Self {
+        self.vote_ids = vote_ids;
+        self
+    }
+
+    /// Shuffle and prove the mix as `trustee`, returning the transaction ready to be signed.
+    ///
+    /// Like `mix`, this is expensive and should ideally be run on its own thread.
+    pub fn build<R: Rng + CryptoRng>(
+        self,
+        rng: &mut R,
+        trustee: &Trustee,
+        encryption_key: &EncryptionPublicKey,
+    ) -> Result<MixTransaction, Error> {
+        let (mixed_ciphertexts, proof) = mix(
+            rng,
+            self.ciphertexts,
+            encryption_key,
+            trustee.index,
+            self.mix_index,
+            self.contest_index,
+            self.batch,
+        )?;
+
+        Ok(MixTransaction::new(
+            self.election_id,
+            self.prev_mix_id,
+            trustee,
+            self.mix_index,
+            self.contest_index,
+            self.batch,
+            self.vote_ids,
+            mixed_ciphertexts,
+            proof,
+        ))
+    }
 }
 
 impl CryptoBallotTransaction for MixTransaction {
@@ -191,6 +343,7 @@ impl CryptoBallotTransaction for MixTransaction {
             {
                 return Err(ValidationError::InvalidPrevMixTransaction);
             }
+            validate_timestamp_order(prev_mix.created_at, self.created_at)?;
 
             prev_mix.mixed_ciphertexts
         } else {
@@ -198,6 +351,13 @@ impl CryptoBallotTransaction for MixTransaction {
                 return Err(ValidationError::OutOfOrderMix);
             }
 
+            // The first mix cannot have been created before voting ended
+            let voting_end_id = Identifier::new(self.election_id, TransactionType::VotingEnd, None);
+            if let Some(voting_end) = store.get_transaction(voting_end_id) {
+                let voting_end: VotingEndTransaction = voting_end.into();
+                validate_timestamp_order(voting_end.created_at, self.created_at)?;
+            }
+
             // Check that vote-ids are in ascending order with no duplicates
             // TODO: Do this in a single function, I think we can use "is_sorted_by" to disallow equalities
             if !&self.vote_ids.is_sorted() {
@@ -212,11 +372,8 @@ impl CryptoBallotTransaction for MixTransaction {
             //       - Validate batching - make sure batched votes are exactly correct
             //       - This will require reading the votes in order and checking for first, or ranging off the final vote_ids of prev mix
 
-            // Make sure all votes are accounted for
-            let votes = store.range(
-                Identifier::start(self.election_id, TransactionType::Vote, None),
-                Identifier::end(self.election_id, TransactionType::Vote, None),
-            );
+            // Make sure all votes are accounted for, other than challenged votes which are not counted
+            let votes = store.get_cast_votes(self.election_id);
 
             if votes.len() != self.vote_ids.len() {
                 return Err(ValidationError::MixWrongNumberOfVotes);
@@ -230,9 +387,7 @@ impl CryptoBallotTransaction for MixTransaction {
 
             let mut ciphertexts = Vec::with_capacity(self.vote_ids.len());
             for vote in votes {
-                let vote: VoteTransaction = vote.into();
-
-                for encrypted_vote in vote.encrypted_votes {
+                for encrypted_vote in vote.tx.encrypted_votes {
                     if encrypted_vote.contest_index == self.contest_index {
                         ciphertexts.push(encrypted_vote.selections);
                     }
@@ -242,13 +397,18 @@ impl CryptoBallotTransaction for MixTransaction {
             ciphertexts
         };
 
-        let enc_key_tx = Identifier::new(self.election_id, TransactionType::EncryptionKey, None);
-        let key_tx: EncryptionKeyTransaction = store
-            .get_transaction(enc_key_tx)
-            .ok_or(ValidationError::EncryptionKeyTransactionDoesNotExist)?
-            .into();
+        // The proof only covers the ciphertexts it was verified against, so they must be exactly the mix's output
+        // set. Otherwise a mixer could add ciphertexts for votes that never voted in this contest.
+        if input_ciphertexts.len() != self.mixed_ciphertexts.len() {
+            return Err(ValidationError::MixWrongNumberOfVotes);
+        }
+
+        let key_tx = store
+            .get_encryption_key(self.election_id)
+            .map_err(|_| ValidationError::EncryptionKeyTransactionDoesNotExist)?;
 
         // Verify that the mix is correct
+        trace_span!("mix.verify", id = %self.id);
         verify_mix(
             input_ciphertexts,
             self.mixed_ciphertexts.clone(),
@@ -337,3 +497,33 @@ where
     let mut uniq = HashSet::new();
     iter.into_iter().all(move |x| uniq.insert(x))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mix_config_num_shuffles() {
+        let config = MixConfig {
+            timeout_secs: 600,
+            batch_size: None,
+        };
+
+        assert!(matches!(config.validate(0), Err(ConfigError::NoShuffles)));
+        assert!(config.validate(1).is_ok());
+        assert!(config.validate(10).is_ok());
+        assert!(matches!(
+            config.validate(11),
+            Err(ConfigError::TooManyShuffles(11))
+        ));
+
+        let config = MixConfig {
+            timeout_secs: 600,
+            batch_size: Some(0),
+        };
+        assert!(matches!(
+            config.validate(1),
+            Err(ConfigError::ZeroBatchSize)
+        ));
+    }
+}
@@ -0,0 +1,98 @@
+use crate::*;
+use ed25519_dalek::PublicKey;
+use uuid::Uuid;
+
+/// An Observer is an official, non-voting participant in an election.
+///
+/// Observers receive every transaction, but cannot vote or decrypt. They sign an ObserverAcknowledgment
+/// transaction to confirm that they have received the election.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Observer {
+    pub id: Uuid,
+    pub name: String,
+
+    #[serde(with = "EdPublicKeyHex")]
+    pub public_key: PublicKey,
+}
+
+/// Transaction 11: ObserverAcknowledgment
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ObserverAcknowledgmentTransaction {
+    pub id: Identifier,
+    pub election: Identifier,
+    pub observer_id: Uuid,
+
+    #[serde(with = "EdPublicKeyHex")]
+    pub observer_public_key: PublicKey,
+
+    /// Optional creation time, in seconds since the UNIX epoch
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<u64>,
+}
+
+impl ObserverAcknowledgmentTransaction {
+    /// Create a new ObserverAcknowledgmentTransaction for the given observer
+    pub fn new(election: Identifier, observer: &Observer) -> Self {
+        ObserverAcknowledgmentTransaction {
+            id: Self::build_id(election, observer.id),
+            election,
+            observer_id: observer.id,
+            observer_public_key: observer.public_key,
+            created_at: None,
+        }
+    }
+
+    pub fn build_id(election_id: Identifier, observer_id: Uuid) -> Identifier {
+        Identifier::new(
+            election_id,
+            TransactionType::ObserverAcknowledgment,
+            Some(*observer_id.as_bytes()),
+        )
+    }
+}
+
+impl CryptoBallotTransaction for ObserverAcknowledgmentTransaction {
+    #[inline(always)]
+    fn id(&self) -> Identifier {
+        self.id
+    }
+
+    #[inline(always)]
+    fn public(&self) -> Option<PublicKey> {
+        Some(self.observer_public_key)
+    }
+
+    #[inline(always)]
+    fn election_id(&self) -> Identifier {
+        self.election
+    }
+
+    #[inline(always)]
+    fn tx_type() -> TransactionType {
+        TransactionType::ObserverAcknowledgment
+    }
+
+    /// Validate the transaction
+    ///
+    /// The validation does the following:
+    ///  - Validates that the observer is designated by the election
+    ///  - Validates that this transaction has been signed by that observer
+    fn validate_tx<S: Store>(&self, store: &S) -> Result<(), ValidationError> {
+        if Self::build_id(self.election, self.observer_id) != self.id {
+            return Err(ValidationError::IdentifierBadComposition);
+        }
+
+        let election = store.get_election(self.election)?;
+
+        let observer = election
+            .get_observer(self.observer_id)
+            .ok_or(ValidationError::ObserverDoesNotExist(self.observer_id))?;
+
+        if observer.public_key != self.observer_public_key {
+            return Err(ValidationError::ObserverPublicKeyMismatch(self.observer_id));
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,130 @@
+use crate::*;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Mutex, PoisonError};
+use uuid::Uuid;
+
+/// A limit on how many votes any one authenticator can authenticate within a sliding window of time.
+///
+/// Set on an election with `ElectionTransaction::auth_rate_limit`, and enforced by `RateLimitingStore`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    /// The most votes an authenticator may authenticate within the window
+    pub max_votes: u32,
+
+    /// The length of the window, in seconds
+    pub window_seconds: u64,
+}
+
+/// A store that rejects votes once an authenticator has authenticated too many in too short a time.
+///
+/// This is an application-layer defence against an authenticator being used as an oracle to mass-produce votes. It
+/// complements the cryptographic authentication rather than replacing it. It only applies to elections with an
+/// `auth_rate_limit`, and only to votes stored through `apply_transaction`. Submission times are kept in memory, so
+/// they are forgotten when the store is dropped, and they are not part of the election record: re-validating the
+/// stored votes later does not re-apply the limit.
+pub struct RateLimitingStore<S: Store, C: Clock = SystemClock> {
+    store: S,
+    clock: C,
+
+    // Times at which votes were accepted, keyed by election and authenticator, oldest first
+    submissions: Mutex<HashMap<(Identifier, Uuid), VecDeque<u64>>>,
+}
+
+impl<S: Store> RateLimitingStore<S> {
+    /// Wrap a store, using the system clock
+    pub fn new(store: S) -> Self {
+        Self::with_clock(store, SystemClock)
+    }
+}
+
+impl<S: Store, C: Clock> RateLimitingStore<S, C> {
+    /// Wrap a store, using `clock` to time submissions
+    pub fn with_clock(store: S, clock: C) -> Self {
+        RateLimitingStore {
+            store,
+            clock,
+            submissions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Get the wrapped store
+    pub fn inner(&self) -> &S {
+        &self.store
+    }
+
+    /// Unwrap the store, discarding the submission history
+    pub fn into_inner(self) -> S {
+        self.store
+    }
+}
+
+impl<S: Store, C: Clock> Store for RateLimitingStore<S, C> {
+    fn get_transaction(&self, id: Identifier) -> Option<SignedTransaction> {
+        self.store.get_transaction(id)
+    }
+
+    fn contains(&self, id: Identifier) -> bool {
+        self.store.contains(id)
+    }
+
+    fn range(&self, start: Identifier, end_inclusive: Identifier) -> Vec<SignedTransaction> {
+        self.store.range(start, end_inclusive)
+    }
+
+    fn list_elections(&self) -> Vec<Identifier> {
+        self.store.list_elections()
+    }
+
+    fn set_checked(&self, tx: SignedTransaction) -> Result<(), StoreError> {
+        self.store.set_checked(tx)
+    }
+
+    fn remove(&self, id: Identifier) -> Result<Option<SignedTransaction>, StoreError> {
+        self.store.remove(id)
+    }
+
+    fn apply_transaction(&self, tx: SignedTransaction) -> Result<(), ApplyError> {
+        let vote = match &tx {
+            SignedTransaction::Vote(vote) => vote,
+            _ => return self.store.apply_transaction(tx),
+        };
+        let rate_limit = match self.store.get_election(vote.election) {
+            Ok(election) => election.auth_rate_limit,
+            Err(_) => None,
+        };
+        let rate_limit = match rate_limit {
+            Some(rate_limit) => rate_limit,
+            None => return self.store.apply_transaction(tx),
+        };
+
+        // Hold the lock until the vote is stored, so concurrent votes can't all slip in under the limit
+        let mut submissions = self
+            .submissions
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let now = self.clock.now();
+        let window_start = now.saturating_sub(rate_limit.window_seconds);
+        let keys: Vec<(Identifier, Uuid)> = vote
+            .authentication
+            .iter()
+            .map(|authentication| (vote.election, authentication.authenticator))
+            .collect();
+        for key in keys.iter() {
+            if let Some(times) = submissions.get_mut(key) {
+                while matches!(times.front(), Some(time) if *time <= window_start) {
+                    times.pop_front();
+                }
+                if times.len() >= rate_limit.max_votes as usize {
+                    return Err(ValidationError::RateLimitExceeded(key.1).into());
+                }
+            }
+        }
+
+        self.store.apply_transaction(tx)?;
+        for key in keys {
+            submissions.entry(key).or_default().push_back(now);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,110 @@
+use crate::*;
+use ed25519_dalek::{PublicKey, SecretKey, Signature, Verifier};
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+
+/// A receipt, signed by the election authority, proving that a vote was recorded.
+///
+/// The commitment is the SHA256 hash of the CBOR-encoded signed vote transaction. A voter who kept a copy of their
+/// vote can later check with `verify` that the vote recorded for the election is exactly the one they cast. The
+/// receipt only identifies the vote by its anonymous id, so holding one does not reveal how the voter voted.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct VoteReceipt {
+    pub vote_id: Identifier,
+    pub election_id: Identifier,
+
+    #[serde(with = "hex_serde")]
+    pub commitment: [u8; 32],
+
+    #[serde(with = "EdSignatureHex")]
+    pub authority_signature: Signature,
+}
+
+impl VoteReceipt {
+    // Issue a receipt, see `ElectionTransaction::issue_receipt`
+    pub(crate) fn issue(vote: &Signed<VoteTransaction>, authority_secret: &SecretKey) -> Self {
+        let vote_id = vote.id();
+        let election_id = vote.election;
+        let commitment = commitment(vote);
+        let authority_signature = sign_detached(
+            authority_secret,
+            &signed_bytes(vote_id, election_id, &commitment),
+        );
+
+        VoteReceipt {
+            vote_id,
+            election_id,
+            commitment,
+            authority_signature,
+        }
+    }
+
+    /// Verify that the receipt was signed by the election authority, and that it commits to the given vote
+    pub fn verify(
+        &self,
+        authority_public: &PublicKey,
+        vote: &Signed<VoteTransaction>,
+    ) -> Result<(), ReceiptError> {
+        let message = signed_bytes(self.vote_id, self.election_id, &self.commitment);
+        authority_public
+            .verify(&message, &self.authority_signature)
+            .map_err(|_| ReceiptError::InvalidSignature)?;
+
+        if vote.id() != self.vote_id || vote.election != self.election_id {
+            return Err(ReceiptError::WrongVote(vote.id()));
+        }
+        if !bool::from(commitment(vote).ct_eq(&self.commitment)) {
+            return Err(ReceiptError::CommitmentMismatch);
+        }
+
+        Ok(())
+    }
+}
+
+/// Verify a receipt against the votes recorded in `store`.
+///
+/// Confirms that the receipt was issued by the election's authority for the vote cast with `anonymous_key`, and that
+/// the vote recorded under that key is exactly the one the receipt commits to. The voter needs only their anonymous
+/// key and the receipt, not a copy of the vote, and reveals nothing about who they are. A vote that was later
+/// challenged (see `ChallengeTransaction`) is still recorded, but fails with `VoteChallenged` since it will not be
+/// counted.
+pub fn verify_receipt<S: Store>(
+    store: &S,
+    election_id: Identifier,
+    anonymous_key: &PublicKey,
+    receipt: &VoteReceipt,
+) -> Result<(), ValidationError> {
+    if receipt.election_id != election_id {
+        return Err(ValidationError::ElectionMismatch);
+    }
+    if receipt.vote_id != VoteTransaction::build_id(election_id, anonymous_key) {
+        return Err(ReceiptError::WrongVote(receipt.vote_id).into());
+    }
+
+    let election = store.get_election(election_id)?;
+    let vote = store.get_vote(receipt.vote_id)?;
+    if vote.anonymous_key != *anonymous_key {
+        return Err(ReceiptError::WrongVote(receipt.vote_id).into());
+    }
+    receipt.verify(&election.authority_public, &vote)?;
+
+    let challenge_id = ChallengeTransaction::build_id(election_id, vote.id());
+    if store.contains(challenge_id) {
+        return Err(ValidationError::VoteChallenged(vote.id()));
+    }
+
+    Ok(())
+}
+
+fn commitment(vote: &Signed<VoteTransaction>) -> [u8; 32] {
+    let bytes = serde_cbor::to_vec(vote).expect("cryptoballot: unexpected error serializing vote");
+    Sha256::digest(&bytes).into()
+}
+
+fn signed_bytes(vote_id: Identifier, election_id: Identifier, commitment: &[u8; 32]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(96);
+    bytes.extend_from_slice(&vote_id.to_array());
+    bytes.extend_from_slice(&election_id.to_array());
+    bytes.extend_from_slice(commitment);
+    bytes
+}
@@ -0,0 +1,504 @@
+use crate::*;
+use cryptid::curve::CurveElem;
+use cryptid::threshold::KeygenCommitment;
+use curve25519_dalek::scalar::Scalar as DalekScalar;
+use ed25519_dalek::PublicKey;
+use indexmap::IndexMap;
+
+/// Transaction 12: TrusteeKeyRevocation
+///
+/// Revokes one of a trustee's KeyGenPublicKey transactions, for example if the trustee discovers that it was compromised.
+/// Revocation is only possible if the election sets `revocations_allowed`, and only before the EncryptionKey transaction
+/// is posted (after which votes may be cast). The trustee must then post a replacement KeyGenPublicKey transaction with
+/// the next `revision` before the EncryptionKey transaction will validate.
+///
+/// Replacing the key only replaces the trustee's KeyGenPublicKey transaction and its proof. If the trustee's keygen
+/// shares themselves are compromised, key generation must be re-run in a new election.
+///
+/// This is posted by the trustee, to replace its own key while key generation is still under way. Once the
+/// EncryptionKey transaction is posted the key can no longer be replaced, and the election authority instead marks the
+/// trustee as inactive with a TrusteeRevocation transaction.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TrusteeKeyRevocationTransaction {
+    pub id: Identifier,
+    pub election: Identifier,
+    pub trustee_index: u8,
+
+    #[serde(with = "EdPublicKeyHex")]
+    pub trustee_public_key: PublicKey,
+
+    /// The revision of the KeyGenPublicKey transaction being revoked
+    pub revision: u8,
+
+    /// The public key from the KeyGenPublicKey transaction being revoked
+    pub old_public_key: cryptid::elgamal::PublicKey,
+
+    /// Optional human-readable reason for the revocation
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+
+    /// Optional creation time, in seconds since the UNIX epoch
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<u64>,
+}
+
+impl TrusteeKeyRevocationTransaction {
+    /// Create a new TrusteeKeyRevocationTransaction revoking the given KeyGenPublicKey transaction
+    pub fn new(revoked: &KeyGenPublicKeyTransaction, reason: Option<String>) -> Self {
+        TrusteeKeyRevocationTransaction {
+            id: Self::build_id(revoked.election, revoked.trustee_index, revoked.revision),
+            election: revoked.election,
+            trustee_index: revoked.trustee_index,
+            trustee_public_key: revoked.trustee_public_key,
+            revision: revoked.revision,
+            old_public_key: revoked.public_key.clone(),
+            reason,
+            created_at: None,
+        }
+    }
+
+    pub fn build_id(election_id: Identifier, trustee_index: u8, revision: u8) -> Identifier {
+        let mut unique_info = [0; 16];
+        unique_info[0] = trustee_index;
+        unique_info[1] = revision;
+        Identifier::new(
+            election_id,
+            TransactionType::TrusteeKeyRevocation,
+            Some(unique_info),
+        )
+    }
+}
+
+impl CryptoBallotTransaction for TrusteeKeyRevocationTransaction {
+    #[inline(always)]
+    fn id(&self) -> Identifier {
+        self.id
+    }
+
+    #[inline(always)]
+    fn public(&self) -> Option<PublicKey> {
+        Some(self.trustee_public_key)
+    }
+
+    #[inline(always)]
+    fn election_id(&self) -> Identifier {
+        self.election
+    }
+
+    #[inline(always)]
+    fn tx_type() -> TransactionType {
+        TransactionType::TrusteeKeyRevocation
+    }
+
+    /// Validate the transaction
+    ///
+    /// The validation does the following:
+    ///  - Validates that the election allows revocations
+    ///  - Validates that this transaction has been signed by a valid trustee
+    ///  - Validates that the revoked KeyGenPublicKey transaction exists and has the same public key
+    ///  - Validates that the EncryptionKey transaction has not yet been posted
+    fn validate_tx<S: Store>(&self, store: &S) -> Result<(), ValidationError> {
+        if Self::build_id(self.election, self.trustee_index, self.revision) != self.id {
+            return Err(ValidationError::IdentifierBadComposition);
+        }
+
+        let election = store.get_election(self.election)?;
+
+        if !election.revocations_allowed {
+            return Err(ValidationError::RevocationsNotAllowed);
+        }
+
+        match election.get_trustee(self.trustee_index) {
+            Some(trustee) if trustee.public_key == self.trustee_public_key => {}
+            _ => return Err(ValidationError::TrusteeDoesNotExist(self.trustee_index)),
+        }
+
+        let revoked_id = KeyGenPublicKeyTransaction::build_revision_id(
+            self.election,
+            self.trustee_index,
+            self.revision,
+        );
+        let revoked = store.get_keygen_public_key(revoked_id)?;
+        if revoked.public_key != self.old_public_key {
+            return Err(ValidationError::RevokedKeyMismatch(self.trustee_index));
+        }
+
+        if store.get_encryption_key(self.election).is_ok() {
+            return Err(ValidationError::RevocationAfterEncryptionKey);
+        }
+
+        Ok(())
+    }
+}
+
+/// Transaction 15: TrusteeRevocation
+///
+/// Posted by the election authority to mark a trustee as inactive, for example if the trustee's secret key is
+/// compromised after the EncryptionKey transaction is posted, when its KeyGenPublicKey can no longer be replaced (see
+/// `TrusteeKeyRevocationTransaction`). Partial decryptions from a revoked trustee are rejected, and decryptions may
+/// not use them.
+///
+/// A trustee can't be revoked once it has posted a partial decryption. Revocations are validated before partial
+/// decryptions when the election is re-validated, so revoking the trustee would invalidate partial decryptions that
+/// were accepted when they were posted.
+///
+/// A trustee can only be revoked if enough trustees remain active to meet the election's `trustees_threshold`, and
+/// only before any partial decryption is posted. Each remaining trustee must then post a TrusteeReshare transaction,
+/// which refreshes the remaining trustees' shares of the election key so that the revoked trustee's share no longer
+/// combines with them. Partial decryptions are rejected until every remaining trustee has re-shared.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TrusteeRevocationTransaction {
+    pub id: Identifier,
+    pub election: Identifier,
+
+    #[serde(with = "EdPublicKeyHex")]
+    pub authority_public_key: PublicKey,
+
+    /// The index of the revoked trustee
+    pub trustee_index: u8,
+
+    /// Optional human-readable reason for the revocation
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+
+    /// Optional creation time, in seconds since the UNIX epoch
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<u64>,
+}
+
+impl TrusteeRevocationTransaction {
+    /// Create a new TrusteeRevocationTransaction, to be signed by the election authority
+    pub fn new(election: &ElectionTransaction, trustee_index: u8, reason: Option<String>) -> Self {
+        TrusteeRevocationTransaction {
+            id: Self::build_id(election.id, trustee_index),
+            election: election.id,
+            authority_public_key: election.authority_public,
+            trustee_index,
+            reason,
+            created_at: None,
+        }
+    }
+
+    pub fn build_id(election_id: Identifier, trustee_index: u8) -> Identifier {
+        let mut unique_info = [0; 16];
+        unique_info[0] = trustee_index;
+        Identifier::new(
+            election_id,
+            TransactionType::TrusteeRevocation,
+            Some(unique_info),
+        )
+    }
+}
+
+impl AuthorityTransaction for TrusteeRevocationTransaction {
+    fn set_authority_public(&mut self, public: PublicKey) {
+        self.authority_public_key = public;
+    }
+}
+
+impl CryptoBallotTransaction for TrusteeRevocationTransaction {
+    #[inline(always)]
+    fn id(&self) -> Identifier {
+        self.id
+    }
+
+    #[inline(always)]
+    fn public(&self) -> Option<PublicKey> {
+        Some(self.authority_public_key)
+    }
+
+    #[inline(always)]
+    fn election_id(&self) -> Identifier {
+        self.election
+    }
+
+    #[inline(always)]
+    fn tx_type() -> TransactionType {
+        TransactionType::TrusteeRevocation
+    }
+
+    /// Validate the transaction
+    ///
+    /// The validation does the following:
+    ///  - Validates that this transaction has been signed by the election authority
+    ///  - Validates that the revoked trustee exists
+    ///  - Validates that no partial decryptions have been posted
+    ///  - Validates that enough trustees remain active to meet the election's trustees_threshold
+    fn validate_tx<S: Store>(&self, store: &S) -> Result<(), ValidationError> {
+        if Self::build_id(self.election, self.trustee_index) != self.id {
+            return Err(ValidationError::IdentifierBadComposition);
+        }
+
+        let election = store.get_election(self.election)?;
+
+        if self.authority_public_key != election.authority_public {
+            return Err(ValidationError::AuthorityPublicKeyMismatch);
+        }
+
+        if election.get_trustee(self.trustee_index).is_none() {
+            return Err(ValidationError::TrusteeDoesNotExist(self.trustee_index));
+        }
+
+        if store
+            .get_all_partial_decryptions(self.election)
+            .iter()
+            .any(|partial| partial.trustee_index == self.trustee_index)
+        {
+            return Err(ValidationError::TrusteeAlreadyDecrypted(self.trustee_index));
+        }
+
+        // The remaining trustees' shares are refreshed after a revocation, so they must not have used them yet
+        if !store.get_all_partial_decryptions(self.election).is_empty() {
+            return Err(ValidationError::PartialDecryptionsAlreadyPosted);
+        }
+
+        // This transaction may already be in the store when the election is re-validated
+        let active = election
+            .trustees
+            .iter()
+            .filter(|trustee| {
+                trustee.index != self.trustee_index
+                    && !store.is_trustee_revoked(self.election, trustee.index)
+            })
+            .count();
+        if active < election.trustees_threshold as usize {
+            return Err(ValidationError::NotEnoughActiveTrustees(
+                election.trustees_threshold as usize,
+                active,
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Transaction 16: TrusteeReshare
+///
+/// Posted by each remaining trustee after a TrusteeRevocation, to refresh the remaining trustees' shares of the
+/// election key so that the revoked trustee's share no longer combines with them.
+///
+/// The trustee picks a random polynomial of degree `trustees_threshold - 1` with a zero constant term, commits to its
+/// other coefficients, and sends its value at every remaining trustee's index to that trustee, encrypted like a keygen
+/// share. Each remaining trustee adds the values it receives to the keygen share it holds from the revoked trustee
+/// (see `Trustee::apply_reshares`). The polynomials' constant terms are zero, so the election key is unchanged, but
+/// the refreshed shares no longer lie on the same polynomial as the revoked trustee's share. A trustee's public key
+/// share moves by the same amount, which anyone can compute from the commitments (see
+/// `Store::get_decryption_public_keys`).
+///
+/// Every remaining trustee must re-share before partial decryptions are accepted, so that the refreshed shares depend
+/// on a polynomial that an honest trustee chose. A trustee that is itself revoked after re-sharing doesn't invalidate
+/// its re-share.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TrusteeReshareTransaction {
+    pub id: Identifier,
+    pub election: Identifier,
+    pub trustee_index: u8,
+
+    #[serde(with = "EdPublicKeyHex")]
+    pub trustee_public_key: PublicKey,
+
+    /// The index of the revoked trustee whose key share is being re-shared
+    pub revoked_index: u8,
+
+    /// Commitments to the re-sharing polynomial's coefficients, from the first-degree coefficient up
+    pub commitment: Vec<CurveElem>,
+
+    /// The re-sharing polynomial's value at each remaining trustee's index, encrypted to that trustee
+    #[serde(with = "indexmap::serde_seq")]
+    pub shares: IndexMap<u8, EncryptedShare>,
+
+    /// Optional creation time, in seconds since the UNIX epoch
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<u64>,
+}
+
+impl TrusteeReshareTransaction {
+    /// Create a new TrusteeReshareTransaction from the output of `Trustee::generate_reshares`
+    pub fn new(
+        election_id: Identifier,
+        trustee_index: u8,
+        trustee_public_key: PublicKey,
+        revoked_index: u8,
+        commitment: Vec<CurveElem>,
+        shares: IndexMap<u8, EncryptedShare>,
+    ) -> Self {
+        TrusteeReshareTransaction {
+            id: Self::build_id(election_id, trustee_index, revoked_index),
+            election: election_id,
+            trustee_index,
+            trustee_public_key,
+            revoked_index,
+            commitment,
+            shares,
+            created_at: None,
+        }
+    }
+
+    pub fn build_id(election_id: Identifier, trustee_index: u8, revoked_index: u8) -> Identifier {
+        let mut unique_info = [0; 16];
+        unique_info[0] = trustee_index;
+        unique_info[1] = revoked_index;
+        Identifier::new(
+            election_id,
+            TransactionType::TrusteeReshare,
+            Some(unique_info),
+        )
+    }
+
+    /// The re-sharing polynomial's value at a trustee's index, in the exponent, computed from the commitments
+    pub fn public_share(&self, trustee_index: u8) -> CurveElem {
+        let x = DalekScalar::from(trustee_index as u64);
+        let mut power = DalekScalar::one();
+
+        let mut share = CurveElem::generator().scaled(&to_cryptid_scalar(&DalekScalar::zero()));
+        for elem in self.commitment.iter() {
+            power *= x;
+            share = share + elem.scaled(&to_cryptid_scalar(&power));
+        }
+        share
+    }
+}
+
+impl CryptoBallotTransaction for TrusteeReshareTransaction {
+    #[inline(always)]
+    fn id(&self) -> Identifier {
+        self.id
+    }
+
+    #[inline(always)]
+    fn public(&self) -> Option<PublicKey> {
+        Some(self.trustee_public_key)
+    }
+
+    #[inline(always)]
+    fn election_id(&self) -> Identifier {
+        self.election
+    }
+
+    #[inline(always)]
+    fn tx_type() -> TransactionType {
+        TransactionType::TrusteeReshare
+    }
+
+    /// Validate the transaction
+    ///
+    /// The validation does the following:
+    ///  - Validates that this transaction has been signed by a valid trustee, other than the revoked trustee
+    ///  - Validates that the election authority has revoked the revoked trustee
+    ///  - Validates that there is a commitment to each coefficient of the re-sharing polynomial but its constant term
+    ///  - Validates that there is a share for every remaining trustee, and none for the revoked trustee
+    ///  - Validates that no partial decryptions have been posted
+    fn validate_tx<S: Store>(&self, store: &S) -> Result<(), ValidationError> {
+        if Self::build_id(self.election, self.trustee_index, self.revoked_index) != self.id {
+            return Err(ValidationError::IdentifierBadComposition);
+        }
+
+        let election = store.get_election(self.election)?;
+
+        match election.get_trustee(self.trustee_index) {
+            Some(trustee) if trustee.public_key == self.trustee_public_key => {}
+            _ => return Err(ValidationError::TrusteeDoesNotExist(self.trustee_index)),
+        }
+        if self.trustee_index == self.revoked_index {
+            return Err(ValidationError::InvalidTrusteeIndex(self.trustee_index));
+        }
+
+        if !store.is_trustee_revoked(self.election, self.revoked_index) {
+            return Err(ValidationError::TrusteeNotRevoked(self.revoked_index));
+        }
+
+        if self.commitment.len() + 1 != election.trustees_threshold as usize {
+            return Err(ValidationError::InvalidReshareCommitment(
+                self.trustee_index,
+            ));
+        }
+
+        for recipient in self.shares.keys() {
+            if *recipient == self.revoked_index || election.get_trustee(*recipient).is_none() {
+                return Err(ValidationError::InvalidTrusteeIndex(*recipient));
+            }
+        }
+        // Trustees revoked since this transaction was posted may also have a share
+        for trustee in election.trustees.iter() {
+            if !self.shares.contains_key(&trustee.index)
+                && !store.is_trustee_revoked(self.election, trustee.index)
+            {
+                return Err(ValidationError::TrusteeShareMissing(trustee.index));
+            }
+        }
+
+        if !store.get_all_partial_decryptions(self.election).is_empty() {
+            return Err(ValidationError::PartialDecryptionsAlreadyPosted);
+        }
+
+        Ok(())
+    }
+}
+
+/// Refresh the trustees' public key shares with the re-sharing polynomials of the given TrusteeReshare transactions.
+///
+/// Each KeyGenPublicKey transaction's `public_key_proof` is replaced with the public share of the trustee's refreshed
+/// key share, which is what its partial decryptions are verified against once the trustee has applied the re-shares.
+pub fn refresh_public_keys(
+    mut pubkeys: Vec<KeyGenPublicKeyTransaction>,
+    reshares: &[TrusteeReshareTransaction],
+) -> Result<Vec<KeyGenPublicKeyTransaction>, ValidationError> {
+    if reshares.is_empty() {
+        return Ok(pubkeys);
+    }
+
+    for pubkey in pubkeys.iter_mut() {
+        let index = pubkey.trustee_index;
+        let mut share = public_key_share(index, &pubkey.public_key_proof)?;
+        for reshare in reshares {
+            share = share + reshare.public_share(index);
+        }
+        pubkey.public_key_proof = serde_json::to_value(share)
+            .and_then(serde_json::from_value)
+            .map_err(|_| ValidationError::InvalidPublicKeyShare(index))?;
+    }
+
+    Ok(pubkeys)
+}
+
+// The commitments in a KeygenCommitment, which cryptid only exposes through its serialized form
+#[derive(Serialize, Deserialize)]
+struct CommitmentElems {
+    elems: Vec<CurveElem>,
+}
+
+// Add the re-sharing polynomials' commitments to a revoked trustee's keygen commitment, so that it commits to the
+// keygen polynomial plus the re-sharing polynomials. The constant term, the trustee's contribution to the election
+// key, is unchanged.
+pub(crate) fn refreshed_commitment(
+    revoked_index: u8,
+    commitment: &KeygenCommitment,
+    reshares: &[&TrusteeReshareTransaction],
+) -> Result<KeygenCommitment, ValidationError> {
+    let mut commitment: CommitmentElems = serde_json::to_value(commitment)
+        .and_then(serde_json::from_value)
+        .map_err(|_| ValidationError::InvalidReshareCommitment(revoked_index))?;
+
+    for reshare in reshares {
+        if reshare.commitment.len() + 1 != commitment.elems.len() {
+            return Err(ValidationError::InvalidReshareCommitment(
+                reshare.trustee_index,
+            ));
+        }
+        for (elem, reshare_elem) in commitment.elems[1..]
+            .iter_mut()
+            .zip(reshare.commitment.iter())
+        {
+            *elem = elem.clone() + reshare_elem.clone();
+        }
+    }
+
+    serde_json::to_value(commitment)
+        .and_then(serde_json::from_value)
+        .map_err(|_| ValidationError::InvalidReshareCommitment(revoked_index))
+}
@@ -0,0 +1,135 @@
+use crate::*;
+use cryptid::threshold::{DecryptShare, PubkeyProof, Threshold, ThresholdParty};
+use rand::{CryptoRng, RngCore};
+
+/// A threshold encryption scheme that votes can be encrypted with.
+///
+/// Votes are encrypted to a single public key, and decrypted by combining the decryption shares of at least
+/// `trustees_threshold` trustees, each of which can be verified against that trustee's public share verification key.
+pub trait EncryptionScheme {
+    type PublicKey;
+    type Ciphertext;
+
+    /// A single trustee's secret share of the election key
+    type SecretShare;
+
+    /// A single trustee's share of the decryption of a ciphertext
+    type DecryptShare;
+
+    /// Public information used to verify a trustee's decryption shares
+    type ShareVerificationKey;
+
+    /// Encrypt a plaintext to the election key
+    fn encrypt<R: CryptoRng + RngCore>(
+        public_key: &Self::PublicKey,
+        plaintext: &[u8],
+        rng: &mut R,
+    ) -> Self::Ciphertext;
+
+    /// Create a trustee's decryption share of a ciphertext
+    fn partial_decrypt<R: CryptoRng + RngCore>(
+        secret_share: &Self::SecretShare,
+        ciphertext: &Self::Ciphertext,
+        rng: &mut R,
+    ) -> Self::DecryptShare;
+
+    /// Combine the decryption shares of at least `threshold` trustees, keyed by trustee index, into the plaintext
+    fn combine(
+        ciphertext: &Self::Ciphertext,
+        threshold: u8,
+        shares: &[(u8, &Self::ShareVerificationKey, &Self::DecryptShare)],
+    ) -> Result<Vec<u8>, ValidationError>;
+}
+
+/// Threshold ElGamal as implemented by cryptid, used for all votes
+pub struct ElGamal;
+
+/// The encryption scheme used by `encrypt_vote`, `decrypt_vote` and the trustees
+pub type DefaultEncryptionScheme = ElGamal;
+
+/// The group that an election's votes are encrypted in, declared by `ElectionTransaction::crypto_params`.
+///
+/// cryptid only implements ElGamal over ristretto255, so that is currently the only choice. Supporting another
+/// curve means adding a variant here along with an `EncryptionScheme` for it.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum CryptoParams {
+    /// ElGamal over the ristretto255 prime-order group (curve25519), as implemented by cryptid
+    Ristretto255,
+}
+
+impl Default for CryptoParams {
+    fn default() -> Self {
+        CryptoParams::Ristretto255
+    }
+}
+
+impl CryptoParams {
+    pub(crate) fn is_default(&self) -> bool {
+        *self == CryptoParams::default()
+    }
+
+    /// Encrypt the selections for a single contest in this group
+    pub fn encrypt_vote<R: CryptoRng + RngCore>(
+        &self,
+        encryption_key: &cryptid::elgamal::PublicKey,
+        vote: Vec<Selection>,
+        rng: &mut R,
+    ) -> Result<Vec<cryptid::elgamal::Ciphertext>, Error> {
+        match self {
+            CryptoParams::Ristretto255 => {
+                encrypt_vote_with::<ElGamal, R>(encryption_key, vote, rng)
+            }
+        }
+    }
+
+    /// Check that every ciphertext is an encryption in this group
+    pub fn validate_ciphertexts(
+        &self,
+        _ciphertexts: &[cryptid::elgamal::Ciphertext],
+    ) -> Result<(), ValidationError> {
+        match self {
+            // A cryptid ciphertext can only hold ristretto255 points, so there is nothing further to check
+            CryptoParams::Ristretto255 => Ok(()),
+        }
+    }
+}
+
+impl EncryptionScheme for ElGamal {
+    type PublicKey = cryptid::elgamal::PublicKey;
+    type Ciphertext = cryptid::elgamal::Ciphertext;
+    type SecretShare = ThresholdParty;
+    type DecryptShare = DecryptShare;
+    type ShareVerificationKey = PubkeyProof;
+
+    fn encrypt<R: CryptoRng + RngCore>(
+        public_key: &Self::PublicKey,
+        plaintext: &[u8],
+        rng: &mut R,
+    ) -> Self::Ciphertext {
+        public_key.encrypt(rng, plaintext)
+    }
+
+    fn partial_decrypt<R: CryptoRng + RngCore>(
+        secret_share: &Self::SecretShare,
+        ciphertext: &Self::Ciphertext,
+        rng: &mut R,
+    ) -> Self::DecryptShare {
+        secret_share.decrypt_share(ciphertext, rng)
+    }
+
+    fn combine(
+        ciphertext: &Self::Ciphertext,
+        threshold: u8,
+        shares: &[(u8, &Self::ShareVerificationKey, &Self::DecryptShare)],
+    ) -> Result<Vec<u8>, ValidationError> {
+        let mut decrypt = cryptid::threshold::Decryption::new(threshold as usize, ciphertext);
+        for (trustee_index, proof, share) in shares {
+            decrypt.add_share(*trustee_index as usize, proof, share);
+        }
+
+        decrypt
+            .finish()
+            .map_err(ValidationError::VoteDecryptionFailed)
+    }
+}
@@ -1,5 +1,6 @@
 // We define in our crate:
 use crate::Error;
+use cryptid::threshold::DecryptShare;
 use ed25519_dalek::PublicKey;
 use ed25519_dalek::Signature;
 use rsa::RSAPublicKey;
@@ -75,3 +76,20 @@ impl Hex<RSAPublicKey> for RSAPublicKeyHex {
         Ok(serde_cbor::from_slice(bytes)?)
     }
 }
+
+// a single-purpose type for use in `#[serde(with)]`
+//
+// The canonical bytes of a DecryptShare are its CBOR encoding.
+pub enum DecryptShareHex {}
+
+impl Hex<DecryptShare> for DecryptShareHex {
+    type Error = Error;
+
+    fn create_bytes(share: &DecryptShare) -> Cow<[u8]> {
+        serde_cbor::to_vec(share).unwrap().into()
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<DecryptShare, Error> {
+        Ok(serde_cbor::from_slice(bytes)?)
+    }
+}
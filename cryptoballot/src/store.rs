@@ -1,5 +1,6 @@
 use crate::*;
 use std::collections::BTreeMap;
+use std::sync::{PoisonError, RwLock, RwLockReadGuard};
 use thiserror::Error;
 
 #[derive(Debug, Clone, Error)]
@@ -20,8 +21,67 @@ pub trait Store {
     /// Get a transaction of an unknown type
     fn get_transaction(&self, id: Identifier) -> Option<SignedTransaction>;
 
+    /// Check whether a transaction is in the store.
+    ///
+    /// The default implementation fetches the whole transaction. Stores that can look up a key without decoding the
+    /// transaction should override it.
+    fn contains(&self, id: Identifier) -> bool {
+        self.get_transaction(id).is_some()
+    }
+
+    /// Get all transactions with identifiers in `[start, end_inclusive]`.
+    ///
+    /// Implementations must return the transactions in ascending identifier order, so that every verifier iterates
+    /// over an election's transactions in the same order and so reaches the same tally.
     fn range(&self, start: Identifier, end_inclusive: Identifier) -> Vec<SignedTransaction>;
 
+    /// Get all transactions with identifiers in `[start, end)`, sorted in ascending identifier order.
+    ///
+    /// To page through a large range, pass the identifier following the last one returned as the next `start`.
+    fn scan_range(&self, start: Identifier, end: Identifier) -> Vec<SignedTransaction> {
+        if start >= end {
+            return Vec::new();
+        }
+
+        let mut txs: Vec<SignedTransaction> = self
+            .range(start, end)
+            .into_iter()
+            .filter(|tx| tx.id() != end)
+            .collect();
+        txs.sort_by_key(|tx| tx.id());
+        txs
+    }
+
+    /// Validate a transaction and store it as a single atomic operation.
+    ///
+    /// Validating and then storing in two steps leaves a gap in which another writer could change the store.
+    /// Stores that are written to by their host (exonum, sawtooth) do not support this and return `StoreError::ReadOnly`.
+    fn apply_transaction(&self, _tx: SignedTransaction) -> Result<(), ApplyError> {
+        Err(StoreError::ReadOnly.into())
+    }
+
+    /// Store a transaction without validating it, unless a different transaction is already stored with its
+    /// identifier, in which case the store is left unchanged and `StoreError::Conflict` is returned.
+    ///
+    /// Storing the same transaction again is not a conflict. The check and the insert are a single atomic operation.
+    /// Stores that are written to by their host do not support this and return `StoreError::ReadOnly`.
+    fn set_checked(&self, _tx: SignedTransaction) -> Result<(), StoreError> {
+        Err(StoreError::ReadOnly)
+    }
+
+    /// Remove a transaction without validating the store afterwards, returning it if it was present.
+    ///
+    /// Transactions that depend on the removed transaction are left in place. Stores that are written to by their
+    /// host, or that are append-only such as `TransactionLog`, do not support this and return `StoreError::ReadOnly`.
+    fn remove(&self, _id: Identifier) -> Result<Option<SignedTransaction>, StoreError> {
+        Err(StoreError::ReadOnly)
+    }
+
+    /// Get all transactions of a type in an election, sorted in ascending identifier order.
+    ///
+    /// The order does not depend on the order the transactions were stored in, so tallies that iterate over the
+    /// result are reproducible. The result is sorted here even though `range` is required to be, since a tally
+    /// that silently depends on a store's iteration order is hard to track down.
     fn get_multiple(
         &self,
         election_id: Identifier,
@@ -30,7 +90,111 @@ pub trait Store {
         let start = Identifier::start(election_id, tx_type, None);
         let end = Identifier::end(election_id, tx_type, None);
 
+        let mut txs = self.range(start, end);
+        txs.sort_by_key(|tx| tx.id());
+        txs
+    }
+
+    /// Get the identifiers of every election in the store, in ascending order
+    fn list_elections(&self) -> Vec<Identifier> {
+        self.range(Identifier::first(), Identifier::last())
+            .iter()
+            .map(|tx| tx.id())
+            .filter(|id| id.transaction_type == TransactionType::Election)
+            .collect()
+    }
+
+    /// Get all observer_acknowledgment transactions for an election
+    fn get_observer_acknowledgments(
+        &self,
+        election_id: Identifier,
+    ) -> Vec<Signed<ObserverAcknowledgmentTransaction>> {
+        self.get_multiple(election_id, TransactionType::ObserverAcknowledgment)
+            .into_iter()
+            .map(|tx| tx.into())
+            .collect()
+    }
+
+    /// Re-validate every transaction in the store, returning each transaction that fails along with its error.
+    ///
+    /// Transactions are validated in dependency order: grouped by election, then by transaction type in the order an
+    /// election progresses (election, observer acknowledgments, keygen, encryption key, votes, challenges, voting end, mixes,
+    /// partial decryptions, decryptions), then by id. A trustee's key revocation is validated directly after the key
+    /// it revokes, and before its replacement. Trustee revocations are validated with the keygen transactions, and the
+    /// re-shares that follow them with the encryption key. Each transaction is validated only against the transactions
+    /// before it that passed, so a corrupted transaction also causes the transactions depending on it to fail.
+    fn validate_all(&self) -> Vec<(Identifier, ValidationError)> {
+        let mut txs = self.range(Identifier::first(), Identifier::last());
+        sort_by_dependency_order(&mut txs);
+
+        let validated = MemStore::default();
+        let mut errors = Vec::new();
+        for tx in txs {
+            let id = tx.id();
+            if let Err(ApplyError::Validation(e)) = validated.apply_transaction(tx) {
+                errors.push((id, e));
+            }
+        }
+        errors
+    }
+
+    /// Get every vote in an election that has not been challenged, sorted in ascending identifier order.
+    ///
+    /// These are the votes that are mixed, decrypted and tallied. A challenged vote has had its plaintext revealed
+    /// (see `ChallengeTransaction`), so it is not counted.
+    fn get_cast_votes(&self, election_id: Identifier) -> Vec<Signed<VoteTransaction>> {
+        self.get_multiple(election_id, TransactionType::Vote)
+            .into_iter()
+            .filter(|vote| {
+                let challenge_id = ChallengeTransaction::build_id(election_id, vote.id());
+                !self.contains(challenge_id)
+            })
+            .map(|tx| tx.into())
+            .collect()
+    }
+
+    /// Get the current keygen_public_key transaction for each trustee in an election, ordered by trustee index.
+    ///
+    /// The current transaction is the one with the highest revision, which may itself have been revoked.
+    fn get_current_keygen_public_keys(
+        &self,
+        election_id: Identifier,
+    ) -> Vec<Signed<KeyGenPublicKeyTransaction>> {
+        let mut current: Vec<Signed<KeyGenPublicKeyTransaction>> = Vec::new();
+        for tx in self.get_multiple(election_id, TransactionType::KeyGenPublicKey) {
+            let tx: Signed<KeyGenPublicKeyTransaction> = tx.into();
+            match current.last_mut() {
+                Some(last) if last.trustee_index == tx.trustee_index => {
+                    if tx.revision > last.revision {
+                        *last = tx;
+                    }
+                }
+                _ => current.push(tx),
+            }
+        }
+        current
+    }
+
+    /// Get the current keygen_public_key transaction for a single trustee
+    fn get_current_keygen_public_key(
+        &self,
+        election_id: Identifier,
+        trustee_index: u8,
+    ) -> Result<Signed<KeyGenPublicKeyTransaction>, TransactionNotFound> {
+        let mask = [trustee_index];
+        let start = Identifier::start(election_id, TransactionType::KeyGenPublicKey, Some(&mask));
+        let end = Identifier::end(election_id, TransactionType::KeyGenPublicKey, Some(&mask));
+
         self.range(start, end)
+            .into_iter()
+            .map(|tx| -> Signed<KeyGenPublicKeyTransaction> { tx.into() })
+            .max_by_key(|tx| tx.revision)
+            .ok_or_else(|| {
+                TransactionNotFound::new(
+                    KeyGenPublicKeyTransaction::build_id(election_id, trustee_index),
+                    TransactionType::KeyGenPublicKey,
+                )
+            })
     }
 
     // TODO: Macro these methods
@@ -71,6 +235,18 @@ pub trait Store {
         }
     }
 
+    /// Get the encryption_key transaction for an election, which commits the joint public key that votes are encrypted to
+    fn get_encryption_key(
+        &self,
+        election_id: Identifier,
+    ) -> Result<Signed<EncryptionKeyTransaction>, TransactionNotFound> {
+        let id = EncryptionKeyTransaction::build_id(election_id);
+        match self.get_transaction(id) {
+            Some(SignedTransaction::EncryptionKey(e)) => Ok(e),
+            _ => Err(TransactionNotFound::new(id, TransactionType::EncryptionKey)),
+        }
+    }
+
     /// Get an Vote transaction
     fn get_vote(&self, id: Identifier) -> Result<Signed<VoteTransaction>, TransactionNotFound> {
         let tx = self.get_transaction(id);
@@ -116,6 +292,128 @@ pub trait Store {
         }
     }
 
+    /// Find a partial decryption by its PartialDecryption transaction ID, whether it was posted on its own or as part
+    /// of one of the trustee's PartialDecryptionBatch transactions.
+    fn find_partial_decryption(&self, id: Identifier) -> Option<PartialDecryptionTransaction> {
+        if let Ok(partial) = self.get_partial_decryption(id) {
+            return Some(partial.tx);
+        }
+
+        self.find_partial_decryption_batch(id)?
+            .partial_decryptions()
+            .into_iter()
+            .find(|partial| partial.id == id)
+    }
+
+    /// Find the PartialDecryptionBatch transaction holding a partial decryption, given its PartialDecryption
+    /// transaction ID. Returns None if the partial decryption was not posted in a batch.
+    fn find_partial_decryption_batch(
+        &self,
+        id: Identifier,
+    ) -> Option<Signed<PartialDecryptionBatchTransaction>> {
+        // The last byte of a partial decryption's unique-info is always the trustee index
+        let trustee_index = [id.unique_info[15]];
+        let start = Identifier::start(
+            id,
+            TransactionType::PartialDecryptionBatch,
+            Some(&trustee_index),
+        );
+        let end = Identifier::end(
+            id,
+            TransactionType::PartialDecryptionBatch,
+            Some(&trustee_index),
+        );
+        self.range(start, end).into_iter().find_map(|tx| {
+            let batch: Signed<PartialDecryptionBatchTransaction> = tx.into();
+            if batch
+                .partial_decryptions()
+                .iter()
+                .any(|partial| partial.id == id)
+            {
+                Some(batch)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Check whether the election authority has revoked a trustee with a TrusteeRevocation transaction
+    fn is_trustee_revoked(&self, election_id: Identifier, trustee_index: u8) -> bool {
+        let id = TrusteeRevocationTransaction::build_id(election_id, trustee_index);
+        self.contains(id)
+    }
+
+    /// Get every TrusteeReshare transaction in an election, sorted in ascending identifier order
+    fn get_trustee_reshares(
+        &self,
+        election_id: Identifier,
+    ) -> Vec<Signed<TrusteeReshareTransaction>> {
+        self.get_multiple(election_id, TransactionType::TrusteeReshare)
+            .into_iter()
+            .map(|tx| tx.into())
+            .collect()
+    }
+
+    /// Get the keygen_public_key transactions that partial decryptions are verified against, ordered by trustee index.
+    ///
+    /// These are the current keygen_public_key transactions, with each trustee's public key share refreshed by the
+    /// election's TrusteeReshare transactions (see `refresh_public_keys`). The remaining trustees' key shares aren't
+    /// settled until each of them has re-shared every revoked trustee's key share, so until then this fails with
+    /// `ValidationError::MissingTrusteeReshare`.
+    fn get_decryption_public_keys(
+        &self,
+        election_id: Identifier,
+    ) -> Result<Vec<KeyGenPublicKeyTransaction>, ValidationError> {
+        let election = self.get_election(election_id)?;
+        let (revoked, remaining): (Vec<&Trustee>, Vec<&Trustee>) = election
+            .trustees
+            .iter()
+            .partition(|trustee| self.is_trustee_revoked(election_id, trustee.index));
+        for revoked in revoked.iter() {
+            for trustee in remaining.iter() {
+                let reshare_id =
+                    TrusteeReshareTransaction::build_id(election_id, trustee.index, revoked.index);
+                if !self.contains(reshare_id) {
+                    return Err(ValidationError::MissingTrusteeReshare(
+                        trustee.index,
+                        revoked.index,
+                    ));
+                }
+            }
+        }
+
+        let pubkeys = self
+            .get_current_keygen_public_keys(election_id)
+            .into_iter()
+            .map(|tx| tx.tx)
+            .collect();
+        let reshares: Vec<TrusteeReshareTransaction> = self
+            .get_trustee_reshares(election_id)
+            .into_iter()
+            .map(|tx| tx.tx)
+            .collect();
+        refresh_public_keys(pubkeys, &reshares)
+    }
+
+    /// Get every partial decryption in an election, with batched partial decryptions expanded into individual
+    /// transactions, sorted in ascending identifier order.
+    fn get_all_partial_decryptions(
+        &self,
+        election_id: Identifier,
+    ) -> Vec<PartialDecryptionTransaction> {
+        let mut partials: Vec<PartialDecryptionTransaction> = self
+            .get_multiple(election_id, TransactionType::PartialDecryption)
+            .into_iter()
+            .map(|tx| tx.into())
+            .collect();
+        for tx in self.get_multiple(election_id, TransactionType::PartialDecryptionBatch) {
+            let batch: PartialDecryptionBatchTransaction = tx.into();
+            partials.extend(batch.partial_decryptions());
+        }
+        partials.sort_by_key(|partial| partial.id);
+        partials
+    }
+
     /// Get a Decryption transaction
     fn get_decryption(
         &self,
@@ -132,22 +430,186 @@ pub trait Store {
     }
 }
 
+// Sort transactions so that each is after every transaction it depends on, as described in `Store::validate_all`
+pub(crate) fn sort_by_dependency_order(txs: &mut [SignedTransaction]) {
+    txs.sort_by_key(|tx| {
+        let id = tx.id();
+        (
+            id.election_id,
+            dependency_order(id.transaction_type),
+            id.unique_info,
+            id.transaction_type as u8,
+        )
+    });
+}
+
+// Position of a transaction type in the life of an election, used to order transactions for validation
+fn dependency_order(tx_type: TransactionType) -> u8 {
+    match tx_type {
+        TransactionType::Election => 0,
+        TransactionType::ObserverAcknowledgment => 1,
+        TransactionType::KeyGenCommitment => 2,
+        TransactionType::KeyGenShare => 3,
+        TransactionType::KeyGenPublicKey => 4,
+        TransactionType::TrusteeKeyRevocation => 4,
+        TransactionType::TrusteeRevocation => 4,
+        TransactionType::EncryptionKey => 5,
+        TransactionType::TrusteeReshare => 5,
+        TransactionType::Vote => 6,
+        TransactionType::Challenge => 7,
+        TransactionType::VotingEnd => 8,
+        TransactionType::Mix => 9,
+        TransactionType::PartialDecryption => 10,
+        TransactionType::PartialDecryptionBatch => 10,
+        TransactionType::Decryption => 11,
+    }
+}
+
 /// A simple store that uses an in-memory BTreeMap
-#[derive(Default, Clone)]
+///
+/// The map is behind a lock so that `apply_transaction` can validate and store without another writer getting in between.
+#[derive(Default)]
 pub struct MemStore {
-    pub(crate) inner: BTreeMap<String, SignedTransaction>,
+    pub(crate) inner: RwLock<BTreeMap<String, SignedTransaction>>,
 }
 
 impl MemStore {
+    /// Store a transaction without validating it
     pub fn set(&mut self, tx: SignedTransaction) {
-        self.inner.insert(tx.id().to_string(), tx);
+        self.write().insert(tx.id().to_string(), tx);
+    }
+
+    /// Remove an election and every transaction in it, returning the removed transactions in identifier order
+    pub fn remove_election_cascade(&self, election_id: Identifier) -> Vec<SignedTransaction> {
+        let start = Identifier::start(election_id, TransactionType::Election, None);
+        let end = Identifier::end(election_id, TransactionType::MAX, None);
+
+        let mut inner = self.inner.write().unwrap_or_else(PoisonError::into_inner);
+        let keys: Vec<String> = inner
+            .range(start.to_string()..=end.to_string())
+            .map(|(key, _)| key.clone())
+            .collect();
+        keys.iter().filter_map(|key| inner.remove(key)).collect()
+    }
+
+    /// Remove every transaction whose election transaction is not in the store, returning the removed transactions
+    pub fn compact(&self) -> Vec<SignedTransaction> {
+        let mut inner = self.inner.write().unwrap_or_else(PoisonError::into_inner);
+        let orphans: Vec<String> = inner
+            .iter()
+            .filter(|(_, tx)| {
+                let election_id = ElectionTransaction::build_id(tx.id().election_id);
+                !inner.contains_key(&election_id.to_string())
+            })
+            .map(|(key, _)| key.clone())
+            .collect();
+        orphans.iter().filter_map(|key| inner.remove(key)).collect()
+    }
+
+    // A poisoned lock is safe to recover from, since the map is only ever modified by a single insert or removal
+    fn read(&self) -> RwLockReadGuard<BTreeMap<String, SignedTransaction>> {
+        self.inner.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn write(&mut self) -> &mut BTreeMap<String, SignedTransaction> {
+        self.inner.get_mut().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Clone for MemStore {
+    fn clone(&self) -> Self {
+        MemStore {
+            inner: RwLock::new(self.read().clone()),
+        }
     }
 }
 
 impl Store for MemStore {
+    fn get_transaction(&self, id: Identifier) -> Option<SignedTransaction> {
+        MemStoreView(&self.read()).get_transaction(id)
+    }
+
+    fn contains(&self, id: Identifier) -> bool {
+        MemStoreView(&self.read()).contains(id)
+    }
+
+    fn range(&self, start: Identifier, end_inclusive: Identifier) -> Vec<SignedTransaction> {
+        MemStoreView(&self.read()).range(start, end_inclusive)
+    }
+
+    // Keys are hex-encoded identifiers, which sort in the same order as the identifiers themselves
+    fn scan_range(&self, start: Identifier, end: Identifier) -> Vec<SignedTransaction> {
+        if start >= end {
+            return Vec::new();
+        }
+
+        self.read()
+            .range(start.to_string()..end.to_string())
+            .map(|(_, tx)| tx.clone())
+            .collect()
+    }
+
+    // Only the identifiers are needed, so avoid cloning every transaction
+    fn list_elections(&self) -> Vec<Identifier> {
+        self.read()
+            .values()
+            .map(|tx| tx.id())
+            .filter(|id| id.transaction_type == TransactionType::Election)
+            .collect()
+    }
+
+    fn apply_transaction(&self, tx: SignedTransaction) -> Result<(), ApplyError> {
+        let mut inner = self.inner.write().unwrap_or_else(PoisonError::into_inner);
+
+        // Validate against the locked map so nothing can be stored between validating and inserting
+        tx.validate(&MemStoreView(&inner))?;
+
+        inner.insert(tx.id().to_string(), tx);
+        Ok(())
+    }
+
+    // Unlike `set`, which silently replaces the stored transaction, this reports the conflict
+    fn set_checked(&self, tx: SignedTransaction) -> Result<(), StoreError> {
+        let mut inner = self.inner.write().unwrap_or_else(PoisonError::into_inner);
+
+        let key = tx.id().to_string();
+        if let Some(existing) = inner.get(&key) {
+            let existing_hash = existing.content_hash();
+            let new_hash = tx.content_hash();
+            if existing_hash != new_hash {
+                return Err(ConflictDetected {
+                    id: tx.id(),
+                    existing_hash,
+                    new_hash,
+                }
+                .into());
+            }
+        }
+
+        inner.insert(key, tx);
+        Ok(())
+    }
+
+    /// Removing an election transaction orphans every other transaction in the election. Use
+    /// `remove_election_cascade` to remove a whole election, or `compact` to clean up orphans afterwards.
+    fn remove(&self, id: Identifier) -> Result<Option<SignedTransaction>, StoreError> {
+        let mut inner = self.inner.write().unwrap_or_else(PoisonError::into_inner);
+        Ok(inner.remove(&id.to_string()))
+    }
+}
+
+// Read-only view of a MemStore's map, so that it can be read while a lock is held
+struct MemStoreView<'a>(&'a BTreeMap<String, SignedTransaction>);
+
+impl<'a> Store for MemStoreView<'a> {
     fn get_transaction(&self, id: Identifier) -> Option<SignedTransaction> {
         let key = id.to_string();
-        self.inner.get(&key).cloned()
+        self.0.get(&key).cloned()
+    }
+
+    // Only check the key, without cloning the transaction
+    fn contains(&self, id: Identifier) -> bool {
+        self.0.contains_key(&id.to_string())
     }
 
     fn range(&self, start: Identifier, end_inclusive: Identifier) -> Vec<SignedTransaction> {
@@ -168,13 +630,34 @@ impl Store for MemStore {
         //end[..15].copy_from_slice(&election_id[..15]);
         //end[16] = (tx_type as u8) + 1;
 
-        for (_, v) in self.inner.range(start..=end) {
+        for (_, v) in self.0.range(start..=end) {
             results.push(v.clone())
         }
         results
     }
 }
 
+/// Validate a log of transactions in order, adding each to the store once it validates.
+///
+/// `progress` is called with `(current, total)` just before each transaction is validated (`current` starts at 1),
+/// so it also fires for the transaction that fails.
+/// On failure, the index of the failing transaction is returned alongside the error.
+pub fn validate_log_with_progress<S: Store, F>(
+    txs: &[SignedTransaction],
+    store: &S,
+    mut progress: F,
+) -> Result<(), (usize, ApplyError)>
+where
+    F: FnMut(usize, usize),
+{
+    let total = txs.len();
+    for (i, tx) in txs.iter().enumerate() {
+        progress(i + 1, total);
+        store.apply_transaction(tx.clone()).map_err(|e| (i, e))?;
+    }
+    Ok(())
+}
+
 impl From<Vec<SignedTransaction>> for MemStore {
     fn from(item: Vec<SignedTransaction>) -> Self {
         let mut memstore = MemStore::default();
@@ -184,3 +667,155 @@ impl From<Vec<SignedTransaction>> for MemStore {
         memstore
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_log_with_progress() {
+        let mut txs = crate::tests::test_election_2();
+        let total = txs.len();
+
+        let store = MemStore::default();
+        let mut calls = Vec::new();
+        validate_log_with_progress(&txs, &store, |current, total| calls.push((current, total)))
+            .unwrap();
+        assert_eq!(calls.len(), total);
+        assert_eq!(calls.last(), Some(&(total, total)));
+
+        // Out of order - the election transaction is missing, so the first transaction fails
+        txs.remove(0);
+        let store = MemStore::default();
+        let mut calls = Vec::new();
+        let (index, _) =
+            validate_log_with_progress(&txs, &store, |current, total| calls.push((current, total)))
+                .unwrap_err();
+        assert_eq!(index, 0);
+        assert_eq!(calls, vec![(1, total - 1)]);
+    }
+
+    #[test]
+    fn test_validate_all() {
+        let txs = crate::tests::test_election_2();
+
+        let mut store = MemStore::from(txs.clone());
+        assert!(store.validate_all().is_empty());
+
+        // Corrupt the first keygen share by giving it the signature of the second
+        let mut shares = txs
+            .into_iter()
+            .filter(|tx| tx.transaction_type() == TransactionType::KeyGenShare);
+        let mut corrupted: Signed<KeyGenShareTransaction> = shares.next().unwrap().into();
+        let other: Signed<KeyGenShareTransaction> = shares.next().unwrap().into();
+        corrupted.sig = other.sig;
+        let corrupted_id = corrupted.id;
+        store.set(corrupted.into());
+
+        // The corrupted share fails first, followed by anything that depends on it
+        let errors = store.validate_all();
+        assert_eq!(errors[0].0, corrupted_id);
+        assert!(matches!(errors[0].1, ValidationError::SignatureError(_)));
+    }
+
+    #[test]
+    fn test_scan_range() {
+        let txs = crate::tests::test_election_2();
+        let election_id = txs[0].id();
+        let store = MemStore::from(txs);
+
+        // All keygen commitments and shares
+        let start = Identifier::start(election_id, TransactionType::KeyGenCommitment, None);
+        let end = Identifier::start(election_id, TransactionType::KeyGenPublicKey, None);
+        let scanned = store.scan_range(start, end);
+        assert_eq!(scanned.len(), 6);
+        assert!(scanned.windows(2).all(|pair| pair[0].id() < pair[1].id()));
+
+        // The end of the range is exclusive
+        let first = scanned[0].id();
+        assert!(store.scan_range(start, first).is_empty());
+        assert_eq!(store.scan_range(first, scanned[1].id()).len(), 1);
+        assert!(store.scan_range(end, start).is_empty());
+    }
+
+    #[test]
+    fn test_memstore_remove() {
+        let txs = crate::tests::test_election_2();
+        let election_id = txs[0].id();
+        let decryption_id = txs
+            .iter()
+            .find(|tx| tx.transaction_type() == TransactionType::Decryption)
+            .unwrap()
+            .id();
+
+        let store = MemStore::from(txs.clone());
+        assert!(store.contains(decryption_id));
+        let removed = store.remove(decryption_id).unwrap().unwrap();
+        assert_eq!(removed.id(), decryption_id);
+        assert!(store.get_transaction(decryption_id).is_none());
+        assert!(!store.contains(decryption_id));
+        assert!(store.remove(decryption_id).unwrap().is_none());
+
+        // Removing the election orphans the rest of it, which compact cleans up
+        store.remove(election_id).unwrap().unwrap();
+        assert_eq!(store.compact().len(), txs.len() - 2);
+        assert!(store
+            .range(Identifier::first(), Identifier::last())
+            .is_empty());
+
+        // Other elections are left alone
+        let mut store = MemStore::from(txs.clone());
+        let other_election = ElectionTransaction::build_id([9; 15]);
+        let mut other_tx: Signed<ElectionTransaction> = txs[0].clone().into();
+        other_tx.tx.id = other_election;
+        store.set(other_tx.into());
+
+        let mut elections = vec![election_id, other_election];
+        elections.sort();
+        assert_eq!(store.list_elections(), elections);
+
+        assert!(store.compact().is_empty());
+        assert_eq!(store.remove_election_cascade(election_id).len(), txs.len());
+        assert!(store.get_transaction(other_election).is_some());
+        assert_eq!(
+            store.range(Identifier::first(), Identifier::last()).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_set_checked_detects_equivocation() {
+        let txs = crate::tests::test_election_2();
+        let partial: Signed<PartialDecryptionTransaction> = txs
+            .iter()
+            .find(|tx| tx.transaction_type() == TransactionType::PartialDecryption)
+            .unwrap()
+            .clone()
+            .into();
+        let trustee_index = partial.trustee_index;
+
+        // A second, different partial decryption of the same ciphertext by the same trustee
+        let mut other = partial.clone();
+        other.tx.created_at = Some(1);
+        let first: SignedTransaction = partial.into();
+        let second: SignedTransaction = other.into();
+        assert_eq!(first.id(), second.id());
+
+        let store = MemStore::default();
+        store.set_checked(first.clone()).unwrap();
+        store.set_checked(first.clone()).unwrap();
+
+        let conflict = match store.set_checked(second.clone()) {
+            Err(StoreError::Conflict(conflict)) => conflict,
+            other => panic!("expected a conflict, got {:?}", other),
+        };
+        assert_eq!(conflict.id, first.id());
+        assert_eq!(conflict.existing_hash, first.content_hash());
+        assert_eq!(conflict.new_hash, second.content_hash());
+        assert_eq!(conflict.equivocating_trustee(), Some(trustee_index));
+
+        // The first partial decryption is kept
+        let stored = store.get_transaction(first.id()).unwrap();
+        assert_eq!(stored.content_hash(), first.content_hash());
+    }
+}
@@ -0,0 +1,220 @@
+use crate::*;
+use indexmap::{IndexMap, IndexSet};
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{ToPrimitive, Zero};
+use rust_decimal::prelude::*;
+use tallystick::RankedCandidate;
+use tallystick::RankedWinners;
+
+/// Single Transferable Vote tally, using the Droop quota and fractional (Gregory) surplus transfer.
+///
+/// All counting is done with exact rational arithmetic, so surplus transfers do not suffer from rounding bias.
+/// Ties (for election or elimination) are broken by the order in which candidates were first seen.
+pub struct StvTally {
+    seats: usize,
+    candidates: IndexSet<String>,
+    ballots: Vec<(Vec<String>, u64)>,
+}
+
+/// The outcome of an STV count
+#[derive(Debug, Clone)]
+pub struct StvCount {
+    /// Elected candidates, in the order they were elected
+    pub elected: Vec<String>,
+
+    /// Eliminated candidates, in the order they were eliminated
+    pub eliminated: Vec<String>,
+
+    pub quota: u64,
+    pub rounds: Vec<TallyRound>,
+}
+
+// A ballot paper being counted, along with its current (possibly fractional) value
+struct Paper<'a> {
+    preferences: &'a [String],
+    value: BigRational,
+}
+
+impl<'a> Paper<'a> {
+    fn top<'b>(&self, continuing: &'b [String]) -> Option<&'a String> {
+        self.preferences.iter().find(|c| continuing.contains(c))
+    }
+}
+
+impl StvTally {
+    pub fn new(seats: usize) -> Self {
+        StvTally {
+            seats,
+            candidates: IndexSet::new(),
+            ballots: Vec::new(),
+        }
+    }
+
+    /// Droop quota: `floor(votes / (seats + 1)) + 1`
+    pub fn quota(&self) -> u64 {
+        let total: u64 = self.ballots.iter().map(|(_, weight)| weight).sum();
+        total / (self.seats as u64 + 1) + 1
+    }
+
+    /// Run the count
+    pub fn count(&self) -> StvCount {
+        let quota = self.quota();
+        let quota_ratio = BigRational::from_integer(BigInt::from(quota));
+
+        let mut papers: Vec<Paper> = self
+            .ballots
+            .iter()
+            .map(|(preferences, weight)| Paper {
+                preferences,
+                value: BigRational::from_integer(BigInt::from(*weight)),
+            })
+            .collect();
+
+        let mut continuing: Vec<String> = self.candidates.iter().cloned().collect();
+        let mut elected = Vec::new();
+        let mut eliminated = Vec::new();
+        let mut rounds = Vec::new();
+
+        while elected.len() < self.seats && !continuing.is_empty() {
+            let mut totals: IndexMap<String, BigRational> = continuing
+                .iter()
+                .map(|c| (c.clone(), BigRational::zero()))
+                .collect();
+            let mut exhausted = BigRational::zero();
+            for paper in papers.iter() {
+                match paper.top(&continuing) {
+                    Some(candidate) => totals[candidate] += &paper.value,
+                    None => exhausted += &paper.value,
+                }
+            }
+
+            let mut round = TallyRound {
+                round: rounds.len() + 1,
+                totals: totals
+                    .iter()
+                    .map(|(c, total)| (c.clone(), ratio_to_decimal(total)))
+                    .collect(),
+                elected: vec![],
+                eliminated: vec![],
+                exhausted: ratio_to_decimal(&exhausted),
+            };
+
+            // If there are only as many candidates left as seats, they are all elected
+            if continuing.len() + elected.len() <= self.seats {
+                let mut remaining: Vec<(String, BigRational)> = totals.into_iter().collect();
+                remaining.sort_by(|a, b| b.1.cmp(&a.1));
+                round.elected = remaining.into_iter().map(|(c, _)| c).collect();
+                elected.extend(round.elected.iter().cloned());
+                rounds.push(round);
+                break;
+            }
+
+            // First candidate with the highest total
+            let (highest, highest_total) = totals
+                .iter()
+                .fold(
+                    None,
+                    |best: Option<(&String, &BigRational)>, (c, t)| match best {
+                        Some((_, best_total)) if best_total >= t => best,
+                        _ => Some((c, t)),
+                    },
+                )
+                .unwrap();
+
+            if *highest_total >= quota_ratio {
+                // Elect, and transfer the surplus at a fraction of each paper's value
+                let highest = highest.clone();
+                let factor = (highest_total - &quota_ratio) / highest_total;
+                for paper in papers.iter_mut() {
+                    if paper.top(&continuing) == Some(&highest) {
+                        paper.value *= &factor;
+                    }
+                }
+                continuing.retain(|c| c != &highest);
+                round.elected.push(highest.clone());
+                elected.push(highest);
+            } else {
+                // Eliminate the last candidate with the lowest total, transferring their papers at full value
+                let (lowest, _) = totals
+                    .iter()
+                    .fold(
+                        None,
+                        |worst: Option<(&String, &BigRational)>, (c, t)| match worst {
+                            Some((_, worst_total)) if worst_total < t => worst,
+                            _ => Some((c, t)),
+                        },
+                    )
+                    .unwrap();
+                let lowest = lowest.clone();
+                continuing.retain(|c| c != &lowest);
+                round.eliminated.push(lowest.clone());
+                eliminated.push(lowest);
+            }
+
+            rounds.push(round);
+        }
+
+        StvCount {
+            elected,
+            eliminated,
+            quota,
+            rounds,
+        }
+    }
+}
+
+impl Tally for StvTally {
+    fn add_vote(&mut self, vote: Vec<Selection>, weight: u64) -> Result<(), TallyError> {
+        let mut preferences: Vec<String> = Vec::with_capacity(vote.len());
+        for selection in vote {
+            if !preferences.contains(&selection.selection) {
+                self.candidates.insert(selection.selection.clone());
+                preferences.push(selection.selection);
+            }
+        }
+        self.ballots.push((preferences, weight));
+        Ok(())
+    }
+
+    /// Totals from the final round of counting
+    fn totals(&self) -> IndexMap<String, Decimal> {
+        match self.count().rounds.pop() {
+            Some(round) => round.totals,
+            None => IndexMap::new(),
+        }
+    }
+
+    /// Elected candidates in order of election, followed by eliminated candidates in reverse order of elimination
+    fn ranked(&self) -> Vec<RankedCandidate<String>> {
+        let count = self.count();
+        count
+            .elected
+            .into_iter()
+            .chain(count.eliminated.into_iter().rev())
+            .enumerate()
+            .map(|(rank, candidate)| RankedCandidate { candidate, rank })
+            .collect()
+    }
+
+    fn winners(&self) -> RankedWinners<String> {
+        RankedWinners::from_ranked(self.ranked(), self.seats)
+    }
+
+    fn rounds(&self) -> Vec<TallyRound> {
+        self.count().rounds
+    }
+}
+
+fn ratio_to_decimal(ratio: &BigRational) -> Decimal {
+    let exact = match (ratio.numer().to_i128(), ratio.denom().to_i128()) {
+        (Some(numer), Some(denom)) => Decimal::from_i128(numer)
+            .zip(Decimal::from_i128(denom))
+            .and_then(|(numer, denom)| numer.checked_div(denom)),
+        _ => None,
+    };
+
+    exact
+        .or_else(|| ratio.to_f64().and_then(Decimal::from_f64))
+        .unwrap_or_default()
+}
@@ -0,0 +1,76 @@
+use crate::*;
+use std::collections::HashSet;
+use std::fmt;
+
+/// A quick overview of an election's progress, as returned by `summarize_election`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ElectionSummary {
+    pub election_id: Identifier,
+
+    /// The election's `title` property, or an empty string if it has none
+    pub title: String,
+
+    /// Votes cast, not counting votes that were challenged
+    pub total_votes: usize,
+
+    /// Decryption transactions posted. There is one for each contest in each vote, or for each ciphertext in the
+    /// final mixes when using a mixnet.
+    pub decrypted_votes: usize,
+
+    /// Trustees that have posted at least one partial decryption
+    pub trustees_participated: usize,
+
+    pub trustees_total: usize,
+
+    pub mixes_completed: usize,
+}
+
+/// Summarize an election's progress from the transactions in `store`.
+///
+/// Transactions are counted, not validated. Use `Store::validate_all` or `verify_election_integrity` to check them.
+pub fn summarize_election<S: Store>(
+    store: &S,
+    election_id: Identifier,
+) -> Result<ElectionSummary, SummaryError> {
+    let election = store.get_election(election_id)?.tx;
+
+    let title = election
+        .properties
+        .get("title")
+        .and_then(|title| title.as_str())
+        .unwrap_or_default()
+        .to_string();
+
+    let trustees_participated: HashSet<u8> = store
+        .get_all_partial_decryptions(election.id)
+        .iter()
+        .map(|partial| partial.trustee_index)
+        .collect();
+
+    Ok(ElectionSummary {
+        election_id: election.id,
+        title,
+        total_votes: store.get_cast_votes(election.id).len(),
+        decrypted_votes: store
+            .get_multiple(election.id, TransactionType::Decryption)
+            .len(),
+        trustees_participated: trustees_participated.len(),
+        trustees_total: election.trustees.len(),
+        mixes_completed: store.get_multiple(election.id, TransactionType::Mix).len(),
+    })
+}
+
+impl fmt::Display for ElectionSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Election:     {}", self.election_id)?;
+        writeln!(f, "Title:        {}", self.title)?;
+        writeln!(f, "Votes:        {}", self.total_votes)?;
+        writeln!(f, "Decrypted:    {}", self.decrypted_votes)?;
+        writeln!(
+            f,
+            "Trustees:     {} of {} participated",
+            self.trustees_participated, self.trustees_total
+        )?;
+        writeln!(f, "Mixes:        {}", self.mixes_completed)
+    }
+}
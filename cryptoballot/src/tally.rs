@@ -1,352 +1,1253 @@
 use crate::*;
 use indexmap::IndexMap;
 use rust_decimal::prelude::*;
+use std::collections::HashSet;
+use std::str::FromStr;
 use tallystick::RankedCandidate;
 use tallystick::RankedWinners;
 
+/// The final results of an election, one `TallyResult` per contest.
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct TallyTransaction {
     pub id: Identifier,
     pub election_id: Identifier,
 
     pub tally: IndexMap<String, TallyResult>,
+
+    /// Trustees (by index) that participated in decrypting the votes
+    #[serde(default)]
+    pub trustees: Vec<u8>,
+
+    /// Optional creation time, in seconds since the UNIX epoch
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<u64>,
+}
+
+impl TallyTransaction {
+    /// Render a human-readable certificate of the results, suitable for printing and posting publicly.
+    pub fn render_certificate(&self) -> String {
+        let mut cert = String::new();
+
+        cert.push_str("CERTIFICATE OF ELECTION RESULTS\n");
+        cert.push_str("===============================\n");
+        cert.push_str(&format!("Election: {}\n", self.election_id));
+        cert.push_str(&format!("Results:  {}\n", self.id));
+        match self.created_at {
+            Some(created_at) => cert.push_str(&format!("Recorded: {}\n", created_at)),
+            None => cert.push_str("Recorded: unknown\n"),
+        }
+        let trustees: Vec<String> = self.trustees.iter().map(|t| t.to_string()).collect();
+        cert.push_str(&format!("Trustees: {}\n", trustees.join(", ")));
+
+        for result in self.tally.values() {
+            cert.push_str(&format!(
+                "\nContest {} ({})\n",
+                result.contest_index, result.contest_id
+            ));
+            cert.push_str(&format!("  Method:  {:?}\n", result.contest_type));
+            cert.push_str(&format!("  Ballots: {}\n", result.num_votes));
+            if !result.spoiled_ballots.is_empty() {
+                cert.push_str(&format!("  Spoiled: {}\n", result.spoiled_ballots.len()));
+            }
+
+            cert.push_str("  Totals:\n");
+            for (candidate, total) in result.totals.iter() {
+                cert.push_str(&format!("    {}: {}\n", candidate, total));
+            }
+
+            let winners = result.winners.clone().into_unranked();
+            cert.push_str(&format!("  Winners: {}\n", winners.join(", ")));
+        }
+
+        cert
+    }
+
+    /// JSON Schema (draft-07) describing the serialized form of a `TallyTransaction`, for machine consumers.
+    pub fn json_schema() -> serde_json::Value {
+        serde_json::json!({
+            "$schema": "http://json-schema.org/draft-07/schema#",
+            "title": "TallyTransaction",
+            "type": "object",
+            "required": ["id", "election_id", "tally"],
+            "properties": {
+                "id": { "type": "string", "pattern": "^[0-9a-f]{64}$" },
+                "election_id": { "type": "string", "pattern": "^[0-9a-f]{64}$" },
+                "trustees": {
+                    "type": "array",
+                    "items": { "type": "integer", "minimum": 1, "maximum": 255 }
+                },
+                "created_at": { "type": "integer", "minimum": 0 },
+                "tally": {
+                    "type": "object",
+                    "additionalProperties": {
+                        "type": "object",
+                        "required": [
+                            "contest_id",
+                            "contest_index",
+                            "contest_type",
+                            "num_votes",
+                            "totals",
+                            "results",
+                            "winners",
+                            "spoiled_ballots"
+                        ],
+                        "properties": {
+                            "contest_id": { "type": "string" },
+                            "contest_index": { "type": "integer", "minimum": 0 },
+                            "contest_type": { "type": "string" },
+                            "num_votes": { "type": "integer", "minimum": 0 },
+                            "totals": {
+                                "type": "object",
+                                "additionalProperties": { "type": ["string", "number"] }
+                            },
+                            "results": { "type": "array" },
+                            "winners": {},
+                            "spoiled_ballots": { "type": "object" }
+                        }
+                    }
+                }
+            }
+        })
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct TallyResult {
     pub contest_id: String,
     pub contest_index: u32,
+    pub contest_type: ContestType,
 
     pub num_votes: usize,
     pub totals: IndexMap<String, Decimal>,
     pub results: Vec<RankedCandidate<String>>,
     pub winners: RankedWinners<String>,
     pub spoiled_ballots: IndexMap<Identifier, SpoiledBallotError>,
+
+    /// Round-by-round counts, for multi-round methods such as STV
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub rounds: Vec<TallyRound>,
+
+    /// Votes for each write-in, which are counted together as `WRITE_IN_CANDIDATE` in the tally itself
+    #[serde(default)]
+    #[serde(skip_serializing_if = "IndexMap::is_empty")]
+    pub write_ins: IndexMap<String, u64>,
+}
+
+/// A single round of counting in a multi-round tally
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TallyRound {
+    pub round: usize,
+
+    /// Totals for each continuing candidate at the start of this round
+    pub totals: IndexMap<String, Decimal>,
+
+    pub elected: Vec<String>,
+    pub eliminated: Vec<String>,
+
+    /// Vote value with no continuing candidate left to transfer to
+    pub exhausted: Decimal,
+}
+
+/// How to choose between candidates that are tied for the last winning place in a contest
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TieBreak {
+    /// Don't break ties, report the tied candidates instead
+    Report,
+
+    /// Pick the tied candidates that sort first by candidate id
+    Lexicographic,
+
+    /// Pick the tied candidates at random, seeded with the given seed so the draw can be independently reproduced.
+    ///
+    /// The tied candidates are sorted by id, then shuffled using ChaCha20, seeded with `SeedableRng::seed_from_u64`.
+    Random(u64),
+
+    /// Fail with `Error::Tie`
+    Error,
+}
+
+impl Default for TieBreak {
+    fn default() -> Self {
+        TieBreak::Report
+    }
+}
+
+impl FromStr for TieBreak {
+    type Err = Error;
+
+    /// Parse `report`, `lexicographic`, `error`, or `random:<seed>`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "report" => Ok(TieBreak::Report),
+            "lexicographic" => Ok(TieBreak::Lexicographic),
+            "error" => Ok(TieBreak::Error),
+            _ => match s.strip_prefix("random:").map(|seed| seed.parse()) {
+                Some(Ok(seed)) => Ok(TieBreak::Random(seed)),
+                _ => Err(Error::UnknownTieBreak(s.to_string())),
+            },
+        }
+    }
+}
+
+/// The winners of a contest after applying a `TieBreak`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ResolvedWinners {
+    /// Candidates that won outright or by breaking a tie
+    pub winners: Vec<String>,
+
+    /// Candidates tied for the remaining places, if the tie was not broken
+    pub tied: Vec<String>,
+
+    /// Number of places left for the tied candidates to fill
+    pub tied_places: usize,
 }
 
 impl TallyResult {
+    /// Tally the votes for a contest using one of the built-in tally methods
+    ///
+    /// Each vote is counted by its weight (see `vote_weight`), and a vote with an invalid weight is an error.
     pub fn tally(
         contest_id: String,
         contest_index: u32,
         num_winners: u32,
         contest_type: ContestType,
         votes: Vec<Vec<Selection>>,
-    ) -> Self {
+    ) -> Result<Self, ValidationError> {
+        let mut tally = contest_type.new_tally(num_winners)?;
+        Self::tally_with(
+            contest_id,
+            contest_index,
+            contest_type,
+            tally.as_mut(),
+            votes,
+        )
+    }
+
+    /// Tally the votes for a contest using the given tally method, which may be a custom implementation of `Tally`
+    pub fn tally_with(
+        contest_id: String,
+        contest_index: u32,
+        contest_type: ContestType,
+        tally: &mut dyn Tally,
+        votes: Vec<Vec<Selection>>,
+    ) -> Result<Self, ValidationError> {
         let num_votes = votes.len();
 
-        // Make sure selections are in order
-        let votes: Vec<Vec<Selection>> = votes
-            .into_iter()
-            .map(|mut vote| {
-                vote.sort_by(|a, b| a.score.cmp(&b.score));
-                vote
-            })
-            .collect();
+        for mut vote in votes {
+            // Make sure selections are in order
+            vote.sort_by(|a, b| a.score.cmp(&b.score));
 
-        match contest_type {
-            ContestType::Plurality => {
-                use tallystick::plurality::DefaultPluralityTally;
-                let mut tally = DefaultPluralityTally::new(num_winners as usize);
+            let weight = vote_weight(&vote)?;
 
-                for vote in votes {
-                    for selection in vote {
-                        tally.add_ref(&selection.selection)
-                    }
-                }
+            tally.add_vote(vote, weight)?;
+        }
 
-                let mut totals = IndexMap::new();
-                for (candidate, total) in tally.totals() {
-                    totals.insert(candidate, total.into());
-                }
+        Ok(TallyResult {
+            contest_id,
+            contest_index,
+            contest_type,
+            num_votes,
+            totals: tally.totals(),
+            results: tally.ranked(),
+            winners: tally.winners(),
+            spoiled_ballots: IndexMap::new(),
+            rounds: tally.rounds(),
+            write_ins: IndexMap::new(),
+        })
+    }
 
-                let ranked = tally.ranked();
-                let winners = tally.winners();
+    /// Get the winners of the contest, breaking any tie for the last winning place with `tie_break`.
+    ///
+    /// Ties are detected from the ranked results: candidates sharing the rank that straddles `num_winners` are tied.
+    pub fn resolve_winners(
+        &self,
+        num_winners: u32,
+        tie_break: TieBreak,
+    ) -> Result<ResolvedWinners, Error> {
+        let num_winners = num_winners as usize;
 
-                TallyResult {
-                    contest_id,
-                    contest_index,
-                    num_votes,
-                    totals,
-                    results: ranked,
-                    winners,
-                    spoiled_ballots: IndexMap::new(),
-                }
-            }
-            ContestType::Score => {
-                use tallystick::score::DefaultScoreTally;
-                let mut tally = DefaultScoreTally::new(num_winners as usize);
+        let mut ranked = self.results.clone();
+        ranked.sort_by_key(|candidate| candidate.rank);
 
-                for vote in votes {
-                    let vote: Vec<(String, u64)> = vote.into_iter().map(|v| v.into()).collect();
-                    tally.add_ref(&vote);
-                }
+        // Take whole ranks until the winning places are filled
+        let mut winners = Vec::with_capacity(num_winners);
+        let mut tied = Vec::new();
+        let mut i = 0;
+        while i < ranked.len() && winners.len() < num_winners {
+            let rank = ranked[i].rank;
+            let group: Vec<String> = ranked[i..]
+                .iter()
+                .take_while(|candidate| candidate.rank == rank)
+                .map(|candidate| candidate.candidate.clone())
+                .collect();
+            i += group.len();
 
-                let mut totals = IndexMap::new();
-                for (candidate, total) in tally.totals() {
-                    totals.insert(candidate, total.into());
-                }
+            if winners.len() + group.len() <= num_winners {
+                winners.extend(group);
+            } else {
+                tied = group;
+            }
+        }
 
-                let ranked = tally.ranked();
-                let winners = tally.winners();
+        if tied.is_empty() {
+            return Ok(ResolvedWinners {
+                winners,
+                tied,
+                tied_places: 0,
+            });
+        }
 
-                TallyResult {
-                    contest_id,
-                    contest_index,
-                    num_votes,
-                    totals,
-                    results: ranked,
+        let tied_places = num_winners - winners.len();
+        tied.sort();
+
+        match tie_break {
+            TieBreak::Report => {
+                return Ok(ResolvedWinners {
                     winners,
-                    spoiled_ballots: IndexMap::new(),
-                }
+                    tied,
+                    tied_places,
+                })
             }
-            ContestType::Approval => {
-                use tallystick::approval::DefaultApprovalTally;
-                let mut tally = DefaultApprovalTally::new(num_winners as usize);
+            TieBreak::Error => return Err(Error::Tie(tied)),
+            TieBreak::Lexicographic => {}
+            TieBreak::Random(seed) => {
+                use rand::seq::SliceRandom;
+                use rand_chacha::rand_core::SeedableRng;
 
-                for vote in votes {
-                    let vote: Vec<String> = vote.into_iter().map(|v| v.selection).collect();
-                    tally.add_ref(&vote);
-                }
+                // ChaCha20 is specified and portable, unlike StdRng, whose algorithm may change between rand versions
+                let mut rng = rand_chacha::ChaCha20Rng::seed_from_u64(seed);
+                tied.shuffle(&mut rng);
+            }
+        }
 
-                let mut totals = IndexMap::new();
-                for (candidate, total) in tally.totals() {
-                    totals.insert(candidate, total.into());
-                }
+        winners.extend(tied.into_iter().take(tied_places));
+        Ok(ResolvedWinners {
+            winners,
+            tied: vec![],
+            tied_places: 0,
+        })
+    }
+}
 
-                let ranked = tally.ranked();
-                let winners = tally.winners();
+/// Tally all the decrypted votes for a contest using the given tally method
+///
+/// Votes whose decryption is marked as spoiled are not counted, and are listed in `spoiled_ballots` instead.
+///
+/// Write-ins (see `Contest::selected_candidate`) are all counted as `WRITE_IN_CANDIDATE`, so that each misspelling
+/// of a candidate doesn't become a candidate of its own. The individual write-ins are counted in `write_ins`. A vote
+/// with a write-in in a contest that doesn't allow them, or that selects the same candidate more than once
+/// (including a second write-in), is also spoiled.
+pub fn tally_decryptions(
+    contest: &Contest,
+    tally: &mut dyn Tally,
+    decryptions: &[DecryptionTransaction],
+) -> Result<TallyResult, ValidationError> {
+    let mut write_ins: IndexMap<String, u64> = IndexMap::new();
+    let decryptions: Vec<&DecryptionTransaction> = decryptions
+        .iter()
+        .filter(|decryption| decryption.contest_index == contest.index)
+        .collect();
 
-                TallyResult {
-                    contest_id,
-                    contest_index,
-                    num_votes,
-                    totals,
-                    results: ranked,
-                    winners,
-                    spoiled_ballots: IndexMap::new(),
-                }
-            }
-            ContestType::Condorcet => {
-                use tallystick::condorcet::DefaultCondorcetTally;
-                let mut tally = DefaultCondorcetTally::new(num_winners as usize);
-
-                for vote in votes {
-                    let vote: Vec<(String, u32)> = vote.into_iter().map(|v| v.into()).collect();
-                    tally
-                        .ranked_add(&vote)
-                        .expect("Unexpected duplicate candidate");
-                }
+    let mut spoiled_ballots = IndexMap::new();
+    let mut votes = Vec::with_capacity(decryptions.len());
+    for decryption in decryptions {
+        if decryption.spoiled {
+            spoiled_ballots.insert(
+                decryption.upstream_id,
+                SpoiledBallotError::IncorrectProtobufFormat,
+            );
+            continue;
+        }
 
-                let mut totals = IndexMap::new();
-                for (candidate, total) in tally.totals() {
-                    totals.insert(format!("{} > {}", candidate.0, candidate.1), total.into());
+        match bucket_write_ins(contest, &decryption.decrypted_vote) {
+            Ok((vote, write_in)) => {
+                let weight = vote_weight(&vote)?;
+                if let Some(write_in) = write_in {
+                    *write_ins.entry(write_in).or_insert(0) += weight;
                 }
+                votes.push(vote);
+            }
+            Err(e) => {
+                spoiled_ballots.insert(decryption.upstream_id, e);
+            }
+        }
+    }
 
-                let ranked = tally.ranked();
-                let winners = tally.winners();
+    let mut result = TallyResult::tally_with(
+        contest.id.clone(),
+        contest.index,
+        contest.contest_type.clone(),
+        tally,
+        votes,
+    )?;
+    result.write_ins = write_ins;
+    result.spoiled_ballots = spoiled_ballots;
+    Ok(result)
+}
 
-                TallyResult {
-                    contest_id,
-                    contest_index,
-                    num_votes,
-                    totals,
-                    results: ranked,
-                    winners,
-                    spoiled_ballots: IndexMap::new(),
-                }
+// Replace the write-in in a vote with `WRITE_IN_CANDIDATE`, returning the vote along with the write-in.
+// Since every write-in is the same candidate in the tally, a vote can have at most one.
+fn bucket_write_ins(
+    contest: &Contest,
+    vote: &[Selection],
+) -> Result<(Vec<Selection>, Option<String>), SpoiledBallotError> {
+    let mut vote = vote.to_vec();
+    let mut write_in = None;
+    let mut candidates = HashSet::with_capacity(vote.len());
+    for selection in vote.iter_mut() {
+        if let SelectedCandidate::WriteIn(candidate) = contest.selected_candidate(selection)? {
+            if write_in.is_some() {
+                return Err(SpoiledBallotError::DuplicateCandidate);
             }
-            ContestType::SchulzeWinning => {
-                use tallystick::schulze::DefaultSchulzeTally;
-                use tallystick::schulze::Variant;
-                let mut tally = DefaultSchulzeTally::new(num_winners as usize, Variant::Winning);
-
-                for vote in votes {
-                    let vote: Vec<(String, u32)> = vote.into_iter().map(|v| v.into()).collect();
-                    tally
-                        .ranked_add(&vote)
-                        .expect("Unexpected duplicate candidate");
-                }
+            write_in = Some(candidate);
+            selection.write_in = true;
+            selection.selection = WRITE_IN_CANDIDATE.to_string();
+        }
 
-                let mut totals = IndexMap::new();
-                for (candidate, total) in tally.totals() {
-                    totals.insert(format!("{} > {}", candidate.0, candidate.1), total.into());
-                }
+        if !candidates.insert(selection.selection.clone()) {
+            return Err(SpoiledBallotError::DuplicateCandidate);
+        }
+    }
 
-                let ranked = tally.ranked();
-                let winners = tally.winners();
+    Ok((vote, write_in))
+}
 
-                TallyResult {
-                    contest_id,
-                    contest_index,
-                    num_votes,
-                    totals,
-                    results: ranked,
-                    winners,
-                    spoiled_ballots: IndexMap::new(),
-                }
-            }
-            ContestType::SchulzeMargin => {
-                use tallystick::schulze::DefaultSchulzeTally;
-                use tallystick::schulze::Variant;
-                let mut tally = DefaultSchulzeTally::new(num_winners as usize, Variant::Margin);
-
-                for vote in votes {
-                    let vote: Vec<(String, u32)> = vote.into_iter().map(|v| v.into()).collect();
-                    tally
-                        .ranked_add(&vote)
-                        .expect("Unexpected duplicate candidate");
-                }
+/// The results of every contest in an election, as returned by `tally_election`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ElectionResults {
+    pub election_id: Identifier,
 
-                let mut totals = IndexMap::new();
-                for (candidate, total) in tally.totals() {
-                    totals.insert(format!("{} > {}", candidate.0, candidate.1), total.into());
-                }
+    /// The number of votes cast in the election, not counting challenged votes
+    pub total_ballots: usize,
 
-                let ranked = tally.ranked();
-                let winners = tally.winners();
+    /// Results keyed by contest id, each with the tally method used, per-candidate totals, and winners
+    pub contests: IndexMap<String, TallyResult>,
+}
 
-                TallyResult {
-                    contest_id,
-                    contest_index,
-                    num_votes,
-                    totals,
-                    results: ranked,
-                    winners,
-                    spoiled_ballots: IndexMap::new(),
-                }
-            }
-            ContestType::SchulzeRatio => {
-                // use tallystick::schulze::SchulzeTally;
-                // use tallystick::schulze::Variant;
+/// Verify an election and tally every contest.
+///
+/// Every transaction in the election is re-validated first (see `Store::validate_all`), and the first failure is
+/// returned. Voting must have ended. Each contest is tallied using its `ContestType` from the votes decrypted so far,
+/// and an election with a contest type that can't be tallied (see `ContestType::is_supported`) is an error.
+pub fn tally_election<S: Store>(
+    store: &S,
+    election_id: Identifier,
+) -> Result<ElectionResults, ValidationError> {
+    let election = store.get_election(election_id)?;
 
-                // TODO: Decimal needs to implement NumCast before this can work.
+    let voting_end_id = Identifier::new(election_id, TransactionType::VotingEnd, None);
+    if !store.contains(voting_end_id) {
+        return Err(ValidationError::MisingVotingEndTransaction);
+    }
 
-                unimplemented!();
-            }
-            ContestType::Borda => {
-                use tallystick::borda::DefaultBordaTally;
-                use tallystick::borda::Variant;
+    let start = Identifier::start(election_id, TransactionType::Election, None);
+    let end = Identifier::end(election_id, TransactionType::MAX, None);
+    let election_store = MemStore::from(store.range(start, end));
+    if let Some((_, e)) = election_store.validate_all().into_iter().next() {
+        return Err(e);
+    }
 
-                let mut tally = DefaultBordaTally::new(num_winners as usize, Variant::Borda);
+    let total_ballots = store.get_cast_votes(election_id).len();
+    let decryptions: Vec<DecryptionTransaction> = store
+        .get_multiple(election_id, TransactionType::Decryption)
+        .into_iter()
+        .map(|tx| tx.into())
+        .collect();
 
-                for vote in votes {
-                    let vote: Vec<String> = vote.into_iter().map(|v| v.selection).collect();
-                    tally
-                        .add_ref(&vote)
-                        .expect("Unexpected duplicate candidate");
-                }
+    let mut contests = IndexMap::with_capacity(election.contests.len());
+    for contest in election.contests.iter() {
+        let mut tally = contest.contest_type.new_tally(contest.num_winners)?;
+        let result = tally_decryptions(contest, tally.as_mut(), &decryptions)?;
+        contests.insert(contest.id.clone(), result);
+    }
 
-                let mut totals = IndexMap::new();
-                for (candidate, total) in tally.totals() {
-                    totals.insert(candidate, total.into());
-                }
+    Ok(ElectionResults {
+        election_id,
+        total_ballots,
+        contests,
+    })
+}
 
-                let ranked = tally.ranked();
-                let winners = tally.winners();
+/// A tally method.
+///
+/// All the built-in methods (see `ContestType`) implement this trait. Implement it to plug in a custom
+/// tally method (for example a jurisdiction specific variant of STV) and pass it to `tally_decryptions`.
+pub trait Tally {
+    /// Add a single decrypted vote, which should count `weight` times.
+    ///
+    /// Selections are sorted by `score` before being added. Fails if the method can't count the vote, such as a
+    /// ranked vote that ranks a candidate more than once.
+    fn add_vote(&mut self, vote: Vec<Selection>, weight: u64) -> Result<(), TallyError>;
 
-                TallyResult {
-                    contest_id,
-                    contest_index,
-                    num_votes,
-                    totals,
-                    results: ranked,
-                    winners,
-                    spoiled_ballots: IndexMap::new(),
-                }
-            }
-            ContestType::BordaClassic => {
-                use tallystick::borda::DefaultBordaTally;
-                use tallystick::borda::Variant;
+    /// Get the totals for each candidate (or pair of candidates for pairwise methods)
+    fn totals(&self) -> IndexMap<String, Decimal>;
 
-                let mut tally = DefaultBordaTally::new(num_winners as usize, Variant::ClassicBorda);
+    /// Get all candidates in ranked order
+    fn ranked(&self) -> Vec<RankedCandidate<String>>;
 
-                for vote in votes {
-                    let vote: Vec<String> = vote.into_iter().map(|v| v.selection).collect();
-                    tally
-                        .add_ref(&vote)
-                        .expect("Unexpected duplicate candidate");
-                }
+    /// Get the winners
+    fn winners(&self) -> RankedWinners<String>;
 
-                let mut totals = IndexMap::new();
-                for (candidate, total) in tally.totals() {
-                    totals.insert(candidate, total.into());
-                }
+    /// Get the round-by-round count, for methods that count in rounds
+    fn rounds(&self) -> Vec<TallyRound> {
+        Vec::new()
+    }
+}
 
-                let ranked = tally.ranked();
-                let winners = tally.winners();
+impl ContestType {
+    /// Whether `new_tally` can tally this contest type. SchulzeRatio can't be tallied yet.
+    pub fn is_supported(&self) -> bool {
+        !matches!(self, ContestType::SchulzeRatio)
+    }
 
-                TallyResult {
-                    contest_id,
-                    contest_index,
-                    num_votes,
-                    totals,
-                    results: ranked,
-                    winners,
-                    spoiled_ballots: IndexMap::new(),
-                }
+    /// Create a new, empty, tally for this contest type
+    ///
+    /// Fails with `TallyError::UnsupportedContestType` if the contest type is not supported (see `is_supported`).
+    pub fn new_tally(&self, num_winners: u32) -> Result<Box<dyn Tally>, TallyError> {
+        use tallystick::approval::DefaultApprovalTally;
+        use tallystick::borda::{self, DefaultBordaTally};
+        use tallystick::condorcet::DefaultCondorcetTally;
+        use tallystick::plurality::DefaultPluralityTally;
+        use tallystick::schulze::{self, DefaultSchulzeTally};
+        use tallystick::score::DefaultScoreTally;
+
+        let num_winners = num_winners as usize;
+
+        let tally: Box<dyn Tally> = match self {
+            ContestType::Plurality => Box::new(DefaultPluralityTally::<String>::new(num_winners)),
+            ContestType::Score => Box::new(DefaultScoreTally::<String>::new(num_winners)),
+            ContestType::Approval => Box::new(DefaultApprovalTally::<String>::new(num_winners)),
+            ContestType::Condorcet => Box::new(DefaultCondorcetTally::<String>::new(num_winners)),
+            ContestType::SchulzeWinning => Box::new(DefaultSchulzeTally::<String>::new(
+                num_winners,
+                schulze::Variant::Winning,
+            )),
+            ContestType::SchulzeMargin => Box::new(DefaultSchulzeTally::<String>::new(
+                num_winners,
+                schulze::Variant::Margin,
+            )),
+            ContestType::SchulzeRatio => {
+                // TODO: Decimal needs to implement NumCast before this can work.
+                return Err(TallyError::UnsupportedContestType(self.clone()));
             }
-            ContestType::BordaDowdall => {
-                use tallystick::borda::DefaultBordaTally;
-                use tallystick::borda::Variant;
+            ContestType::Borda => Box::new(DefaultBordaTally::<String>::new(
+                num_winners,
+                borda::Variant::Borda,
+            )),
+            ContestType::BordaClassic => Box::new(DefaultBordaTally::<String>::new(
+                num_winners,
+                borda::Variant::ClassicBorda,
+            )),
+            ContestType::BordaDowdall => Box::new(DefaultBordaTally::<String>::new(
+                num_winners,
+                borda::Variant::Dowdall,
+            )),
+            ContestType::BordaModifiedClassic => Box::new(DefaultBordaTally::<String>::new(
+                num_winners,
+                borda::Variant::ModifiedClassicBorda,
+            )),
+            ContestType::SingleTransferableVote => Box::new(StvTally::new(num_winners)),
+        };
+        Ok(tally)
+    }
+}
 
-                let mut tally = DefaultBordaTally::new(num_winners as usize, Variant::Dowdall);
+// Built-in tally methods
+// ----------------------
+// `Self::totals(self)` etc. resolve to the inherent tallystick methods, not back to `Tally`
 
-                for vote in votes {
-                    let vote: Vec<String> = vote.into_iter().map(|v| v.selection).collect();
-                    tally
-                        .add_ref(&vote)
-                        .expect("Unexpected duplicate candidate");
-                }
+impl Tally for tallystick::plurality::DefaultPluralityTally<String> {
+    fn add_vote(&mut self, vote: Vec<Selection>, weight: u64) -> Result<(), TallyError> {
+        for selection in vote {
+            self.add_weighted_ref(&selection.selection, weight)
+        }
+        Ok(())
+    }
 
-                let mut totals = IndexMap::new();
-                for (candidate, total) in tally.totals() {
-                    totals.insert(candidate, total.into());
-                }
+    fn totals(&self) -> IndexMap<String, Decimal> {
+        let mut totals = IndexMap::new();
+        for (candidate, total) in Self::totals(self) {
+            totals.insert(candidate, total.into());
+        }
+        totals
+    }
 
-                let ranked = tally.ranked();
-                let winners = tally.winners();
+    fn ranked(&self) -> Vec<RankedCandidate<String>> {
+        Self::ranked(self)
+    }
 
-                TallyResult {
-                    contest_id,
-                    contest_index,
-                    num_votes,
-                    totals,
-                    results: ranked,
-                    winners,
-                    spoiled_ballots: IndexMap::new(),
-                }
-            }
-            ContestType::BordaModifiedClassic => {
-                use tallystick::borda::DefaultBordaTally;
-                use tallystick::borda::Variant;
-
-                let mut tally =
-                    DefaultBordaTally::new(num_winners as usize, Variant::ModifiedClassicBorda);
-
-                for vote in votes {
-                    let vote: Vec<String> = vote.into_iter().map(|v| v.selection).collect();
-                    tally
-                        .add_ref(&vote)
-                        .expect("Unexpected duplicate candidate");
-                }
+    fn winners(&self) -> RankedWinners<String> {
+        Self::winners(self)
+    }
+}
 
-                let mut totals = IndexMap::new();
-                for (candidate, total) in tally.totals() {
-                    totals.insert(candidate, total.into());
-                }
+impl Tally for tallystick::score::DefaultScoreTally<String> {
+    fn add_vote(&mut self, vote: Vec<Selection>, weight: u64) -> Result<(), TallyError> {
+        let vote: Vec<(String, u64)> = vote
+            .into_iter()
+            .map(|v| (v.selection, v.score as u64 * weight))
+            .collect();
+        self.add_ref(&vote);
+        Ok(())
+    }
 
-                let ranked = tally.ranked();
-                let winners = tally.winners();
+    fn totals(&self) -> IndexMap<String, Decimal> {
+        let mut totals = IndexMap::new();
+        for (candidate, total) in Self::totals(self) {
+            totals.insert(candidate, total.into());
+        }
+        totals
+    }
 
-                TallyResult {
-                    contest_id,
-                    contest_index,
-                    num_votes,
-                    totals,
-                    results: ranked,
-                    winners,
-                    spoiled_ballots: IndexMap::new(),
-                }
-            }
+    fn ranked(&self) -> Vec<RankedCandidate<String>> {
+        Self::ranked(self)
+    }
+
+    fn winners(&self) -> RankedWinners<String> {
+        Self::winners(self)
+    }
+}
+
+impl Tally for tallystick::approval::DefaultApprovalTally<String> {
+    fn add_vote(&mut self, vote: Vec<Selection>, weight: u64) -> Result<(), TallyError> {
+        let vote: Vec<String> = vote.into_iter().map(|v| v.selection).collect();
+        self.add_weighted_ref(&vote, weight);
+        Ok(())
+    }
+
+    fn totals(&self) -> IndexMap<String, Decimal> {
+        let mut totals = IndexMap::new();
+        for (candidate, total) in Self::totals(self) {
+            totals.insert(candidate, total.into());
+        }
+        totals
+    }
+
+    fn ranked(&self) -> Vec<RankedCandidate<String>> {
+        Self::ranked(self)
+    }
+
+    fn winners(&self) -> RankedWinners<String> {
+        Self::winners(self)
+    }
+}
+
+impl Tally for tallystick::condorcet::DefaultCondorcetTally<String> {
+    fn add_vote(&mut self, vote: Vec<Selection>, weight: u64) -> Result<(), TallyError> {
+        let vote: Vec<(String, u32)> = vote.into_iter().map(|v| v.into()).collect();
+        self.ranked_add_weighted(&vote, weight)
+            .map_err(|_| TallyError::DuplicateCandidate)
+    }
+
+    fn totals(&self) -> IndexMap<String, Decimal> {
+        let mut totals = IndexMap::new();
+        for (candidate, total) in Self::totals(self) {
+            totals.insert(format!("{} > {}", candidate.0, candidate.1), total.into());
+        }
+        totals
+    }
+
+    fn ranked(&self) -> Vec<RankedCandidate<String>> {
+        Self::ranked(self)
+    }
+
+    fn winners(&self) -> RankedWinners<String> {
+        Self::winners(self)
+    }
+}
+
+impl Tally for tallystick::schulze::DefaultSchulzeTally<String> {
+    fn add_vote(&mut self, vote: Vec<Selection>, weight: u64) -> Result<(), TallyError> {
+        let vote: Vec<(String, u32)> = vote.into_iter().map(|v| v.into()).collect();
+        self.ranked_add_weighted(&vote, weight)
+            .map_err(|_| TallyError::DuplicateCandidate)
+    }
+
+    fn totals(&self) -> IndexMap<String, Decimal> {
+        let mut totals = IndexMap::new();
+        for (candidate, total) in Self::totals(self) {
+            totals.insert(format!("{} > {}", candidate.0, candidate.1), total.into());
+        }
+        totals
+    }
+
+    fn ranked(&self) -> Vec<RankedCandidate<String>> {
+        Self::ranked(self)
+    }
+
+    fn winners(&self) -> RankedWinners<String> {
+        Self::winners(self)
+    }
+}
+
+impl Tally for tallystick::borda::DefaultBordaTally<String> {
+    fn add_vote(&mut self, vote: Vec<Selection>, weight: u64) -> Result<(), TallyError> {
+        let vote: Vec<String> = vote.into_iter().map(|v| v.selection).collect();
+        self.add_weighted_ref(&vote, weight)
+            .map_err(|_| TallyError::DuplicateCandidate)
+    }
+
+    fn totals(&self) -> IndexMap<String, Decimal> {
+        let mut totals = IndexMap::new();
+        for (candidate, total) in Self::totals(self) {
+            totals.insert(candidate, total.into());
+        }
+        totals
+    }
+
+    fn ranked(&self) -> Vec<RankedCandidate<String>> {
+        Self::ranked(self)
+    }
+
+    fn winners(&self) -> RankedWinners<String> {
+        Self::winners(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn selection(candidate: &str) -> Vec<Selection> {
+        vec![Selection {
+            write_in: false,
+            score: 0,
+            selection: candidate.to_string(),
+            weight: None,
+        }]
+    }
+
+    #[test]
+    fn render_certificate() {
+        let election_id = Identifier {
+            election_id: [1; 15],
+            transaction_type: TransactionType::Election,
+            unique_info: [0; 16],
+        };
+
+        let votes = vec![selection("alice"), selection("alice"), selection("bob")];
+        let result =
+            TallyResult::tally("mayor".to_string(), 0, 1, ContestType::Plurality, votes).unwrap();
+
+        let mut tally = IndexMap::new();
+        tally.insert(result.contest_id.clone(), result);
+
+        let tally_tx = TallyTransaction {
+            id: election_id,
+            election_id,
+            tally,
+            trustees: vec![1, 2],
+            created_at: Some(1_600_000_000),
+        };
+
+        let cert = tally_tx.render_certificate();
+        assert!(cert.contains(&election_id.to_string()));
+        assert!(cert.contains("Method:  Plurality"));
+        assert!(cert.contains("Ballots: 3"));
+        assert!(cert.contains("alice: 2"));
+        assert!(cert.contains("bob: 1"));
+        assert!(cert.contains("Winners: alice"));
+        assert!(cert.contains("Trustees: 1, 2"));
+
+        // Serialized form should cover everything the schema requires
+        let schema = TallyTransaction::json_schema();
+        let value = serde_json::to_value(&tally_tx).unwrap();
+        for key in schema["required"].as_array().unwrap() {
+            assert!(value.get(key.as_str().unwrap()).is_some());
+        }
+    }
+
+    #[test]
+    fn weighted_plurality() {
+        let mut heavy = selection("bob");
+        heavy[0].weight = Some(5);
+
+        let votes = vec![selection("alice"), selection("alice"), heavy];
+        let result =
+            TallyResult::tally("mayor".to_string(), 0, 1, ContestType::Plurality, votes).unwrap();
+
+        assert_eq!(result.num_votes, 3);
+        assert_eq!(result.totals["bob"], Decimal::from(5));
+        assert_eq!(result.winners.into_unranked(), vec!["bob".to_string()]);
+
+        let mut zero = selection("bob");
+        zero[0].weight = Some(0);
+        assert!(matches!(
+            vote_weight(&zero),
+            Err(ValidationError::InvalidVoteWeight)
+        ));
+
+        // Invalid weights are rejected rather than counted as 1
+        let votes = vec![selection("alice"), zero];
+        assert!(matches!(
+            TallyResult::tally("mayor".to_string(), 0, 1, ContestType::Plurality, votes),
+            Err(ValidationError::InvalidVoteWeight)
+        ));
+
+        // Weights are added to the totals in one step, so even the largest weight is cheap to count
+        let ranked = |first: &str, second: &str, weight: Option<u32>| {
+            vec![
+                Selection {
+                    write_in: false,
+                    score: 0,
+                    selection: first.to_string(),
+                    weight,
+                },
+                Selection {
+                    write_in: false,
+                    score: 1,
+                    selection: second.to_string(),
+                    weight,
+                },
+            ]
+        };
+        let contest_types = vec![
+            ContestType::Condorcet,
+            ContestType::SchulzeWinning,
+            ContestType::Borda,
+        ];
+        for contest_type in contest_types {
+            let votes = vec![
+                ranked("alice", "bob", None),
+                ranked("alice", "bob", None),
+                ranked("bob", "alice", Some(u32::MAX)),
+            ];
+            let result =
+                TallyResult::tally("mayor".to_string(), 0, 1, contest_type, votes).unwrap();
+            assert_eq!(result.winners.into_unranked(), vec!["bob".to_string()]);
+        }
+    }
+
+    #[test]
+    fn duplicate_candidate_is_an_error() {
+        let vote = vec![
+            Selection {
+                write_in: false,
+                score: 0,
+                selection: "alice".to_string(),
+                weight: None,
+            },
+            Selection {
+                write_in: false,
+                score: 1,
+                selection: "alice".to_string(),
+                weight: None,
+            },
+        ];
+
+        // Ranking the same candidate twice can't be counted, but doesn't panic
+        let contest_types = vec![
+            ContestType::Condorcet,
+            ContestType::SchulzeMargin,
+            ContestType::Borda,
+        ];
+        for contest_type in contest_types {
+            let votes = vec![selection("alice"), vote.clone()];
+            assert!(matches!(
+                TallyResult::tally("mayor".to_string(), 0, 1, contest_type, votes),
+                Err(ValidationError::Tally(TallyError::DuplicateCandidate))
+            ));
+        }
+    }
+
+    #[test]
+    fn tie_break() {
+        let votes = vec![
+            selection("carol"),
+            selection("bob"),
+            selection("alice"),
+            selection("carol"),
+        ];
+        let result =
+            TallyResult::tally("council".to_string(), 0, 2, ContestType::Plurality, votes).unwrap();
+
+        // carol wins outright, alice and bob are tied for the second seat
+        let reported = result.resolve_winners(2, TieBreak::Report).unwrap();
+        assert_eq!(reported.winners, vec!["carol".to_string()]);
+        assert_eq!(reported.tied, vec!["alice".to_string(), "bob".to_string()]);
+        assert_eq!(reported.tied_places, 1);
+
+        let resolved = result.resolve_winners(2, TieBreak::Lexicographic).unwrap();
+        assert_eq!(
+            resolved.winners,
+            vec!["carol".to_string(), "alice".to_string()]
+        );
+        assert!(resolved.tied.is_empty());
+
+        // The same seed always draws the same winner
+        let random = result.resolve_winners(2, TieBreak::Random(42)).unwrap();
+        assert_eq!(random.winners.len(), 2);
+        assert_eq!(
+            random,
+            result.resolve_winners(2, TieBreak::Random(42)).unwrap()
+        );
+
+        assert!(matches!(
+            result.resolve_winners(2, TieBreak::Error),
+            Err(Error::Tie(_))
+        ));
+
+        // No tie for a single seat
+        let single = result.resolve_winners(1, TieBreak::Error).unwrap();
+        assert_eq!(single.winners, vec!["carol".to_string()]);
+
+        assert_eq!(
+            "random:42".parse::<TieBreak>().unwrap(),
+            TieBreak::Random(42)
+        );
+        assert!("random".parse::<TieBreak>().is_err());
+    }
+
+    // A custom tally method that ignores write-ins and otherwise counts like plurality
+    struct NoWriteInTally(tallystick::plurality::DefaultPluralityTally<String>);
+
+    impl Tally for NoWriteInTally {
+        fn add_vote(&mut self, vote: Vec<Selection>, weight: u64) -> Result<(), TallyError> {
+            let vote = vote.into_iter().filter(|s| !s.write_in).collect();
+            self.0.add_vote(vote, weight)
+        }
+
+        fn totals(&self) -> IndexMap<String, Decimal> {
+            Tally::totals(&self.0)
+        }
+
+        fn ranked(&self) -> Vec<RankedCandidate<String>> {
+            Tally::ranked(&self.0)
+        }
+
+        fn winners(&self) -> RankedWinners<String> {
+            Tally::winners(&self.0)
+        }
+    }
+
+    #[test]
+    fn custom_tally() {
+        let mut write_in = selection("zed");
+        write_in[0].write_in = true;
+
+        let votes = vec![
+            selection("alice"),
+            write_in.clone(),
+            write_in.clone(),
+            write_in,
+        ];
+
+        let mut tally = NoWriteInTally(tallystick::plurality::DefaultPluralityTally::new(1));
+        let result = TallyResult::tally_with(
+            "mayor".to_string(),
+            0,
+            ContestType::Plurality,
+            &mut tally,
+            votes,
+        )
+        .unwrap();
+
+        assert_eq!(result.num_votes, 4);
+        assert!(!result.totals.contains_key("zed"));
+        assert_eq!(result.winners.into_unranked(), vec!["alice".to_string()]);
+    }
+
+    #[test]
+    fn write_ins_counted_together() {
+        let candidate = |id: &str| Candidate {
+            id: id.to_string(),
+            properties: IndexMap::new(),
+        };
+        let contest = Contest {
+            id: "mayor".to_string(),
+            index: 0,
+            contest_type: ContestType::Plurality,
+            num_winners: 1,
+            write_in: true,
+            candidates: vec![candidate("alice"), candidate("bob")],
+            question: None,
+            properties: IndexMap::new(),
+        };
+
+        let mut zed = selection("zed");
+        zed[0].write_in = true;
+        let votes = vec![
+            selection("alice"),
+            selection("alise"), // A misspelling, not a new candidate
+            zed.clone(),
+            zed,
+            selection("bob"),
+        ];
+
+        assert_eq!(
+            contest.selected_candidate(&votes[0][0]).unwrap(),
+            SelectedCandidate::Registered("alice".to_string())
+        );
+        assert_eq!(
+            contest.selected_candidate(&votes[1][0]).unwrap(),
+            SelectedCandidate::WriteIn("alise".to_string())
+        );
+
+        let election_id = Identifier {
+            election_id: [1; 15],
+            transaction_type: TransactionType::Election,
+            unique_info: [0; 16],
+        };
+        let decryptions: Vec<DecryptionTransaction> = votes
+            .into_iter()
+            .enumerate()
+            .map(|(i, vote)| {
+                let vote_id =
+                    Identifier::new(election_id, TransactionType::Vote, Some([i as u8; 16]));
+                DecryptionTransaction::new(election_id, vote_id, 0, 0, vec![1], vote)
+            })
+            .collect();
+
+        let mut tally = contest.contest_type.new_tally(contest.num_winners).unwrap();
+        let result = tally_decryptions(&contest, tally.as_mut(), &decryptions).unwrap();
+
+        assert_eq!(result.totals[WRITE_IN_CANDIDATE], Decimal::from(3));
+        assert!(!result.totals.contains_key("alise"));
+        assert!(!result.totals.contains_key("zed"));
+        assert_eq!(result.write_ins["alise"], 1);
+        assert_eq!(result.write_ins["zed"], 2);
+        assert_eq!(
+            result.winners.into_unranked(),
+            vec![WRITE_IN_CANDIDATE.to_string()]
+        );
+    }
+
+    #[test]
+    fn write_ins_that_cant_be_counted_spoil_the_ballot() {
+        let candidate = |id: &str| Candidate {
+            id: id.to_string(),
+            properties: IndexMap::new(),
+        };
+        let mut contest = Contest {
+            id: "mayor".to_string(),
+            index: 0,
+            contest_type: ContestType::Borda,
+            num_winners: 1,
+            write_in: true,
+            candidates: vec![candidate("alice"), candidate("bob")],
+            question: None,
+            properties: IndexMap::new(),
+        };
+
+        // Ranking two write-ins would rank WRITE_IN_CANDIDATE twice
+        let mut two_write_ins = ranked(&["zed", "yan", "alice"]);
+        two_write_ins[0].write_in = true;
+        let mut write_in = ranked(&["zed", "alice"]);
+        write_in[0].write_in = true;
+        let misspelled = ranked(&["alise", "bob"]);
+        let votes = vec![
+            ranked(&["alice", "bob"]),
+            two_write_ins,
+            write_in,
+            misspelled,
+        ];
+
+        let election_id = Identifier {
+            election_id: [1; 15],
+            transaction_type: TransactionType::Election,
+            unique_info: [0; 16],
+        };
+        let vote_id =
+            |i: usize| Identifier::new(election_id, TransactionType::Vote, Some([i as u8; 16]));
+        let decryptions: Vec<DecryptionTransaction> = votes
+            .into_iter()
+            .enumerate()
+            .map(|(i, vote)| {
+                DecryptionTransaction::new(election_id, vote_id(i), 0, 0, vec![1], vote)
+            })
+            .collect();
+
+        let mut tally = contest.contest_type.new_tally(contest.num_winners).unwrap();
+        let result = tally_decryptions(&contest, tally.as_mut(), &decryptions).unwrap();
+        assert_eq!(result.num_votes, 3);
+        assert_eq!(result.spoiled_ballots.len(), 1);
+        assert!(matches!(
+            result.spoiled_ballots[&vote_id(1)],
+            SpoiledBallotError::DuplicateCandidate
+        ));
+        assert_eq!(result.write_ins["zed"], 1);
+        assert_eq!(result.write_ins["alise"], 1);
+        assert!(!result.write_ins.contains_key("yan"));
+
+        // Without write-ins, neither a marked write-in nor an unknown candidate can be counted
+        contest.write_in = false;
+        let mut tally = contest.contest_type.new_tally(contest.num_winners).unwrap();
+        let result = tally_decryptions(&contest, tally.as_mut(), &decryptions).unwrap();
+        assert_eq!(result.num_votes, 1);
+        assert!(result.write_ins.is_empty());
+        assert!(matches!(
+            result.spoiled_ballots[&vote_id(1)],
+            SpoiledBallotError::WriteInNotAllowed
+        ));
+        assert!(matches!(
+            result.spoiled_ballots[&vote_id(2)],
+            SpoiledBallotError::WriteInNotAllowed
+        ));
+        assert!(matches!(
+            result.spoiled_ballots[&vote_id(3)],
+            SpoiledBallotError::CandidateNotFound
+        ));
+    }
+
+    fn ranked(preferences: &[&str]) -> Vec<Selection> {
+        preferences
+            .iter()
+            .enumerate()
+            .map(|(rank, candidate)| Selection {
+                write_in: false,
+                score: rank as u32,
+                selection: candidate.to_string(),
+                weight: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn single_transferable_vote() {
+        // 20 voters electing 3 seats, Droop quota is 6
+        let mut votes = Vec::new();
+        votes.extend(vec![ranked(&["orange"]); 4]);
+        votes.extend(vec![ranked(&["pear", "orange"]); 2]);
+        votes.extend(vec![ranked(&["chocolate", "strawberry"]); 8]);
+        votes.extend(vec![ranked(&["chocolate", "bonbon"]); 4]);
+        votes.push(ranked(&["strawberry"]));
+        votes.push(ranked(&["bonbon"]));
+
+        let result = TallyResult::tally(
+            "dessert".to_string(),
+            0,
+            3,
+            ContestType::SingleTransferableVote,
+            votes,
+        )
+        .unwrap();
+
+        assert_eq!(
+            result.winners.into_unranked(),
+            vec![
+                "chocolate".to_string(),
+                "orange".to_string(),
+                "strawberry".to_string()
+            ]
+        );
+
+        // Chocolate is elected in the first round with a surplus of 6, transferred at half value
+        let rounds = &result.rounds;
+        assert_eq!(rounds[0].totals["chocolate"], Decimal::from(12));
+        assert_eq!(rounds[0].elected, vec!["chocolate".to_string()]);
+        assert_eq!(rounds[1].totals["strawberry"], Decimal::from(5));
+        assert_eq!(rounds[1].totals["bonbon"], Decimal::from(3));
+
+        // Pear is eliminated, electing orange on transfer
+        assert_eq!(rounds[1].eliminated, vec!["pear".to_string()]);
+        assert_eq!(rounds[2].totals["orange"], Decimal::from(6));
+        assert_eq!(rounds[2].elected, vec!["orange".to_string()]);
+
+        // Bonbon is eliminated and their votes exhaust, leaving strawberry to fill the last seat
+        assert_eq!(rounds[3].eliminated, vec!["bonbon".to_string()]);
+        assert_eq!(rounds[4].exhausted, Decimal::from(3));
+        assert_eq!(rounds[4].elected, vec!["strawberry".to_string()]);
+        assert_eq!(rounds.len(), 5);
+    }
+
+    #[test]
+    fn single_transferable_vote_fractional_surplus() {
+        // 7 votes for 2 seats gives a quota of 3, so alice's surplus of 2 transfers at 2/5 per ballot
+        let mut votes = Vec::new();
+        votes.extend(vec![ranked(&["alice", "bob"]); 3]);
+        votes.extend(vec![ranked(&["alice", "carol"]); 2]);
+        votes.push(ranked(&["bob"]));
+        votes.push(ranked(&["carol"]));
+
+        let mut tally = StvTally::new(2);
+        let result = TallyResult::tally_with(
+            "council".to_string(),
+            0,
+            ContestType::SingleTransferableVote,
+            &mut tally,
+            votes,
+        )
+        .unwrap();
+
+        assert_eq!(tally.quota(), 3);
+        assert_eq!(result.rounds[1].totals["bob"], Decimal::new(22, 1));
+        assert_eq!(result.rounds[1].totals["carol"], Decimal::new(18, 1));
+        assert_eq!(
+            result.winners.into_unranked(),
+            vec!["alice".to_string(), "bob".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_tally_election() {
+        let txs = crate::tests::test_election_2();
+        let election_id = txs[0].id();
+
+        let store = MemStore::from(txs.clone());
+        let results = tally_election(&store, election_id).unwrap();
+        assert_eq!(results.election_id, election_id);
+        assert_eq!(results.total_ballots, 1);
+        assert_eq!(results.contests.len(), 1);
+
+        let result = &results.contests[0];
+        assert_eq!(result.num_votes, 1);
+        assert_eq!(
+            result.winners.clone().into_unranked(),
+            vec!["Barak Obama".to_string()]
+        );
+
+        // Results can't be tallied until voting has ended
+        let before_end: Vec<SignedTransaction> = txs
+            .into_iter()
+            .filter(|tx| (tx.transaction_type() as u8) < TransactionType::VotingEnd as u8)
+            .collect();
+        let store = MemStore::from(before_end);
+        assert!(matches!(
+            tally_election(&store, election_id),
+            Err(ValidationError::MisingVotingEndTransaction)
+        ));
+    }
+
+    #[test]
+    fn test_tally_independent_of_insertion_order() {
+        let txs = crate::tests::test_election_2();
+        let election_id = txs[0].id();
+
+        let mut forward = MemStore::default();
+        for tx in txs.iter().cloned() {
+            forward.set(tx);
         }
+        let mut reverse = MemStore::default();
+        for tx in txs.iter().rev().cloned() {
+            reverse.set(tx);
+        }
+
+        for tx_type in &[
+            TransactionType::KeyGenPublicKey,
+            TransactionType::PartialDecryption,
+            TransactionType::Decryption,
+        ] {
+            let ids = |store: &MemStore| -> Vec<Identifier> {
+                store
+                    .get_multiple(election_id, *tx_type)
+                    .iter()
+                    .map(|tx| tx.id())
+                    .collect()
+            };
+            let forward_ids = ids(&forward);
+            let mut sorted = forward_ids.clone();
+            sorted.sort();
+            assert_eq!(forward_ids, sorted);
+            assert_eq!(forward_ids, ids(&reverse));
+        }
+
+        let forward_results = tally_election(&forward, election_id).unwrap();
+        let reverse_results = tally_election(&reverse, election_id).unwrap();
+        assert_eq!(
+            serde_json::to_string(&forward_results).unwrap(),
+            serde_json::to_string(&reverse_results).unwrap()
+        );
     }
 }
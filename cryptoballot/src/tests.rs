@@ -1,10 +1,17 @@
 use super::*;
 use rand::SeedableRng;
 
+/// Load the transactions of the recorded election in `test_elections/test_election_2`
+pub(crate) fn test_election_2() -> Vec<SignedTransaction> {
+    let file_bytes =
+        std::fs::read("../test_elections/test_election_2/test_election_2.json").unwrap();
+    serde_json::from_slice(&file_bytes).unwrap()
+}
+
 #[test]
 fn end_to_end_election_no_mix() {
     let mut test_rng = rand::rngs::StdRng::from_seed([0u8; 32]);
-    let mut store = MemStore::default();
+    let store = MemStore::default();
 
     // Create election authority public and private key
     let (authority_secret, authority_public) = generate_keypair();
@@ -25,6 +32,7 @@ fn end_to_end_election_no_mix() {
         write_in: true,
         num_winners: 1,
         candidates: vec![],
+        question: None,
         properties: indexmap::IndexMap::new(),
     };
 
@@ -51,8 +59,7 @@ fn end_to_end_election_no_mix() {
     let election = Signed::sign(&authority_secret, election).unwrap();
 
     // Validate the election transaction and store it
-    election.validate(&store).unwrap();
-    store.set(election.clone().into());
+    store.apply_transaction(election.clone().into()).unwrap();
 
     // Generate keygen_commitment transactions for each trustee
     let x25519_public_1 = trustee_1.x25519_public_key(&trustee_1_secret, election.id);
@@ -66,8 +73,7 @@ fn end_to_end_election_no_mix() {
         commit_1,
     );
     let commit_1_tx = Signed::sign(&trustee_1_secret, commit_1_tx).unwrap();
-    commit_1_tx.validate(&store).unwrap();
-    store.set(commit_1_tx.clone().into());
+    store.apply_transaction(commit_1_tx.clone().into()).unwrap();
 
     let x25519_public_2 = trustee_2.x25519_public_key(&trustee_2_secret, election.id);
     let commit_2 = trustee_1.keygen_commitment(&trustee_2_secret, election.id);
@@ -79,8 +85,7 @@ fn end_to_end_election_no_mix() {
         commit_2,
     );
     let commit_2_tx = Signed::sign(&trustee_2_secret, commit_2_tx).unwrap();
-    commit_2_tx.validate(&store).unwrap();
-    store.set(commit_2_tx.clone().into());
+    store.apply_transaction(commit_2_tx.clone().into()).unwrap();
 
     let x25519_public_3 = trustee_3.x25519_public_key(&trustee_3_secret, election.id);
     let commit_3 = trustee_3.keygen_commitment(&trustee_3_secret, election.id);
@@ -92,8 +97,7 @@ fn end_to_end_election_no_mix() {
         commit_3,
     );
     let commit_3_tx = Signed::sign(&trustee_3_secret, commit_3_tx).unwrap();
-    commit_3_tx.validate(&store).unwrap();
-    store.set(commit_3_tx.clone().into());
+    store.apply_transaction(commit_3_tx.clone().into()).unwrap();
 
     // Grab cmommitments out of the commitment transactions
     let commitments = [
@@ -142,8 +146,7 @@ fn end_to_end_election_no_mix() {
         share_1.clone(),
     );
     let share_1_tx = Signed::sign(&trustee_1_secret, share_1_tx).unwrap();
-    share_1_tx.validate(&store).unwrap();
-    store.set(share_1_tx.clone().into());
+    store.apply_transaction(share_1_tx.clone().into()).unwrap();
 
     let share_2 = trustee_2.generate_shares(
         &mut test_rng,
@@ -159,8 +162,7 @@ fn end_to_end_election_no_mix() {
         share_2.clone(),
     );
     let share_2_tx = Signed::sign(&trustee_2_secret, share_2_tx).unwrap();
-    share_2_tx.validate(&store).unwrap();
-    store.set(share_2_tx.clone().into());
+    store.apply_transaction(share_2_tx.clone().into()).unwrap();
 
     let share_3 = trustee_3.generate_shares(
         &mut test_rng,
@@ -176,8 +178,7 @@ fn end_to_end_election_no_mix() {
         share_3.clone(),
     );
     let share_3_tx = Signed::sign(&trustee_3_secret, share_3_tx).unwrap();
-    share_3_tx.validate(&store).unwrap();
-    store.set(share_3_tx.clone().into());
+    store.apply_transaction(share_3_tx.clone().into()).unwrap();
 
     // Generate keygen_public_key transaction for each trustee
     let all_shares = vec![
@@ -207,8 +208,7 @@ fn end_to_end_election_no_mix() {
         pk_1_proof,
     );
     let pk_1_tx = Signed::sign(&trustee_1_secret, pk_1_tx).unwrap();
-    pk_1_tx.validate(&store).unwrap();
-    store.set(pk_1_tx.clone().into());
+    store.apply_transaction(pk_1_tx.clone().into()).unwrap();
 
     let pk_2_shares: Vec<(u8, EncryptedShare)> = all_shares
         .iter()
@@ -231,8 +231,7 @@ fn end_to_end_election_no_mix() {
         pk_2_proof,
     );
     let pk_2_tx = Signed::sign(&trustee_2_secret, pk_2_tx).unwrap();
-    pk_2_tx.validate(&store).unwrap();
-    store.set(pk_2_tx.clone().into());
+    store.apply_transaction(pk_2_tx.clone().into()).unwrap();
 
     let pk_3_shares: Vec<(u8, EncryptedShare)> = all_shares
         .iter()
@@ -255,21 +254,22 @@ fn end_to_end_election_no_mix() {
         pk_3_proof,
     );
     let pk_3_tx = Signed::sign(&trustee_3_secret, pk_3_tx).unwrap();
-    pk_3_tx.validate(&store).unwrap();
-    store.set(pk_3_tx.clone().into());
+    store.apply_transaction(pk_3_tx.clone().into()).unwrap();
 
     // Generate an encryption_key transaction
     let encryption_key_tx =
         EncryptionKeyTransaction::new(election.id, authority_public, pk_1_tx.inner().public_key);
     let encryption_key_tx = Signed::sign(&authority_secret, encryption_key_tx).unwrap();
-    encryption_key_tx.validate(&store).unwrap();
-    store.set(encryption_key_tx.clone().into());
+    store
+        .apply_transaction(encryption_key_tx.clone().into())
+        .unwrap();
 
     // Create a vote transaction
     let selection = Selection {
         write_in: false,
         score: 0,
         selection: "Barak Obama".to_string(),
+        weight: None,
     };
 
     // Encrypt the secret vote
@@ -304,17 +304,17 @@ fn end_to_end_election_no_mix() {
     let vote = Signed::sign(&voter_secret, vote).unwrap();
 
     // Validate the vote transaction and store it
-    vote.validate(&store).unwrap();
-    store.set(vote.clone().into());
+    store.apply_transaction(vote.clone().into()).unwrap();
 
     // Voting is over!
     // ---------------
 
     // Generate VotingEnd transaction to mark the end of voting
-    let voting_end_tx = VotingEndTransaction::new(election.id, election.authority_public);
+    let voting_end_tx = VotingEndTransaction::build(&election, &[vote.clone()]);
     let voting_end_tx = Signed::sign(&authority_secret, voting_end_tx).unwrap();
-    voting_end_tx.validate(&store).unwrap();
-    store.set(voting_end_tx.clone().into());
+    store
+        .apply_transaction(voting_end_tx.clone().into())
+        .unwrap();
 
     // Generate a partial-decryption transactions
     let partial_decrypt_1 = trustee_1
@@ -338,8 +338,9 @@ fn end_to_end_election_no_mix() {
         vec![partial_decrypt_1],
     );
     let partial_decrypt_1_tx = Signed::sign(&trustee_1_secret, partial_decrypt_1_tx).unwrap();
-    partial_decrypt_1_tx.validate(&store).unwrap();
-    store.set(partial_decrypt_1_tx.clone().into());
+    store
+        .apply_transaction(partial_decrypt_1_tx.clone().into())
+        .unwrap();
 
     let partial_decrypt_2 = trustee_2
         .partial_decrypt(
@@ -362,8 +363,9 @@ fn end_to_end_election_no_mix() {
         vec![partial_decrypt_2],
     );
     let partial_decrypt_2_tx = Signed::sign(&trustee_2_secret, partial_decrypt_2_tx).unwrap();
-    partial_decrypt_2_tx.validate(&store).unwrap();
-    store.set(partial_decrypt_2_tx.clone().into());
+    store
+        .apply_transaction(partial_decrypt_2_tx.clone().into())
+        .unwrap();
 
     let partials = vec![
         partial_decrypt_1_tx.tx.clone(),
@@ -393,8 +395,9 @@ fn end_to_end_election_no_mix() {
 
     // TODO: Add a decryptor public key to make it meaningful??  It does't really matter..
     let decrypted_tx = Signed::sign(&trustee_1_secret, decrypted_tx).unwrap();
-    decrypted_tx.validate(&store).unwrap();
-    store.set(decrypted_tx.clone().into());
+    store
+        .apply_transaction(decrypted_tx.clone().into())
+        .unwrap();
 
     // Decrypted vote should match secret vote
     assert_eq!(selection, decrypted_tx.inner().decrypted_vote[0]);
@@ -428,7 +431,7 @@ fn end_to_end_election_no_mix() {
 #[test]
 fn end_to_end_election_with_mix() {
     let mut test_rng = rand::rngs::StdRng::from_seed([0u8; 32]);
-    let mut store = MemStore::default();
+    let store = MemStore::default();
 
     // Create election authority public and private key
     let (authority_secret, authority_public) = generate_keypair();
@@ -449,6 +452,7 @@ fn end_to_end_election_with_mix() {
         write_in: true,
         num_winners: 1,
         candidates: vec![],
+        question: None,
         properties: indexmap::IndexMap::new(),
     };
 
@@ -479,8 +483,7 @@ fn end_to_end_election_with_mix() {
     let election = Signed::sign(&authority_secret, election).unwrap();
 
     // Validate the election transaction and store it
-    election.validate(&store).unwrap();
-    store.set(election.clone().into());
+    store.apply_transaction(election.clone().into()).unwrap();
 
     // Generate keygen_commitment transactions for each trustee
     let x25519_public_1 = trustee_1.x25519_public_key(&trustee_1_secret, election.id);
@@ -494,8 +497,7 @@ fn end_to_end_election_with_mix() {
         commit_1,
     );
     let commit_1_tx = Signed::sign(&trustee_1_secret, commit_1_tx).unwrap();
-    commit_1_tx.validate(&store).unwrap();
-    store.set(commit_1_tx.clone().into());
+    store.apply_transaction(commit_1_tx.clone().into()).unwrap();
 
     let x25519_public_2 = trustee_2.x25519_public_key(&trustee_2_secret, election.id);
     let commit_2 = trustee_1.keygen_commitment(&trustee_2_secret, election.id);
@@ -507,8 +509,7 @@ fn end_to_end_election_with_mix() {
         commit_2,
     );
     let commit_2_tx = Signed::sign(&trustee_2_secret, commit_2_tx).unwrap();
-    commit_2_tx.validate(&store).unwrap();
-    store.set(commit_2_tx.clone().into());
+    store.apply_transaction(commit_2_tx.clone().into()).unwrap();
 
     let x25519_public_3 = trustee_3.x25519_public_key(&trustee_3_secret, election.id);
     let commit_3 = trustee_3.keygen_commitment(&trustee_3_secret, election.id);
@@ -520,8 +521,7 @@ fn end_to_end_election_with_mix() {
         commit_3,
     );
     let commit_3_tx = Signed::sign(&trustee_3_secret, commit_3_tx).unwrap();
-    commit_3_tx.validate(&store).unwrap();
-    store.set(commit_3_tx.clone().into());
+    store.apply_transaction(commit_3_tx.clone().into()).unwrap();
 
     // Grab cmommitments out of the commitment transactions
     let commitments = [
@@ -570,8 +570,7 @@ fn end_to_end_election_with_mix() {
         share_1.clone(),
     );
     let share_1_tx = Signed::sign(&trustee_1_secret, share_1_tx).unwrap();
-    share_1_tx.validate(&store).unwrap();
-    store.set(share_1_tx.clone().into());
+    store.apply_transaction(share_1_tx.clone().into()).unwrap();
 
     let share_2 = trustee_2.generate_shares(
         &mut test_rng,
@@ -587,8 +586,7 @@ fn end_to_end_election_with_mix() {
         share_2.clone(),
     );
     let share_2_tx = Signed::sign(&trustee_2_secret, share_2_tx).unwrap();
-    share_2_tx.validate(&store).unwrap();
-    store.set(share_2_tx.clone().into());
+    store.apply_transaction(share_2_tx.clone().into()).unwrap();
 
     let share_3 = trustee_3.generate_shares(
         &mut test_rng,
@@ -604,8 +602,7 @@ fn end_to_end_election_with_mix() {
         share_3.clone(),
     );
     let share_3_tx = Signed::sign(&trustee_3_secret, share_3_tx).unwrap();
-    share_3_tx.validate(&store).unwrap();
-    store.set(share_3_tx.clone().into());
+    store.apply_transaction(share_3_tx.clone().into()).unwrap();
 
     // Generate keygen_public_key transaction for each trustee
     let all_shares = vec![
@@ -635,8 +632,7 @@ fn end_to_end_election_with_mix() {
         pk_1_proof,
     );
     let pk_1_tx = Signed::sign(&trustee_1_secret, pk_1_tx).unwrap();
-    pk_1_tx.validate(&store).unwrap();
-    store.set(pk_1_tx.clone().into());
+    store.apply_transaction(pk_1_tx.clone().into()).unwrap();
 
     let pk_2_shares: Vec<(u8, EncryptedShare)> = all_shares
         .iter()
@@ -659,8 +655,7 @@ fn end_to_end_election_with_mix() {
         pk_2_proof,
     );
     let pk_2_tx = Signed::sign(&trustee_2_secret, pk_2_tx).unwrap();
-    pk_2_tx.validate(&store).unwrap();
-    store.set(pk_2_tx.clone().into());
+    store.apply_transaction(pk_2_tx.clone().into()).unwrap();
 
     let pk_3_shares: Vec<(u8, EncryptedShare)> = all_shares
         .iter()
@@ -683,21 +678,22 @@ fn end_to_end_election_with_mix() {
         pk_3_proof,
     );
     let pk_3_tx = Signed::sign(&trustee_3_secret, pk_3_tx).unwrap();
-    pk_3_tx.validate(&store).unwrap();
-    store.set(pk_3_tx.clone().into());
+    store.apply_transaction(pk_3_tx.clone().into()).unwrap();
 
     // Generate an encryption_key transaction
     let encryption_key_tx =
         EncryptionKeyTransaction::new(election.id, authority_public, pk_1_tx.inner().public_key);
     let encryption_key_tx = Signed::sign(&authority_secret, encryption_key_tx).unwrap();
-    encryption_key_tx.validate(&store).unwrap();
-    store.set(encryption_key_tx.clone().into());
+    store
+        .apply_transaction(encryption_key_tx.clone().into())
+        .unwrap();
 
     // Create a vote transaction
     let selection = Selection {
         write_in: false,
         score: 0,
         selection: "Barak Obama".to_string(),
+        weight: None,
     };
 
     // Encrypt the secret vote
@@ -732,8 +728,7 @@ fn end_to_end_election_with_mix() {
     let vote = Signed::sign(&voter_secret, vote).unwrap();
 
     // Validate the vote transaction and store it
-    vote.validate(&store).unwrap();
-    store.set(vote.clone().into());
+    store.apply_transaction(vote.clone().into()).unwrap();
 
     // Generate an second vote transaction
 
@@ -742,6 +737,7 @@ fn end_to_end_election_with_mix() {
         write_in: false,
         score: 0,
         selection: "Santa".to_string(),
+        weight: None,
     };
 
     // Encrypt the secret vote
@@ -776,17 +772,17 @@ fn end_to_end_election_with_mix() {
     let vote_2 = Signed::sign(&voter_secret_2, vote_2).unwrap();
 
     // Validate the vote transaction and store it
-    vote_2.validate(&store).unwrap();
-    store.set(vote_2.clone().into());
+    store.apply_transaction(vote_2.clone().into()).unwrap();
 
     // Voting is over!
     // ---------------
 
     // Generate VotingEnd transaction to mark the end of voting
-    let voting_end_tx = VotingEndTransaction::new(election.id, election.authority_public);
+    let voting_end_tx = VotingEndTransaction::build(&election, &[vote.clone(), vote_2.clone()]);
     let voting_end_tx = Signed::sign(&authority_secret, voting_end_tx).unwrap();
-    voting_end_tx.validate(&store).unwrap();
-    store.set(voting_end_tx.clone().into());
+    store
+        .apply_transaction(voting_end_tx.clone().into())
+        .unwrap();
 
     let mut votes = vec![vote.clone(), vote_2.clone()];
     votes.sort_by(|v1, v2| v1.id().cmp(&v2.id()));
@@ -822,8 +818,9 @@ fn end_to_end_election_with_mix() {
         proof,
     );
     let shuffle_tx_1 = Signed::sign(&trustee_1_secret, shuffle_tx_1).unwrap();
-    shuffle_tx_1.validate(&store).unwrap();
-    store.set(shuffle_tx_1.clone().into());
+    store
+        .apply_transaction(shuffle_tx_1.clone().into())
+        .unwrap();
 
     // Generate the second mix transaction
     let (shuffle_2, proof) = mix(
@@ -849,8 +846,9 @@ fn end_to_end_election_with_mix() {
         proof,
     );
     let shuffle_tx_2 = Signed::sign(&trustee_2_secret, shuffle_tx_2).unwrap();
-    shuffle_tx_2.validate(&store).unwrap();
-    store.set(shuffle_tx_2.clone().into());
+    store
+        .apply_transaction(shuffle_tx_2.clone().into())
+        .unwrap();
 
     // Generate a partial-decryption transactions
     let upstream_index = 0;
@@ -875,8 +873,9 @@ fn end_to_end_election_with_mix() {
         vec![partial_decrypt_1_1],
     );
     let partial_decrypt_1_1_tx = Signed::sign(&trustee_1_secret, partial_decrypt_1_1_tx).unwrap();
-    partial_decrypt_1_1_tx.validate(&store).unwrap();
-    store.set(partial_decrypt_1_1_tx.clone().into());
+    store
+        .apply_transaction(partial_decrypt_1_1_tx.clone().into())
+        .unwrap();
 
     let partial_decrypt_1_2 = trustee_2
         .partial_decrypt(
@@ -899,8 +898,9 @@ fn end_to_end_election_with_mix() {
         vec![partial_decrypt_1_2],
     );
     let partial_decrypt_1_2_tx = Signed::sign(&trustee_2_secret, partial_decrypt_1_2_tx).unwrap();
-    partial_decrypt_1_2_tx.validate(&store).unwrap();
-    store.set(partial_decrypt_1_2_tx.clone().into());
+    store
+        .apply_transaction(partial_decrypt_1_2_tx.clone().into())
+        .unwrap();
 
     let partials = vec![
         partial_decrypt_1_1_tx.tx.clone(),
@@ -931,8 +931,9 @@ fn end_to_end_election_with_mix() {
     // TODO: Add a decryptor public key to make it meaningful??  It does't really matter..
     // TODO: Do this and require it to be a trustee
     let decrypted_tx_1 = Signed::sign(&trustee_1_secret, decrypted_tx_1).unwrap();
-    decrypted_tx_1.validate(&store).unwrap();
-    store.set(decrypted_tx_1.clone().into());
+    store
+        .apply_transaction(decrypted_tx_1.clone().into())
+        .unwrap();
 
     // Generate a second partial-decryption transactions
     let upstream_index = 1;
@@ -957,8 +958,9 @@ fn end_to_end_election_with_mix() {
         vec![partial_decrypt_2_1],
     );
     let partial_decrypt_2_1_tx = Signed::sign(&trustee_1_secret, partial_decrypt_2_1_tx).unwrap();
-    partial_decrypt_2_1_tx.validate(&store).unwrap();
-    store.set(partial_decrypt_2_1_tx.clone().into());
+    store
+        .apply_transaction(partial_decrypt_2_1_tx.clone().into())
+        .unwrap();
 
     let partial_decrypt_2_2 = trustee_2
         .partial_decrypt(
@@ -981,8 +983,9 @@ fn end_to_end_election_with_mix() {
         vec![partial_decrypt_2_2],
     );
     let partial_decrypt_2_2_tx = Signed::sign(&trustee_2_secret, partial_decrypt_2_2_tx).unwrap();
-    partial_decrypt_2_2_tx.validate(&store).unwrap();
-    store.set(partial_decrypt_2_2_tx.clone().into());
+    store
+        .apply_transaction(partial_decrypt_2_2_tx.clone().into())
+        .unwrap();
 
     let partials = vec![
         partial_decrypt_2_1_tx.tx.clone(),
@@ -1013,8 +1016,9 @@ fn end_to_end_election_with_mix() {
     // TODO: Add a decryptor public key to make it meaningful??  It does't really matter..
     // TODO: Do this and require it to be a trustee
     let decrypted_tx_2 = Signed::sign(&trustee_1_secret, decrypted_tx_2).unwrap();
-    decrypted_tx_2.validate(&store).unwrap();
-    store.set(decrypted_tx_2.clone().into());
+    store
+        .apply_transaction(decrypted_tx_2.clone().into())
+        .unwrap();
 
     // Decrypted votes should match secret votes, but unknown order
     let secret_votes = vec![selection, selection_2];
@@ -1071,7 +1075,7 @@ fn test_all_elections() {
         let entry = entry.unwrap();
         let path = entry.path();
         if path.is_dir() {
-            let mut store = MemStore::default();
+            let store = MemStore::default();
 
             let mut paths: Vec<_> = std::fs::read_dir(path)
                 .unwrap()
@@ -1093,17 +1097,2874 @@ fn test_all_elections() {
                 };
 
                 for tx in txs {
-                    if let Err(e) = tx.validate(&store) {
+                    let (tx_type, id) = (tx.transaction_type(), tx.id());
+                    if let Err(e) = store.apply_transaction(tx) {
                         panic!(
                             "Failed to validate {} trancaction {}. Error: {}",
-                            tx.transaction_type(),
-                            tx.id(),
-                            e
+                            tx_type, id, e
                         );
                     }
-                    store.set(tx);
                 }
             }
         }
     }
 }
+
+#[test]
+fn test_trustee_key_revocation() {
+    let mut test_rng = rand::rngs::StdRng::from_seed([0u8; 32]);
+    let store = MemStore::default();
+    let (authority_secret, authority_public) = generate_keypair();
+
+    let trustees = vec![Trustee::new(1, 2, 2), Trustee::new(2, 2, 2)];
+
+    let mut election = ElectionTransaction::new(authority_public);
+    election.trustees = trustees
+        .iter()
+        .map(|(trustee, _)| trustee.clone())
+        .collect();
+    election.trustees_threshold = 2;
+    election.authenticators_threshold = 0;
+    election.revocations_allowed = true;
+    let election = Signed::sign(&authority_secret, election).unwrap();
+    store.apply_transaction(election.clone().into()).unwrap();
+
+    // Run key generation
+    let mut commitments = Vec::new();
+    let mut x25519_public_keys = Vec::new();
+    for (trustee, secret) in trustees.iter() {
+        let x25519_public = trustee.x25519_public_key(secret, election.id);
+        let commitment = trustee.keygen_commitment(secret, election.id);
+        let commit_tx = KeyGenCommitmentTransaction::new(
+            election.id,
+            trustee.index,
+            trustee.public_key,
+            x25519_public,
+            commitment.clone(),
+        );
+        let commit_tx = Signed::sign(secret, commit_tx).unwrap();
+        store.apply_transaction(commit_tx.into()).unwrap();
+        commitments.push((trustee.index, commitment));
+        x25519_public_keys.push((trustee.index, x25519_public));
+    }
+
+    let mut all_shares = Vec::new();
+    for (trustee, secret) in trustees.iter() {
+        let shares = trustee.generate_shares(
+            &mut test_rng,
+            secret,
+            &x25519_public_keys,
+            election.id,
+            &commitments,
+        );
+        let share_tx = KeyGenShareTransaction::new(
+            election.id,
+            trustee.index,
+            trustee.public_key,
+            shares.clone(),
+        );
+        let share_tx = Signed::sign(secret, share_tx).unwrap();
+        store.apply_transaction(share_tx.into()).unwrap();
+        all_shares.push((trustee.index, shares));
+    }
+
+    let mut pk_txs = Vec::new();
+    for (trustee, secret) in trustees.iter() {
+        let shares: Vec<(u8, EncryptedShare)> = all_shares
+            .iter()
+            .map(|(index, shares)| (*index, shares.get(&trustee.index).unwrap().clone()))
+            .collect();
+        let (pk, pk_proof) = trustee
+            .generate_public_key(
+                secret,
+                &x25519_public_keys,
+                &commitments,
+                &shares,
+                election.id,
+            )
+            .unwrap();
+        let pk_tx = KeyGenPublicKeyTransaction::new(
+            election.id,
+            trustee.index,
+            trustee.public_key,
+            pk,
+            pk_proof,
+        );
+        let pk_tx = Signed::sign(secret, pk_tx).unwrap();
+        store.apply_transaction(pk_tx.clone().into()).unwrap();
+        pk_txs.push(pk_tx);
+    }
+
+    // Trustee 1 revokes their key
+    let (trustee_1, trustee_1_secret) = &trustees[0];
+    let revocation =
+        TrusteeKeyRevocationTransaction::new(&pk_txs[0], Some("Laptop stolen".to_string()));
+    let revocation = Signed::sign(trustee_1_secret, revocation).unwrap();
+    store.apply_transaction(revocation.into()).unwrap();
+
+    // The encryption key cannot be posted until the revoked key is replaced
+    let encryption_key = pk_txs[1].public_key.clone();
+    let encryption_key_tx =
+        EncryptionKeyTransaction::new(election.id, authority_public, encryption_key);
+    let encryption_key_tx = Signed::sign(&authority_secret, encryption_key_tx).unwrap();
+    assert!(matches!(
+        encryption_key_tx.validate(&store),
+        Err(ValidationError::RevokedKeyNotReplaced(1))
+    ));
+
+    // Replacements must follow on from the revoked revision
+    let mut replacement = pk_txs[0].tx.clone().with_revision(2);
+    let skipped = Signed::sign(trustee_1_secret, replacement.clone()).unwrap();
+    assert!(matches!(
+        skipped.validate(&store),
+        Err(ValidationError::KeyGenPublicKeyNotRevoked(1))
+    ));
+    replacement = replacement.with_revision(1);
+    let replacement = Signed::sign(trustee_1_secret, replacement).unwrap();
+    store.apply_transaction(replacement.clone().into()).unwrap();
+
+    assert_eq!(
+        store
+            .get_current_keygen_public_key(election.id, trustee_1.index)
+            .unwrap()
+            .revision,
+        1
+    );
+    store.apply_transaction(encryption_key_tx.into()).unwrap();
+
+    // Once the encryption key is posted, keys can no longer be revoked
+    let late_revocation = TrusteeKeyRevocationTransaction::new(&replacement, None);
+    let late_revocation = Signed::sign(trustee_1_secret, late_revocation).unwrap();
+    assert!(matches!(
+        late_revocation.validate(&store),
+        Err(ValidationError::RevocationAfterEncryptionKey)
+    ));
+}
+
+#[test]
+fn test_observer_acknowledgment() {
+    let mut test_rng = rand::rngs::StdRng::from_seed([0u8; 32]);
+    let (observer_secret, observer_public) = generate_keypair();
+    let (imposter_secret, imposter_public) = generate_keypair();
+
+    let observer = Observer {
+        id: uuid::Uuid::new_v4(),
+        name: "League of Voters".to_string(),
+        public_key: observer_public,
+    };
+
+    let keyed = KeyedElection::new_with(&mut test_rng, 1, 1, |election| {
+        election.observers = vec![observer.clone()];
+        election.require_observer_acknowledgments = true;
+    });
+    let store = &keyed.store;
+    let election = &keyed.election;
+
+    // No vote can be cast until the observer has acknowledged the election
+    let selection = Selection {
+        write_in: false,
+        score: 0,
+        selection: "Barak Obama".to_string(),
+        weight: None,
+    };
+    let encrypted_vote = EncryptedVote {
+        contest_index: 0,
+        selections: encrypt_vote(&keyed.encryption_key, vec![selection], &mut test_rng).unwrap(),
+    };
+    let (vote, voter_secret) =
+        VoteTransaction::new(election.id, "TEST".to_string(), vec![encrypted_vote]);
+    let vote = Signed::sign(&voter_secret, vote).unwrap();
+    assert!(matches!(
+        vote.validate(store),
+        Err(ValidationError::MissingObserverAcknowledgment(id)) if id == observer.id
+    ));
+
+    // An imposter cannot acknowledge on behalf of the observer
+    let mut imposter = observer.clone();
+    imposter.public_key = imposter_public;
+    let bad_ack = ObserverAcknowledgmentTransaction::new(election.id, &imposter);
+    let bad_ack = Signed::sign(&imposter_secret, bad_ack).unwrap();
+    assert!(matches!(
+        bad_ack.validate(store),
+        Err(ValidationError::ObserverPublicKeyMismatch(_))
+    ));
+
+    let ack = ObserverAcknowledgmentTransaction::new(election.id, &observer);
+    let ack = Signed::sign(&observer_secret, ack).unwrap();
+    store.apply_transaction(ack.into()).unwrap();
+
+    assert_eq!(store.get_observer_acknowledgments(election.id).len(), 1);
+
+    // Once acknowledged, voting can go ahead
+    store.apply_transaction(vote.into()).unwrap();
+}
+
+// An election whose trustees have all finished key generation, with the encryption key posted
+struct KeyedElection {
+    store: MemStore,
+    election: Signed<ElectionTransaction>,
+    authority_secret: ed25519_dalek::SecretKey,
+    trustees: Vec<(Trustee, ed25519_dalek::SecretKey)>,
+    x25519_public_keys: Vec<(u8, x25519_dalek::PublicKey)>,
+    commitments: Vec<(u8, cryptid::threshold::KeygenCommitment)>,
+    trustee_shares: Vec<Vec<(u8, EncryptedShare)>>,
+    pubkeys: Vec<KeyGenPublicKeyTransaction>,
+    encryption_key: cryptid::elgamal::PublicKey,
+}
+
+impl KeyedElection {
+    // A single ballot "TEST" with a single plurality contest, and no authenticators
+    fn new<R: rand::Rng + rand::CryptoRng>(rng: &mut R, num_trustees: u8, threshold: u8) -> Self {
+        Self::new_with(rng, num_trustees, threshold, |_| {})
+    }
+
+    // As `new`, letting `configure` change the election before it is signed
+    fn new_with<R: rand::Rng + rand::CryptoRng>(
+        rng: &mut R,
+        num_trustees: u8,
+        threshold: u8,
+        configure: impl FnOnce(&mut ElectionTransaction),
+    ) -> Self {
+        let store = MemStore::default();
+        let (authority_secret, authority_public) = generate_keypair();
+
+        let trustees: Vec<_> = (1..=num_trustees)
+            .map(|index| Trustee::new(index, num_trustees as usize, threshold))
+            .collect();
+
+        let mut election = ElectionTransaction::new(authority_public);
+        election.ballots = vec![Ballot {
+            id: "TEST".to_string(),
+            contests: vec![0],
+            properties: indexmap::IndexMap::new(),
+        }];
+        election.contests = vec![Contest {
+            id: "TESTCONTEST".to_string(),
+            index: 0,
+            contest_type: ContestType::Plurality,
+            write_in: true,
+            num_winners: 1,
+            candidates: vec![],
+            question: None,
+            properties: indexmap::IndexMap::new(),
+        }];
+        election.trustees = trustees
+            .iter()
+            .map(|(trustee, _)| trustee.clone())
+            .collect();
+        election.trustees_threshold = threshold;
+        election.authenticators_threshold = 0;
+        configure(&mut election);
+        let election = Signed::sign(&authority_secret, election).unwrap();
+        store.apply_transaction(election.clone().into()).unwrap();
+
+        let mut commitments = Vec::new();
+        let mut x25519_public_keys = Vec::new();
+        for (trustee, secret) in trustees.iter() {
+            let x25519_public = trustee.x25519_public_key(secret, election.id);
+            let commitment = trustee.keygen_commitment(secret, election.id);
+            let commit_tx = KeyGenCommitmentTransaction::new(
+                election.id,
+                trustee.index,
+                trustee.public_key,
+                x25519_public,
+                commitment.clone(),
+            );
+            let commit_tx = Signed::sign(secret, commit_tx).unwrap();
+            store.apply_transaction(commit_tx.into()).unwrap();
+            commitments.push((trustee.index, commitment));
+            x25519_public_keys.push((trustee.index, x25519_public));
+        }
+
+        let mut all_shares = Vec::new();
+        for (trustee, secret) in trustees.iter() {
+            let shares = trustee.generate_shares(
+                rng,
+                secret,
+                &x25519_public_keys,
+                election.id,
+                &commitments,
+            );
+            let share_tx = KeyGenShareTransaction::new(
+                election.id,
+                trustee.index,
+                trustee.public_key,
+                shares.clone(),
+            );
+            let share_tx = Signed::sign(secret, share_tx).unwrap();
+            store.apply_transaction(share_tx.into()).unwrap();
+            all_shares.push((trustee.index, shares));
+        }
+
+        let mut trustee_shares = Vec::new();
+        let mut pubkeys = Vec::new();
+        for (trustee, secret) in trustees.iter() {
+            let shares: Vec<(u8, EncryptedShare)> = all_shares
+                .iter()
+                .map(|(index, shares)| (*index, shares.get(&trustee.index).unwrap().clone()))
+                .collect();
+            let (pk, pk_proof) = trustee
+                .generate_public_key(
+                    secret,
+                    &x25519_public_keys,
+                    &commitments,
+                    &shares,
+                    election.id,
+                )
+                .unwrap();
+            let pk_tx = KeyGenPublicKeyTransaction::new(
+                election.id,
+                trustee.index,
+                trustee.public_key,
+                pk,
+                pk_proof,
+            );
+            let pk_tx = Signed::sign(secret, pk_tx).unwrap();
+            store.apply_transaction(pk_tx.clone().into()).unwrap();
+            trustee_shares.push(shares);
+            pubkeys.push(pk_tx.tx);
+        }
+
+        let encryption_key = pubkeys[0].public_key.clone();
+        let encryption_key_tx =
+            EncryptionKeyTransaction::new(election.id, authority_public, encryption_key.clone());
+        let encryption_key_tx = Signed::sign(&authority_secret, encryption_key_tx).unwrap();
+        store.apply_transaction(encryption_key_tx.into()).unwrap();
+
+        KeyedElection {
+            store,
+            election,
+            authority_secret,
+            trustees,
+            x25519_public_keys,
+            commitments,
+            trustee_shares,
+            pubkeys,
+            encryption_key,
+        }
+    }
+
+    // Post a vote for contest 0 with the given ciphertexts
+    fn vote(&self, selections: Vec<cryptid::elgamal::Ciphertext>) -> Signed<VoteTransaction> {
+        let encrypted_vote = EncryptedVote {
+            contest_index: 0,
+            selections,
+        };
+        let (vote, voter_secret) =
+            VoteTransaction::new(self.election.id, "TEST".to_string(), vec![encrypted_vote]);
+        let vote = Signed::sign(&voter_secret, vote).unwrap();
+        self.store.apply_transaction(vote.clone().into()).unwrap();
+        vote
+    }
+
+    fn end_voting(&self) {
+        let votes: Vec<Signed<VoteTransaction>> = self
+            .store
+            .get_multiple(self.election.id, TransactionType::Vote)
+            .into_iter()
+            .map(|tx| tx.into())
+            .collect();
+        let voting_end_tx = VotingEndTransaction::build(&self.election, &votes);
+        let voting_end_tx = Signed::sign(&self.authority_secret, voting_end_tx).unwrap();
+        self.store.apply_transaction(voting_end_tx.into()).unwrap();
+    }
+
+    // Partially decrypt a vote as the trustee at `position` in `trustees`, without posting it
+    fn partial_decrypt<R: rand::Rng + rand::CryptoRng>(
+        &self,
+        rng: &mut R,
+        position: usize,
+        vote: &VoteTransaction,
+    ) -> Signed<PartialDecryptionTransaction> {
+        let (trustee, secret) = &self.trustees[position];
+        let reshares: Vec<TrusteeReshareTransaction> = self
+            .store
+            .get_trustee_reshares(self.election.id)
+            .into_iter()
+            .map(|tx| tx.tx)
+            .collect();
+        let (commitments, shares) = trustee
+            .apply_reshares(
+                rng,
+                secret,
+                &self.x25519_public_keys,
+                &self.commitments,
+                &self.trustee_shares[position],
+                &reshares,
+                self.election.id,
+            )
+            .unwrap();
+        let partials = vote.encrypted_votes[0]
+            .selections
+            .iter()
+            .map(|ciphertext| {
+                trustee
+                    .partial_decrypt(
+                        rng,
+                        secret,
+                        &self.x25519_public_keys,
+                        &commitments,
+                        &shares,
+                        ciphertext,
+                        self.election.id,
+                    )
+                    .unwrap()
+            })
+            .collect();
+        let partial_tx = PartialDecryptionTransaction::new(
+            self.election.id,
+            vote.id,
+            0,
+            trustee.index,
+            0,
+            trustee.public_key,
+            partials,
+        );
+        Signed::sign(secret, partial_tx).unwrap()
+    }
+
+    // Re-share a revoked trustee's key share as the trustee at `position` in `trustees`, without posting it
+    fn reshare<R: rand::Rng + rand::CryptoRng>(
+        &self,
+        rng: &mut R,
+        position: usize,
+        revoked_index: u8,
+    ) -> Signed<TrusteeReshareTransaction> {
+        let (trustee, secret) = &self.trustees[position];
+        let x25519_public_keys: Vec<_> = self
+            .x25519_public_keys
+            .iter()
+            .filter(|(index, _)| !self.store.is_trustee_revoked(self.election.id, *index))
+            .cloned()
+            .collect();
+        let (commitment, shares) =
+            trustee.generate_reshares(rng, secret, &x25519_public_keys, self.election.id);
+        let reshare_tx = TrusteeReshareTransaction::new(
+            self.election.id,
+            trustee.index,
+            trustee.public_key,
+            revoked_index,
+            commitment,
+            shares,
+        );
+        Signed::sign(secret, reshare_tx).unwrap()
+    }
+}
+
+#[test]
+fn test_derive_election_public_key() {
+    let mut test_rng = rand::rngs::StdRng::from_seed([0u8; 32]);
+    let keyed = KeyedElection::new(&mut test_rng, 3, 2);
+    let election = &keyed.election;
+
+    // Any threshold of public key shares interpolate the same key
+    assert_eq!(
+        election.derive_election_public_key(&keyed.pubkeys).unwrap(),
+        keyed.encryption_key
+    );
+    assert_eq!(
+        election
+            .derive_election_public_key(&keyed.pubkeys[1..])
+            .unwrap(),
+        keyed.encryption_key
+    );
+    assert!(matches!(
+        election.derive_election_public_key(&keyed.pubkeys[..1]),
+        Err(ValidationError::WrongNumberOfPublicKeyTransactions)
+    ));
+
+    // A trustee reporting another joint key is caught, even when its share isn't interpolated
+    let mut other_key = keyed.pubkeys.clone();
+    other_key[2].public_key = KeyedElection::new(&mut test_rng, 1, 1).encryption_key;
+    assert!(matches!(
+        election.derive_election_public_key(&other_key),
+        Err(ValidationError::MismatchedEncryptionKey(3))
+    ));
+
+    // Substituting another trustee's share changes the derived key
+    let mut swapped = keyed.pubkeys.clone();
+    swapped[0].public_key_proof = swapped[2].public_key_proof.clone();
+    assert!(matches!(
+        election.derive_election_public_key(&swapped),
+        Err(ValidationError::MismatchedEncryptionKey(1))
+    ));
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_decrypt_with_combined_key() {
+    let mut test_rng = rand::rngs::StdRng::from_seed([0u8; 32]);
+    let keyed = KeyedElection::new(&mut test_rng, 3, 2);
+
+    let selection = Selection {
+        write_in: true,
+        score: 0,
+        selection: "Barak Obama".to_string(),
+        weight: None,
+    };
+    let selections = encrypt_vote(
+        &keyed.encryption_key,
+        vec![selection.clone()],
+        &mut test_rng,
+    )
+    .unwrap();
+
+    // A trusted dealer holding the key shares of the first two trustees can decrypt the vote on their own
+    let combined_key: Vec<_> = keyed.trustees[0..2]
+        .iter()
+        .enumerate()
+        .map(|(position, (trustee, secret))| {
+            let party = trustee
+                .secret_share(
+                    secret,
+                    &keyed.x25519_public_keys,
+                    &keyed.commitments,
+                    &keyed.trustee_shares[position],
+                    keyed.election.id,
+                )
+                .unwrap();
+            (trustee.index, party)
+        })
+        .collect();
+    let decrypted =
+        decrypt_with_combined_key(&mut test_rng, &selections, 2, &combined_key).unwrap();
+    assert_eq!(decrypted, vec![selection]);
+}
+
+#[test]
+fn test_partials_for_another_vote() {
+    let mut test_rng = rand::rngs::StdRng::from_seed([0u8; 32]);
+    let keyed = KeyedElection::new(&mut test_rng, 3, 2);
+    let election = &keyed.election;
+
+    let selection = Selection {
+        write_in: false,
+        score: 0,
+        selection: "Barak Obama".to_string(),
+        weight: None,
+    };
+    let mut votes = Vec::new();
+    for _ in 0..2 {
+        let selections = encrypt_vote(
+            &keyed.encryption_key,
+            vec![selection.clone()],
+            &mut test_rng,
+        )
+        .unwrap();
+        votes.push(keyed.vote(selections));
+    }
+
+    let partials: Vec<_> = (0..2)
+        .map(|position| keyed.partial_decrypt(&mut test_rng, position, &votes[0]).tx)
+        .collect();
+    let decrypted = decrypt_vote(
+        &votes[0].encrypted_votes[0].selections,
+        election.trustees_threshold,
+        &election.trustees,
+        &keyed.pubkeys,
+        &partials,
+    )
+    .unwrap();
+    assert_eq!(decrypted, vec![selection]);
+
+    // Partials for one vote can't be combined to decrypt another
+    assert!(matches!(
+        decrypt_vote(
+            &votes[1].encrypted_votes[0].selections,
+            election.trustees_threshold,
+            &election.trustees,
+            &keyed.pubkeys,
+            &partials,
+        ),
+        Err(ValidationError::PartialDecryptionProofFailed)
+    ));
+}
+
+#[test]
+fn test_trustee_absence() {
+    let mut test_rng = rand::rngs::StdRng::from_seed([0u8; 32]);
+    let keyed = KeyedElection::new(&mut test_rng, 5, 3);
+    let store = &keyed.store;
+    let election = &keyed.election;
+
+    let selection = Selection {
+        write_in: false,
+        score: 0,
+        selection: "Barak Obama".to_string(),
+        weight: None,
+    };
+    let selections = encrypt_vote(
+        &keyed.encryption_key,
+        vec![selection.clone()],
+        &mut test_rng,
+    )
+    .unwrap();
+    let vote = keyed.vote(selections);
+    keyed.end_voting();
+
+    // Trustees 4 and 5 never post their partial decryptions
+    let partial_txs: Vec<_> = (0..5)
+        .map(|position| keyed.partial_decrypt(&mut test_rng, position, &vote))
+        .collect();
+    for (collected, partial_tx) in partial_txs[0..3].iter().enumerate() {
+        assert_eq!(
+            partials_available(store, election.id, vote.id, 0, 0).unwrap(),
+            (collected, 3)
+        );
+        assert!(!can_decrypt(store, election.id, vote.id, 0, 0));
+        store.apply_transaction(partial_tx.clone().into()).unwrap();
+    }
+    assert!(can_decrypt(store, election.id, vote.id, 0, 0));
+
+    let partials: Vec<PartialDecryptionTransaction> =
+        partial_txs[0..3].iter().map(|tx| tx.tx.clone()).collect();
+    let decrypted = decrypt_vote(
+        &vote.encrypted_votes[0].selections,
+        election.trustees_threshold,
+        &election.trustees,
+        &keyed.pubkeys,
+        &partials,
+    )
+    .unwrap();
+    assert_eq!(decrypted[0], selection);
+
+    // The quorum can still decrypt, formally recording the absence of trustees 4 and 5
+    let (_, trustee_1_secret) = &keyed.trustees[0];
+    let mut decryption =
+        DecryptionTransaction::new(election.id, vote.id, 0, 0, vec![1, 2, 3], decrypted);
+    decryption.record_trustee_absence(election);
+    assert!(decryption.recover_decryption_without_quorum);
+    assert_eq!(decryption.absent_trustees, vec![4, 5]);
+    let signed = Signed::sign(trustee_1_secret, decryption.clone()).unwrap();
+    signed.validate(store).unwrap();
+
+    // Every trustee must either have participated or be recorded as absent
+    let mut incomplete = decryption.clone();
+    incomplete.absent_trustees = vec![5];
+    let incomplete = Signed::sign(trustee_1_secret, incomplete).unwrap();
+    assert!(matches!(
+        incomplete.validate(store),
+        Err(ValidationError::TrusteeAbsenceMismatch(4))
+    ));
+
+    // A trustee who posted a partial decryption was not absent
+    let late_store = store.clone();
+    late_store
+        .apply_transaction(partial_txs[3].clone().into())
+        .unwrap();
+    assert!(matches!(
+        signed.validate(&late_store),
+        Err(ValidationError::TrusteeAbsenceMismatch(4))
+    ));
+}
+
+#[test]
+fn test_spoiled_ballot() {
+    let mut test_rng = rand::rngs::StdRng::from_seed([0u8; 32]);
+    let keyed = KeyedElection::new(&mut test_rng, 3, 2);
+    let store = &keyed.store;
+    let election = &keyed.election;
+
+    let selection = Selection {
+        write_in: false,
+        score: 0,
+        selection: "Barak Obama".to_string(),
+        weight: None,
+    };
+    let selections = encrypt_vote(
+        &keyed.encryption_key,
+        vec![selection.clone()],
+        &mut test_rng,
+    )
+    .unwrap();
+    let vote = keyed.vote(selections);
+
+    // A selection whose text is not UTF-8: field 3 (selection), length 2, bytes 0xff 0xfe
+    let not_utf8 = [0x1a, 0x02, 0xff, 0xfe];
+    let ciphertext = ElGamal::encrypt(&keyed.encryption_key, &not_utf8, &mut test_rng);
+    let spoiled_vote = keyed.vote(vec![ciphertext]);
+    keyed.end_voting();
+
+    let (_, trustee_1_secret) = &keyed.trustees[0];
+    let mut decryptions = Vec::new();
+    for vote in [&vote, &spoiled_vote].iter() {
+        let mut partials = Vec::new();
+        for position in 0..2 {
+            let partial_tx = keyed.partial_decrypt(&mut test_rng, position, vote);
+            store.apply_transaction(partial_tx.clone().into()).unwrap();
+            partials.push(partial_tx.tx);
+        }
+        decryptions.push(decrypt_vote(
+            &vote.encrypted_votes[0].selections,
+            election.trustees_threshold,
+            &election.trustees,
+            &keyed.pubkeys,
+            &partials,
+        ));
+    }
+
+    let decrypted = decryptions.remove(0).unwrap();
+    assert_eq!(decrypted[0], selection);
+    assert!(matches!(
+        decryptions.remove(0),
+        Err(ValidationError::VoteDecodingError(_))
+    ));
+
+    // The undecodable vote can only be decrypted as spoiled
+    let not_spoiled =
+        DecryptionTransaction::new(election.id, spoiled_vote.id, 0, 0, vec![1, 2], vec![]);
+    let not_spoiled = Signed::sign(trustee_1_secret, not_spoiled).unwrap();
+    assert!(matches!(
+        not_spoiled.validate(store),
+        Err(ValidationError::VoteDecodingError(_))
+    ));
+
+    let spoiled =
+        DecryptionTransaction::new_spoiled(election.id, spoiled_vote.id, 0, 0, vec![1, 2]);
+    let spoiled = Signed::sign(trustee_1_secret, spoiled).unwrap();
+    store.apply_transaction(spoiled.into()).unwrap();
+
+    // ...and a valid vote can't be spoiled
+    let wrongly_spoiled =
+        DecryptionTransaction::new_spoiled(election.id, vote.id, 0, 0, vec![1, 2]);
+    let wrongly_spoiled = Signed::sign(trustee_1_secret, wrongly_spoiled).unwrap();
+    assert!(matches!(
+        wrongly_spoiled.validate(store),
+        Err(ValidationError::VoteDecryptionMismatch)
+    ));
+
+    let decryption = DecryptionTransaction::new(election.id, vote.id, 0, 0, vec![1, 2], decrypted);
+    let decryption = Signed::sign(trustee_1_secret, decryption).unwrap();
+    store.apply_transaction(decryption.into()).unwrap();
+
+    // The spoiled ballot is reported, but not counted
+    let results = tally_election(store, election.id).unwrap();
+    assert_eq!(results.total_ballots, 2);
+    let result = &results.contests["TESTCONTEST"];
+    assert_eq!(result.num_votes, 1);
+    assert_eq!(result.spoiled_ballots.len(), 1);
+    assert!(matches!(
+        result.spoiled_ballots.get(&spoiled_vote.id),
+        Some(SpoiledBallotError::IncorrectProtobufFormat)
+    ));
+}
+
+#[test]
+fn test_challenge() {
+    let mut test_rng = rand::rngs::StdRng::from_seed([0u8; 32]);
+    let keyed = KeyedElection::new(&mut test_rng, 3, 2);
+    let store = &keyed.store;
+    let election = &keyed.election;
+
+    let selection = Selection {
+        write_in: false,
+        score: 0,
+        selection: "Barak Obama".to_string(),
+        weight: None,
+    };
+    let plaintext = vec![PlaintextVote {
+        contest_index: 0,
+        selections: vec![selection.clone()],
+    }];
+
+    // The voter encrypts with a seed, so the encryption can later be reproduced
+    let seed: [u8; 32] = rand::Rng::gen(&mut test_rng);
+    let encrypted_votes = encrypt_vote_with_seed(&keyed.encryption_key, &plaintext, seed).unwrap();
+    let (vote, voter_secret) =
+        VoteTransaction::new(election.id, "TEST".to_string(), encrypted_votes);
+    let vote = Signed::sign(&voter_secret, vote).unwrap();
+    store.apply_transaction(vote.clone().into()).unwrap();
+
+    // A challenge with the wrong plaintext does not reproduce the vote
+    let mut wrong_selection = selection.clone();
+    wrong_selection.selection = "Santa Claus".to_string();
+    let wrong_plaintext = vec![PlaintextVote {
+        contest_index: 0,
+        selections: vec![wrong_selection],
+    }];
+    let wrong = ChallengeTransaction::new(&vote, wrong_plaintext, seed);
+    let wrong = Signed::sign(&voter_secret, wrong).unwrap();
+    assert!(matches!(
+        wrong.validate(store),
+        Err(ValidationError::ChallengeEncryptionMismatch)
+    ));
+
+    // Only the voter can challenge their vote
+    let (other_secret, _) = generate_keypair();
+    let mut impostor = ChallengeTransaction::new(&vote, plaintext.clone(), seed);
+    impostor.anonymous_key = (&other_secret).into();
+    let impostor = Signed::sign(&other_secret, impostor).unwrap();
+    assert!(matches!(
+        impostor.validate(store),
+        Err(ValidationError::ChallengeKeyMismatch)
+    ));
+
+    let challenge = ChallengeTransaction::new(&vote, plaintext, seed);
+    let challenge = Signed::sign(&voter_secret, challenge).unwrap();
+    store.apply_transaction(challenge.into()).unwrap();
+
+    // The voter re-casts, and only the re-cast vote is counted
+    let selections = encrypt_vote(
+        &keyed.encryption_key,
+        vec![selection.clone()],
+        &mut test_rng,
+    )
+    .unwrap();
+    let recast = keyed.vote(selections);
+    keyed.end_voting();
+
+    let cast_votes = store.get_cast_votes(election.id);
+    assert_eq!(cast_votes.len(), 1);
+    assert_eq!(cast_votes[0].id(), recast.id());
+
+    let partial_tx = keyed.partial_decrypt(&mut test_rng, 0, &vote);
+    assert!(matches!(
+        partial_tx.validate(store),
+        Err(ValidationError::VoteChallenged(id)) if id == vote.id()
+    ));
+    let partial_tx = keyed.partial_decrypt(&mut test_rng, 0, &recast);
+    partial_tx.validate(store).unwrap();
+
+    assert!(store.validate_all().is_empty());
+}
+
+#[test]
+fn test_external_trustees() {
+    let (authority_secret, authority_public) = generate_keypair();
+    let store = MemStore::default();
+
+    // Two trustees generate their own keys and only share their public keys...
+    let external: Vec<_> = (1..=2)
+        .map(|index| {
+            let (secret, public) = generate_keypair();
+            let trustee = Trustee::from_public_key(uuid::Uuid::new_v4(), index, public);
+            (trustee, secret)
+        })
+        .collect();
+
+    // ...and a third is registered without an index
+    let (registered, registered_secret) = Trustee::register(uuid::Uuid::new_v4());
+    assert_eq!(registered.index, 0);
+
+    let mut election = ElectionTransaction::new(authority_public);
+    for (trustee, _) in external.iter() {
+        election.add_trustee(trustee.clone()).unwrap();
+    }
+    assert_eq!(election.add_trustee(registered.clone()).unwrap(), 3);
+    election.trustees_threshold = 2;
+    election.authenticators_threshold = 0;
+
+    let trustee = election.get_trustee_by_id(registered.id).unwrap();
+    assert_eq!(trustee.index, 3);
+    assert_eq!(trustee.public_key, registered.public_key);
+
+    let election = Signed::sign(&authority_secret, election).unwrap();
+    store.apply_transaction(election.clone().into()).unwrap();
+
+    // Each trustee can take part in key generation with their own secret
+    let mut trustees: Vec<_> = external
+        .into_iter()
+        .map(|(trustee, secret)| (trustee.public_key, secret))
+        .collect();
+    trustees.push((registered.public_key, registered_secret));
+    for (public_key, secret) in trustees.iter() {
+        let trustee = Trustee::from_election_tx(&election, *public_key);
+        assert_eq!(trustee.num_trustees, 3);
+        assert_eq!(trustee.threshold, 2);
+
+        let commit_tx = KeyGenCommitmentTransaction::new(
+            election.id,
+            trustee.index,
+            trustee.public_key,
+            trustee.x25519_public_key(secret, election.id),
+            trustee.keygen_commitment(secret, election.id),
+        );
+        let commit_tx = Signed::sign(secret, commit_tx).unwrap();
+        store.apply_transaction(commit_tx.into()).unwrap();
+    }
+
+    // Trustee ids survive serialization of the election
+    let json = serde_json::to_string(&election).unwrap();
+    let roundtrip: Signed<ElectionTransaction> = serde_json::from_str(&json).unwrap();
+    assert!(roundtrip.get_trustee_by_id(registered.id).is_some());
+    roundtrip.verify_signature().unwrap();
+}
+
+#[test]
+fn test_decryption_created_before_voting_end() {
+    let mut test_rng = rand::rngs::StdRng::from_seed([0u8; 32]);
+    let keyed = KeyedElection::new(&mut test_rng, 1, 1);
+    let store = &keyed.store;
+    let election = &keyed.election;
+
+    let selection = Selection {
+        write_in: false,
+        score: 0,
+        selection: "Barak Obama".to_string(),
+        weight: None,
+    };
+    let selections = encrypt_vote(&keyed.encryption_key, vec![selection], &mut test_rng).unwrap();
+    let vote = keyed.vote(selections);
+
+    let mut voting_end_tx = VotingEndTransaction::build(election, &[vote.clone()]);
+    voting_end_tx.created_at = Some(1_600_000_000);
+    let voting_end_tx = Signed::sign(&keyed.authority_secret, voting_end_tx).unwrap();
+    store.apply_transaction(voting_end_tx.into()).unwrap();
+
+    let partial_tx = keyed.partial_decrypt(&mut test_rng, 0, &vote);
+    store.apply_transaction(partial_tx.clone().into()).unwrap();
+    let decrypted = decrypt_vote(
+        &vote.encrypted_votes[0].selections,
+        election.trustees_threshold,
+        &election.trustees,
+        &keyed.pubkeys,
+        &[partial_tx.tx],
+    )
+    .unwrap();
+    let mut decrypted_tx =
+        DecryptionTransaction::new(election.id, vote.id, 0, 0, vec![1], decrypted);
+    let (_, trustee_secret) = &keyed.trustees[0];
+
+    // A decryption claiming to be created before voting ended must be rejected
+    decrypted_tx.created_at = Some(1_599_999_999);
+    let early_decrypted_tx = Signed::sign(trustee_secret, decrypted_tx.clone()).unwrap();
+    assert!(matches!(
+        early_decrypted_tx.validate(store),
+        Err(ValidationError::TimestampOrderViolation(
+            1_600_000_000,
+            1_599_999_999
+        ))
+    ));
+
+    decrypted_tx.created_at = Some(1_600_000_000);
+    let decrypted_tx = Signed::sign(trustee_secret, decrypted_tx).unwrap();
+    store.apply_transaction(decrypted_tx.into()).unwrap();
+}
+
+#[test]
+fn test_vote_audit_package() {
+    let mut test_rng = rand::rngs::StdRng::from_seed([0u8; 32]);
+    let keyed = KeyedElection::new(&mut test_rng, 1, 1);
+    let store = &keyed.store;
+    let election = &keyed.election;
+
+    let selection = Selection {
+        write_in: false,
+        score: 0,
+        selection: "Barak Obama".to_string(),
+        weight: None,
+    };
+    let selections = encrypt_vote(&keyed.encryption_key, vec![selection], &mut test_rng).unwrap();
+    let vote = keyed.vote(selections);
+    keyed.end_voting();
+
+    let partial_tx = keyed.partial_decrypt(&mut test_rng, 0, &vote);
+    store.apply_transaction(partial_tx.clone().into()).unwrap();
+    let decrypted = decrypt_vote(
+        &vote.encrypted_votes[0].selections,
+        election.trustees_threshold,
+        &election.trustees,
+        &keyed.pubkeys,
+        &[partial_tx.tx],
+    )
+    .unwrap();
+    let decrypted_tx = DecryptionTransaction::new(election.id, vote.id, 0, 0, vec![1], decrypted);
+    let (_, trustee_secret) = &keyed.trustees[0];
+    let decrypted_tx = Signed::sign(trustee_secret, decrypted_tx).unwrap();
+    store
+        .apply_transaction(decrypted_tx.clone().into())
+        .unwrap();
+
+    // The voter can check their vote from its audit package alone
+    let package =
+        AuditPackage::for_vote(store, election.id, vote.id, &keyed.authority_secret).unwrap();
+    let package: AuditPackage =
+        serde_json::from_str(&serde_json::to_string(&package).unwrap()).unwrap();
+    let summary = package.verify().unwrap();
+    assert_eq!(summary.vote_id, vote.id);
+    assert_eq!(summary.contests[&0], decrypted_tx.inner().decrypted_vote);
+
+    let mut tampered = package.clone();
+    tampered.decryptions[0].tx.decrypted_vote[0].selection = "Somebody Else".to_string();
+    assert!(matches!(
+        tampered.verify(),
+        Err(AuditError::InvalidSignature)
+    ));
+}
+
+#[test]
+fn test_decryption_deadline() {
+    let mut test_rng = rand::rngs::StdRng::from_seed([0u8; 32]);
+    let keyed = KeyedElection::new_with(&mut test_rng, 1, 1, |election| {
+        election.decryption_deadline = Some(4_000_000_000);
+    });
+
+    let selection = Selection {
+        write_in: false,
+        score: 0,
+        selection: "Barak Obama".to_string(),
+        weight: None,
+    };
+    let selections = encrypt_vote(&keyed.encryption_key, vec![selection], &mut test_rng).unwrap();
+    let vote = keyed.vote(selections);
+    keyed.end_voting();
+    let partial_tx = keyed.partial_decrypt(&mut test_rng, 0, &vote);
+
+    // Partial decryptions are refused once the decryption deadline has passed
+    let clock = MockClock::new(4_000_000_001);
+    let store = DeadlineStore::with_clock(keyed.store, &clock);
+    assert!(matches!(
+        store.apply_transaction(partial_tx.clone().into()),
+        Err(ApplyError::Validation(
+            ValidationError::DecryptionDeadlineExpired(4_000_000_000)
+        ))
+    ));
+
+    // ...even if the trustee signed it as created before the deadline
+    let (_, trustee_secret) = &keyed.trustees[0];
+    let mut backdated = partial_tx.tx.clone();
+    backdated.created_at = Some(1_600_000_000);
+    let backdated = Signed::sign(trustee_secret, backdated).unwrap();
+    assert!(matches!(
+        store.apply_transaction(backdated.into()),
+        Err(ApplyError::Validation(
+            ValidationError::DecryptionDeadlineExpired(4_000_000_000)
+        ))
+    ));
+
+    // Up to the deadline it is accepted
+    clock.set(4_000_000_000);
+    store.apply_transaction(partial_tx.into()).unwrap();
+}
+
+#[test]
+fn test_duplicate_partial_decryption() {
+    let mut test_rng = rand::rngs::StdRng::from_seed([0u8; 32]);
+    let keyed = KeyedElection::new(&mut test_rng, 1, 1);
+    let store = &keyed.store;
+
+    let selection = Selection {
+        write_in: false,
+        score: 0,
+        selection: "Barak Obama".to_string(),
+        weight: None,
+    };
+    let selections = encrypt_vote(&keyed.encryption_key, vec![selection], &mut test_rng).unwrap();
+    let vote = keyed.vote(selections);
+    keyed.end_voting();
+
+    let partial_tx = keyed.partial_decrypt(&mut test_rng, 0, &vote);
+    store.apply_transaction(partial_tx.clone().into()).unwrap();
+
+    // The same partial decryption can't be posted twice
+    assert!(matches!(
+        store.apply_transaction(partial_tx.clone().into()),
+        Err(ApplyError::Validation(ValidationError::DuplicatePartialDecryption(id)))
+            if id == partial_tx.id()
+    ));
+}
+
+#[test]
+fn test_partial_decryption_batch() {
+    let mut test_rng = rand::rngs::StdRng::from_seed([0u8; 32]);
+    let keyed = KeyedElection::new(&mut test_rng, 3, 2);
+    let store = &keyed.store;
+    let election = &keyed.election;
+
+    let selection = Selection {
+        write_in: false,
+        score: 0,
+        selection: "Barak Obama".to_string(),
+        weight: None,
+    };
+    let votes: Vec<_> = (0..2)
+        .map(|_| {
+            let selections = encrypt_vote(
+                &keyed.encryption_key,
+                vec![selection.clone()],
+                &mut test_rng,
+            )
+            .unwrap();
+            keyed.vote(selections)
+        })
+        .collect();
+    keyed.end_voting();
+
+    // Trustee 1 partially decrypts both votes in a single batch
+    let (trustee_1, trustee_1_secret) = &keyed.trustees[0];
+    let batched: Vec<BatchedPartialDecryption> = votes
+        .iter()
+        .map(|vote| {
+            let partial = keyed.partial_decrypt(&mut test_rng, 0, vote);
+            BatchedPartialDecryption {
+                upstream_id: partial.upstream_id,
+                upstream_index: partial.upstream_index,
+                contest_index: partial.contest_index,
+                partial_decryption: partial.tx.partial_decryption,
+            }
+        })
+        .collect();
+
+    // Every share in a batch must verify
+    let mut bad_batch = batched.clone();
+    bad_batch[0].partial_decryption = batched[1].partial_decryption.clone();
+    let bad_batch_tx = PartialDecryptionBatchTransaction::new(
+        election.id,
+        trustee_1.index,
+        trustee_1.public_key,
+        0,
+        bad_batch,
+    );
+    let bad_batch_tx = Signed::sign(trustee_1_secret, bad_batch_tx).unwrap();
+    assert!(matches!(
+        bad_batch_tx.validate(store),
+        Err(ValidationError::PartialDecryptionProofFailed)
+    ));
+
+    let empty_tx = PartialDecryptionBatchTransaction::new(
+        election.id,
+        trustee_1.index,
+        trustee_1.public_key,
+        0,
+        vec![],
+    );
+    assert!(matches!(
+        empty_tx.validate_tx(store),
+        Err(ValidationError::EmptyPartialDecryptionBatch)
+    ));
+
+    let batch_tx = PartialDecryptionBatchTransaction::new(
+        election.id,
+        trustee_1.index,
+        trustee_1.public_key,
+        0,
+        batched,
+    );
+    let batch_tx = Signed::sign(trustee_1_secret, batch_tx).unwrap();
+    store.apply_transaction(batch_tx.into()).unwrap();
+
+    // Trustee 1 can't also post the same partial decryption on its own
+    let duplicate = keyed.partial_decrypt(&mut test_rng, 0, &votes[0]);
+    assert!(matches!(
+        store.apply_transaction(duplicate.clone().into()),
+        Err(ApplyError::Validation(ValidationError::DuplicatePartialDecryption(id)))
+            if id == duplicate.id()
+    ));
+
+    // Trustee 2 posts its partial decryptions individually, and the two forms combine to decrypt each vote
+    for vote in votes.iter() {
+        let partial_tx = keyed.partial_decrypt(&mut test_rng, 1, vote);
+        store.apply_transaction(partial_tx.into()).unwrap();
+
+        let decryption = DecryptionTransaction::new(
+            election.id,
+            vote.id,
+            0,
+            0,
+            vec![1, 2],
+            vec![selection.clone()],
+        );
+        let decryption = Signed::sign(trustee_1_secret, decryption).unwrap();
+        store.apply_transaction(decryption.into()).unwrap();
+    }
+
+    let partials = store.get_all_partial_decryptions(election.id);
+    assert_eq!(partials.len(), 4);
+    assert!(store.validate_all().is_empty());
+}
+
+#[test]
+fn test_duplicate_contest_vote() {
+    let mut test_rng = rand::rngs::StdRng::from_seed([0u8; 32]);
+    let keyed = KeyedElection::new(&mut test_rng, 3, 2);
+
+    let selection = Selection {
+        write_in: false,
+        score: 0,
+        selection: "Barak Obama".to_string(),
+        weight: None,
+    };
+    let encrypted_votes: Vec<EncryptedVote> = (0..2)
+        .map(|_| EncryptedVote {
+            contest_index: 0,
+            selections: encrypt_vote(
+                &keyed.encryption_key,
+                vec![selection.clone()],
+                &mut test_rng,
+            )
+            .unwrap(),
+        })
+        .collect();
+
+    // Each contest is decrypted on its own, so a vote can't encrypt the same contest twice
+    let (vote, voter_secret) =
+        VoteTransaction::new(keyed.election.id, "TEST".to_string(), encrypted_votes);
+    assert_eq!(vote.get_contest(0).unwrap().selections.len(), 1);
+    assert!(vote.get_contest(1).is_none());
+    let vote = Signed::sign(&voter_secret, vote).unwrap();
+    assert!(matches!(
+        vote.validate(&keyed.store),
+        Err(ValidationError::DuplicateContestVote(0))
+    ));
+}
+
+#[test]
+fn test_plurality_overvote() {
+    let mut test_rng = rand::rngs::StdRng::from_seed([0u8; 32]);
+    let keyed = KeyedElection::new(&mut test_rng, 1, 1);
+
+    let selection = |name: &str| Selection {
+        write_in: false,
+        score: 0,
+        selection: name.to_string(),
+        weight: None,
+    };
+    let selections = vec![selection("Barak Obama"), selection("Santa Claus")];
+    let encrypted_vote = EncryptedVote {
+        contest_index: 0,
+        selections: encrypt_vote(&keyed.encryption_key, selections, &mut test_rng).unwrap(),
+    };
+
+    // Contest 0 is a plurality contest, so selecting two candidates is an overvote
+    let (vote, voter_secret) =
+        VoteTransaction::new(keyed.election.id, "TEST".to_string(), vec![encrypted_vote]);
+    let vote = Signed::sign(&voter_secret, vote).unwrap();
+    assert!(matches!(
+        vote.validate_vote_range(&keyed.store),
+        Err(ValidationError::Overvote(0))
+    ));
+    assert!(matches!(
+        vote.validate(&keyed.store),
+        Err(ValidationError::Overvote(0))
+    ));
+
+    // With two winners, each voter may select two candidates, but not three
+    let keyed = KeyedElection::new_with(&mut test_rng, 1, 1, |election| {
+        election.contests[0].num_winners = 2;
+    });
+    let encrypt = |selections: Vec<Selection>, rng: &mut rand::rngs::StdRng| {
+        let encrypted_vote = EncryptedVote {
+            contest_index: 0,
+            selections: encrypt_vote(&keyed.encryption_key, selections, rng).unwrap(),
+        };
+        let (vote, voter_secret) =
+            VoteTransaction::new(keyed.election.id, "TEST".to_string(), vec![encrypted_vote]);
+        Signed::sign(&voter_secret, vote).unwrap()
+    };
+    let vote = encrypt(
+        vec![selection("Barak Obama"), selection("Santa Claus")],
+        &mut test_rng,
+    );
+    vote.validate_vote_range(&keyed.store).unwrap();
+    let vote = encrypt(
+        vec![
+            selection("Barak Obama"),
+            selection("Santa Claus"),
+            selection("Tooth Fairy"),
+        ],
+        &mut test_rng,
+    );
+    assert!(matches!(
+        vote.validate_vote_range(&keyed.store),
+        Err(ValidationError::Overvote(0))
+    ));
+}
+
+#[test]
+fn test_tally_unsupported_contest_type() {
+    let mut test_rng = rand::rngs::StdRng::from_seed([0u8; 32]);
+    let keyed = KeyedElection::new_with(&mut test_rng, 1, 1, |election| {
+        election.contests[0].contest_type = ContestType::SchulzeRatio;
+    });
+    keyed.end_voting();
+
+    // The contest can't be tallied, which is an error rather than a panic
+    assert!(matches!(
+        tally_election(&keyed.store, keyed.election.id),
+        Err(ValidationError::Tally(TallyError::UnsupportedContestType(
+            ContestType::SchulzeRatio
+        )))
+    ));
+}
+
+#[test]
+fn test_summarize_election() {
+    let mut test_rng = rand::rngs::StdRng::from_seed([0u8; 32]);
+    let keyed = KeyedElection::new(&mut test_rng, 3, 2);
+    let store = &keyed.store;
+    let election = &keyed.election;
+
+    let selection = Selection {
+        write_in: false,
+        score: 0,
+        selection: "Barak Obama".to_string(),
+        weight: None,
+    };
+    let selections = encrypt_vote(
+        &keyed.encryption_key,
+        vec![selection.clone()],
+        &mut test_rng,
+    )
+    .unwrap();
+    let vote = keyed.vote(selections);
+
+    let summary = summarize_election(store, election.id).unwrap();
+    assert_eq!(summary.election_id, election.id);
+    assert_eq!(summary.title, "");
+    assert_eq!(summary.total_votes, 1);
+    assert_eq!(summary.decrypted_votes, 0);
+    assert_eq!(summary.trustees_participated, 0);
+    assert_eq!(summary.trustees_total, 3);
+    assert_eq!(summary.mixes_completed, 0);
+
+    keyed.end_voting();
+    for position in 0..2 {
+        let partial_tx = keyed.partial_decrypt(&mut test_rng, position, &vote);
+        store.apply_transaction(partial_tx.into()).unwrap();
+    }
+    let (_, trustee_1_secret) = &keyed.trustees[0];
+    let decryption =
+        DecryptionTransaction::new(election.id, vote.id, 0, 0, vec![1, 2], vec![selection]);
+    let decryption = Signed::sign(trustee_1_secret, decryption).unwrap();
+    store.apply_transaction(decryption.into()).unwrap();
+
+    let summary = summarize_election(store, election.id).unwrap();
+    assert_eq!(summary.decrypted_votes, 1);
+    assert_eq!(summary.trustees_participated, 2);
+    assert!(summary
+        .to_string()
+        .contains("Trustees:     2 of 3 participated"));
+
+    let missing = ElectionTransaction::build_id([1; 15]);
+    assert!(matches!(
+        summarize_election(store, missing),
+        Err(SummaryError::TransactionNotFound(_))
+    ));
+}
+
+#[test]
+fn test_verify_trustee_participation() {
+    let mut test_rng = rand::rngs::StdRng::from_seed([0u8; 32]);
+    let keyed = KeyedElection::new(&mut test_rng, 3, 2);
+    let store = &keyed.store;
+    let election = &keyed.election;
+
+    let selection = Selection {
+        write_in: false,
+        score: 0,
+        selection: "Barak Obama".to_string(),
+        weight: None,
+    };
+    let mut votes = Vec::new();
+    for _ in 0..2 {
+        let selections = encrypt_vote(
+            &keyed.encryption_key,
+            vec![selection.clone()],
+            &mut test_rng,
+        )
+        .unwrap();
+        votes.push(keyed.vote(selections));
+    }
+    keyed.end_voting();
+
+    // Trustee 1 partially decrypts both votes, trustee 2 only the first, and trustee 3 neither
+    for vote in votes.iter() {
+        let partial_tx = keyed.partial_decrypt(&mut test_rng, 0, vote);
+        store.apply_transaction(partial_tx.into()).unwrap();
+    }
+    let partial_tx = keyed.partial_decrypt(&mut test_rng, 1, &votes[0]);
+    store.apply_transaction(partial_tx.into()).unwrap();
+
+    let target = |vote: &Signed<VoteTransaction>| DecryptionTarget {
+        upstream_id: vote.id,
+        contest_index: 0,
+        upstream_index: 0,
+    };
+
+    let report = verify_trustee_participation(store, election.id, 1).unwrap();
+    assert_eq!(report.accepted.len(), 2);
+    assert!(report.is_complete());
+
+    let report = verify_trustee_participation(store, election.id, 2).unwrap();
+    assert_eq!(report.accepted, vec![target(&votes[0])]);
+    assert_eq!(report.missing, vec![target(&votes[1])]);
+    assert!(!report.is_complete());
+
+    let report = verify_trustee_participation(store, election.id, 3).unwrap();
+    assert!(report.accepted.is_empty());
+    assert_eq!(report.missing.len(), 2);
+}
+
+#[test]
+fn test_vote_bundle() {
+    let mut test_rng = rand::rngs::StdRng::from_seed([0u8; 32]);
+    let keyed = KeyedElection::new(&mut test_rng, 3, 2);
+    let store = &keyed.store;
+    let election = &keyed.election;
+
+    let selection = Selection {
+        write_in: false,
+        score: 0,
+        selection: "Barak Obama".to_string(),
+        weight: None,
+    };
+    let mut votes = Vec::new();
+    for _ in 0..2 {
+        let selections = encrypt_vote(
+            &keyed.encryption_key,
+            vec![selection.clone()],
+            &mut test_rng,
+        )
+        .unwrap();
+        votes.push(keyed.vote(selections));
+    }
+    keyed.end_voting();
+
+    let (_, trustee_1_secret) = &keyed.trustees[0];
+    for vote in votes.iter() {
+        for position in 0..2 {
+            let partial_tx = keyed.partial_decrypt(&mut test_rng, position, vote);
+            store.apply_transaction(partial_tx.into()).unwrap();
+        }
+        let decryption = DecryptionTransaction::new(
+            election.id,
+            vote.id,
+            0,
+            0,
+            vec![1, 2],
+            vec![selection.clone()],
+        );
+        let decryption = Signed::sign(trustee_1_secret, decryption).unwrap();
+        store.apply_transaction(decryption.into()).unwrap();
+    }
+
+    let bundle = vote_bundle(store, votes[0].id).unwrap();
+    let ids: Vec<Identifier> = bundle.iter().map(|tx| tx.id()).collect();
+
+    // Both votes are needed for voting_end, but only the first vote's decryption
+    assert!(ids.contains(&votes[1].id));
+    assert!(ids.contains(&DecryptionTransaction::build_id(
+        election.id,
+        votes[0].id,
+        0,
+        0
+    )));
+    assert!(!ids.contains(&DecryptionTransaction::build_id(
+        election.id,
+        votes[1].id,
+        0,
+        0
+    )));
+    assert_eq!(
+        bundle
+            .iter()
+            .filter(|tx| tx.transaction_type() == TransactionType::PartialDecryption)
+            .count(),
+        2
+    );
+
+    // The bundle verifies on its own
+    let bundle_store = MemStore::from(bundle);
+    assert!(bundle_store.validate_all().is_empty());
+}
+
+#[test]
+fn test_auth_rate_limit() {
+    let mut test_rng = rand::rngs::StdRng::from_seed([0u8; 32]);
+    let (authenticator, authn_secrets) =
+        Authenticator::new(256, &vec!["TEST".to_string()]).unwrap();
+    let authn_secret = authn_secrets.get("TEST").unwrap();
+    let authn_public = authenticator.public_keys.get("TEST").unwrap().as_ref();
+    let keyed = KeyedElection::new_with(&mut test_rng, 1, 1, |election| {
+        election.authenticators = vec![authenticator.clone()];
+        election.authenticators_threshold = 1;
+        election.auth_rate_limit = Some(RateLimit {
+            max_votes: 2,
+            window_seconds: 60,
+        });
+    });
+    let election = &keyed.election;
+    let encryption_key = keyed.encryption_key.clone();
+
+    // Unauthenticated votes would get around the limit
+    let mut unauthenticated = election.tx.clone();
+    unauthenticated.authenticators_threshold = 0;
+    let unauthenticated = Signed::sign(&keyed.authority_secret, unauthenticated).unwrap();
+    assert!(matches!(
+        unauthenticated.validate(&MemStore::default()),
+        Err(ValidationError::RateLimitUnauthenticated)
+    ));
+
+    let clock = MockClock::new(1_600_000_000);
+    let store = RateLimitingStore::with_clock(keyed.store, &clock);
+
+    let selection = Selection {
+        write_in: false,
+        score: 0,
+        selection: "Barak Obama".to_string(),
+        weight: None,
+    };
+    let mut new_vote = |authenticated: bool| {
+        let encrypted_vote = EncryptedVote {
+            contest_index: 0,
+            selections: encrypt_vote(&encryption_key, vec![selection.clone()], &mut test_rng)
+                .unwrap(),
+        };
+        let (mut vote, voter_secret) =
+            VoteTransaction::new(election.id, "TEST".to_string(), vec![encrypted_vote]);
+        if !authenticated {
+            return SignedTransaction::from(Signed::sign(&voter_secret, vote).unwrap());
+        }
+        let auth_package = AuthPackage::new(election.id, "TEST".to_string(), vote.anonymous_key);
+        let (blinded, unblinder) = auth_package.blind(&authn_public);
+        let authentication = authenticator.authenticate(&authn_secret, &blinded);
+        vote.authentication
+            .push(authentication.unblind(&authn_public, unblinder));
+        SignedTransaction::from(Signed::sign(&voter_secret, vote).unwrap())
+    };
+
+    // Every vote must be authenticated, so none can get around the limit
+    assert!(matches!(
+        store.apply_transaction(new_vote(false)),
+        Err(ApplyError::Validation(
+            ValidationError::NotEnoughAuthentications(1, 0)
+        ))
+    ));
+
+    // The authenticator can authenticate two votes a minute, so the third is rejected
+    store.apply_transaction(new_vote(true)).unwrap();
+    clock.advance(10);
+    store.apply_transaction(new_vote(true)).unwrap();
+    clock.advance(10);
+    let third = new_vote(true);
+    assert!(matches!(
+        store.apply_transaction(third.clone()),
+        Err(ApplyError::Validation(ValidationError::RateLimitExceeded(id))) if id == authenticator.id
+    ));
+    assert!(!store.contains(third.id()));
+
+    // Once the first vote falls out of the window, there is room for another
+    clock.advance(41);
+    store.apply_transaction(third).unwrap();
+    assert_eq!(
+        store.get_multiple(election.id, TransactionType::Vote).len(),
+        3
+    );
+}
+
+#[test]
+fn test_vote_weight_certification() {
+    let mut test_rng = rand::rngs::StdRng::from_seed([0u8; 32]);
+    let (authenticator, authn_secrets) =
+        Authenticator::new(256, &vec!["TEST".to_string()]).unwrap();
+    let authn_secret = authn_secrets.get("TEST").unwrap();
+    let authn_public = authenticator.public_keys.get("TEST").unwrap().as_ref();
+    let keyed = KeyedElection::new_with(&mut test_rng, 1, 1, |election| {
+        election.authenticators = vec![authenticator.clone()];
+        election.authenticators_threshold = 1;
+        election.max_vote_weight = Some(10);
+    });
+    let store = &keyed.store;
+    let election = &keyed.election;
+
+    // Without an authenticator nobody would certify the weights
+    let mut unauthenticated = election.tx.clone();
+    unauthenticated.authenticators_threshold = 0;
+    let unauthenticated = Signed::sign(&keyed.authority_secret, unauthenticated).unwrap();
+    assert!(matches!(
+        unauthenticated.validate(&MemStore::default()),
+        Err(ValidationError::WeightedVotesUnauthenticated)
+    ));
+
+    // The authenticator certifies `certified`, the vote claims `claimed`, and its selection is encrypted with
+    // `encrypted`
+    let mut weighted_vote = |certified: u32, claimed: u32, encrypted: u32| {
+        let selection = Selection {
+            write_in: false,
+            score: 0,
+            selection: "Barak Obama".to_string(),
+            weight: Some(encrypted),
+        };
+        let encrypted_vote = EncryptedVote {
+            contest_index: 0,
+            selections: encrypt_vote(&keyed.encryption_key, vec![selection], &mut test_rng)
+                .unwrap(),
+        };
+        let (mut vote, voter_secret) =
+            VoteTransaction::new(election.id, "TEST".to_string(), vec![encrypted_vote]);
+        vote.weight = Some(claimed);
+        let auth_package = AuthPackage::new(election.id, "TEST".to_string(), vote.anonymous_key)
+            .with_weight(Some(certified));
+        let (blinded, unblinder) = auth_package.blind(&authn_public);
+        let authentication = authenticator.authenticate(&authn_secret, &blinded);
+        vote.authentication
+            .push(authentication.unblind(&authn_public, unblinder));
+        Signed::sign(&voter_secret, vote).unwrap()
+    };
+
+    // The voter can't claim more weight than they were certified for, or than the election allows
+    let vote = weighted_vote(5, 5, 5);
+    store.apply_transaction(vote.clone().into()).unwrap();
+    assert!(matches!(
+        weighted_vote(5, 7, 7).validate(store),
+        Err(ValidationError::AuthenticationMismatch)
+    ));
+    assert!(matches!(
+        weighted_vote(11, 11, 11).validate(store),
+        Err(ValidationError::VoteWeightExceedsMax(11, 10))
+    ));
+
+    // A vote whose encrypted weight differs from its certified weight is caught once it is decrypted
+    let tampered = weighted_vote(3, 3, 9);
+    store.apply_transaction(tampered.clone().into()).unwrap();
+    keyed.end_voting();
+
+    let (_, trustee_secret) = &keyed.trustees[0];
+    for (vote, result) in [(&vote, Ok(5)), (&tampered, Err((3, 9)))].iter() {
+        let partial_tx = keyed.partial_decrypt(&mut test_rng, 0, vote);
+        store.apply_transaction(partial_tx.clone().into()).unwrap();
+        let decrypted = decrypt_vote(
+            &vote.encrypted_votes[0].selections,
+            election.trustees_threshold,
+            &election.trustees,
+            &keyed.pubkeys,
+            &[partial_tx.tx],
+        )
+        .unwrap();
+        let decryption = DecryptionTransaction::new(election.id, vote.id, 0, 0, vec![1], decrypted);
+        let decryption = Signed::sign(trustee_secret, decryption).unwrap();
+        match result {
+            Ok(weight) => {
+                store.apply_transaction(decryption.into()).unwrap();
+                let results = tally_election(store, election.id).unwrap();
+                assert_eq!(
+                    results.contests["TESTCONTEST"].totals["Barak Obama"],
+                    rust_decimal::Decimal::from(*weight)
+                );
+            }
+            Err((certified, encrypted)) => assert!(matches!(
+                decryption.validate(store),
+                Err(ValidationError::VoteWeightMismatch(c, e)) if c == *certified && e == *encrypted
+            )),
+        }
+    }
+}
+
+#[test]
+fn test_mix_transaction_builder() {
+    let mut test_rng = rand::rngs::StdRng::from_seed([0u8; 32]);
+    let keyed = KeyedElection::new_with(&mut test_rng, 3, 2, |election| {
+        election.mix_config = Some(MixConfig {
+            timeout_secs: 600,
+            batch_size: None,
+        });
+    });
+    let store = &keyed.store;
+    let election = &keyed.election;
+
+    let selection = |name: &str| Selection {
+        write_in: false,
+        score: 0,
+        selection: name.to_string(),
+        weight: None,
+    };
+    let mut expected = vec![selection("Barak Obama"), selection("Santa Claus")];
+    let mut votes: Vec<_> = expected
+        .iter()
+        .map(|selection| {
+            let selections = encrypt_vote(
+                &keyed.encryption_key,
+                vec![selection.clone()],
+                &mut test_rng,
+            )
+            .unwrap();
+            keyed.vote(selections)
+        })
+        .collect();
+    keyed.end_voting();
+    votes.sort_by_key(|vote| vote.id());
+
+    // The first mix shuffles the votes' ciphertexts, in ascending order of vote ID
+    let vote_ciphertexts: Vec<_> = votes
+        .iter()
+        .map(|vote| vote.encrypted_votes[0].selections.clone())
+        .collect();
+    let (trustee_1, trustee_1_secret) = &keyed.trustees[0];
+    let mix_1 = MixTransactionBuilder::new(election.id, vote_ciphertexts.clone(), 0)
+        .with_vote_ids(votes.iter().map(|vote| vote.id()).collect())
+        .build(&mut test_rng, trustee_1, &keyed.encryption_key)
+        .unwrap();
+    assert!(mix_1.verify_shuffle_proof(&vote_ciphertexts, &keyed.encryption_key));
+    assert!(!mix_1.verify_shuffle_proof(&mix_1.mixed_ciphertexts, &keyed.encryption_key));
+    assert!(!mix_1.verify_shuffle_proof(&vote_ciphertexts[..1], &keyed.encryption_key));
+    let mix_1 = Signed::sign(trustee_1_secret, mix_1).unwrap();
+    store.apply_transaction(mix_1.clone().into()).unwrap();
+
+    // The second mix shuffles the output of the first
+    let (trustee_2, trustee_2_secret) = &keyed.trustees[1];
+    let mix_2 = MixTransactionBuilder::after(&mix_1)
+        .build(&mut test_rng, trustee_2, &keyed.encryption_key)
+        .unwrap();
+    assert_eq!(mix_2.prev_mix_id, Some(mix_1.id()));
+    assert_eq!(mix_2.mix_index, 1);
+    assert!(mix_2.verify_shuffle_proof(&mix_1.mixed_ciphertexts, &keyed.encryption_key));
+    assert!(!mix_2.verify_shuffle_proof(&vote_ciphertexts, &keyed.encryption_key));
+    let mix_2 = Signed::sign(trustee_2_secret, mix_2).unwrap();
+    store.apply_transaction(mix_2.clone().into()).unwrap();
+
+    // With both proofs checked, the shuffled votes can be decrypted
+    let mut decrypted = Vec::new();
+    for (upstream_index, ciphertexts) in mix_2.mixed_ciphertexts.iter().enumerate() {
+        let mut partials = Vec::new();
+        for (position, (trustee, secret)) in keyed.trustees[0..2].iter().enumerate() {
+            let partial = trustee
+                .partial_decrypt(
+                    &mut test_rng,
+                    secret,
+                    &keyed.x25519_public_keys,
+                    &keyed.commitments,
+                    &keyed.trustee_shares[position],
+                    &ciphertexts[0],
+                    election.id,
+                )
+                .unwrap();
+            let partial_tx = PartialDecryptionTransaction::new(
+                election.id,
+                mix_2.id(),
+                upstream_index as u16,
+                trustee.index,
+                0,
+                trustee.public_key,
+                vec![partial],
+            );
+            let partial_tx = Signed::sign(secret, partial_tx).unwrap();
+            store.apply_transaction(partial_tx.clone().into()).unwrap();
+            partials.push(partial_tx.tx);
+        }
+
+        let selections = decrypt_vote(
+            ciphertexts,
+            election.trustees_threshold,
+            &election.trustees,
+            &keyed.pubkeys,
+            &partials,
+        )
+        .unwrap();
+        decrypted.push(selections[0].clone());
+    }
+
+    decrypted.sort_by(|a, b| a.selection.cmp(&b.selection));
+    expected.sort_by(|a, b| a.selection.cmp(&b.selection));
+    assert_eq!(decrypted, expected);
+}
+
+#[test]
+fn test_mix_covers_upstream_ciphertexts() {
+    let mut test_rng = rand::rngs::StdRng::from_seed([0u8; 32]);
+    let keyed = KeyedElection::new_with(&mut test_rng, 1, 1, |election| {
+        election.mix_config = Some(MixConfig {
+            timeout_secs: 600,
+            batch_size: None,
+        });
+    });
+    let (trustee, trustee_secret) = &keyed.trustees[0];
+
+    let selection = |name: &str| Selection {
+        write_in: false,
+        score: 0,
+        selection: name.to_string(),
+        weight: None,
+    };
+
+    // One voter votes in the contest, and another doesn't
+    let selections = encrypt_vote(
+        &keyed.encryption_key,
+        vec![selection("Barak Obama")],
+        &mut test_rng,
+    )
+    .unwrap();
+    let vote = keyed.vote(selections);
+    let (abstention, abstention_secret) =
+        VoteTransaction::new(keyed.election.id, "TEST".to_string(), vec![]);
+    let abstention = Signed::sign(&abstention_secret, abstention).unwrap();
+    keyed
+        .store
+        .apply_transaction(abstention.clone().into())
+        .unwrap();
+    keyed.end_voting();
+
+    let mut vote_ids = vec![vote.id(), abstention.id()];
+    vote_ids.sort();
+
+    // The mixer shuffles in a ballot of their own for the voter who didn't vote in the contest
+    let injected = encrypt_vote(
+        &keyed.encryption_key,
+        vec![selection("Santa Claus")],
+        &mut test_rng,
+    )
+    .unwrap();
+    let input = vec![vote.encrypted_votes[0].selections.clone(), injected];
+    let mix = MixTransactionBuilder::new(keyed.election.id, input.clone(), 0)
+        .with_vote_ids(vote_ids)
+        .build(&mut test_rng, trustee, &keyed.encryption_key)
+        .unwrap();
+
+    // The proof is sound for the ciphertexts it was made over, but those aren't the upstream ciphertexts
+    assert!(mix.verify_shuffle_proof(&input, &keyed.encryption_key));
+    let mix = Signed::sign(trustee_secret, mix).unwrap();
+    assert!(matches!(
+        mix.validate(&keyed.store),
+        Err(ValidationError::MixWrongNumberOfVotes)
+    ));
+}
+
+#[test]
+fn test_partial_decryption_requires_final_mix() {
+    let mut test_rng = rand::rngs::StdRng::from_seed([0u8; 32]);
+    let keyed = KeyedElection::new_with(&mut test_rng, 2, 2, |election| {
+        election.mix_config = Some(MixConfig {
+            timeout_secs: 600,
+            batch_size: None,
+        });
+    });
+    let election = &keyed.election;
+
+    let selection = Selection {
+        write_in: false,
+        score: 0,
+        selection: "Barak Obama".to_string(),
+        weight: None,
+    };
+    let selections = encrypt_vote(&keyed.encryption_key, vec![selection], &mut test_rng).unwrap();
+    let vote = keyed.vote(selections);
+    keyed.end_voting();
+
+    // The vote can't be decrypted directly
+    let partial_tx = keyed.partial_decrypt(&mut test_rng, 0, &vote);
+    assert!(matches!(
+        partial_tx.validate(&keyed.store),
+        Err(ValidationError::MixRequired)
+    ));
+
+    // Nor can the output of the first of the two mixes
+    let (trustee, trustee_secret) = &keyed.trustees[0];
+    let mix = MixTransactionBuilder::new(
+        election.id,
+        vec![vote.encrypted_votes[0].selections.clone()],
+        0,
+    )
+    .with_vote_ids(vec![vote.id()])
+    .build(&mut test_rng, trustee, &keyed.encryption_key)
+    .unwrap();
+    let mix = Signed::sign(trustee_secret, mix).unwrap();
+    keyed.store.apply_transaction(mix.clone().into()).unwrap();
+
+    let partial_tx = PartialDecryptionTransaction::new(
+        election.id,
+        mix.id(),
+        0,
+        trustee.index,
+        0,
+        trustee.public_key,
+        partial_tx.tx.partial_decryption,
+    );
+    let partial_tx = Signed::sign(trustee_secret, partial_tx).unwrap();
+    assert!(matches!(
+        partial_tx.validate(&keyed.store),
+        Err(ValidationError::WrongMixSelected)
+    ));
+}
+
+#[test]
+fn test_reauthorize() {
+    let mut test_rng = rand::rngs::StdRng::from_seed([0u8; 32]);
+    let keyed = KeyedElection::new(&mut test_rng, 1, 1);
+    keyed.end_voting();
+    let election_id = keyed.election.id();
+    let (new_secret, new_public) = generate_keypair();
+    let (other_secret, _other_public) = generate_keypair();
+
+    // Only the current authority can hand the election over, and only to a matching keypair
+    assert!(matches!(
+        keyed
+            .election
+            .clone()
+            .reauthorize(&other_secret, &new_secret, new_public),
+        Err(ReauthorizeError::WrongAuthorityKey)
+    ));
+    assert!(matches!(
+        keyed
+            .election
+            .clone()
+            .reauthorize(&keyed.authority_secret, &other_secret, new_public),
+        Err(ReauthorizeError::MismatchedPublicKeys)
+    ));
+
+    let election = keyed
+        .election
+        .clone()
+        .reauthorize(&keyed.authority_secret, &new_secret, new_public)
+        .unwrap();
+    assert_eq!(election.authority_public, new_public);
+    assert_eq!(election.id(), election_id);
+    election.verify_signature().unwrap();
+
+    let old_encryption_key = keyed.store.get_encryption_key(election_id).unwrap();
+    let encryption_key = old_encryption_key
+        .clone()
+        .reauthorize(&keyed.authority_secret, &new_secret, new_public)
+        .unwrap();
+    let voting_end_id = Identifier::new(election_id, TransactionType::VotingEnd, None);
+    let voting_end: Signed<VotingEndTransaction> =
+        keyed.store.get_transaction(voting_end_id).unwrap().into();
+    let voting_end = voting_end
+        .reauthorize(&keyed.authority_secret, &new_secret, new_public)
+        .unwrap();
+
+    // With all of the authority's transactions re-signed, the election validates under the new authority...
+    let mut store = keyed.store.clone();
+    store.set(election.into());
+    store.set(encryption_key.into());
+    store.set(voting_end.into());
+    assert!(store.validate_all().is_empty());
+
+    // ...but not if one is left signed by the old authority
+    store.set(old_encryption_key.into());
+    assert!(!store.validate_all().is_empty());
+}
+
+#[test]
+fn test_cast_vote() {
+    let mut test_rng = rand::rngs::StdRng::from_seed([0u8; 32]);
+    let (authenticator, authn_secrets) =
+        Authenticator::new(256, &vec!["TEST".to_string()]).unwrap();
+    let authn_secret = authn_secrets.get("TEST").unwrap();
+    let keyed = KeyedElection::new_with(&mut test_rng, 1, 1, |election| {
+        election.authenticators = vec![authenticator.clone()];
+        election.authenticators_threshold = 1;
+    });
+    let store = &keyed.store;
+    let election = &keyed.election;
+
+    let selection = Selection {
+        write_in: false,
+        score: 0,
+        selection: "Barak Obama".to_string(),
+        weight: None,
+    };
+    let mut credential = VoterCredential {
+        secret_key: generate_keypair().0,
+        authn_secrets: std::collections::HashMap::new(),
+        weight: None,
+    };
+    let mut votes = indexmap::IndexMap::new();
+    votes.insert(0, vec![selection]);
+
+    // The vote is validated before it is returned, so it can't be cast without enough authentications
+    assert!(matches!(
+        cast_vote(store, election, "TEST", votes.clone(), &credential, [0; 32]),
+        Err(CastVoteError::NotEnoughAuthentications(1, 0))
+    ));
+
+    credential
+        .authn_secrets
+        .insert(authenticator.id, authn_secret.clone());
+    let cast = cast_vote(store, election, "TEST", votes, &credential, [0; 32]).unwrap();
+    assert_eq!(cast.authentication.len(), 1);
+    assert_eq!(cast.encrypted_votes[0].contest_index, 0);
+    store.apply_transaction(cast.into()).unwrap();
+}
+
+#[test]
+fn test_vote_receipt() {
+    let mut test_rng = rand::rngs::StdRng::from_seed([0u8; 32]);
+    let keyed = KeyedElection::new(&mut test_rng, 1, 1);
+    let election = &keyed.election;
+
+    let selection = Selection {
+        write_in: false,
+        score: 0,
+        selection: "Barak Obama".to_string(),
+        weight: None,
+    };
+    let mut new_vote = || {
+        let encrypted_vote = EncryptedVote {
+            contest_index: 0,
+            selections: encrypt_vote(
+                &keyed.encryption_key,
+                vec![selection.clone()],
+                &mut test_rng,
+            )
+            .unwrap(),
+        };
+        let (vote, voter_secret) =
+            VoteTransaction::new(election.id, "TEST".to_string(), vec![encrypted_vote]);
+        Signed::sign(&voter_secret, vote).unwrap()
+    };
+    let vote = new_vote();
+    let other = new_vote();
+
+    // The election authority issues a receipt that the voter can check against their vote
+    let receipt = election.issue_receipt(&vote, &keyed.authority_secret);
+    receipt.verify(&election.authority_public, &vote).unwrap();
+    assert!(matches!(
+        receipt.verify(&election.authority_public, &other),
+        Err(ReceiptError::WrongVote(_))
+    ));
+    let mut altered = vote.clone();
+    altered.tx.created_at = Some(1);
+    assert!(matches!(
+        receipt.verify(&election.authority_public, &altered),
+        Err(ReceiptError::CommitmentMismatch)
+    ));
+    assert!(matches!(
+        receipt.verify(&generate_keypair().1, &vote),
+        Err(ReceiptError::InvalidSignature)
+    ));
+}
+
+#[test]
+fn test_verify_receipt() {
+    let mut test_rng = rand::rngs::StdRng::from_seed([0u8; 32]);
+    let keyed = KeyedElection::new(&mut test_rng, 1, 1);
+    let store = &keyed.store;
+    let election = &keyed.election;
+
+    let selection = Selection {
+        write_in: false,
+        score: 0,
+        selection: "Barak Obama".to_string(),
+        weight: None,
+    };
+    let selections = encrypt_vote(
+        &keyed.encryption_key,
+        vec![selection.clone()],
+        &mut test_rng,
+    )
+    .unwrap();
+    let vote = keyed.vote(selections);
+
+    // A vote that was never posted
+    let encrypted_vote = EncryptedVote {
+        contest_index: 0,
+        selections: encrypt_vote(&keyed.encryption_key, vec![selection], &mut test_rng).unwrap(),
+    };
+    let (unposted, voter_secret) =
+        VoteTransaction::new(election.id, "TEST".to_string(), vec![encrypted_vote]);
+    let unposted = Signed::sign(&voter_secret, unposted).unwrap();
+
+    // The voter can check the receipt against the bulletin board with just their anonymous key
+    let receipt = election.issue_receipt(&vote, &keyed.authority_secret);
+    verify_receipt(store, election.id, &vote.anonymous_key, &receipt).unwrap();
+    assert!(matches!(
+        verify_receipt(store, election.id, &unposted.anonymous_key, &receipt),
+        Err(ValidationError::InvalidReceipt(ReceiptError::WrongVote(_)))
+    ));
+    let unposted_receipt = election.issue_receipt(&unposted, &keyed.authority_secret);
+    assert!(matches!(
+        verify_receipt(
+            store,
+            election.id,
+            &unposted.anonymous_key,
+            &unposted_receipt
+        ),
+        Err(ValidationError::TransactionNotFound(_))
+    ));
+}
+
+#[test]
+fn test_vote_created_after_voting_end() {
+    let mut test_rng = rand::rngs::StdRng::from_seed([0u8; 32]);
+    let keyed = KeyedElection::new(&mut test_rng, 1, 1);
+    let store = &keyed.store;
+    let election = &keyed.election;
+
+    let selection = Selection {
+        write_in: false,
+        score: 0,
+        selection: "Barak Obama".to_string(),
+        weight: None,
+    };
+    let mut new_vote = |created_at: u64| {
+        let encrypted_vote = EncryptedVote {
+            contest_index: 0,
+            selections: encrypt_vote(
+                &keyed.encryption_key,
+                vec![selection.clone()],
+                &mut test_rng,
+            )
+            .unwrap(),
+        };
+        let (mut vote, voter_secret) =
+            VoteTransaction::new(election.id, "TEST".to_string(), vec![encrypted_vote]);
+        vote.created_at = Some(created_at);
+        Signed::sign(&voter_secret, vote).unwrap()
+    };
+    let vote = new_vote(1_599_999_999);
+    let late_vote = new_vote(1_600_000_001);
+    let future_vote = new_vote(u64::MAX);
+
+    // A vote can't be dated far enough ahead to stop voting from ever ending
+    let clock = MockClock::new(1_600_000_000);
+    late_vote.validate_tx_with_clock(store, &clock).unwrap();
+    assert!(matches!(
+        future_vote.validate_tx_with_clock(store, &clock),
+        Err(ValidationError::CreatedInFuture(u64::MAX, 1_600_000_000))
+    ));
+
+    // Voting can't end before a vote that claims to have been cast later
+    store.apply_transaction(vote.clone().into()).unwrap();
+    store.apply_transaction(late_vote.clone().into()).unwrap();
+    let mut voting_end_tx = VotingEndTransaction::build(election, &[vote, late_vote.clone()]);
+    voting_end_tx.created_at = Some(1_600_000_000);
+    let voting_end_tx = Signed::sign(&keyed.authority_secret, voting_end_tx).unwrap();
+    assert!(matches!(
+        voting_end_tx.validate(store),
+        Err(ValidationError::VoteAfterVotingEnd(id)) if id == late_vote.id()
+    ));
+}
+
+#[test]
+fn test_voting_end_vote_commitment() {
+    let mut test_rng = rand::rngs::StdRng::from_seed([0u8; 32]);
+    let keyed = KeyedElection::new(&mut test_rng, 1, 1);
+    let store = &keyed.store;
+    let election = &keyed.election;
+
+    let selection = Selection {
+        write_in: false,
+        score: 0,
+        selection: "Barak Obama".to_string(),
+        weight: None,
+    };
+    let selections = encrypt_vote(
+        &keyed.encryption_key,
+        vec![selection.clone()],
+        &mut test_rng,
+    )
+    .unwrap();
+    let vote = keyed.vote(selections);
+
+    let voting_end_tx = VotingEndTransaction::build(election, &[vote]);
+    let voting_end_tx = Signed::sign(&keyed.authority_secret, voting_end_tx).unwrap();
+    voting_end_tx.validate(store).unwrap();
+
+    // It can't commit to a different set of votes than were cast
+    let late_store = store.clone();
+    let encrypted_vote = EncryptedVote {
+        contest_index: 0,
+        selections: encrypt_vote(&keyed.encryption_key, vec![selection], &mut test_rng).unwrap(),
+    };
+    let (late_vote, voter_secret) =
+        VoteTransaction::new(election.id, "TEST".to_string(), vec![encrypted_vote]);
+    let late_vote = Signed::sign(&voter_secret, late_vote).unwrap();
+    late_store.apply_transaction(late_vote.into()).unwrap();
+    assert!(matches!(
+        voting_end_tx.verify_vote_commitment(&late_store),
+        Err(ValidationError::VoteCountMismatch(1, 2))
+    ));
+    let mut wrong_root = voting_end_tx.tx.clone();
+    wrong_root.vote_commitment.as_mut().unwrap().vote_root = [0; 32];
+    assert!(matches!(
+        wrong_root.verify_vote_commitment(store),
+        Err(ValidationError::VoteRootMismatch)
+    ));
+
+    // Transactions from before the commitment was introduced have none to check
+    let mut uncommitted = voting_end_tx.tx.clone();
+    uncommitted.vote_commitment = None;
+    uncommitted.verify_vote_commitment(&late_store).unwrap();
+    let uncommitted_json = serde_json::to_value(&uncommitted).unwrap();
+    assert!(uncommitted_json.get("vote_commitment").is_none());
+    let uncommitted: VotingEndTransaction = serde_json::from_value(uncommitted_json).unwrap();
+    assert!(uncommitted.vote_commitment.is_none());
+
+    store.apply_transaction(voting_end_tx.into()).unwrap();
+}
+
+#[test]
+fn test_voting_end_vote_root() {
+    let mut test_rng = rand::rngs::StdRng::from_seed([0u8; 32]);
+    let keyed = KeyedElection::new(&mut test_rng, 1, 1);
+
+    let votes: Vec<_> = (0..5)
+        .map(|_| {
+            let selection = Selection {
+                write_in: false,
+                score: 0,
+                selection: "Barak Obama".to_string(),
+                weight: None,
+            };
+            let selections =
+                encrypt_vote(&keyed.encryption_key, vec![selection], &mut test_rng).unwrap();
+            keyed.vote(selections)
+        })
+        .collect();
+    keyed.end_voting();
+
+    // The authority commits to the merkle root of the votes when voting ends, which proves each vote's inclusion
+    let voting_end_id = Identifier::new(keyed.election.id, TransactionType::VotingEnd, None);
+    let voting_end: VotingEndTransaction =
+        keyed.store.get_transaction(voting_end_id).unwrap().into();
+    let vote_root = voting_end.vote_commitment.unwrap().vote_root;
+    let tree = MerkleAuditTree::from_votes(&votes);
+    assert_eq!(tree.root(), vote_root);
+    for vote in votes.iter() {
+        assert!(tree
+            .inclusion_proof(vote.id())
+            .unwrap()
+            .verify(vote_root, vote));
+    }
+}
+
+#[test]
+fn test_decryption_progress() {
+    let mut test_rng = rand::rngs::StdRng::from_seed([0u8; 32]);
+    let keyed = KeyedElection::new(&mut test_rng, 1, 1);
+    let election = &keyed.election;
+
+    let selection = Selection {
+        write_in: false,
+        score: 0,
+        selection: "Barak Obama".to_string(),
+        weight: None,
+    };
+    let mut votes = Vec::new();
+    for _ in 0..2 {
+        let selections = encrypt_vote(
+            &keyed.encryption_key,
+            vec![selection.clone()],
+            &mut test_rng,
+        )
+        .unwrap();
+        votes.push(keyed.vote(selections));
+    }
+    keyed.end_voting();
+    assert_eq!(expected_decryptions(&keyed.store, election.id).unwrap(), 2);
+    assert_eq!(
+        decryption_progress(&keyed.store, election.id).unwrap(),
+        (0, 2)
+    );
+
+    let partial_tx = keyed.partial_decrypt(&mut test_rng, 0, &votes[0]);
+    keyed.store.apply_transaction(partial_tx.into()).unwrap();
+    let (_, trustee_secret) = &keyed.trustees[0];
+    let decryption =
+        DecryptionTransaction::new(election.id, votes[0].id, 0, 0, vec![1], vec![selection]);
+    let decryption = Signed::sign(trustee_secret, decryption).unwrap();
+    keyed.store.apply_transaction(decryption.into()).unwrap();
+    assert_eq!(
+        decryption_progress(&keyed.store, election.id).unwrap(),
+        (1, 2)
+    );
+
+    let missing = ElectionTransaction::build_id([7; 15]);
+    assert!(decryption_progress(&keyed.store, missing).is_err());
+}
+
+#[test]
+fn test_pending_partial_decryptions() {
+    let mut test_rng = rand::rngs::StdRng::from_seed([0u8; 32]);
+    let deadline = 4_000_000_000;
+    let keyed = KeyedElection::new_with(&mut test_rng, 2, 1, |election| {
+        election.decryption_deadline = Some(deadline);
+    });
+    let election = &keyed.election;
+    let trustee_ids: Vec<_> = keyed
+        .trustees
+        .iter()
+        .map(|(trustee, _)| trustee.id)
+        .collect();
+
+    let selection = Selection {
+        write_in: false,
+        score: 0,
+        selection: "Barak Obama".to_string(),
+        weight: None,
+    };
+    let selections = encrypt_vote(&keyed.encryption_key, vec![selection], &mut test_rng).unwrap();
+    let vote = keyed.vote(selections);
+
+    // Nothing is pending until voting has ended
+    assert!(pending_partial_decryptions(&keyed.store, election.id).is_empty());
+    keyed.end_voting();
+    assert_eq!(
+        pending_partial_decryptions(&keyed.store, election.id),
+        trustee_ids
+    );
+
+    let partial_tx = keyed.partial_decrypt(&mut test_rng, 0, &vote);
+    keyed.store.apply_transaction(partial_tx.into()).unwrap();
+    assert_eq!(
+        pending_partial_decryptions(&keyed.store, election.id),
+        vec![trustee_ids[1]]
+    );
+
+    // A missing trustee is only overdue once the deadline has passed
+    let clock = MockClock::new(deadline);
+    assert!(!partial_decryptions_overdue(
+        &keyed.store,
+        election.id,
+        &clock
+    ));
+    clock.advance(1);
+    assert!(partial_decryptions_overdue(
+        &keyed.store,
+        election.id,
+        &clock
+    ));
+
+    let partial_tx = keyed.partial_decrypt(&mut test_rng, 1, &vote);
+    keyed.store.apply_transaction(partial_tx.into()).unwrap();
+    assert!(pending_partial_decryptions(&keyed.store, election.id).is_empty());
+    assert!(!partial_decryptions_overdue(
+        &keyed.store,
+        election.id,
+        &clock
+    ));
+}
+
+#[test]
+fn test_decryption_requires_every_trustee_public_key() {
+    let mut test_rng = rand::rngs::StdRng::from_seed([0u8; 32]);
+    let keyed = KeyedElection::new(&mut test_rng, 2, 1);
+    let election = &keyed.election;
+
+    let selection = Selection {
+        write_in: false,
+        score: 0,
+        selection: "Barak Obama".to_string(),
+        weight: None,
+    };
+    let selections = encrypt_vote(
+        &keyed.encryption_key,
+        vec![selection.clone()],
+        &mut test_rng,
+    )
+    .unwrap();
+    let vote = keyed.vote(selections);
+    keyed.end_voting();
+    let partial_tx = keyed.partial_decrypt(&mut test_rng, 0, &vote);
+    keyed.store.apply_transaction(partial_tx.into()).unwrap();
+
+    let (_, trustee_secret) = &keyed.trustees[0];
+    let decryption =
+        DecryptionTransaction::new(election.id, vote.id, 0, 0, vec![1], vec![selection]);
+    let decryption = Signed::sign(trustee_secret, decryption).unwrap();
+    decryption.validate(&keyed.store).unwrap();
+
+    // Without the second trustee's public key, decryption is rejected even though only one share is needed
+    let (missing_trustee, _) = &keyed.trustees[1];
+    let missing_id = KeyGenPublicKeyTransaction::build_id(election.id, missing_trustee.index);
+    let store = MemStore::from(
+        keyed
+            .store
+            .range(Identifier::first(), Identifier::last())
+            .into_iter()
+            .filter(|tx| tx.id() != missing_id)
+            .collect::<Vec<_>>(),
+    );
+    assert!(matches!(
+        decryption.validate(&store),
+        Err(ValidationError::MissingTrusteePublicKey(id)) if id == missing_trustee.id
+    ));
+}
+
+// An AsyncStore over a MemStore, whose futures are always ready
+struct ReadyStore(MemStore);
+
+impl AsyncStore for ReadyStore {
+    fn get_transaction(&self, id: Identifier) -> StoreFuture<'_, Option<SignedTransaction>> {
+        Box::pin(std::future::ready(self.0.get_transaction(id)))
+    }
+
+    fn range(
+        &self,
+        start: Identifier,
+        end_inclusive: Identifier,
+    ) -> StoreFuture<'_, Vec<SignedTransaction>> {
+        Box::pin(std::future::ready(self.0.range(start, end_inclusive)))
+    }
+
+    fn apply_transaction(&self, tx: SignedTransaction) -> StoreFuture<'_, Result<(), ApplyError>> {
+        Box::pin(std::future::ready(self.0.apply_transaction(tx)))
+    }
+}
+
+// Runs futures that are ready on their first poll
+struct PollOnce;
+
+impl BlockOn for PollOnce {
+    fn block_on<F: std::future::Future>(&self, future: F) -> F::Output {
+        use std::future::Future;
+
+        struct NoopWake;
+        impl std::task::Wake for NoopWake {
+            fn wake(self: std::sync::Arc<Self>) {}
+        }
+
+        let waker = std::task::Waker::from(std::sync::Arc::new(NoopWake));
+        let mut context = std::task::Context::from_waker(&waker);
+        match Box::pin(future).as_mut().poll(&mut context) {
+            std::task::Poll::Ready(output) => output,
+            std::task::Poll::Pending => panic!("future was not ready"),
+        }
+    }
+}
+
+#[test]
+fn test_verify_key_shares() {
+    let mut rng = rand::rngs::StdRng::from_seed([0u8; 32]);
+    let keyed = KeyedElection::new(&mut rng, 3, 2);
+    let (trustee_2, trustee_2_secret) = &keyed.trustees[1];
+
+    // The shares sent to trustee 2 match the published commitments
+    trustee_2
+        .verify_key_shares(
+            trustee_2_secret,
+            &keyed.x25519_public_keys,
+            &keyed.commitments,
+            &keyed.trustee_shares[1],
+            keyed.election.id,
+        )
+        .unwrap();
+
+    // Trustee 1's shares were made from a different polynomial than the one it committed to
+    let (_, other_secret) = generate_keypair();
+    let (trustee_1, _) = &keyed.trustees[0];
+    let mut commitments = keyed.commitments.clone();
+    commitments[0].1 = trustee_1.keygen_commitment(&other_secret, keyed.election.id);
+
+    let result = trustee_2.verify_key_shares(
+        trustee_2_secret,
+        &keyed.x25519_public_keys,
+        &commitments,
+        &keyed.trustee_shares[1],
+        keyed.election.id,
+    );
+    assert!(matches!(
+        result,
+        Err(ValidationError::SecretShareCommitmentMismatch(1))
+    ));
+
+    let result = trustee_2.generate_public_key(
+        trustee_2_secret,
+        &keyed.x25519_public_keys,
+        &commitments,
+        &keyed.trustee_shares[1],
+        keyed.election.id,
+    );
+    assert!(matches!(
+        result,
+        Err(ValidationError::SecretShareCommitmentMismatch(1))
+    ));
+}
+
+#[test]
+fn test_encryption_proof() {
+    let mut rng = rand::rngs::StdRng::from_seed([0u8; 32]);
+    let keyed = KeyedElection::new_with(&mut rng, 3, 2, |election| {
+        election.require_encryption_proofs = true;
+    });
+    let selection = Selection {
+        write_in: true,
+        score: 0,
+        selection: "Barak Obama".to_string(),
+        weight: None,
+    };
+
+    // A vote without proofs is rejected
+    let selections =
+        encrypt_vote(&keyed.encryption_key, vec![selection.clone()], &mut rng).unwrap();
+    let encrypted_vote = EncryptedVote {
+        contest_index: 0,
+        selections,
+    };
+    let (vote, voter_secret) =
+        VoteTransaction::new(keyed.election.id, "TEST".to_string(), vec![encrypted_vote]);
+    let unproven = Signed::sign(&voter_secret, vote).unwrap();
+    assert!(matches!(
+        unproven.validate(&keyed.store),
+        Err(ValidationError::MissingEncryptionProof)
+    ));
+
+    // With proofs bound to its anonymous key, the vote is accepted
+    let (mut vote, voter_secret) =
+        VoteTransaction::new(keyed.election.id, "TEST".to_string(), vec![]);
+    let plaintext = vec![PlaintextVote {
+        contest_index: 0,
+        selections: vec![selection],
+    }];
+    let seed: [u8; 32] = rand::Rng::gen(&mut rng);
+    let (encrypted_votes, proof) = encrypt_vote_with_proof(
+        &keyed.encryption_key,
+        keyed.election.id,
+        &vote.anonymous_key,
+        &plaintext,
+        seed,
+        &mut rng,
+    )
+    .unwrap();
+    vote.encrypted_votes = encrypted_votes;
+    vote.encryption_proof = proof;
+    let proven = Signed::sign(&voter_secret, vote.clone()).unwrap();
+    proven.validate(&keyed.store).unwrap();
+
+    // The proofs can't be copied onto a vote with another anonymous key
+    let (mut copied, copied_secret) =
+        VoteTransaction::new(keyed.election.id, "TEST".to_string(), vec![]);
+    copied.encrypted_votes = vote.encrypted_votes.clone();
+    copied.encryption_proof = vote.encryption_proof.clone();
+    let copied = Signed::sign(&copied_secret, copied).unwrap();
+    assert!(matches!(
+        copied.validate(&keyed.store),
+        Err(ValidationError::InvalidEncryptionProof(0))
+    ));
+
+    // Nor do they prove a ciphertext the voter didn't encrypt
+    vote.encrypted_votes[0].selections = unproven.encrypted_votes[0].selections.clone();
+    let tampered = Signed::sign(&voter_secret, vote).unwrap();
+    assert!(matches!(
+        tampered.validate(&keyed.store),
+        Err(ValidationError::InvalidEncryptionProof(0))
+    ));
+
+    // The ciphertexts are drawn from the seed, so the vote can still be challenged
+    keyed
+        .store
+        .apply_transaction(proven.clone().into())
+        .unwrap();
+    let challenge = ChallengeTransaction::new(&proven, plaintext, seed);
+    let challenge = Signed::sign(&voter_secret, challenge).unwrap();
+    challenge.validate(&keyed.store).unwrap();
+}
+
+#[test]
+fn test_vote_validity_proof() {
+    let mut rng = rand::rngs::StdRng::from_seed([0u8; 32]);
+    let keyed = KeyedElection::new_with(&mut rng, 3, 2, |election| {
+        election.require_vote_validity_proofs = true;
+        let contest = &mut election.contests[0];
+        contest.write_in = false;
+        contest.num_winners = 2;
+        contest.candidates = ["Alice", "Bob", "Carol"]
+            .iter()
+            .map(|id| Candidate {
+                id: id.to_string(),
+                properties: indexmap::IndexMap::new(),
+            })
+            .collect();
+    });
+    let select = |candidate: &str| Selection {
+        write_in: false,
+        score: 0,
+        selection: candidate.to_string(),
+        weight: None,
+    };
+    let encrypt =
+        |rng: &mut rand::rngs::StdRng, vote: &VoteTransaction, selections: Vec<Selection>| {
+            let plaintext = vec![PlaintextVote {
+                contest_index: 0,
+                selections,
+            }];
+            let seed: [u8; 32] = rand::Rng::gen(&mut *rng);
+            encrypt_vote_with_validity_proof(
+                &keyed.election,
+                &keyed.encryption_key,
+                &vote.anonymous_key,
+                &plaintext,
+                seed,
+                rng,
+            )
+        };
+
+    // A vote for two of the declared candidates is accepted
+    let (mut vote, voter_secret) =
+        VoteTransaction::new(keyed.election.id, "TEST".to_string(), vec![]);
+    let (encrypted_votes, encryption_proof, validity_proof) =
+        encrypt(&mut rng, &vote, vec![select("Alice"), select("Bob")]).unwrap();
+    vote.encrypted_votes = encrypted_votes;
+    vote.encryption_proof = encryption_proof;
+    vote.vote_validity_proof = Some(validity_proof);
+    let valid = Signed::sign(&voter_secret, vote.clone()).unwrap();
+    valid.validate(&keyed.store).unwrap();
+
+    // The election requires the proofs
+    let mut unproven = vote.clone();
+    unproven.vote_validity_proof = None;
+    let unproven = Signed::sign(&voter_secret, unproven).unwrap();
+    assert!(matches!(
+        unproven.validate(&keyed.store),
+        Err(ValidationError::MissingVoteValidityProof)
+    ));
+
+    // Nothing can be proven for an undeclared candidate, or for the same candidate twice
+    assert!(matches!(
+        encrypt(&mut rng, &vote, vec![select("Mallory")]),
+        Err(Error::InvalidPluralitySelection(0))
+    ));
+    assert!(matches!(
+        encrypt(&mut rng, &vote, vec![select("Alice"), select("Alice")]),
+        Err(Error::InvalidPluralitySelection(0))
+    ));
+
+    // Encrypting 2 for Alice by splicing together two votes' proofs for her: each selection is a valid candidate,
+    // but their sum isn't two distinct candidates
+    let (reversed_votes, reversed_proof, reversed_validity) =
+        encrypt(&mut rng, &vote, vec![select("Bob"), select("Alice")]).unwrap();
+    let mut doubled = vote.clone();
+    doubled.encrypted_votes[0].selections[1] = reversed_votes[0].selections[1].clone();
+    doubled.encryption_proof.0[0][1] = reversed_proof.0[0][1].clone();
+    let mut doubled_validity = vote.vote_validity_proof.clone().unwrap();
+    doubled_validity.0[0].selections[1] = reversed_validity.0[0].selections[1].clone();
+    doubled.vote_validity_proof = Some(doubled_validity.clone());
+    let doubled_vote = Signed::sign(&voter_secret, doubled.clone()).unwrap();
+    assert!(matches!(
+        doubled_vote.validate(&keyed.store),
+        Err(ValidationError::InvalidVoteValidityProof(0))
+    ));
+
+    // Nor can the sum proof be left out
+    doubled_validity.0[0].sum = None;
+    doubled.vote_validity_proof = Some(doubled_validity);
+    let doubled_vote = Signed::sign(&voter_secret, doubled).unwrap();
+    assert!(matches!(
+        doubled_vote.validate(&keyed.store),
+        Err(ValidationError::InvalidVoteValidityProof(0))
+    ));
+
+    // Encrypting a weight of 2 for Alice in an unweighted vote isn't a valid selection either
+    let mut weighted = select("Alice");
+    weighted.weight = Some(2);
+    let (mut overweight, overweight_secret) =
+        VoteTransaction::new(keyed.election.id, "TEST".to_string(), vec![]);
+    let (encrypted_votes, encryption_proof, validity_proof) =
+        encrypt(&mut rng, &overweight, vec![weighted]).unwrap();
+    overweight.encrypted_votes = encrypted_votes;
+    overweight.encryption_proof = encryption_proof;
+    overweight.vote_validity_proof = Some(validity_proof);
+    let overweight = Signed::sign(&overweight_secret, overweight).unwrap();
+    assert!(matches!(
+        overweight.validate(&keyed.store),
+        Err(ValidationError::InvalidVoteValidityProof(0))
+    ));
+}
+
+#[test]
+fn test_trustee_revocation() {
+    let mut test_rng = rand::rngs::StdRng::from_seed([0u8; 32]);
+    let keyed = KeyedElection::new(&mut test_rng, 3, 2);
+    let election = &keyed.election;
+    let trustee_ids: Vec<_> = keyed
+        .trustees
+        .iter()
+        .map(|(trustee, _)| trustee.id)
+        .collect();
+
+    let selection = Selection {
+        write_in: false,
+        score: 0,
+        selection: "Barak Obama".to_string(),
+        weight: None,
+    };
+    let selections = encrypt_vote(
+        &keyed.encryption_key,
+        vec![selection.clone()],
+        &mut test_rng,
+    )
+    .unwrap();
+    let vote = keyed.vote(selections);
+    keyed.end_voting();
+
+    // Partial decryptions computed before the revocation, with the trustees' original key shares
+    let revoked_partial = keyed.partial_decrypt(&mut test_rng, 2, &vote);
+    let stale_partial = keyed.partial_decrypt(&mut test_rng, 0, &vote);
+
+    // Only the election authority can revoke a trustee
+    let revocation =
+        TrusteeRevocationTransaction::new(election, 3, Some("Key compromised".to_string()));
+    let (other_secret, other_public) = generate_keypair();
+    let mut forged = revocation.clone();
+    forged.authority_public_key = other_public;
+    let forged = Signed::sign(&other_secret, forged).unwrap();
+    assert!(matches!(
+        forged.validate(&keyed.store),
+        Err(ValidationError::AuthorityPublicKeyMismatch)
+    ));
+
+    // A trustee can't be re-shared before it is revoked
+    let reshare_tx = keyed.reshare(&mut test_rng, 0, 3);
+    assert!(matches!(
+        reshare_tx.validate(&keyed.store),
+        Err(ValidationError::TrusteeNotRevoked(3))
+    ));
+
+    let revocation = Signed::sign(&keyed.authority_secret, revocation).unwrap();
+    keyed.store.apply_transaction(revocation.into()).unwrap();
+    assert!(keyed.store.is_trustee_revoked(election.id, 3));
+    assert_eq!(
+        pending_partial_decryptions(&keyed.store, election.id),
+        trustee_ids[..2].to_vec()
+    );
+
+    // Revoking another trustee would leave too few to decrypt
+    let revocation = TrusteeRevocationTransaction::new(election, 2, None);
+    let revocation = Signed::sign(&keyed.authority_secret, revocation).unwrap();
+    assert!(matches!(
+        revocation.validate(&keyed.store),
+        Err(ValidationError::NotEnoughActiveTrustees(2, 1))
+    ));
+
+    // The revoked trustee's partial decryptions are rejected, and the remaining trustees can't decrypt until each of
+    // them has re-shared the revoked trustee's key share
+    assert!(matches!(
+        revoked_partial.validate(&keyed.store),
+        Err(ValidationError::TrusteeRevoked(3))
+    ));
+    assert!(matches!(
+        stale_partial.validate(&keyed.store),
+        Err(ValidationError::MissingTrusteeReshare(1, 3))
+    ));
+
+    // The revoked trustee can't re-share its own key share, and a re-share must reach every remaining trustee
+    let reshare_tx = keyed.reshare(&mut test_rng, 2, 3);
+    assert!(matches!(
+        reshare_tx.validate(&keyed.store),
+        Err(ValidationError::InvalidTrusteeIndex(3))
+    ));
+    let mut reshare_tx = keyed.reshare(&mut test_rng, 0, 3).tx;
+    reshare_tx.shares.shift_remove(&2);
+    let reshare_tx = Signed::sign(&keyed.trustees[0].1, reshare_tx).unwrap();
+    assert!(matches!(
+        reshare_tx.validate(&keyed.store),
+        Err(ValidationError::TrusteeShareMissing(2))
+    ));
+
+    for position in 0..2 {
+        let reshare_tx = keyed.reshare(&mut test_rng, position, 3);
+        keyed.store.apply_transaction(reshare_tx.into()).unwrap();
+    }
+
+    // Partial decryptions with the original key shares no longer verify, the refreshed shares decrypt as before
+    assert!(matches!(
+        stale_partial.validate(&keyed.store),
+        Err(ValidationError::PartialDecryptionProofFailed)
+    ));
+    let mut partials = Vec::new();
+    for position in 0..2 {
+        let partial_tx = keyed.partial_decrypt(&mut test_rng, position, &vote);
+        keyed
+            .store
+            .apply_transaction(partial_tx.clone().into())
+            .unwrap();
+        partials.push(partial_tx.tx);
+    }
+    assert!(pending_partial_decryptions(&keyed.store, election.id).is_empty());
+
+    let pubkeys = keyed.store.get_decryption_public_keys(election.id).unwrap();
+    let ciphertexts = &vote.encrypted_votes[0].selections;
+    let decrypted = decrypt_vote(ciphertexts, 2, &election.trustees, &pubkeys, &partials).unwrap();
+    assert_eq!(decrypted, vec![selection.clone()]);
+
+    let decryption = DecryptionTransaction::new(election.id, vote.id, 0, 0, vec![1, 2], decrypted);
+    let decryption = Signed::sign(&keyed.trustees[0].1, decryption).unwrap();
+    keyed.store.apply_transaction(decryption.into()).unwrap();
+
+    let package =
+        AuditPackage::for_vote(&keyed.store, election.id, vote.id, &keyed.authority_secret)
+            .unwrap();
+    assert_eq!(
+        package.verify().unwrap().contests[&0],
+        vec![selection.clone()]
+    );
+
+    // The revoked trustee's key share no longer combines with a refreshed share to decrypt the vote
+    let mixed_pubkeys = vec![pubkeys[0].clone(), keyed.pubkeys[2].clone()];
+    let mixed_partials = vec![partials[0].clone(), revoked_partial.tx.clone()];
+    let mixed = decrypt_vote(
+        ciphertexts,
+        2,
+        &election.trustees,
+        &mixed_pubkeys,
+        &mixed_partials,
+    );
+    assert!(!matches!(mixed, Ok(decrypted) if decrypted == vec![selection]));
+
+    // Once partial decryptions are posted, the key shares can't be refreshed again, and a trustee that has partially
+    // decrypted can't be revoked, since that would invalidate its partial decryptions when the election is
+    // re-validated
+    let reshare_tx = keyed.reshare(&mut test_rng, 0, 3);
+    assert!(matches!(
+        reshare_tx.validate(&keyed.store),
+        Err(ValidationError::PartialDecryptionsAlreadyPosted)
+    ));
+    let revocation = TrusteeRevocationTransaction::new(election, 1, None);
+    let revocation = Signed::sign(&keyed.authority_secret, revocation).unwrap();
+    assert!(matches!(
+        revocation.validate(&keyed.store),
+        Err(ValidationError::TrusteeAlreadyDecrypted(1))
+    ));
+    assert!(keyed.store.validate_all().is_empty());
+}
+
+#[test]
+fn test_blocking_store() {
+    let mut test_rng = rand::rngs::StdRng::from_seed([0u8; 32]);
+    let keyed = KeyedElection::new(&mut test_rng, 1, 1);
+    let election = &keyed.election;
+
+    let selection = Selection {
+        write_in: false,
+        score: 0,
+        selection: "Barak Obama".to_string(),
+        weight: None,
+    };
+    let selections = encrypt_vote(
+        &keyed.encryption_key,
+        vec![selection.clone()],
+        &mut test_rng,
+    )
+    .unwrap();
+    let vote = keyed.vote(selections);
+    keyed.end_voting();
+    let partial_tx = keyed.partial_decrypt(&mut test_rng, 0, &vote);
+    keyed
+        .store
+        .apply_transaction(partial_tx.clone().into())
+        .unwrap();
+
+    let store = BlockingStore::new(ReadyStore(keyed.store.clone()), PollOnce);
+    assert_eq!(store.list_elections(), vec![election.id]);
+    assert!(store.contains(vote.id));
+
+    // Validation runs unchanged on top of the async store
+    let (_, trustee_secret) = &keyed.trustees[0];
+    let decryption =
+        DecryptionTransaction::new(election.id, vote.id, 0, 0, vec![1], vec![selection]);
+    let decryption = Signed::sign(trustee_secret, decryption).unwrap();
+    decryption.validate(&store).unwrap();
+    store.apply_transaction(decryption.clone().into()).unwrap();
+    assert!(store.inner().0.contains(decryption.id()));
+
+    // The store-independent check can also be run on transactions fetched some other way
+    let partials = vec![partial_tx.tx];
+    let mut wrong = decryption.tx.clone();
+    wrong.decrypted_vote[0].selection = "Santa Claus".to_string();
+    assert!(matches!(
+        wrong.verify_decryption(
+            election,
+            &vote.encrypted_votes[0].selections,
+            &keyed.pubkeys,
+            &partials,
+        ),
+        Err(ValidationError::VoteDecryptionMismatch)
+    ));
+    decryption
+        .verify_decryption(
+            election,
+            &vote.encrypted_votes[0].selections,
+            &keyed.pubkeys,
+            &partials,
+        )
+        .unwrap();
+}
@@ -1,9 +1,7 @@
 use crate::*;
-use ed25519_dalek::ExpandedSecretKey;
 use ed25519_dalek::PublicKey;
 use ed25519_dalek::SecretKey;
 use ed25519_dalek::Signature;
-use ed25519_dalek::Verifier;
 use num_enum::IntoPrimitive;
 use num_enum::TryFromPrimitive;
 use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
@@ -14,6 +12,26 @@ use std::convert::TryFrom;
 use std::convert::TryInto;
 use std::ops::Deref;
 use std::str::FromStr;
+use subtle::{Choice, ConstantTimeEq};
+
+/// Binary encoding used when packing and unpacking a `SignedTransaction`
+///
+/// CBOR is the default, and is always what signatures are computed over.
+///
+/// Only self-describing formats are supported: transactions are internally tagged by `type`
+/// and omit unset optional fields, neither of which survive a round-trip through
+/// formats such as bincode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Cbor,
+    MessagePack,
+}
+
+impl Default for Encoding {
+    fn default() -> Self {
+        Encoding::Cbor
+    }
+}
 
 /// An unsigned transaction
 /// TODO: Implment From going for specific tx to this emum and vice versa
@@ -31,6 +49,12 @@ pub enum Transaction {
     Mix(MixTransaction),
     PartialDecryption(PartialDecryptionTransaction),
     Decryption(DecryptionTransaction),
+    ObserverAcknowledgment(ObserverAcknowledgmentTransaction),
+    TrusteeKeyRevocation(TrusteeKeyRevocationTransaction),
+    Challenge(ChallengeTransaction),
+    PartialDecryptionBatch(PartialDecryptionBatchTransaction),
+    TrusteeRevocation(TrusteeRevocationTransaction),
+    TrusteeReshare(TrusteeReshareTransaction),
 }
 
 impl Transaction {
@@ -47,6 +71,12 @@ impl Transaction {
             Transaction::Mix(_) => TransactionType::Mix,
             Transaction::PartialDecryption(_) => TransactionType::PartialDecryption,
             Transaction::Decryption(_) => TransactionType::Decryption,
+            Transaction::ObserverAcknowledgment(_) => TransactionType::ObserverAcknowledgment,
+            Transaction::TrusteeKeyRevocation(_) => TransactionType::TrusteeKeyRevocation,
+            Transaction::Challenge(_) => TransactionType::Challenge,
+            Transaction::PartialDecryptionBatch(_) => TransactionType::PartialDecryptionBatch,
+            Transaction::TrusteeRevocation(_) => TransactionType::TrusteeRevocation,
+            Transaction::TrusteeReshare(_) => TransactionType::TrusteeReshare,
         }
     }
 
@@ -63,6 +93,12 @@ impl Transaction {
             Transaction::Mix(tx) => tx.id,
             Transaction::PartialDecryption(tx) => tx.id,
             Transaction::Decryption(tx) => tx.id,
+            Transaction::ObserverAcknowledgment(tx) => tx.id,
+            Transaction::TrusteeKeyRevocation(tx) => tx.id,
+            Transaction::Challenge(tx) => tx.id,
+            Transaction::PartialDecryptionBatch(tx) => tx.id,
+            Transaction::TrusteeRevocation(tx) => tx.id,
+            Transaction::TrusteeReshare(tx) => tx.id,
         }
     }
 
@@ -82,6 +118,12 @@ impl Transaction {
             Transaction::Mix(tx) => tx.validate_tx(s),
             Transaction::PartialDecryption(tx) => tx.validate_tx(s),
             Transaction::Decryption(tx) => tx.validate_tx(s),
+            Transaction::ObserverAcknowledgment(tx) => tx.validate_tx(s),
+            Transaction::TrusteeKeyRevocation(tx) => tx.validate_tx(s),
+            Transaction::Challenge(tx) => tx.validate_tx(s),
+            Transaction::PartialDecryptionBatch(tx) => tx.validate_tx(s),
+            Transaction::TrusteeRevocation(tx) => tx.validate_tx(s),
+            Transaction::TrusteeReshare(tx) => tx.validate_tx(s),
         }
     }
 }
@@ -101,6 +143,12 @@ pub enum SignedTransaction {
     Mix(Signed<MixTransaction>),
     PartialDecryption(Signed<PartialDecryptionTransaction>),
     Decryption(Signed<DecryptionTransaction>),
+    ObserverAcknowledgment(Signed<ObserverAcknowledgmentTransaction>),
+    TrusteeKeyRevocation(Signed<TrusteeKeyRevocationTransaction>),
+    Challenge(Signed<ChallengeTransaction>),
+    PartialDecryptionBatch(Signed<PartialDecryptionBatchTransaction>),
+    TrusteeRevocation(Signed<TrusteeRevocationTransaction>),
+    TrusteeReshare(Signed<TrusteeReshareTransaction>),
 }
 
 impl SignedTransaction {
@@ -117,9 +165,114 @@ impl SignedTransaction {
             SignedTransaction::Mix(_) => TransactionType::Mix,
             SignedTransaction::PartialDecryption(_) => TransactionType::PartialDecryption,
             SignedTransaction::Decryption(_) => TransactionType::Decryption,
+            SignedTransaction::ObserverAcknowledgment(_) => TransactionType::ObserverAcknowledgment,
+            SignedTransaction::TrusteeKeyRevocation(_) => TransactionType::TrusteeKeyRevocation,
+            SignedTransaction::Challenge(_) => TransactionType::Challenge,
+            SignedTransaction::PartialDecryptionBatch(_) => TransactionType::PartialDecryptionBatch,
+            SignedTransaction::TrusteeRevocation(_) => TransactionType::TrusteeRevocation,
+            SignedTransaction::TrusteeReshare(_) => TransactionType::TrusteeReshare,
         }
     }
 
+    /// Get the IDs of the transactions this transaction directly refers to.
+    ///
+    /// Only references that can be worked out from the transaction itself are included: for example an
+    /// EncryptionKey transaction depends on every trustee's KeyGenPublicKey transaction, but does not name them,
+    /// so its only input is the election.
+    pub fn inputs(&self) -> Vec<Identifier> {
+        match self {
+            SignedTransaction::Election(_) => vec![],
+            SignedTransaction::KeyGenCommitment(signed) => vec![signed.election],
+            SignedTransaction::KeyGenShare(signed) => {
+                let mut inputs = vec![KeyGenCommitmentTransaction::build_id(
+                    signed.election,
+                    signed.trustee_index,
+                )];
+                for recipient in signed.shares.keys() {
+                    if *recipient != signed.trustee_index {
+                        inputs.push(KeyGenCommitmentTransaction::build_id(
+                            signed.election,
+                            *recipient,
+                        ));
+                    }
+                }
+                inputs
+            }
+            SignedTransaction::KeyGenPublicKey(signed) => {
+                let mut inputs = vec![KeyGenShareTransaction::build_id(
+                    signed.election,
+                    signed.trustee_index,
+                )];
+                if signed.revision > 0 {
+                    inputs.push(TrusteeKeyRevocationTransaction::build_id(
+                        signed.election,
+                        signed.trustee_index,
+                        signed.revision - 1,
+                    ));
+                }
+                inputs
+            }
+            SignedTransaction::EncryptionKey(signed) => vec![signed.election],
+            SignedTransaction::Vote(signed) => {
+                vec![EncryptionKeyTransaction::build_id(signed.election)]
+            }
+            SignedTransaction::VotingEnd(signed) => vec![signed.election],
+            SignedTransaction::Mix(signed) => match signed.prev_mix_id {
+                Some(prev_mix_id) => vec![prev_mix_id],
+                None => signed.vote_ids.clone(),
+            },
+            SignedTransaction::PartialDecryption(signed) => vec![signed.upstream_id],
+            SignedTransaction::Decryption(signed) => signed
+                .trustees
+                .iter()
+                .map(|trustee_index| {
+                    PartialDecryptionTransaction::build_id(
+                        signed.election_id,
+                        signed.upstream_id,
+                        signed.contest_index,
+                        signed.upstream_index,
+                        *trustee_index,
+                    )
+                })
+                .collect(),
+            SignedTransaction::ObserverAcknowledgment(signed) => vec![signed.election],
+            SignedTransaction::TrusteeKeyRevocation(signed) => {
+                vec![KeyGenPublicKeyTransaction::build_revision_id(
+                    signed.election,
+                    signed.trustee_index,
+                    signed.revision,
+                )]
+            }
+            SignedTransaction::Challenge(signed) => vec![signed.vote_id],
+            SignedTransaction::PartialDecryptionBatch(signed) => {
+                let mut inputs: Vec<Identifier> = Vec::new();
+                for decryption in signed.decryptions.iter() {
+                    if !inputs.contains(&decryption.upstream_id) {
+                        inputs.push(decryption.upstream_id);
+                    }
+                }
+                inputs
+            }
+            SignedTransaction::TrusteeRevocation(signed) => vec![signed.election],
+            SignedTransaction::TrusteeReshare(signed) => {
+                vec![TrusteeRevocationTransaction::build_id(
+                    signed.election,
+                    signed.revoked_index,
+                )]
+            }
+        }
+    }
+
+    /// Get the transaction type name (eg `"election"`, `"vote"`), suitable for logging and metrics
+    pub fn type_name(&self) -> &'static str {
+        self.transaction_type().name()
+    }
+
+    /// Get the plural transaction type name (eg `"elections"`, `"votes"`), suitable for display
+    pub fn type_name_plural(&self) -> &'static str {
+        self.transaction_type().name_plural()
+    }
+
     /// Pack into bytes
     pub fn as_bytes(&self) -> Vec<u8> {
         serde_cbor::to_vec(self).expect("cryptoballot: Unexpected error packing transaction")
@@ -130,6 +283,29 @@ impl SignedTransaction {
         Ok(serde_cbor::from_slice(bytes)?)
     }
 
+    /// The SHA256 hash of the packed transaction, including its signature
+    pub fn content_hash(&self) -> [u8; 32] {
+        use sha2::{Digest, Sha256};
+        Sha256::digest(&self.as_bytes()).into()
+    }
+
+    /// Pack into bytes using the given encoding
+    pub fn as_bytes_with(&self, encoding: Encoding) -> Vec<u8> {
+        match encoding {
+            Encoding::Cbor => self.as_bytes(),
+            Encoding::MessagePack => rmp_serde::to_vec_named(self)
+                .expect("cryptoballot: Unexpected error packing transaction"),
+        }
+    }
+
+    /// Unpack from bytes using the given encoding
+    pub fn from_bytes_with(encoding: Encoding, bytes: &[u8]) -> Result<Self, Error> {
+        match encoding {
+            Encoding::Cbor => Self::from_bytes(bytes),
+            Encoding::MessagePack => Ok(rmp_serde::from_slice(bytes)?),
+        }
+    }
+
     /// Get the transaction ID
     pub fn id(&self) -> Identifier {
         match self {
@@ -143,6 +319,12 @@ impl SignedTransaction {
             SignedTransaction::Mix(signed) => signed.tx.id,
             SignedTransaction::PartialDecryption(signed) => signed.tx.id,
             SignedTransaction::Decryption(signed) => signed.tx.id,
+            SignedTransaction::ObserverAcknowledgment(signed) => signed.tx.id,
+            SignedTransaction::TrusteeKeyRevocation(signed) => signed.tx.id,
+            SignedTransaction::Challenge(signed) => signed.tx.id,
+            SignedTransaction::PartialDecryptionBatch(signed) => signed.tx.id,
+            SignedTransaction::TrusteeRevocation(signed) => signed.tx.id,
+            SignedTransaction::TrusteeReshare(signed) => signed.tx.id,
         }
     }
 
@@ -165,6 +347,12 @@ impl SignedTransaction {
             SignedTransaction::Mix(tx) => tx.validate(s),
             SignedTransaction::PartialDecryption(tx) => tx.validate(s),
             SignedTransaction::Decryption(tx) => tx.validate(s),
+            SignedTransaction::ObserverAcknowledgment(tx) => tx.validate(s),
+            SignedTransaction::TrusteeKeyRevocation(tx) => tx.validate(s),
+            SignedTransaction::Challenge(tx) => tx.validate(s),
+            SignedTransaction::PartialDecryptionBatch(tx) => tx.validate(s),
+            SignedTransaction::TrusteeRevocation(tx) => tx.validate(s),
+            SignedTransaction::TrusteeReshare(tx) => tx.validate(s),
         }
     }
 
@@ -180,6 +368,12 @@ impl SignedTransaction {
             SignedTransaction::Mix(tx) => tx.verify_signature(),
             SignedTransaction::PartialDecryption(tx) => tx.verify_signature(),
             SignedTransaction::Decryption(tx) => tx.verify_signature(),
+            SignedTransaction::ObserverAcknowledgment(tx) => tx.verify_signature(),
+            SignedTransaction::TrusteeKeyRevocation(tx) => tx.verify_signature(),
+            SignedTransaction::Challenge(tx) => tx.verify_signature(),
+            SignedTransaction::PartialDecryptionBatch(tx) => tx.verify_signature(),
+            SignedTransaction::TrusteeRevocation(tx) => tx.verify_signature(),
+            SignedTransaction::TrusteeReshare(tx) => tx.verify_signature(),
         }
     }
 
@@ -195,6 +389,12 @@ impl SignedTransaction {
             SignedTransaction::Mix(tx) => tx.public(),
             SignedTransaction::PartialDecryption(tx) => tx.public(),
             SignedTransaction::Decryption(tx) => tx.public(),
+            SignedTransaction::ObserverAcknowledgment(tx) => tx.public(),
+            SignedTransaction::TrusteeKeyRevocation(tx) => tx.public(),
+            SignedTransaction::Challenge(tx) => tx.public(),
+            SignedTransaction::PartialDecryptionBatch(tx) => tx.public(),
+            SignedTransaction::TrusteeRevocation(tx) => tx.public(),
+            SignedTransaction::TrusteeReshare(tx) => tx.public(),
         }
     }
 }
@@ -222,6 +422,13 @@ pub trait CryptoBallotTransaction: Serialize + sealed::Sealed {
     }
 }
 
+/// A transaction signed by the election authority, which can be re-signed for a new authority with
+/// `Signed::reauthorize`
+pub trait AuthorityTransaction: CryptoBallotTransaction {
+    /// Replace the authority public-key that the transaction is signed with
+    fn set_authority_public(&mut self, public: PublicKey);
+}
+
 /// Seal CryptoBallot Transaction so they can't be implemented outside this crate
 mod sealed {
     pub trait Sealed {}
@@ -236,6 +443,12 @@ mod sealed {
     impl Sealed for crate::MixTransaction {}
     impl Sealed for crate::PartialDecryptionTransaction {}
     impl Sealed for crate::DecryptionTransaction {}
+    impl Sealed for crate::ObserverAcknowledgmentTransaction {}
+    impl Sealed for crate::TrusteeKeyRevocationTransaction {}
+    impl Sealed for crate::ChallengeTransaction {}
+    impl Sealed for crate::PartialDecryptionBatchTransaction {}
+    impl Sealed for crate::TrusteeRevocationTransaction {}
+    impl Sealed for crate::TrusteeReshareTransaction {}
 }
 
 /// A generic signed transaction
@@ -258,9 +471,7 @@ impl<T: CryptoBallotTransaction + Serialize> Signed<T> {
         }
 
         let serialized = transaction.as_bytes();
-
-        let expanded: ExpandedSecretKey = secret.into();
-        let signature = expanded.sign(&serialized, &public_key);
+        let signature = sign_detached(secret, &serialized);
 
         Ok(Signed {
             tx: transaction,
@@ -268,17 +479,43 @@ impl<T: CryptoBallotTransaction + Serialize> Signed<T> {
         })
     }
 
+    /// The exact bytes covered by the signature, for verifying it outside of this library.
+    ///
+    /// This is the CBOR encoding (RFC 7049, as produced by `serde_cbor`) of the unsigned transaction `tx`,
+    /// with no domain-separation prefix and no pre-hashing. The signature is a standard ed25519 signature over
+    /// these bytes, made with the key returned by `public()`.
+    pub fn signed_message_bytes(&self) -> Vec<u8> {
+        self.tx.as_bytes()
+    }
+
     /// Verify the signature on a signed transaction
     pub fn verify_signature(&self) -> Result<(), ValidationError> {
-        let serialized = self.tx.as_bytes();
+        trace_span!("signature.verify");
+        let serialized = self.signed_message_bytes();
 
         if let Some(tx_public) = self.tx.public() {
-            Ok(tx_public.verify(&serialized, &self.sig)?)
+            Ok(verify_detached(&tx_public, &serialized, &self.sig)?)
         } else {
             Ok(())
         }
     }
 
+    /// Verify the signature against `original_packed`, the exact bytes that were signed (see `signed_message_bytes`),
+    /// and check that the transaction still re-serializes to those bytes.
+    ///
+    /// `verify_signature` only checks the signature over a fresh serialization of `tx`. This also catches a
+    /// transaction that was changed after it was signed, or whose encoding has drifted from the one that was signed.
+    pub fn verify_against_bytes(&self, original_packed: &[u8]) -> Result<(), ValidationError> {
+        if self.signed_message_bytes() != original_packed {
+            return Err(ValidationError::SignedBytesMismatch);
+        }
+
+        if let Some(tx_public) = self.tx.public() {
+            verify_detached(&tx_public, original_packed, &self.sig)?;
+        }
+        Ok(())
+    }
+
     /// Get the inner unsigned transaction
     pub fn inner(&self) -> &T {
         &self.tx
@@ -291,10 +528,92 @@ impl<T: CryptoBallotTransaction + Serialize> Signed<T> {
 
     /// Verify the signature and validate the transaction
     pub fn validate<S: Store>(&self, store: &S) -> Result<(), ValidationError> {
+        trace_span!("validate", id = %self.id(), tx_type = %T::tx_type());
+
+        let result = self
+            .verify_signature()
+            .and_then(|_| self.validate_tx(store));
+        trace_outcome!(result);
+
+        result
+    }
+
+    /// Drop the signature, producing an unsigned draft that can be reviewed and signed again
+    pub fn strip_signature(self) -> UnsignedTransaction<T> {
+        UnsignedTransaction::new(self.tx)
+    }
+}
+
+/// An unsigned transaction, such as a draft election circulated for review before the authority signs it.
+///
+/// Only signed transactions can be stored or validated, so an `UnsignedTransaction` must be signed with `sign` first.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct UnsignedTransaction<T: CryptoBallotTransaction + Serialize> {
+    pub tx: T,
+}
+
+impl<T: CryptoBallotTransaction + Serialize> UnsignedTransaction<T> {
+    /// Wrap an unsigned transaction
+    pub fn new(transaction: T) -> Self {
+        UnsignedTransaction { tx: transaction }
+    }
+
+    /// Sign the transaction, see `Signed::sign`
+    pub fn sign(self, secret: &SecretKey) -> Result<Signed<T>, Error> {
+        Signed::sign(secret, self.tx)
+    }
+
+    /// Get the inner transaction
+    pub fn into_inner(self) -> T {
+        self.tx
+    }
+
+    /// Serialize the draft to JSON
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self)
+            .expect("cryptoballot: Unexpected error serializing transaction")
+    }
+
+    /// Deserialize a draft from JSON
+    pub fn from_json(json: &str) -> Result<Self, Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        Ok(serde_json::from_str(json)?)
+    }
+}
+
+impl<T: CryptoBallotTransaction + Serialize> Deref for UnsignedTransaction<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.tx
+    }
+}
+
+impl<T: AuthorityTransaction + Serialize> Signed<T> {
+    /// Re-sign a transaction for a new election authority, such as when custody of an election is transferred.
+    ///
+    /// The existing signature must be valid and `old_secret` must be the key it was made with. Transactions signed by
+    /// the authority must carry the election's current authority key, so re-authorizing the election transaction
+    /// means re-authorizing its encryption_key and voting_end transactions as well.
+    pub fn reauthorize(
+        self,
+        old_secret: &SecretKey,
+        new_secret: &SecretKey,
+        new_public: PublicKey,
+    ) -> Result<Signed<T>, ReauthorizeError> {
         self.verify_signature()?;
-        self.validate_tx(store)?;
+        if self.tx.public() != Some(PublicKey::from(old_secret)) {
+            return Err(ReauthorizeError::WrongAuthorityKey);
+        }
+        if PublicKey::from(new_secret) != new_public {
+            return Err(ReauthorizeError::MismatchedPublicKeys);
+        }
 
-        Ok(())
+        let mut tx = self.tx;
+        tx.set_authority_public(new_public);
+        Ok(Signed::sign(new_secret, tx)?)
     }
 }
 
@@ -316,9 +635,15 @@ impl<T: CryptoBallotTransaction + Serialize> Deref for Signed<T> {
 ///
 /// The identifier defines the election, transction-type, and a unique identifier.
 ///
+/// Identifiers are ordered lexicographically over their 32 byte layout (see `to_array`): first by election-id,
+/// then by transaction-type, then by unique-info. Their (lowercase hex) string form sorts in the same order.
+///
+/// Equality is constant-time: `==` compares all 32 bytes with `subtle::ConstantTimeEq`, so the time taken does not
+/// depend on where two identifiers first differ. Ordering (`<`, `cmp`) is not constant-time.
+///
 /// TODO: Entire aligned-struct is 40 bytes, larger than our ideal Copy limit of 32 bytes
 ///       We should combine election_id and tx_type and have utility functions
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[derive(Copy, Clone, Debug, Eq, Hash)]
 pub struct Identifier {
     pub election_id: [u8; 15],             // Will align to 16 bytes
     pub transaction_type: TransactionType, // Will align to 8 bytes
@@ -342,6 +667,24 @@ impl Identifier {
         }
     }
 
+    /// The lowest possible identifier, sorting before every transaction in every election
+    pub fn first() -> Self {
+        Self {
+            election_id: [0; 15],
+            transaction_type: TransactionType::Election,
+            unique_info: [0; 16],
+        }
+    }
+
+    /// The highest possible identifier, sorting after every transaction in every election
+    pub fn last() -> Self {
+        Self {
+            election_id: [255; 15],
+            transaction_type: TransactionType::MAX,
+            unique_info: [255; 16],
+        }
+    }
+
     // Create a new identifier that starts at the start of a transaction type
     pub fn start(
         election_id: Identifier,
@@ -426,13 +769,14 @@ impl FromStr for Identifier {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let bytes = hex::decode(s).map_err(|_| Error::IdentifierBadHex)?;
 
-        if bytes.len() != 32 && bytes.len() != 16 {
+        if bytes.len() != 32 {
             return Err(Error::IdentifierBadLen);
         }
 
         // These unwraps are OK - we know the length is valid
         let election_id: [u8; 15] = bytes[0..15].try_into().unwrap();
-        let transaction_type = TransactionType::try_from_primitive(bytes[15]).unwrap();
+        let transaction_type = TransactionType::try_from_primitive(bytes[15])
+            .map_err(|_| Error::IdentifierBadType(bytes[15]))?;
 
         let unique_info: [u8; 16] = bytes[16..].try_into().unwrap();
 
@@ -469,6 +813,18 @@ impl std::fmt::Display for Identifier {
     }
 }
 
+impl ConstantTimeEq for Identifier {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        self.to_array()[..].ct_eq(&other.to_array()[..])
+    }
+}
+
+impl PartialEq for Identifier {
+    fn eq(&self, other: &Self) -> bool {
+        self.ct_eq(other).into()
+    }
+}
+
 impl PartialOrd for Identifier {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         let election_ord = self.election_id.cmp(&other.election_id);
@@ -514,12 +870,18 @@ impl From<Identifier> for [u8; 32] {
 }
 
 /// A transaction type
+///
+/// The discriminant is written into every `Identifier` (and so into every stored key), so each value is fixed once
+/// assigned. New transaction types must take the next unused value, and existing values must never be renumbered or
+/// reused. The enum is `non_exhaustive` so that adding a transaction type is not a breaking change for downstream
+/// matches.
 // TODO: Maybe make Election = 0 to align with identifiers in merkle-tree
 #[derive(
     Serialize, Deserialize, TryFromPrimitive, IntoPrimitive, Copy, Debug, Clone, PartialEq, Eq, Hash,
 )]
 #[serde(rename_all = "snake_case")]
 #[repr(u8)]
+#[non_exhaustive]
 pub enum TransactionType {
     Election = 1,
     KeyGenCommitment = 2,
@@ -531,9 +893,18 @@ pub enum TransactionType {
     Mix = 8,
     PartialDecryption = 9,
     Decryption = 10,
+    ObserverAcknowledgment = 11,
+    TrusteeKeyRevocation = 12,
+    Challenge = 13,
+    PartialDecryptionBatch = 14,
+    TrusteeRevocation = 15,
+    TrusteeReshare = 16,
 }
 
 impl TransactionType {
+    /// The highest transaction type, which sorts last within an election. Update this when adding a new type.
+    pub const MAX: TransactionType = TransactionType::TrusteeReshare;
+
     pub fn hex_string(&self) -> &str {
         match self {
             TransactionType::Election => "01",
@@ -546,10 +917,16 @@ impl TransactionType {
             TransactionType::Mix => "08",
             TransactionType::PartialDecryption => "09",
             TransactionType::Decryption => "0a",
+            TransactionType::ObserverAcknowledgment => "0b",
+            TransactionType::TrusteeKeyRevocation => "0c",
+            TransactionType::Challenge => "0d",
+            TransactionType::PartialDecryptionBatch => "0e",
+            TransactionType::TrusteeRevocation => "0f",
+            TransactionType::TrusteeReshare => "10",
         }
     }
 
-    pub fn name(&self) -> &str {
+    pub fn name(&self) -> &'static str {
         match self {
             TransactionType::Election => "election",
             TransactionType::KeyGenCommitment => "key_gen_commitment",
@@ -561,6 +938,33 @@ impl TransactionType {
             TransactionType::Mix => "mix",
             TransactionType::PartialDecryption => "partial_decryption",
             TransactionType::Decryption => "decryption",
+            TransactionType::ObserverAcknowledgment => "observer_acknowledgment",
+            TransactionType::TrusteeKeyRevocation => "trustee_key_revocation",
+            TransactionType::Challenge => "challenge",
+            TransactionType::PartialDecryptionBatch => "partial_decryption_batch",
+            TransactionType::TrusteeRevocation => "trustee_revocation",
+            TransactionType::TrusteeReshare => "trustee_reshare",
+        }
+    }
+
+    pub fn name_plural(&self) -> &'static str {
+        match self {
+            TransactionType::Election => "elections",
+            TransactionType::KeyGenCommitment => "key_gen_commitments",
+            TransactionType::KeyGenShare => "key_gen_shares",
+            TransactionType::KeyGenPublicKey => "key_gen_public_keys",
+            TransactionType::EncryptionKey => "encryption_keys",
+            TransactionType::Vote => "votes",
+            TransactionType::VotingEnd => "voting_ends",
+            TransactionType::Mix => "mixes",
+            TransactionType::PartialDecryption => "partial_decryptions",
+            TransactionType::Decryption => "decryptions",
+            TransactionType::ObserverAcknowledgment => "observer_acknowledgments",
+            TransactionType::TrusteeKeyRevocation => "trustee_key_revocations",
+            TransactionType::Challenge => "challenges",
+            TransactionType::PartialDecryptionBatch => "partial_decryption_batches",
+            TransactionType::TrusteeRevocation => "trustee_revocations",
+            TransactionType::TrusteeReshare => "trustee_reshares",
         }
     }
 
@@ -670,6 +1074,60 @@ impl From<SignedTransaction> for Signed<DecryptionTransaction> {
     }
 }
 
+impl From<SignedTransaction> for Signed<ObserverAcknowledgmentTransaction> {
+    fn from(tx: SignedTransaction) -> Self {
+        match tx {
+            SignedTransaction::ObserverAcknowledgment(tx) => tx,
+            _ => panic!("wrong transaction type expected"),
+        }
+    }
+}
+
+impl From<SignedTransaction> for Signed<TrusteeKeyRevocationTransaction> {
+    fn from(tx: SignedTransaction) -> Self {
+        match tx {
+            SignedTransaction::TrusteeKeyRevocation(tx) => tx,
+            _ => panic!("wrong transaction type expected"),
+        }
+    }
+}
+
+impl From<SignedTransaction> for Signed<ChallengeTransaction> {
+    fn from(tx: SignedTransaction) -> Self {
+        match tx {
+            SignedTransaction::Challenge(tx) => tx,
+            _ => panic!("wrong transaction type expected"),
+        }
+    }
+}
+
+impl From<SignedTransaction> for Signed<PartialDecryptionBatchTransaction> {
+    fn from(tx: SignedTransaction) -> Self {
+        match tx {
+            SignedTransaction::PartialDecryptionBatch(tx) => tx,
+            _ => panic!("wrong transaction type expected"),
+        }
+    }
+}
+
+impl From<SignedTransaction> for Signed<TrusteeRevocationTransaction> {
+    fn from(tx: SignedTransaction) -> Self {
+        match tx {
+            SignedTransaction::TrusteeRevocation(tx) => tx,
+            _ => panic!("wrong transaction type expected"),
+        }
+    }
+}
+
+impl From<SignedTransaction> for Signed<TrusteeReshareTransaction> {
+    fn from(tx: SignedTransaction) -> Self {
+        match tx {
+            SignedTransaction::TrusteeReshare(tx) => tx,
+            _ => panic!("wrong transaction type expected"),
+        }
+    }
+}
+
 impl From<SignedTransaction> for ElectionTransaction {
     fn from(tx: SignedTransaction) -> Self {
         match tx {
@@ -751,6 +1209,60 @@ impl From<SignedTransaction> for DecryptionTransaction {
     }
 }
 
+impl From<SignedTransaction> for ObserverAcknowledgmentTransaction {
+    fn from(tx: SignedTransaction) -> Self {
+        match tx {
+            SignedTransaction::ObserverAcknowledgment(tx) => tx.tx,
+            _ => panic!("wrong transaction type expected"),
+        }
+    }
+}
+
+impl From<SignedTransaction> for TrusteeKeyRevocationTransaction {
+    fn from(tx: SignedTransaction) -> Self {
+        match tx {
+            SignedTransaction::TrusteeKeyRevocation(tx) => tx.tx,
+            _ => panic!("wrong transaction type expected"),
+        }
+    }
+}
+
+impl From<SignedTransaction> for ChallengeTransaction {
+    fn from(tx: SignedTransaction) -> Self {
+        match tx {
+            SignedTransaction::Challenge(tx) => tx.tx,
+            _ => panic!("wrong transaction type expected"),
+        }
+    }
+}
+
+impl From<SignedTransaction> for PartialDecryptionBatchTransaction {
+    fn from(tx: SignedTransaction) -> Self {
+        match tx {
+            SignedTransaction::PartialDecryptionBatch(tx) => tx.tx,
+            _ => panic!("wrong transaction type expected"),
+        }
+    }
+}
+
+impl From<SignedTransaction> for TrusteeRevocationTransaction {
+    fn from(tx: SignedTransaction) -> Self {
+        match tx {
+            SignedTransaction::TrusteeRevocation(tx) => tx.tx,
+            _ => panic!("wrong transaction type expected"),
+        }
+    }
+}
+
+impl From<SignedTransaction> for TrusteeReshareTransaction {
+    fn from(tx: SignedTransaction) -> Self {
+        match tx {
+            SignedTransaction::TrusteeReshare(tx) => tx.tx,
+            _ => panic!("wrong transaction type expected"),
+        }
+    }
+}
+
 impl From<SignedTransaction> for PartialDecryptionTransaction {
     fn from(tx: SignedTransaction) -> Self {
         match tx {
@@ -820,6 +1332,42 @@ impl From<Signed<DecryptionTransaction>> for SignedTransaction {
     }
 }
 
+impl From<Signed<ObserverAcknowledgmentTransaction>> for SignedTransaction {
+    fn from(tx: Signed<ObserverAcknowledgmentTransaction>) -> Self {
+        SignedTransaction::ObserverAcknowledgment(tx)
+    }
+}
+
+impl From<Signed<TrusteeKeyRevocationTransaction>> for SignedTransaction {
+    fn from(tx: Signed<TrusteeKeyRevocationTransaction>) -> Self {
+        SignedTransaction::TrusteeKeyRevocation(tx)
+    }
+}
+
+impl From<Signed<ChallengeTransaction>> for SignedTransaction {
+    fn from(tx: Signed<ChallengeTransaction>) -> Self {
+        SignedTransaction::Challenge(tx)
+    }
+}
+
+impl From<Signed<PartialDecryptionBatchTransaction>> for SignedTransaction {
+    fn from(tx: Signed<PartialDecryptionBatchTransaction>) -> Self {
+        SignedTransaction::PartialDecryptionBatch(tx)
+    }
+}
+
+impl From<Signed<TrusteeRevocationTransaction>> for SignedTransaction {
+    fn from(tx: Signed<TrusteeRevocationTransaction>) -> Self {
+        SignedTransaction::TrusteeRevocation(tx)
+    }
+}
+
+impl From<Signed<TrusteeReshareTransaction>> for SignedTransaction {
+    fn from(tx: Signed<TrusteeReshareTransaction>) -> Self {
+        SignedTransaction::TrusteeReshare(tx)
+    }
+}
+
 impl AsRef<ElectionTransaction> for SignedTransaction {
     fn as_ref(&self) -> &ElectionTransaction {
         match self {
@@ -910,10 +1458,65 @@ impl AsRef<DecryptionTransaction> for SignedTransaction {
     }
 }
 
+impl AsRef<ObserverAcknowledgmentTransaction> for SignedTransaction {
+    fn as_ref(&self) -> &ObserverAcknowledgmentTransaction {
+        match self {
+            SignedTransaction::ObserverAcknowledgment(signed) => &signed.tx,
+            _ => panic!("wrong transaction type expected"),
+        }
+    }
+}
+
+impl AsRef<TrusteeKeyRevocationTransaction> for SignedTransaction {
+    fn as_ref(&self) -> &TrusteeKeyRevocationTransaction {
+        match self {
+            SignedTransaction::TrusteeKeyRevocation(signed) => &signed.tx,
+            _ => panic!("wrong transaction type expected"),
+        }
+    }
+}
+
+impl AsRef<ChallengeTransaction> for SignedTransaction {
+    fn as_ref(&self) -> &ChallengeTransaction {
+        match self {
+            SignedTransaction::Challenge(signed) => &signed.tx,
+            _ => panic!("wrong transaction type expected"),
+        }
+    }
+}
+
+impl AsRef<PartialDecryptionBatchTransaction> for SignedTransaction {
+    fn as_ref(&self) -> &PartialDecryptionBatchTransaction {
+        match self {
+            SignedTransaction::PartialDecryptionBatch(signed) => &signed.tx,
+            _ => panic!("wrong transaction type expected"),
+        }
+    }
+}
+
+impl AsRef<TrusteeRevocationTransaction> for SignedTransaction {
+    fn as_ref(&self) -> &TrusteeRevocationTransaction {
+        match self {
+            SignedTransaction::TrusteeRevocation(signed) => &signed.tx,
+            _ => panic!("wrong transaction type expected"),
+        }
+    }
+}
+
+impl AsRef<TrusteeReshareTransaction> for SignedTransaction {
+    fn as_ref(&self) -> &TrusteeReshareTransaction {
+        match self {
+            SignedTransaction::TrusteeReshare(signed) => &signed.tx,
+            _ => panic!("wrong transaction type expected"),
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
 
     use super::*;
+    use proptest::prelude::*;
     use rand::Rng;
 
     #[test]
@@ -928,6 +1531,12 @@ mod test {
         assert!(TransactionType::Mix as u8 == 8);
         assert!(TransactionType::PartialDecryption as u8 == 9);
         assert!(TransactionType::Decryption as u8 == 10);
+        assert!(TransactionType::ObserverAcknowledgment as u8 == 11);
+        assert!(TransactionType::TrusteeKeyRevocation as u8 == 12);
+        assert!(TransactionType::Challenge as u8 == 13);
+        assert!(TransactionType::PartialDecryptionBatch as u8 == 14);
+        assert!(TransactionType::TrusteeRevocation as u8 == 15);
+        assert!(TransactionType::TrusteeReshare as u8 == 16);
 
         let mut rng = rand::thread_rng();
         let election_id = ElectionTransaction::build_id(rng.gen());
@@ -939,4 +1548,414 @@ mod test {
 
         assert_eq!(election_id, from_string);
     }
+
+    #[test]
+    fn test_transaction_type_discriminants() {
+        let types = [
+            (TransactionType::Election, 1),
+            (TransactionType::KeyGenCommitment, 2),
+            (TransactionType::KeyGenShare, 3),
+            (TransactionType::KeyGenPublicKey, 4),
+            (TransactionType::EncryptionKey, 5),
+            (TransactionType::Vote, 6),
+            (TransactionType::VotingEnd, 7),
+            (TransactionType::Mix, 8),
+            (TransactionType::PartialDecryption, 9),
+            (TransactionType::Decryption, 10),
+            (TransactionType::ObserverAcknowledgment, 11),
+            (TransactionType::TrusteeKeyRevocation, 12),
+            (TransactionType::Challenge, 13),
+            (TransactionType::PartialDecryptionBatch, 14),
+            (TransactionType::TrusteeRevocation, 15),
+            (TransactionType::TrusteeReshare, 16),
+        ];
+
+        for (tx_type, value) in types.iter() {
+            assert_eq!(*tx_type as u8, *value);
+            assert_eq!(u8::from(*tx_type), *value);
+            assert_eq!(
+                TransactionType::try_from_primitive(*value).unwrap(),
+                *tx_type
+            );
+            assert_eq!(tx_type.hex_string(), format!("{:02x}", value));
+
+            let id = Identifier::new(ElectionTransaction::build_id([7; 15]), *tx_type, None);
+            assert_eq!(id.to_bytes()[15], *value);
+        }
+
+        // Unassigned values are rejected
+        assert!(TransactionType::try_from_primitive(0).is_err());
+        assert!(TransactionType::try_from_primitive(17).is_err());
+    }
+
+    #[test]
+    fn test_identifier_constant_time_eq() {
+        let election_id = ElectionTransaction::build_id([7; 15]);
+        let id = Identifier::new(election_id, TransactionType::Vote, Some([9; 16]));
+
+        let mut other = id;
+        assert!(bool::from(id.ct_eq(&other)));
+        assert_eq!(id, other);
+
+        other.unique_info[15] ^= 1;
+        assert!(!bool::from(id.ct_eq(&other)));
+        assert_ne!(id, other);
+
+        let other = Identifier::new(election_id, TransactionType::Decryption, Some([9; 16]));
+        assert_ne!(id, other);
+    }
+
+    #[test]
+    fn test_signed_message_bytes() {
+        use ed25519_dalek::Verifier;
+
+        let (secret, public) = generate_keypair();
+        let election = ElectionTransaction::new(public);
+        let signed = Signed::sign(&secret, election).unwrap();
+
+        let message = signed.signed_message_bytes();
+        assert!(public.verify(&message, &signed.sig).is_ok());
+
+        // Any other bytes should not verify
+        let mut tampered = message.clone();
+        tampered.push(0);
+        assert!(public.verify(&tampered, &signed.sig).is_err());
+    }
+
+    #[test]
+    fn test_verify_against_bytes() {
+        let (secret, public) = generate_keypair();
+        let election = ElectionTransaction::new(public);
+        let signed = Signed::sign(&secret, election).unwrap();
+        let packed = signed.signed_message_bytes();
+        signed.verify_against_bytes(&packed).unwrap();
+
+        // A single changed byte is caught
+        let mut tampered = packed.clone();
+        let last = tampered.len() - 1;
+        tampered[last] ^= 1;
+        assert!(matches!(
+            signed.verify_against_bytes(&tampered),
+            Err(ValidationError::SignedBytesMismatch)
+        ));
+
+        // As is a field changed after signing
+        let mut mutated = signed.clone();
+        mutated.tx.is_production = true;
+        assert!(matches!(
+            mutated.verify_against_bytes(&packed),
+            Err(ValidationError::SignedBytesMismatch)
+        ));
+    }
+
+    fn any_transaction_type() -> impl Strategy<Value = TransactionType> {
+        (1u8..=TransactionType::MAX as u8)
+            .prop_map(|value| TransactionType::try_from_primitive(value).unwrap())
+    }
+
+    proptest! {
+        #[test]
+        fn identifier_string_roundtrip(
+            election_id in any::<[u8; 15]>(),
+            transaction_type in any_transaction_type(),
+            unique_info in any::<[u8; 16]>(),
+        ) {
+            let id = Identifier {
+                election_id,
+                transaction_type,
+                unique_info,
+            };
+
+            let stringed = id.to_string();
+            prop_assert_eq!(stringed.len(), 64);
+            prop_assert_eq!(Identifier::from_str(&stringed).unwrap(), id);
+        }
+
+        #[test]
+        fn identifier_wrong_length(bytes in prop::collection::vec(any::<u8>(), 0..64)) {
+            prop_assume!(bytes.len() != 32);
+
+            let result = Identifier::from_str(&hex::encode(&bytes));
+            prop_assert!(matches!(result, Err(Error::IdentifierBadLen)));
+        }
+
+        #[test]
+        fn identifier_unknown_type(bytes in any::<[u8; 32]>()) {
+            prop_assume!(TransactionType::try_from_primitive(bytes[15]).is_err());
+
+            let result = Identifier::from_str(&hex::encode(&bytes));
+            prop_assert!(matches!(result, Err(Error::IdentifierBadType(t)) if t == bytes[15]));
+
+            // Deserializing it, as when unpacking a transaction, is an error rather than a panic
+            let json = format!("\"{}\"", hex::encode(&bytes));
+            prop_assert!(serde_json::from_str::<Identifier>(&json).is_err());
+        }
+
+        // Unpacking arbitrary bytes must return an error, never panic
+        #[test]
+        fn unpack_arbitrary_bytes(bytes in prop::collection::vec(any::<u8>(), 0..2048)) {
+            let _ = SignedTransaction::from_bytes(&bytes);
+            let _ = SignedTransaction::from_bytes_with(Encoding::MessagePack, &bytes);
+        }
+
+        #[test]
+        fn unpack_damaged_transaction(
+            cut in any::<prop::sample::Index>(),
+            flip in any::<prop::sample::Index>(),
+            xor in 1u8..=255,
+        ) {
+            let (secret, public) = generate_keypair();
+            let election = Signed::sign(&secret, ElectionTransaction::new(public)).unwrap();
+            let packed = SignedTransaction::from(election).as_bytes();
+
+            // A truncated transaction never unpacks
+            let truncated = &packed[..cut.index(packed.len())];
+            prop_assert!(SignedTransaction::from_bytes(truncated).is_err());
+
+            // A corrupted one might, but must not panic
+            let mut corrupted = packed.clone();
+            corrupted[flip.index(packed.len())] ^= xor;
+            let _ = SignedTransaction::from_bytes(&corrupted);
+        }
+    }
+
+    #[test]
+    fn test_strip_signature() {
+        let (authority_secret, authority_public) = generate_keypair();
+        let signed = Signed::sign(
+            &authority_secret,
+            ElectionTransaction::new(authority_public),
+        )
+        .unwrap();
+        let election_id = signed.id();
+
+        let draft = signed.clone().strip_signature();
+        let json = draft.to_json();
+
+        // A draft is not a signed transaction
+        assert!(serde_json::from_str::<SignedTransaction>(&json).is_err());
+
+        let draft = UnsignedTransaction::<ElectionTransaction>::from_json(&json).unwrap();
+        assert_eq!(draft.id(), election_id);
+
+        let (other_secret, _other_public) = generate_keypair();
+        assert!(draft.clone().sign(&other_secret).is_err());
+
+        let election = draft.sign(&authority_secret).unwrap();
+        election.verify_signature().unwrap();
+        assert_eq!(election.sig, signed.sig);
+    }
+
+    // A signed transaction of the given type, taken from the recorded test election if it has one
+    fn sample_transaction(tx_type: TransactionType) -> SignedTransaction {
+        let txs = crate::tests::test_election_2();
+        let recorded = |tx_type: TransactionType| {
+            txs.iter()
+                .find(|tx| tx.transaction_type() == tx_type)
+                .unwrap()
+                .clone()
+        };
+        let election: ElectionTransaction = recorded(TransactionType::Election).into();
+        let vote: VoteTransaction = recorded(TransactionType::Vote).into();
+        let (secret, public) = generate_keypair();
+
+        match tx_type {
+            TransactionType::Election
+            | TransactionType::KeyGenCommitment
+            | TransactionType::KeyGenShare
+            | TransactionType::KeyGenPublicKey
+            | TransactionType::EncryptionKey
+            | TransactionType::Vote
+            | TransactionType::VotingEnd
+            | TransactionType::PartialDecryption
+            | TransactionType::Decryption => recorded(tx_type),
+            TransactionType::Mix => {
+                let encryption_key: EncryptionKeyTransaction =
+                    recorded(TransactionType::EncryptionKey).into();
+                let (trustee, trustee_secret) = Trustee::new(1, 1, 1);
+                let ciphertexts = vec![vote.encrypted_votes[0].selections.clone(); 2];
+                let mix = MixTransactionBuilder::new(election.id, ciphertexts, 0)
+                    .with_vote_ids(vec![vote.id; 2])
+                    .build(
+                        &mut rand::thread_rng(),
+                        &trustee,
+                        &encryption_key.encryption_key,
+                    )
+                    .unwrap();
+                Signed::sign(&trustee_secret, mix).unwrap().into()
+            }
+            TransactionType::ObserverAcknowledgment => {
+                let observer = Observer {
+                    id: uuid::Uuid::new_v4(),
+                    name: "Observer".to_string(),
+                    public_key: public,
+                };
+                let ack = ObserverAcknowledgmentTransaction::new(election.id, &observer);
+                Signed::sign(&secret, ack).unwrap().into()
+            }
+            TransactionType::TrusteeKeyRevocation => {
+                let public_key: KeyGenPublicKeyTransaction =
+                    recorded(TransactionType::KeyGenPublicKey).into();
+                let mut revocation =
+                    TrusteeKeyRevocationTransaction::new(&public_key, Some("Lost".to_string()));
+                revocation.trustee_public_key = public;
+                Signed::sign(&secret, revocation).unwrap().into()
+            }
+            TransactionType::Challenge => {
+                let mut challenge = ChallengeTransaction::new(&vote, vec![], [7; 32]);
+                challenge.anonymous_key = public;
+                Signed::sign(&secret, challenge).unwrap().into()
+            }
+            TransactionType::PartialDecryptionBatch => {
+                let partial: PartialDecryptionTransaction =
+                    recorded(TransactionType::PartialDecryption).into();
+                let batched = BatchedPartialDecryption {
+                    upstream_id: partial.upstream_id,
+                    upstream_index: partial.upstream_index,
+                    contest_index: partial.contest_index,
+                    partial_decryption: partial.partial_decryption,
+                };
+                let batch = PartialDecryptionBatchTransaction::new(
+                    election.id,
+                    partial.trustee_index,
+                    public,
+                    0,
+                    vec![batched],
+                );
+                Signed::sign(&secret, batch).unwrap().into()
+            }
+            TransactionType::TrusteeRevocation => {
+                let mut election = election;
+                election.authority_public = public;
+                let revocation = TrusteeRevocationTransaction::new(&election, 1, None);
+                Signed::sign(&secret, revocation).unwrap().into()
+            }
+            TransactionType::TrusteeReshare => {
+                let (trustee, trustee_secret) = Trustee::new(1, 3, 2);
+                let x25519_public_keys: Vec<_> = (1..=2)
+                    .map(|index| {
+                        (
+                            index,
+                            trustee.x25519_public_key(&trustee_secret, election.id),
+                        )
+                    })
+                    .collect();
+                let (commitment, shares) = trustee.generate_reshares(
+                    &mut rand::thread_rng(),
+                    &trustee_secret,
+                    &x25519_public_keys,
+                    election.id,
+                );
+                let reshare = TrusteeReshareTransaction::new(
+                    election.id,
+                    trustee.index,
+                    trustee.public_key,
+                    3,
+                    commitment,
+                    shares,
+                );
+                Signed::sign(&trustee_secret, reshare).unwrap().into()
+            }
+        }
+    }
+
+    // Pack and unpack a transaction of the given type with every encoding
+    fn assert_roundtrip(tx_type: TransactionType) {
+        let tx = sample_transaction(tx_type);
+        assert_eq!(tx.transaction_type(), tx_type);
+
+        for encoding in &[Encoding::Cbor, Encoding::MessagePack] {
+            let packed = tx.as_bytes_with(*encoding);
+            let unpacked = SignedTransaction::from_bytes_with(*encoding, &packed)
+                .unwrap_or_else(|e| panic!("{} with {:?}: {}", tx_type, encoding, e));
+            assert_eq!(unpacked.id(), tx.id(), "{} with {:?}", tx_type, encoding);
+            assert_eq!(
+                unpacked.as_bytes(),
+                tx.as_bytes(),
+                "{} with {:?}",
+                tx_type,
+                encoding
+            );
+        }
+    }
+
+    #[test]
+    fn test_roundtrip_election() {
+        assert_roundtrip(TransactionType::Election);
+    }
+
+    #[test]
+    fn test_roundtrip_keygen_commitment() {
+        assert_roundtrip(TransactionType::KeyGenCommitment);
+    }
+
+    #[test]
+    fn test_roundtrip_keygen_share() {
+        assert_roundtrip(TransactionType::KeyGenShare);
+    }
+
+    #[test]
+    fn test_roundtrip_keygen_public_key() {
+        assert_roundtrip(TransactionType::KeyGenPublicKey);
+    }
+
+    #[test]
+    fn test_roundtrip_encryption_key() {
+        assert_roundtrip(TransactionType::EncryptionKey);
+    }
+
+    #[test]
+    fn test_roundtrip_vote() {
+        assert_roundtrip(TransactionType::Vote);
+    }
+
+    #[test]
+    fn test_roundtrip_voting_end() {
+        assert_roundtrip(TransactionType::VotingEnd);
+    }
+
+    #[test]
+    fn test_roundtrip_mix() {
+        assert_roundtrip(TransactionType::Mix);
+    }
+
+    #[test]
+    fn test_roundtrip_partial_decryption() {
+        assert_roundtrip(TransactionType::PartialDecryption);
+    }
+
+    #[test]
+    fn test_roundtrip_decryption() {
+        assert_roundtrip(TransactionType::Decryption);
+    }
+
+    #[test]
+    fn test_roundtrip_observer_acknowledgment() {
+        assert_roundtrip(TransactionType::ObserverAcknowledgment);
+    }
+
+    #[test]
+    fn test_roundtrip_trustee_key_revocation() {
+        assert_roundtrip(TransactionType::TrusteeKeyRevocation);
+    }
+
+    #[test]
+    fn test_roundtrip_challenge() {
+        assert_roundtrip(TransactionType::Challenge);
+    }
+
+    #[test]
+    fn test_roundtrip_partial_decryption_batch() {
+        assert_roundtrip(TransactionType::PartialDecryptionBatch);
+    }
+
+    #[test]
+    fn test_roundtrip_trustee_revocation() {
+        assert_roundtrip(TransactionType::TrusteeRevocation);
+    }
+
+    #[test]
+    fn test_roundtrip_trustee_reshare() {
+        assert_roundtrip(TransactionType::TrusteeReshare);
+    }
 }
@@ -0,0 +1,271 @@
+use crate::*;
+use memmap2::Mmap;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::{PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// Magic bytes at the start of every transaction log
+pub const TRANSACTION_LOG_MAGIC: [u8; 7] = *b"CBTXLOG";
+
+/// Current version of the transaction log file format
+pub const TRANSACTION_LOG_VERSION: u8 = 1;
+
+const HEADER_LEN: usize = 8;
+const ENTRY_HEADER_LEN: usize = 8;
+
+/// A durable, append-only, on-disk transaction store.
+///
+/// The file starts with `TRANSACTION_LOG_MAGIC` followed by a version byte. Each entry is then a little-endian u32
+/// payload length, a little-endian u32 CRC32 checksum of the payload, and the payload itself: the CBOR-encoded
+/// `SignedTransaction`.
+///
+/// The log is memory-mapped and indexed when it is opened, after which reads are served from memory. New
+/// transactions are appended to the end of the file and synced to disk before they become visible.
+pub struct TransactionLog {
+    inner: RwLock<LogInner>,
+}
+
+struct LogInner {
+    file: File,
+    index: MemStore,
+}
+
+impl TransactionLog {
+    /// Open a transaction log, creating it if it does not exist.
+    ///
+    /// Fails with `LogError::Truncated` if the last entry was only partially written, in which case
+    /// `recover` can be used to discard it.
+    pub fn open(path: &Path) -> Result<Self, LogError> {
+        let (log, valid_len, file_len) = Self::load(path)?;
+        if valid_len != file_len {
+            return Err(LogError::Truncated(valid_len));
+        }
+        Ok(log)
+    }
+
+    /// Open a transaction log after a crash, discarding a partially written final entry.
+    ///
+    /// Returns the log along with the number of bytes discarded. Corruption anywhere other than in the final
+    /// entry is not the result of a crash, and is still an error.
+    pub fn recover(path: &Path) -> Result<(Self, usize), LogError> {
+        let (log, valid_len, file_len) = Self::load(path)?;
+        if valid_len != file_len {
+            let inner = log.read();
+            inner.file.set_len(valid_len)?;
+            inner.file.sync_all()?;
+        }
+        Ok((log, (file_len - valid_len) as usize))
+    }
+
+    /// Append a transaction without validating it
+    pub fn set(&mut self, tx: SignedTransaction) -> Result<(), LogError> {
+        let mut inner = self.write();
+        append(&mut inner.file, &tx)?;
+        inner.index.set(tx);
+        Ok(())
+    }
+
+    // Read every complete entry, returning the log, the length of the valid prefix of the file, and the file length
+    fn load(path: &Path) -> Result<(Self, u64, u64), LogError> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(path)?;
+
+        let file_len = file.metadata()?.len();
+        if file_len == 0 {
+            file.write_all(&TRANSACTION_LOG_MAGIC)?;
+            file.write_all(&[TRANSACTION_LOG_VERSION])?;
+            file.sync_all()?;
+            let log = Self::new(file, MemStore::default());
+            return Ok((log, HEADER_LEN as u64, HEADER_LEN as u64));
+        }
+
+        // Safety: the log is only ever appended to, so the mapped bytes are not modified while we read them
+        let map = unsafe { Mmap::map(&file)? };
+        if map.len() < HEADER_LEN || map[..TRANSACTION_LOG_MAGIC.len()] != TRANSACTION_LOG_MAGIC {
+            return Err(LogError::BadMagic);
+        }
+        let version = map[TRANSACTION_LOG_MAGIC.len()];
+        if version != TRANSACTION_LOG_VERSION {
+            return Err(LogError::UnsupportedVersion(version));
+        }
+
+        let mut index = MemStore::default();
+        let mut offset = HEADER_LEN;
+        while offset < map.len() {
+            let entry = &map[offset..];
+            if entry.len() < ENTRY_HEADER_LEN {
+                break;
+            }
+            let len = u32::from_le_bytes([entry[0], entry[1], entry[2], entry[3]]) as usize;
+            let checksum = u32::from_le_bytes([entry[4], entry[5], entry[6], entry[7]]);
+            let end = ENTRY_HEADER_LEN + len;
+            if entry.len() < end {
+                // A length running past the end of the file is a torn write only if this is the final entry
+                if is_torn_payload(&entry[ENTRY_HEADER_LEN..]) {
+                    break;
+                }
+                return Err(LogError::Corrupt(offset as u64));
+            }
+
+            let payload = &entry[ENTRY_HEADER_LEN..end];
+            if crc32fast::hash(payload) != checksum {
+                // A bad checksum on the final entry is a torn write, anywhere else it is corruption
+                if entry.len() == end {
+                    break;
+                }
+                return Err(LogError::Corrupt(offset as u64));
+            }
+            let tx: SignedTransaction = serde_cbor::from_slice(payload)?;
+            index.set(tx);
+            offset += end;
+        }
+        drop(map);
+
+        Ok((Self::new(file, index), offset as u64, file_len))
+    }
+
+    fn new(file: File, index: MemStore) -> Self {
+        TransactionLog {
+            inner: RwLock::new(LogInner { file, index }),
+        }
+    }
+
+    fn read(&self) -> RwLockReadGuard<LogInner> {
+        self.inner.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn write(&self) -> RwLockWriteGuard<LogInner> {
+        self.inner.write().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Store for TransactionLog {
+    fn get_transaction(&self, id: Identifier) -> Option<SignedTransaction> {
+        self.read().index.get_transaction(id)
+    }
+
+    fn contains(&self, id: Identifier) -> bool {
+        self.read().index.contains(id)
+    }
+
+    fn range(&self, start: Identifier, end_inclusive: Identifier) -> Vec<SignedTransaction> {
+        self.read().index.range(start, end_inclusive)
+    }
+
+    fn scan_range(&self, start: Identifier, end: Identifier) -> Vec<SignedTransaction> {
+        self.read().index.scan_range(start, end)
+    }
+
+    fn list_elections(&self) -> Vec<Identifier> {
+        self.read().index.list_elections()
+    }
+
+    fn apply_transaction(&self, tx: SignedTransaction) -> Result<(), ApplyError> {
+        let mut inner = self.write();
+
+        // Validate while holding the lock, and only index the transaction once it is on disk
+        tx.validate(&inner.index)?;
+        append(&mut inner.file, &tx).map_err(StoreError::from)?;
+        inner.index.set(tx);
+        Ok(())
+    }
+}
+
+fn append(file: &mut File, tx: &SignedTransaction) -> Result<(), LogError> {
+    let payload = serde_cbor::to_vec(tx)?;
+
+    let mut entry = Vec::with_capacity(ENTRY_HEADER_LEN + payload.len());
+    entry.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    entry.extend_from_slice(&crc32fast::hash(&payload).to_le_bytes());
+    entry.extend_from_slice(&payload);
+
+    // Roll back a partial write, so that later entries are not appended after a torn one
+    let len = file.metadata()?.len();
+    if let Err(e) = file.write_all(&entry).and_then(|_| file.sync_data()) {
+        let _ = file.set_len(len);
+        return Err(e.into());
+    }
+    Ok(())
+}
+
+// Whether `payload` could be the start of a single partially written payload, rather than a whole payload followed
+// by more entries, which means the entry's length was corrupted
+fn is_torn_payload(payload: &[u8]) -> bool {
+    let mut deserializer = serde_cbor::Deserializer::from_slice(payload);
+    let value: Result<serde_cbor::Value, _> = serde::Deserialize::deserialize(&mut deserializer);
+    match value {
+        Ok(_) => deserializer.byte_offset() == payload.len(),
+        Err(e) => e.is_eof(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transaction_log() {
+        let txs = crate::tests::test_election_2();
+        let path =
+            std::env::temp_dir().join(format!("cryptoballot_test_log_{}", uuid::Uuid::new_v4()));
+
+        // Transactions are validated on the way in and survive a reopen
+        let log = TransactionLog::open(&path).unwrap();
+        for tx in txs.iter().cloned() {
+            log.apply_transaction(tx).unwrap();
+        }
+        drop(log);
+
+        let log = TransactionLog::open(&path).unwrap();
+        let all = log.range(Identifier::first(), Identifier::last());
+        assert_eq!(all.len(), txs.len());
+        assert!(log.validate_all().is_empty());
+        drop(log);
+
+        // A torn final entry is rejected by open, and discarded by recover
+        let clean_len = std::fs::metadata(&path).unwrap().len();
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap();
+        std::io::Write::write_all(&mut file, &[200, 0, 0, 0, 1, 2, 3, 4, 5]).unwrap();
+        drop(file);
+
+        assert!(matches!(
+            TransactionLog::open(&path),
+            Err(LogError::Truncated(len)) if len == clean_len
+        ));
+        let (log, discarded) = TransactionLog::recover(&path).unwrap();
+        assert_eq!(discarded, 9);
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), clean_len);
+        assert_eq!(
+            log.range(Identifier::first(), Identifier::last()).len(),
+            txs.len()
+        );
+        drop(log);
+
+        // Corruption before the final entry is not recoverable, even if it makes the entry look torn
+        let bytes = std::fs::read(&path).unwrap();
+        let mut long_entry = bytes.clone();
+        long_entry[TRANSACTION_LOG_MAGIC.len() + 1..][..4].copy_from_slice(&u32::MAX.to_le_bytes());
+        std::fs::write(&path, &long_entry).unwrap();
+        assert!(matches!(
+            TransactionLog::recover(&path),
+            Err(LogError::Corrupt(8))
+        ));
+
+        let mut bytes = bytes;
+        bytes[TRANSACTION_LOG_MAGIC.len() + 1 + 8] ^= 0xff;
+        std::fs::write(&path, &bytes).unwrap();
+        assert!(matches!(
+            TransactionLog::recover(&path),
+            Err(LogError::Corrupt(8))
+        ));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,11 +1,13 @@
 use crate::*;
 use core::iter::FromIterator;
+use cryptid::curve::CurveElem;
 use cryptid::elgamal::Ciphertext;
 use cryptid::elgamal::PublicKey as ElGamalPublicKey;
 use cryptid::threshold::DecryptShare;
 use cryptid::threshold::PubkeyProof;
 use cryptid::threshold::{KeygenCommitment, Threshold, ThresholdGenerator, ThresholdParty};
 use cryptid::Scalar;
+use curve25519_dalek::scalar::Scalar as DalekScalar;
 use ed25519_dalek::PublicKey;
 use ed25519_dalek::SecretKey;
 use hex::{FromHex, ToHex};
@@ -20,7 +22,9 @@ use serde::{
 };
 use sha2::Sha256;
 use std::convert::TryFrom;
+use uuid::Uuid;
 use x25519_dalek as x25519;
+use zeroize::{Zeroize, ZeroizeOnDrop};
 
 const ENCRYPT_NONCE_SIZE: usize = 12;
 
@@ -30,6 +34,13 @@ const ENCRYPT_NONCE_SIZE: usize = 12;
 /// the total number of trustees. Any quorum of trustees may decrypt the votes.
 #[derive(Serialize, Deserialize, Clone, Default)]
 pub struct Trustee {
+    /// Stable identifier for the trustee, independent of their index in any one election.
+    ///
+    /// Trustees in elections created before trustees had IDs have the nil UUID.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Uuid::is_nil")]
+    pub id: Uuid,
+
     #[serde(with = "EdPublicKeyHex")]
     pub public_key: PublicKey,
     pub index: u8,
@@ -65,6 +76,8 @@ impl Trustee {
     }
 
     /// Create a new trustee
+    ///
+    /// The returned secret key is zeroed from memory when dropped.
     pub fn new(index: u8, num_trustees: usize, threshold: u8) -> (Self, SecretKey) {
         if index == 0 {
             panic!("Trustee index cannot be zero");
@@ -73,6 +86,7 @@ impl Trustee {
         let (secret, public_key) = generate_keypair();
 
         let trustee = Trustee {
+            id: Uuid::new_v4(),
             index: index,
             public_key,
             num_trustees,
@@ -81,6 +95,30 @@ impl Trustee {
         (trustee, secret)
     }
 
+    /// Create a trustee from a public key that the trustee generated themselves.
+    ///
+    /// In a distributed deployment each trustee generates their own keypair and only shares the public key with the
+    /// election authority. `num_trustees` and `threshold` are filled in from the election (see `from_election_tx`).
+    /// If `index` is zero, one is assigned when the trustee is added to an election with `add_trustee`.
+    pub fn from_public_key(id: Uuid, index: u8, public_key: PublicKey) -> Self {
+        Trustee {
+            id,
+            public_key,
+            index,
+            num_trustees: 0,
+            threshold: 0,
+        }
+    }
+
+    /// Register a new trustee, generating their keypair.
+    ///
+    /// The trustee's index is assigned when they are added to an election with `add_trustee`. The returned secret key
+    /// is zeroed from memory when dropped.
+    pub fn register(id: Uuid) -> (Self, SecretKey) {
+        let (secret, public_key) = generate_keypair();
+        (Self::from_public_key(id, 0, public_key), secret)
+    }
+
     pub fn keygen_commitment(&self, sk: &SecretKey, election_id: Identifier) -> KeygenCommitment {
         self.generator(sk, election_id).get_commitment()
     }
@@ -103,14 +141,15 @@ impl Trustee {
 
         let mut shares = IndexMap::with_capacity(commitments.len());
         for (index, public_key) in x25519_public_keys {
-            let share = theshold_generator
+            let mut share = theshold_generator
                 .get_polynomial_share(*index as usize)
                 .unwrap();
 
             let shared_secret = self.shared_secret(sk, election_id, public_key);
 
             // Encrypt the share with the public key such that only the holder of the secret key can decrypt.
-            let encrypted = EncryptedShare::new(rng, shared_secret, &share);
+            let encrypted = EncryptedShare::new(rng, &shared_secret.0, &share);
+            share.zeroize();
 
             shares.insert(*index, encrypted);
         }
@@ -118,14 +157,18 @@ impl Trustee {
         shares
     }
 
-    fn decrypt_shares(
+    /// Decrypt the key-generation shares sent to this trustee, paired with the index of the trustee who sent each.
+    ///
+    /// The decrypted shares are secret: with `sk` and the commitments, they make up this trustee's share of the
+    /// election key. They are zeroed from memory when dropped.
+    pub fn decrypt_key_shares(
         &self,
         sk: &SecretKey,
         shares: &[(u8, EncryptedShare)],
         x25519_public_keys: &[(u8, x25519::PublicKey)],
         election_id: Identifier,
-    ) -> Result<Vec<(u8, Scalar)>, ValidationError> {
-        let mut decrypted_shared = Vec::<(u8, Scalar)>::with_capacity(shares.len());
+    ) -> Result<Vec<DecryptedShare>, ValidationError> {
+        let mut decrypted_shared = Vec::<DecryptedShare>::with_capacity(shares.len());
         for (sender_index, share) in shares {
             let mut public_key = None;
             for (index, x25519_public_key) in x25519_public_keys {
@@ -136,9 +179,12 @@ impl Trustee {
             let public_key = public_key.ok_or(ValidationError::TrusteeMissing(*sender_index))?;
 
             let shared_secret = self.shared_secret(sk, election_id, public_key);
-            let decrypted = share.decrypt(shared_secret)?;
+            let decrypted = share.decrypt(&shared_secret.0)?;
 
-            decrypted_shared.push((*sender_index, decrypted));
+            decrypted_shared.push(DecryptedShare {
+                sender_index: *sender_index,
+                share: decrypted,
+            });
         }
 
         Ok(decrypted_shared)
@@ -151,7 +197,10 @@ impl Trustee {
         h.expand(b"cryptoballot_trustee_x25519_secret_key", &mut secret)
             .unwrap();
 
-        x25519::StaticSecret::from(secret)
+        let x25519_secret = x25519::StaticSecret::from(secret);
+        secret.zeroize();
+
+        x25519_secret
     }
 
     pub fn x25519_public_key(&self, sk: &SecretKey, election_id: Identifier) -> x25519::PublicKey {
@@ -165,19 +214,39 @@ impl Trustee {
         sk: &SecretKey,
         election_id: Identifier,
         sender: &x25519::PublicKey,
-    ) -> [u8; 32] {
+    ) -> SharedSecret {
         let x25519_secret_key = self.x25519_secret_key(sk, election_id);
         let shared_secret = x25519_secret_key.diffie_hellman(sender);
 
         // Generate a HKDF, using the election-id as the salt
         let h = Hkdf::<Sha256>::new(Some(&election_id.to_bytes()), shared_secret.as_bytes());
-        let mut shared_bytes = [0u8; 32]; // 256 bits of security
-        h.expand(b"cryptoballot_trustee_shared_secret", &mut shared_bytes)
+        let mut shared_bytes = SharedSecret([0u8; 32]); // 256 bits of security
+        h.expand(b"cryptoballot_trustee_shared_secret", &mut shared_bytes.0)
             .unwrap();
 
         shared_bytes
     }
 
+    /// Check the key-generation shares sent to this trustee against the commitments their senders published.
+    ///
+    /// Shares are encrypted to their recipient, so this can't be checked when a KeyGenShare transaction is posted.
+    /// Each trustee should instead check the shares sent to it before posting its KeyGenPublicKey transaction,
+    /// and publicly accuse a sender whose share fails with `SecretShareCommitmentMismatch`.
+    pub fn verify_key_shares(
+        &self,
+        sk: &SecretKey,
+        x25519_public_keys: &[(u8, x25519::PublicKey)],
+        commitments: &[(u8, KeygenCommitment)],
+        shares: &[(u8, EncryptedShare)],
+        election_id: Identifier,
+    ) -> Result<(), ValidationError> {
+        let decryped_shares =
+            self.decrypt_key_shares(sk, shares, x25519_public_keys, election_id)?;
+        self.receive_shares(sk, commitments, &decryped_shares, election_id)?;
+
+        Ok(())
+    }
+
     pub fn generate_public_key(
         &self,
         sk: &SecretKey,
@@ -186,11 +255,147 @@ impl Trustee {
         shares: &[(u8, EncryptedShare)], // From, Share
         election_id: Identifier,
     ) -> Result<(ElGamalPublicKey, PubkeyProof), ValidationError> {
-        let decryped_shares = self.decrypt_shares(sk, shares, x25519_public_keys, election_id)?;
-        let party = self.generate_party(sk, &commitments, &decryped_shares, election_id);
+        let decryped_shares =
+            self.decrypt_key_shares(sk, shares, x25519_public_keys, election_id)?;
+        let party = self.generate_party(sk, &commitments, &decryped_shares, election_id)?;
+
         Ok((party.pubkey(), party.pubkey_proof()))
     }
 
+    /// This trustee's secret share of the election key, for a trusted dealer to use with `decrypt_with_combined_key`
+    pub fn secret_share(
+        &self,
+        sk: &SecretKey,
+        x25519_public_keys: &[(u8, x25519::PublicKey)],
+        commitments: &[(u8, KeygenCommitment)],
+        shares: &[(u8, EncryptedShare)],
+        election_id: Identifier,
+    ) -> Result<ThresholdParty, ValidationError> {
+        let decryped_shares =
+            self.decrypt_key_shares(sk, shares, x25519_public_keys, election_id)?;
+        self.generate_party(sk, &commitments, &decryped_shares, election_id)
+    }
+
+    /// Re-share a revoked trustee's key share, for a TrusteeReshare transaction.
+    ///
+    /// Picks a random polynomial of degree `threshold - 1` with a zero constant term, and returns the commitments to
+    /// its other coefficients along with its value at the index of each trustee in `x25519_public_keys`, encrypted to
+    /// that trustee. These should be the keys of every trustee that hasn't been revoked, including this one.
+    pub fn generate_reshares<R: Rng + CryptoRng>(
+        &self,
+        rng: &mut R,
+        sk: &SecretKey,
+        x25519_public_keys: &[(u8, x25519::PublicKey)],
+        election_id: Identifier,
+    ) -> (Vec<CurveElem>, IndexMap<u8, EncryptedShare>) {
+        let mut coefficients = Vec::with_capacity(self.threshold.saturating_sub(1) as usize);
+        for _ in 1..self.threshold {
+            coefficients.push(DalekScalar::random(rng));
+        }
+        let commitment = coefficients
+            .iter()
+            .map(|coefficient| CurveElem::generator().scaled(&to_cryptid_scalar(coefficient)))
+            .collect();
+
+        let mut shares = IndexMap::with_capacity(x25519_public_keys.len());
+        for (index, public_key) in x25519_public_keys {
+            let mut value = evaluate_reshare(&coefficients, *index);
+            let mut share = to_cryptid_scalar(&value);
+            value.zeroize();
+
+            let shared_secret = self.shared_secret(sk, election_id, public_key);
+            let encrypted = EncryptedShare::new(rng, &shared_secret.0, &share);
+            share.zeroize();
+
+            shares.insert(*index, encrypted);
+        }
+        coefficients.zeroize();
+
+        (commitment, shares)
+    }
+
+    /// Refresh this trustee's share of the election key with the TrusteeReshare transactions posted in the election.
+    ///
+    /// Each re-share sent to this trustee is checked against its commitments, and added to the keygen share this
+    /// trustee holds from the revoked trustee it re-shares. The revoked trustee's commitment is adjusted to match, so
+    /// the returned commitments and shares can be passed to `partial_decrypt` and `secret_share` in place of the
+    /// originals. As with keygen shares, a trustee whose re-share fails the check with `SecretShareCommitmentMismatch`
+    /// should be publicly accused.
+    pub fn apply_reshares<R: Rng + CryptoRng>(
+        &self,
+        rng: &mut R,
+        sk: &SecretKey,
+        x25519_public_keys: &[(u8, x25519::PublicKey)],
+        commitments: &[(u8, KeygenCommitment)],
+        shares: &[(u8, EncryptedShare)],
+        reshares: &[TrusteeReshareTransaction],
+        election_id: Identifier,
+    ) -> Result<(Vec<(u8, KeygenCommitment)>, Vec<(u8, EncryptedShare)>), ValidationError> {
+        let x25519_public_key = |index: u8| {
+            x25519_public_keys
+                .iter()
+                .find(|(trustee_index, _)| *trustee_index == index)
+                .map(|(_, public_key)| public_key)
+                .ok_or(ValidationError::TrusteeMissing(index))
+        };
+
+        let mut commitments = commitments.to_vec();
+        let mut shares = shares.to_vec();
+        for (revoked_index, commitment) in commitments.iter_mut() {
+            let revoked_index = *revoked_index;
+            let revoked_reshares: Vec<&TrusteeReshareTransaction> = reshares
+                .iter()
+                .filter(|reshare| reshare.revoked_index == revoked_index)
+                .collect();
+            if revoked_reshares.is_empty() {
+                continue;
+            }
+
+            let position = shares
+                .iter()
+                .position(|(sender_index, _)| *sender_index == revoked_index)
+                .ok_or(ValidationError::TrusteeShareMissing(revoked_index))?;
+            let shared_secret =
+                self.shared_secret(sk, election_id, x25519_public_key(revoked_index)?);
+            let mut original = shares[position].1.decrypt(&shared_secret.0)?;
+            let mut share = to_dalek_scalar(&original);
+            original.zeroize();
+
+            for reshare in revoked_reshares.iter() {
+                let encrypted = reshare
+                    .shares
+                    .get(&self.index)
+                    .ok_or(ValidationError::TrusteeShareMissing(self.index))?;
+                let dealer_secret =
+                    self.shared_secret(sk, election_id, x25519_public_key(reshare.trustee_index)?);
+                let mut value = encrypted.decrypt(&dealer_secret.0)?;
+                let valid =
+                    CurveElem::generator().scaled(&value) == reshare.public_share(self.index);
+                let mut delta = to_dalek_scalar(&value);
+                value.zeroize();
+
+                if !valid {
+                    delta.zeroize();
+                    share.zeroize();
+                    return Err(ValidationError::SecretShareCommitmentMismatch(
+                        reshare.trustee_index,
+                    ));
+                }
+                share += delta;
+                delta.zeroize();
+            }
+
+            let mut refreshed = to_cryptid_scalar(&share);
+            share.zeroize();
+            shares[position].1 = EncryptedShare::new(rng, &shared_secret.0, &refreshed);
+            refreshed.zeroize();
+
+            *commitment = refreshed_commitment(revoked_index, commitment, &revoked_reshares)?;
+        }
+
+        Ok((commitments, shares))
+    }
+
     pub fn partial_decrypt<R: Rng + CryptoRng>(
         &self,
         rng: &mut R,
@@ -201,10 +406,11 @@ impl Trustee {
         encrypted_vote: &Ciphertext,
         election_id: Identifier,
     ) -> Result<DecryptShare, ValidationError> {
-        let decryped_shares = self.decrypt_shares(sk, shares, x25519_public_keys, election_id)?;
-        let party = self.generate_party(sk, &commitments, &decryped_shares, election_id);
+        let decryped_shares =
+            self.decrypt_key_shares(sk, shares, x25519_public_keys, election_id)?;
+        let party = self.generate_party(sk, &commitments, &decryped_shares, election_id)?;
 
-        Ok(party.decrypt_share(encrypted_vote, rng))
+        Ok(ElGamal::partial_decrypt(&party, encrypted_vote, rng))
     }
 
     // Generate a cryptid generator derived from the secret-key
@@ -216,6 +422,7 @@ impl Trustee {
             .unwrap();
 
         let mut rng = ChaCha20Rng::from_seed(seed);
+        seed.zeroize();
 
         ThresholdGenerator::new(
             &mut rng,
@@ -229,9 +436,25 @@ impl Trustee {
         &self,
         sk: &SecretKey,
         commitments: &[(u8, KeygenCommitment)],
-        shares: &[(u8, Scalar)],
+        shares: &[DecryptedShare],
+        election_id: Identifier,
+    ) -> Result<ThresholdParty, ValidationError> {
+        let theshold_generator = self.receive_shares(sk, commitments, shares, election_id)?;
+
+        Ok(theshold_generator.finish().unwrap())
+    }
+
+    // Feed the commitments and decrypted shares into this trustee's generator.
+    //
+    // The generator runs the Feldman VSS check on each share, that g^share is the sender's committed polynomial
+    // evaluated in the exponent at this trustee's index: g^share == Π C_k^(index^k)
+    fn receive_shares(
+        &self,
+        sk: &SecretKey,
+        commitments: &[(u8, KeygenCommitment)],
+        shares: &[DecryptedShare],
         election_id: Identifier,
-    ) -> ThresholdParty {
+    ) -> Result<ThresholdGenerator, ValidationError> {
         let mut theshold_generator = self.generator(sk, election_id);
 
         for (index, commitment) in commitments {
@@ -240,29 +463,66 @@ impl Trustee {
                 .expect("Invalid commitment") // TODO Result
         }
 
-        for (index, share) in shares {
+        for share in shares {
             theshold_generator
-                .receive_share(*index as usize, &share)
-                .expect("Invalid share") // TODO Result
+                .receive_share(share.sender_index as usize, &share.share)
+                .map_err(|_| ValidationError::SecretShareCommitmentMismatch(share.sender_index))?;
         }
 
-        theshold_generator.finish().unwrap()
+        Ok(theshold_generator)
     }
 }
 
+// Evaluate a re-sharing polynomial, given its coefficients from the first-degree coefficient up, at a trustee's index
+fn evaluate_reshare(coefficients: &[DalekScalar], index: u8) -> DalekScalar {
+    let x = DalekScalar::from(index as u64);
+    let mut power = DalekScalar::one();
+
+    let mut value = DalekScalar::zero();
+    for coefficient in coefficients {
+        power *= x;
+        value += coefficient * power;
+    }
+    value
+}
+
+// cryptid scalars are always reduced, so they convert to dalek scalars byte for byte
+fn to_dalek_scalar(scalar: &Scalar) -> DalekScalar {
+    let mut bytes = [0u8; 32];
+    bytes.copy_from_slice(&scalar.as_bytes()[..]);
+    let converted = DalekScalar::from_bytes_mod_order(bytes);
+    bytes.zeroize();
+
+    converted
+}
+
+/// A key-generation share sent to a trustee, after the trustee has decrypted it.
+///
+/// Decrypted shares are secret, so they are zeroed from memory when dropped.
+#[derive(Zeroize, ZeroizeOnDrop)]
+pub struct DecryptedShare {
+    /// The index of the trustee who sent the share
+    pub sender_index: u8,
+    pub share: Scalar,
+}
+
+// The AES key shared between two trustees for encrypting key-generation shares, zeroed from memory when dropped
+#[derive(Zeroize, ZeroizeOnDrop)]
+struct SharedSecret([u8; 32]);
+
 #[derive(Clone, Debug)]
 pub struct EncryptedShare(Vec<u8>);
 
 impl EncryptedShare {
     pub fn new<R: Rng + CryptoRng>(
         rng: &mut R,
-        shared_secret: [u8; 32],
+        shared_secret: &[u8; 32],
         share: &Scalar,
     ) -> EncryptedShare {
         use aes_gcm::aead::{Aead, NewAead};
         use aes_gcm::{Aes256Gcm, Key, Nonce}; // Or `Aes128Gcm`
 
-        let key = Key::from_slice(&shared_secret);
+        let key = Key::from_slice(shared_secret);
         let cipher = Aes256Gcm::new(key);
 
         let nonce = rng.gen::<[u8; ENCRYPT_NONCE_SIZE]>();
@@ -289,12 +549,12 @@ impl EncryptedShare {
         Self(bytes)
     }
 
-    pub fn decrypt(&self, shared_secret: [u8; 32]) -> Result<Scalar, ValidationError> {
+    pub fn decrypt(&self, shared_secret: &[u8; 32]) -> Result<Scalar, ValidationError> {
         use aes_gcm::aead::{Aead, NewAead};
         use aes_gcm::{Aes256Gcm, Key, Nonce};
 
-        let key = Key::from(shared_secret);
-        let cipher = Aes256Gcm::new(&key);
+        let key = Key::from_slice(shared_secret);
+        let cipher = Aes256Gcm::new(key);
         let nonce = Nonce::from_slice(&self.0[..ENCRYPT_NONCE_SIZE]);
 
         let plaintext = cipher
@@ -536,3 +796,57 @@ fn trustee_e2e_test() {
 
     assert_eq!(vote.as_bytes(), &decrypted);
 }
+
+#[test]
+fn trustee_secrets_zeroize_test() {
+    fn assert_zeroize_on_drop<T: ZeroizeOnDrop>() {}
+    assert_zeroize_on_drop::<DecryptedShare>();
+    assert_zeroize_on_drop::<SharedSecret>();
+
+    let mut share = DecryptedShare {
+        sender_index: 2,
+        share: Scalar::try_from(vec![7u8; 32]).unwrap(),
+    };
+    share.zeroize();
+    assert_eq!(share.sender_index, 0);
+    assert!(share.share.as_bytes().iter().all(|b| *b == 0));
+
+    let mut shared_secret = SharedSecret([7u8; 32]);
+    shared_secret.zeroize();
+    assert_eq!(shared_secret.0, [0u8; 32]);
+}
+
+// Drop a decrypted share in a page locked into memory with mlock, which keeps it from being swapped out, and check
+// that the memory it occupied was wiped in place
+#[cfg(unix)]
+#[test]
+fn trustee_secrets_zeroize_in_locked_memory_test() {
+    use std::alloc::{alloc_zeroed, dealloc, Layout};
+
+    assert_eq!(std::mem::size_of::<Scalar>(), 32);
+    let layout = Layout::from_size_align(4096, 4096).unwrap();
+    unsafe {
+        let page = alloc_zeroed(layout);
+        assert!(!page.is_null());
+        assert_eq!(libc::mlock(page as *const libc::c_void, layout.size()), 0);
+
+        let share = page as *mut DecryptedShare;
+        share.write(DecryptedShare {
+            sender_index: 2,
+            share: Scalar::try_from(vec![7u8; 32]).unwrap(),
+        });
+        let share_bytes = std::ptr::addr_of!((*share).share) as *const u8;
+        assert!(std::slice::from_raw_parts(share_bytes, 32)
+            .iter()
+            .all(|b| *b == 7));
+
+        std::ptr::drop_in_place(share);
+        assert_eq!(std::ptr::addr_of!((*share).sender_index).read(), 0);
+        assert!(std::slice::from_raw_parts(share_bytes, 32)
+            .iter()
+            .all(|b| *b == 0));
+
+        assert_eq!(libc::munlock(page as *const libc::c_void, layout.size()), 0);
+        dealloc(page, layout);
+    }
+}
@@ -1,6 +1,11 @@
+use crate::ValidationError;
+use ed25519_dalek::ExpandedSecretKey;
 use ed25519_dalek::Keypair;
 use ed25519_dalek::PublicKey;
 use ed25519_dalek::SecretKey;
+use ed25519_dalek::Signature;
+use ed25519_dalek::SignatureError;
+use ed25519_dalek::Verifier;
 
 /// Generate an ed25519 keypair
 pub fn generate_keypair() -> (SecretKey, PublicKey) {
@@ -8,3 +13,48 @@ pub fn generate_keypair() -> (SecretKey, PublicKey) {
     let Keypair { public, secret } = Keypair::generate(&mut csprng);
     (secret, public)
 }
+
+/// Sign arbitrary data with an ed25519 secret key, outside of a `Signed` transaction.
+///
+/// This is the same signing convention used by `Signed::sign`: the data is signed as-is with
+/// standard ed25519 (which hashes the message internally with SHA-512), with no prefix or pre-hashing.
+/// For transactions the signed bytes are the CBOR encoding of the unsigned transaction (see `CryptoBallotTransaction::as_bytes`).
+/// Callers signing other data (for example a list of mix outputs) should sign a canonical encoding of it, such as CBOR.
+pub fn sign_detached(secret: &SecretKey, data: &[u8]) -> Signature {
+    let public = PublicKey::from(secret);
+    let expanded: ExpandedSecretKey = secret.into();
+    expanded.sign(data, &public)
+}
+
+/// Verify a signature produced by `sign_detached`
+pub fn verify_detached(
+    public: &PublicKey,
+    data: &[u8],
+    sig: &Signature,
+) -> Result<(), SignatureError> {
+    public.verify(data, sig)
+}
+
+/// Validate that a transaction (created at `later`) was not created before a transaction it depends on (created at `earlier`).
+///
+/// Timestamps are optional, so if either is missing there is nothing to check.
+pub(crate) fn validate_timestamp_order(
+    earlier: Option<u64>,
+    later: Option<u64>,
+) -> Result<(), ValidationError> {
+    if let (Some(earlier), Some(later)) = (earlier, later) {
+        if later < earlier {
+            return Err(ValidationError::TimestampOrderViolation(earlier, later));
+        }
+    }
+    Ok(())
+}
+
+// Used with `#[serde(skip_serializing_if)]` so that default flags do not change the signed bytes
+pub(crate) fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+pub(crate) fn is_zero(value: &u8) -> bool {
+    *value == 0
+}
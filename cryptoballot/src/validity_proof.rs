@@ -0,0 +1,527 @@
+use crate::*;
+use cryptid::curve::CurveElem;
+use cryptid::elgamal::PublicKey as EncryptionPublicKey;
+use curve25519_dalek::scalar::Scalar as DalekScalar;
+use ed25519_dalek::PublicKey;
+use prost::Message;
+use rand::{CryptoRng, RngCore};
+use sha2::{Digest, Sha512};
+
+/// Proofs that each closed plurality contest in a vote selects distinct candidates declared by the contest.
+///
+/// A contest is closed if it is a plurality contest with a list of candidates and no write-ins, so its valid
+/// selections are known in advance: a selection of one of its candidates, with no score and the vote's weight. Each
+/// selection in the contest is proven to encrypt one of them. If there is more than one selection, the sum of their
+/// ciphertexts is also proven to encrypt the sum of that many distinct candidates, so no candidate can be selected
+/// twice. Selections in other contests are only checked once the vote is decrypted.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct VoteValidityProof(pub Vec<ContestValidityProof>);
+
+/// The validity proofs for a single closed plurality contest (see `VoteValidityProof`)
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ContestValidityProof {
+    pub contest_index: u32,
+
+    /// For each selection, in order, a proof that it encrypts one of the contest's candidates
+    pub selections: Vec<DisjunctiveChaumPedersen>,
+
+    /// If there is more than one selection, a proof that together they encrypt as many distinct candidates.
+    ///
+    /// The proof has a branch for every set of that many candidates, so it grows quickly with the number of winners.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sum: Option<DisjunctiveChaumPedersen>,
+}
+
+/// A disjunctive Chaum-Pedersen proof that an ElGamal ciphertext encrypts one of a list of plaintexts, without
+/// revealing which.
+///
+/// For a ciphertext `(c1, c2)` under the election key `y`, the branch for each plaintext `m` shows that
+/// `log_g(c1) == log_y(c2 - m)`, which only holds if the ciphertext encrypts `m`. One branch is proven and the others
+/// are simulated, and the branches' challenges must sum to the Fiat-Shamir challenge, so only one of them can be
+/// simulated. This is the 0/1 proof used by Helios, with the plaintexts 0 and 1 generalized to any list.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DisjunctiveChaumPedersen(pub Vec<ChaumPedersenBranch>);
+
+/// One branch of a `DisjunctiveChaumPedersen` proof
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ChaumPedersenBranch {
+    /// g^w for the prover's random nonce w
+    pub commitment_g: CurveElem,
+
+    /// y^w for the same nonce
+    pub commitment_y: CurveElem,
+
+    /// This branch's share of the Fiat-Shamir challenge
+    #[serde(with = "hex_serde")]
+    pub challenge: [u8; 32],
+
+    /// w + c·r, where c is this branch's challenge
+    #[serde(with = "hex_serde")]
+    pub response: [u8; 32],
+}
+
+impl VoteValidityProof {
+    /// Verify the proofs for every closed plurality contest in the vote against the election's encryption key
+    pub fn verify(
+        &self,
+        vote: &VoteTransaction,
+        election: &ElectionTransaction,
+        encryption_key: &EncryptionPublicKey,
+    ) -> Result<(), ValidationError> {
+        // Every proof must be for a different closed contest in the vote
+        for (i, proof) in self.0.iter().enumerate() {
+            let contest_index = proof.contest_index;
+            let closed = election
+                .get_contest(contest_index)
+                .and_then(|contest| valid_selections(contest, vote.weight))
+                .is_some();
+            if !closed
+                || vote.get_contest(contest_index).is_none()
+                || self.0[..i]
+                    .iter()
+                    .any(|other| other.contest_index == contest_index)
+            {
+                return Err(ValidationError::InvalidVoteValidityProof(contest_index));
+            }
+        }
+
+        let y = key_element(encryption_key)?;
+        for encrypted_vote in &vote.encrypted_votes {
+            let contest_index = encrypted_vote.contest_index;
+            let valid = match election
+                .get_contest(contest_index)
+                .and_then(|contest| valid_selections(contest, vote.weight))
+            {
+                Some(valid) => valid,
+                None => continue,
+            };
+            let proof = self
+                .0
+                .iter()
+                .find(|proof| proof.contest_index == contest_index)
+                .ok_or(ValidationError::MissingVoteValidityProof)?;
+
+            let targets: Vec<CurveElem> = valid
+                .iter()
+                .map(|selection| plaintext_element(encryption_key, selection))
+                .collect();
+            let context = ValidityContext {
+                election_id: vote.election,
+                anonymous_key: &vote.anonymous_key,
+                contest_index,
+                selection_index: None,
+            };
+            if !proof.verify(&y, encrypted_vote, &targets, context) {
+                return Err(ValidationError::InvalidVoteValidityProof(contest_index));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl ContestValidityProof {
+    // Prove that each selection encrypts the candidate at its index in `chosen`, which must all be different
+    fn prove<R: CryptoRng + RngCore>(
+        y: &CurveElem,
+        encrypted_vote: &EncryptedVote,
+        targets: &[CurveElem],
+        chosen: &[usize],
+        randomness: &[DalekScalar],
+        context: ValidityContext,
+        rng: &mut R,
+    ) -> Self {
+        let mut selections = Vec::with_capacity(chosen.len());
+        for (selection_index, ((ciphertext, index), r)) in encrypted_vote
+            .selections
+            .iter()
+            .zip(chosen.iter())
+            .zip(randomness.iter())
+            .enumerate()
+        {
+            let context = ValidityContext {
+                selection_index: Some(selection_index),
+                ..context
+            };
+            selections.push(DisjunctiveChaumPedersen::prove(
+                y,
+                (&ciphertext.c1, &ciphertext.c2),
+                targets,
+                *index,
+                r,
+                &context,
+                rng,
+            ));
+        }
+
+        let sum = if chosen.len() > 1 {
+            let mut chosen_set = chosen.to_vec();
+            chosen_set.sort_unstable();
+            let index = combinations(targets.len(), chosen.len())
+                .iter()
+                .position(|set| *set == chosen_set)
+                .expect("chosen candidates are distinct");
+            let (c1, c2) = sum_ciphertexts(encrypted_vote);
+            let r = randomness
+                .iter()
+                .fold(DalekScalar::zero(), |sum, r| sum + r);
+            Some(DisjunctiveChaumPedersen::prove(
+                y,
+                (&c1, &c2),
+                &sum_targets(targets, chosen.len()),
+                index,
+                &r,
+                &context,
+                rng,
+            ))
+        } else {
+            None
+        };
+
+        ContestValidityProof {
+            contest_index: encrypted_vote.contest_index,
+            selections,
+            sum,
+        }
+    }
+
+    fn verify(
+        &self,
+        y: &CurveElem,
+        encrypted_vote: &EncryptedVote,
+        targets: &[CurveElem],
+        context: ValidityContext,
+    ) -> bool {
+        if self.selections.len() != encrypted_vote.selections.len() {
+            return false;
+        }
+        for (selection_index, (ciphertext, proof)) in encrypted_vote
+            .selections
+            .iter()
+            .zip(self.selections.iter())
+            .enumerate()
+        {
+            let context = ValidityContext {
+                selection_index: Some(selection_index),
+                ..context
+            };
+            if !proof.verify(y, (&ciphertext.c1, &ciphertext.c2), targets, &context) {
+                return false;
+            }
+        }
+
+        match &self.sum {
+            Some(sum) if encrypted_vote.selections.len() > 1 => {
+                let (c1, c2) = sum_ciphertexts(encrypted_vote);
+                let targets = sum_targets(targets, encrypted_vote.selections.len());
+                sum.verify(y, (&c1, &c2), &targets, &context)
+            }
+            Some(_) => false,
+            None => encrypted_vote.selections.len() <= 1,
+        }
+    }
+}
+
+impl DisjunctiveChaumPedersen {
+    // Prove that the ciphertext, whose first component is g^r, encrypts `targets[index]`
+    fn prove<R: CryptoRng + RngCore>(
+        y: &CurveElem,
+        ciphertext: (&CurveElem, &CurveElem),
+        targets: &[CurveElem],
+        index: usize,
+        r: &DalekScalar,
+        context: &ValidityContext,
+        rng: &mut R,
+    ) -> Self {
+        let (c1, c2) = ciphertext;
+        let g = CurveElem::generator();
+        let w = DalekScalar::random(rng);
+
+        // Simulate every other branch by choosing its challenge and response, and solving for its commitments
+        let mut branches = Vec::with_capacity(targets.len());
+        let mut simulated = DalekScalar::zero();
+        for (i, target) in targets.iter().enumerate() {
+            if i == index {
+                branches.push(ChaumPedersenBranch {
+                    commitment_g: scale(&g, &w),
+                    commitment_y: scale(y, &w),
+                    challenge: [0; 32],
+                    response: [0; 32],
+                });
+                continue;
+            }
+
+            let challenge = DalekScalar::random(rng);
+            let response = DalekScalar::random(rng);
+            let d = subtract(c2, target);
+            branches.push(ChaumPedersenBranch {
+                commitment_g: subtract(&scale(&g, &response), &scale(c1, &challenge)),
+                commitment_y: subtract(&scale(y, &response), &scale(&d, &challenge)),
+                challenge: challenge.to_bytes(),
+                response: response.to_bytes(),
+            });
+            simulated += challenge;
+        }
+
+        let challenge =
+            fiat_shamir_challenge(y, ciphertext, targets, &branches, context) - simulated;
+        branches[index].challenge = challenge.to_bytes();
+        branches[index].response = (w + challenge * r).to_bytes();
+
+        DisjunctiveChaumPedersen(branches)
+    }
+
+    // Check that g^response == commitment_g · c1^challenge and y^response == commitment_y · (c2 / target)^challenge
+    // for every branch, and that the challenges sum to the Fiat-Shamir challenge
+    fn verify(
+        &self,
+        y: &CurveElem,
+        ciphertext: (&CurveElem, &CurveElem),
+        targets: &[CurveElem],
+        context: &ValidityContext,
+    ) -> bool {
+        if self.0.len() != targets.len() {
+            return false;
+        }
+
+        let (c1, c2) = ciphertext;
+        let g = CurveElem::generator();
+        let mut challenges = DalekScalar::zero();
+        for (branch, target) in self.0.iter().zip(targets.iter()) {
+            let challenge = DalekScalar::from_canonical_bytes(branch.challenge);
+            let response = DalekScalar::from_canonical_bytes(branch.response);
+            let (challenge, response) = match (challenge, response) {
+                (Some(challenge), Some(response)) => (challenge, response),
+                _ => return false,
+            };
+
+            let d = subtract(c2, target);
+            if scale(&g, &response) != branch.commitment_g.clone() + scale(c1, &challenge) {
+                return false;
+            }
+            if scale(y, &response) != branch.commitment_y.clone() + scale(&d, &challenge) {
+                return false;
+            }
+            challenges += challenge;
+        }
+
+        challenges == fiat_shamir_challenge(y, ciphertext, targets, &self.0, context)
+    }
+}
+
+/// Encrypt every contest in a vote with randomness drawn from `seed`, with proofs of knowledge of the randomness and
+/// proofs that each closed plurality contest is valid (see `VoteValidityProof`).
+///
+/// The encrypted votes and encryption proof are those `encrypt_vote_with_proof` produces, so the vote can still be
+/// challenged by revealing the seed. Each closed plurality contest must select distinct candidates it declares, with
+/// no score and the weight the vote will be authenticated with, or `Error::InvalidPluralitySelection` is returned. The
+/// returned validity proof goes in the vote's `vote_validity_proof`.
+pub fn encrypt_vote_with_validity_proof<R: CryptoRng + RngCore>(
+    election: &ElectionTransaction,
+    encryption_key: &EncryptionPublicKey,
+    anonymous_key: &PublicKey,
+    votes: &[PlaintextVote],
+    seed: [u8; 32],
+    rng: &mut R,
+) -> Result<(Vec<EncryptedVote>, EncryptionProof, VoteValidityProof), Error> {
+    let (encrypted_votes, encryption_proof, randomness) =
+        encrypt_vote_with_randomness(encryption_key, election.id, anonymous_key, votes, seed, rng)?;
+
+    let y = key_element(encryption_key)?;
+    let mut validity_proof = VoteValidityProof::default();
+    for ((vote, encrypted_vote), randomness) in votes
+        .iter()
+        .zip(encrypted_votes.iter())
+        .zip(randomness.iter())
+    {
+        let contest_index = vote.contest_index;
+        let weight = vote
+            .selections
+            .first()
+            .and_then(|selection| selection.weight);
+        let valid = match election
+            .get_contest(contest_index)
+            .and_then(|contest| valid_selections(contest, weight))
+        {
+            Some(valid) => valid,
+            None => continue,
+        };
+
+        // Which of the contest's candidates each selection is for
+        let mut chosen = Vec::with_capacity(vote.selections.len());
+        for selection in vote.selections.iter() {
+            match valid.iter().position(|valid| valid == selection) {
+                Some(index) if !chosen.contains(&index) => chosen.push(index),
+                _ => return Err(Error::InvalidPluralitySelection(contest_index)),
+            }
+        }
+
+        let targets: Vec<CurveElem> = valid
+            .iter()
+            .map(|selection| plaintext_element(encryption_key, selection))
+            .collect();
+        let context = ValidityContext {
+            election_id: election.id,
+            anonymous_key,
+            contest_index,
+            selection_index: None,
+        };
+        validity_proof.0.push(ContestValidityProof::prove(
+            &y,
+            encrypted_vote,
+            &targets,
+            &chosen,
+            randomness,
+            context,
+            rng,
+        ));
+    }
+
+    Ok((encrypted_votes, encryption_proof, validity_proof))
+}
+
+// The valid selections of a closed contest: one for each candidate, with no score and the vote's weight
+fn valid_selections(contest: &Contest, weight: Option<u32>) -> Option<Vec<Selection>> {
+    let closed = matches!(contest.contest_type, ContestType::Plurality)
+        && !contest.write_in
+        && !contest.candidates.is_empty();
+    if !closed {
+        return None;
+    }
+
+    let valid = contest
+        .candidates
+        .iter()
+        .map(|candidate| Selection {
+            write_in: false,
+            score: 0,
+            selection: candidate.id.clone(),
+            weight,
+        })
+        .collect();
+
+    Some(valid)
+}
+
+// The element a selection is encoded as before it is encrypted, which is the second component of its encryption
+// without any randomness
+fn plaintext_element(encryption_key: &EncryptionPublicKey, selection: &Selection) -> CurveElem {
+    let mut buf = Vec::with_capacity(selection.encoded_len());
+
+    // Unwrap OK - the buffer has room for the whole encoding
+    selection.encode(&mut buf).unwrap();
+
+    encryption_key
+        .encrypt_with_r(&buf, &to_cryptid_scalar(&DalekScalar::zero()))
+        .c2
+}
+
+// cryptid doesn't expose the element inside its public key, so it is read from the key's serialized form, `{"y": y}`
+fn key_element(encryption_key: &EncryptionPublicKey) -> Result<CurveElem, ValidationError> {
+    let mut key =
+        serde_json::to_value(encryption_key).map_err(|_| ValidationError::InvalidPublicKey)?;
+    serde_json::from_value(key["y"].take()).map_err(|_| ValidationError::InvalidPublicKey)
+}
+
+// The sum of every set of `k` distinct targets, in the order of `combinations`
+fn sum_targets(targets: &[CurveElem], k: usize) -> Vec<CurveElem> {
+    combinations(targets.len(), k)
+        .iter()
+        .map(|set| sum_elements(set.iter().map(|index| targets[*index].clone())))
+        .collect()
+}
+
+// Every set of `k` distinct indexes below `n`, in lexicographic order
+fn combinations(n: usize, k: usize) -> Vec<Vec<usize>> {
+    let mut combinations = Vec::new();
+    if k > n {
+        return combinations;
+    }
+
+    let mut combination: Vec<usize> = (0..k).collect();
+    loop {
+        combinations.push(combination.clone());
+
+        // Advance the last index that can still be advanced, and reset the indexes after it
+        let mut i = k;
+        loop {
+            if i == 0 {
+                return combinations;
+            }
+            i -= 1;
+            if combination[i] < n - k + i {
+                break;
+            }
+        }
+        combination[i] += 1;
+        for j in i + 1..k {
+            combination[j] = combination[j - 1] + 1;
+        }
+    }
+}
+
+// The homomorphic sum of a contest's ciphertexts, which encrypts the sum of their plaintexts
+fn sum_ciphertexts(encrypted_vote: &EncryptedVote) -> (CurveElem, CurveElem) {
+    let c1 = sum_elements(encrypted_vote.selections.iter().map(|c| c.c1.clone()));
+    let c2 = sum_elements(encrypted_vote.selections.iter().map(|c| c.c2.clone()));
+    (c1, c2)
+}
+
+fn sum_elements(mut elements: impl Iterator<Item = CurveElem>) -> CurveElem {
+    let first = elements.next().expect("at least one element to sum");
+    elements.fold(first, |sum, element| sum + element)
+}
+
+fn scale(element: &CurveElem, scalar: &DalekScalar) -> CurveElem {
+    element.scaled(&to_cryptid_scalar(scalar))
+}
+
+fn subtract(a: &CurveElem, b: &CurveElem) -> CurveElem {
+    a.clone() + scale(b, &-DalekScalar::one())
+}
+
+// Where a proof sits, which it is bound to so that it can't be replayed elsewhere
+#[derive(Clone, Copy)]
+struct ValidityContext<'a> {
+    election_id: Identifier,
+    anonymous_key: &'a PublicKey,
+    contest_index: u32,
+
+    // The selection the proof is for, or None for a contest's sum proof
+    selection_index: Option<usize>,
+}
+
+fn fiat_shamir_challenge(
+    y: &CurveElem,
+    ciphertext: (&CurveElem, &CurveElem),
+    targets: &[CurveElem],
+    branches: &[ChaumPedersenBranch],
+    context: &ValidityContext,
+) -> DalekScalar {
+    // Unwraps OK - these are all plain serializable values
+    let mut hasher = Sha512::new();
+    hasher.update(b"cryptoballot_vote_validity_proof");
+    hasher.update(context.election_id.to_array());
+    hasher.update(context.anonymous_key.as_bytes());
+    hasher.update(context.contest_index.to_le_bytes());
+    match context.selection_index {
+        Some(selection_index) => {
+            hasher.update([1u8]);
+            hasher.update((selection_index as u64).to_le_bytes());
+        }
+        None => hasher.update([0u8]),
+    }
+    hasher.update(serde_cbor::to_vec(y).unwrap());
+    hasher.update(serde_cbor::to_vec(ciphertext.0).unwrap());
+    hasher.update(serde_cbor::to_vec(ciphertext.1).unwrap());
+    for target in targets {
+        hasher.update(serde_cbor::to_vec(target).unwrap());
+    }
+    for branch in branches {
+        hasher.update(serde_cbor::to_vec(&branch.commitment_g).unwrap());
+        hasher.update(serde_cbor::to_vec(&branch.commitment_y).unwrap());
+    }
+
+    DalekScalar::from_hash(hasher)
+}
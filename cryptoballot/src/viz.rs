@@ -0,0 +1,99 @@
+use crate::*;
+use std::fmt::Write;
+
+/// Render an election's transactions as a Graphviz `digraph`, in the DOT language.
+///
+/// Each transaction is a node labeled with its type and a short ID, colored by type. There is an edge from each of
+/// a transaction's `inputs` to the transaction, for inputs that are in the store. Render it with, for example,
+/// `dot -Tsvg election.dot > election.svg`.
+pub fn export_dot<S: Store>(store: &S, election_id: Identifier) -> String {
+    let start = Identifier::start(election_id, TransactionType::Election, None);
+    let end = Identifier::end(election_id, TransactionType::MAX, None);
+    let txs = store.range(start, end);
+
+    // Unwraps OK - writing to a String can't fail
+    let mut dot = String::new();
+    writeln!(dot, "digraph \"{}\" {{", election_id).unwrap();
+    writeln!(dot, "    node [shape=box, style=filled];").unwrap();
+    for tx in txs.iter() {
+        let id = tx.id();
+        writeln!(
+            dot,
+            "    \"{}\" [label=\"{}\\n{}\", fillcolor=\"{}\"];",
+            id,
+            id.transaction_type.name(),
+            short_id(id),
+            node_color(id.transaction_type)
+        )
+        .unwrap();
+    }
+    for tx in txs.iter() {
+        for input in tx.inputs() {
+            if txs.iter().any(|tx| tx.id() == input) {
+                writeln!(dot, "    \"{}\" -> \"{}\";", input, tx.id()).unwrap();
+            }
+        }
+    }
+    dot.push_str("}\n");
+
+    dot
+}
+
+// A short hex ID to label nodes with. All transactions share the election part of the ID, so use the unique part.
+fn short_id(id: Identifier) -> String {
+    if id.transaction_type == TransactionType::Election {
+        hex::encode(&id.election_id[..4])
+    } else {
+        hex::encode(&id.unique_info[..4])
+    }
+}
+
+fn node_color(tx_type: TransactionType) -> &'static str {
+    match tx_type {
+        TransactionType::Election => "gold",
+        TransactionType::KeyGenCommitment => "lightblue",
+        TransactionType::KeyGenShare => "lightskyblue",
+        TransactionType::KeyGenPublicKey => "steelblue1",
+        TransactionType::EncryptionKey => "royalblue1",
+        TransactionType::Vote => "palegreen",
+        TransactionType::VotingEnd => "orange",
+        TransactionType::Mix => "plum",
+        TransactionType::PartialDecryption => "lightpink",
+        TransactionType::PartialDecryptionBatch => "hotpink",
+        TransactionType::Decryption => "salmon",
+        TransactionType::ObserverAcknowledgment => "lightgrey",
+        TransactionType::TrusteeKeyRevocation => "tomato",
+        TransactionType::TrusteeRevocation => "red",
+        TransactionType::TrusteeReshare => "indianred",
+        TransactionType::Challenge => "khaki",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_dot() {
+        let txs = crate::tests::test_election_2();
+        let election_id = txs[0].id();
+        let store = MemStore::from(txs.clone());
+
+        let dot = export_dot(&store, election_id);
+        assert!(dot.starts_with("digraph"));
+        for tx in txs.iter() {
+            let id = tx.id();
+            assert!(dot.contains(&format!("\"{}\" [label=\"{}\\n", id, tx.type_name())));
+            for input in tx.inputs() {
+                assert!(dot.contains(&format!("\"{}\" -> \"{}\";", input, id)));
+            }
+        }
+
+        // The decryption depends on both partial decryptions
+        let decryption = txs
+            .iter()
+            .find(|tx| tx.transaction_type() == TransactionType::Decryption)
+            .unwrap();
+        assert_eq!(decryption.inputs().len(), 2);
+    }
+}
@@ -2,9 +2,15 @@ use crate::*;
 use cryptid::elgamal::Ciphertext;
 use ed25519_dalek::PublicKey;
 use ed25519_dalek::SecretKey;
+use indexmap::IndexMap;
 use prost::Message;
 use rand::{CryptoRng, RngCore};
+use rand_chacha::rand_core::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use rsa::RSAPrivateKey;
+use std::collections::{HashMap, HashSet};
 use std::convert::TryInto;
+use uuid::Uuid;
 
 /// Transaction 6: Vote
 ///
@@ -29,6 +35,32 @@ pub struct VoteTransaction {
 
     /// A set of authentications, certifying that the anonymous_key provided can vote this election and ballot.
     pub authentication: Vec<Authentication>,
+
+    /// Voting weight (eg. number of shares held), defaults to 1 if not set.
+    ///
+    /// The weight is part of the auth package, so it is certified by the authenticators rather than chosen by the
+    /// voter. It may not exceed the election's `max_vote_weight`, and each encrypted selection must carry the same
+    /// weight, which is checked once the vote is decrypted.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub weight: Option<u32>,
+
+    /// Proofs that the voter knows the randomness each encrypted selection was encrypted with (see `EncryptionProof`).
+    /// Required if the election sets `require_encryption_proofs`, and always checked when present.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "EncryptionProof::is_empty")]
+    pub encryption_proof: EncryptionProof,
+
+    /// Proofs that each closed plurality contest selects distinct candidates it declares (see `VoteValidityProof`).
+    /// Required if the election sets `require_vote_validity_proofs`, and always checked when present.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vote_validity_proof: Option<VoteValidityProof>,
+
+    /// Optional creation time, in seconds since the UNIX epoch
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<u64>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -53,11 +85,47 @@ impl VoteTransaction {
             encrypted_votes,
             anonymous_key: public_key,
             authentication: vec![],
+            weight: None,
+            encryption_proof: EncryptionProof::default(),
+            vote_validity_proof: None,
+            created_at: None,
         };
 
         (vote, secret_key)
     }
 
+    /// Get the encrypted selections for a single contest.
+    ///
+    /// Each contest on the ballot is encrypted separately, and is partially decrypted and decrypted in its own
+    /// transactions, so decrypting one contest reveals nothing about the voter's other contests.
+    pub fn get_contest(&self, contest_index: u32) -> Option<&EncryptedVote> {
+        self.encrypted_votes
+            .iter()
+            .find(|encrypted_vote| encrypted_vote.contest_index == contest_index)
+    }
+
+    /// Check that no contest has more selections than its contest type allows.
+    ///
+    /// Each selection is encrypted separately, so a plurality overvote is visible from the number of ciphertexts. Which
+    /// candidates were selected is not: the vote's `vote_validity_proof` shows that for closed plurality contests, and
+    /// a selection of an undeclared candidate is otherwise only caught once the vote is decrypted.
+    pub fn validate_vote_range<S: Store>(&self, store: &S) -> Result<(), ValidationError> {
+        let election = store.get_election(self.election)?;
+        for encrypted_vote in &self.encrypted_votes {
+            let contest = match election.get_contest(encrypted_vote.contest_index) {
+                Some(contest) => contest,
+                None => continue,
+            };
+            // A plurality contest with several winners lets each voter select up to that many candidates
+            let is_plurality = matches!(contest.contest_type, ContestType::Plurality);
+            if is_plurality && encrypted_vote.selections.len() > contest.num_winners as usize {
+                return Err(ValidationError::Overvote(encrypted_vote.contest_index));
+            }
+        }
+
+        Ok(())
+    }
+
     pub fn build_id(election_id: Identifier, public_key: &PublicKey) -> Identifier {
         let unique_info = public_key.as_bytes();
         Identifier::new(
@@ -66,31 +134,23 @@ impl VoteTransaction {
             Some(unique_info[0..16].try_into().unwrap()),
         )
     }
-}
-
-impl CryptoBallotTransaction for VoteTransaction {
-    #[inline(always)]
-    fn id(&self) -> Identifier {
-        self.id
-    }
-
-    #[inline(always)]
-    fn public(&self) -> Option<PublicKey> {
-        Some(self.anonymous_key)
-    }
 
-    #[inline(always)]
-    fn election_id(&self) -> Identifier {
-        self.election
-    }
-
-    #[inline(always)]
-    fn tx_type() -> TransactionType {
-        TransactionType::Vote
-    }
+    /// Validate the transaction, using `clock` to reject votes created in the future.
+    ///
+    /// A vote may be created at most `MAX_CLOCK_SKEW_SECS` ahead of `clock`. Otherwise a vote dated far in the future
+    /// would prevent voting from ever ending, since a VotingEnd transaction may not predate any vote.
+    pub fn validate_tx_with_clock<S: Store, C: Clock>(
+        &self,
+        store: &S,
+        clock: &C,
+    ) -> Result<(), ValidationError> {
+        if let Some(created_at) = self.created_at {
+            let now = clock.now();
+            if created_at > now.saturating_add(MAX_CLOCK_SKEW_SECS) {
+                return Err(ValidationError::CreatedInFuture(created_at, now));
+            }
+        }
 
-    /// Validate the vote transaction
-    fn validate_tx<S: Store>(&self, store: &S) -> Result<(), ValidationError> {
         // Check the ID
         if Self::build_id(self.election, &self.anonymous_key) != self.id {
             return Err(ValidationError::IdentifierBadComposition);
@@ -111,31 +171,71 @@ impl CryptoBallotTransaction for VoteTransaction {
         }
 
         // Validate that there is a EncryptionKeyTransaction
-        let enc_key_tx = Identifier::new(self.election, TransactionType::EncryptionKey, None);
-        if store.get_transaction(enc_key_tx).is_none() {
-            return Err(ValidationError::EncryptionKeyTransactionDoesNotExist);
-        }
+        let encryption_key = store
+            .get_encryption_key(self.election)
+            .map_err(|_| ValidationError::EncryptionKeyTransactionDoesNotExist)?;
 
         // Validate that there isn't a VotingEnd Transactipn
         let enc_key_tx = Identifier::new(self.election, TransactionType::VotingEnd, None);
-        if store.get_transaction(enc_key_tx).is_some() {
+        if store.contains(enc_key_tx) {
             return Err(ValidationError::VotingHasEnded);
         }
 
-        // TODO: minimum authentication needed to be defined in election
+        // Validate that all observers have acknowledged the election, if required
+        if election.require_observer_acknowledgments {
+            for observer in election.observers.iter() {
+                let ack_id =
+                    ObserverAcknowledgmentTransaction::build_id(self.election, observer.id);
+                if !store.contains(ack_id) {
+                    return Err(ValidationError::MissingObserverAcknowledgment(observer.id));
+                }
+            }
+        }
+
+        // Weights are certified by the authenticators below, but are still bounded by the election
+        if let Some(weight) = self.weight {
+            let max_vote_weight = election.max_vote_weight.unwrap_or(1);
+            if weight == 0 {
+                return Err(ValidationError::InvalidVoteWeight);
+            }
+            if weight > max_vote_weight {
+                return Err(ValidationError::VoteWeightExceedsMax(
+                    weight,
+                    max_vote_weight,
+                ));
+            }
+        }
+
+        // The authentication must cover this transaction's own election, ballot, anonymous key and weight,
+        // otherwise it could be replayed onto a different anonymous key
+        let package = AuthPackage::new(self.election, self.ballot_id.clone(), self.anonymous_key)
+            .with_weight(self.weight);
         for authn in self.authentication.iter() {
             let authenticator = election
                 .get_authenticator(authn.authenticator)
                 .ok_or(ValidationError::AuthDoesNotExist)?;
 
             authenticator
-                .verify(
-                    election.id,
-                    &self.ballot_id,
-                    &self.anonymous_key,
-                    &authn.signature,
-                )
-                .map_err(|_| ValidationError::AuthFailed)?;
+                .verify_package(&package, &authn.signature)
+                .map_err(|e| match e {
+                    ValidationError::AuthSignatureVerificationFailed => {
+                        ValidationError::AuthenticationMismatch
+                    }
+                    _ => ValidationError::AuthFailed,
+                })?;
+        }
+
+        // Each authenticator counts once towards the election's threshold
+        let authenticators: HashSet<Uuid> = self
+            .authentication
+            .iter()
+            .map(|authn| authn.authenticator)
+            .collect();
+        if authenticators.len() < election.authenticators_threshold as usize {
+            return Err(ValidationError::NotEnoughAuthentications(
+                election.authenticators_threshold as usize,
+                authenticators.len(),
+            ));
         }
 
         let ballot = match election.get_ballot(&self.ballot_id) {
@@ -150,22 +250,298 @@ impl CryptoBallotTransaction for VoteTransaction {
             }
         }
 
+        // Each contest is decrypted separately, so each must be encrypted exactly once
+        for (i, encrypted_vote) in self.encrypted_votes.iter().enumerate() {
+            let contest_index = encrypted_vote.contest_index;
+            if self.encrypted_votes[..i]
+                .iter()
+                .any(|other| other.contest_index == contest_index)
+            {
+                return Err(ValidationError::DuplicateContestVote(contest_index));
+            }
+        }
+
+        for encrypted_vote in &self.encrypted_votes {
+            election
+                .crypto_params
+                .validate_ciphertexts(&encrypted_vote.selections)?;
+        }
+
+        // Without the proofs, a voter could copy another voter's ciphertexts into their own vote
+        if election.require_encryption_proofs || !self.encryption_proof.is_empty() {
+            self.encryption_proof
+                .verify(self, &encryption_key.encryption_key)?;
+        }
+
+        self.validate_vote_range(store)?;
+
+        // Without the validity proofs, a closed plurality contest could select an undeclared candidate, or one twice.
+        // The range is checked first, since the proofs grow with the number of selections.
+        if election.require_vote_validity_proofs || self.vote_validity_proof.is_some() {
+            self.vote_validity_proof
+                .as_ref()
+                .ok_or(ValidationError::MissingVoteValidityProof)?
+                .verify(self, &election, &encryption_key.encryption_key)?;
+        }
+
         Ok(())
     }
 }
 
+impl CryptoBallotTransaction for VoteTransaction {
+    #[inline(always)]
+    fn id(&self) -> Identifier {
+        self.id
+    }
+
+    #[inline(always)]
+    fn public(&self) -> Option<PublicKey> {
+        Some(self.anonymous_key)
+    }
+
+    #[inline(always)]
+    fn election_id(&self) -> Identifier {
+        self.election
+    }
+
+    #[inline(always)]
+    fn tx_type() -> TransactionType {
+        TransactionType::Vote
+    }
+
+    /// Validate the vote transaction
+    fn validate_tx<S: Store>(&self, store: &S) -> Result<(), ValidationError> {
+        self.validate_tx_with_clock(store, &SystemClock)
+    }
+}
+
 /// Encrypt a vote with the public key provided by the encryption_key transaction (EncryptionKeyTransaction.encryption_key)
 pub fn encrypt_vote<R: CryptoRng + RngCore>(
     encryption_key: &cryptid::elgamal::PublicKey,
     vote: Vec<Selection>,
     rng: &mut R,
 ) -> Result<Vec<cryptid::elgamal::Ciphertext>, Error> {
+    encrypt_vote_with::<DefaultEncryptionScheme, R>(encryption_key, vote, rng)
+}
+
+/// Encrypt a vote with the given encryption scheme, each selection being encrypted separately
+pub fn encrypt_vote_with<E: EncryptionScheme, R: CryptoRng + RngCore>(
+    encryption_key: &E::PublicKey,
+    vote: Vec<Selection>,
+    rng: &mut R,
+) -> Result<Vec<E::Ciphertext>, Error> {
     let mut results = Vec::with_capacity(vote.len());
     for selection in vote {
         let mut buf = Vec::with_capacity(selection.encoded_len());
         selection.encode(&mut buf)?;
-        results.push(encryption_key.encrypt(rng, &buf))
+        results.push(E::encrypt(encryption_key, &buf, rng))
     }
 
     Ok(results)
 }
+
+/// The plaintext selections for a single contest, as revealed by a ChallengeTransaction
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PlaintextVote {
+    pub contest_index: u32,
+    pub selections: Vec<Selection>,
+}
+
+/// Encrypt every contest in a vote with randomness drawn from `seed`.
+///
+/// The same plaintext and seed always produce the same encrypted votes, so the voter can later reveal both in a
+/// ChallengeTransaction to prove the vote was encrypted honestly. The seed must be freshly generated for each vote and
+/// kept secret unless the vote is challenged: anyone who knows it can decrypt the vote. `encrypt_vote_with_proof`
+/// produces the same encrypted votes, along with proofs.
+pub fn encrypt_vote_with_seed(
+    encryption_key: &cryptid::elgamal::PublicKey,
+    votes: &[PlaintextVote],
+    seed: [u8; 32],
+) -> Result<Vec<EncryptedVote>, Error> {
+    let mut rng = ChaCha20Rng::from_seed(seed);
+    let mut encrypted_votes = Vec::with_capacity(votes.len());
+    for vote in votes {
+        let mut selections = Vec::with_capacity(vote.selections.len());
+        for selection in vote.selections.iter() {
+            let (ciphertext, _) = encrypt_selection_with_seed(encryption_key, selection, &mut rng)?;
+            selections.push(ciphertext);
+        }
+        encrypted_votes.push(EncryptedVote {
+            contest_index: vote.contest_index,
+            selections,
+        });
+    }
+
+    Ok(encrypted_votes)
+}
+
+/// Everything needed to cast a vote on a single ballot.
+///
+/// `secret_key` becomes the vote's anonymous key, so it must be freshly generated and never linked to the voter.
+/// `authn_secrets` holds, for each authenticator (keyed by authenticator id), that authenticator's secret key for
+/// the ballot being voted. In a real deployment the authenticators keep these secrets and sign the blinded
+/// auth-package remotely; holding them together is only appropriate when this process is also the authenticator
+/// (for example a supervised voting kiosk, or tests).
+pub struct VoterCredential {
+    pub secret_key: SecretKey,
+    pub authn_secrets: HashMap<Uuid, RSAPrivateKey>,
+
+    /// The voting weight the authenticators certify for this voter, or None for an unweighted vote
+    pub weight: Option<u32>,
+}
+
+/// Encrypt, authenticate and sign a vote in one step.
+///
+/// `votes` maps contest indexes to the plaintext selections for that contest. Every selection is given the
+/// credential's weight. The vote is encrypted with randomness drawn from `seed` (see `encrypt_vote_with_seed`), so
+/// the voter can challenge it by revealing the seed, which must otherwise be kept secret. Closed plurality contests are
+/// proven valid (see `encrypt_vote_with_validity_proof`). The vote is authenticated by every authenticator that
+/// `voter_credential` holds a secret for, and is validated against `store` before being returned, so it is ready to
+/// be posted.
+pub fn cast_vote<S: Store>(
+    store: &S,
+    election: &Signed<ElectionTransaction>,
+    ballot_id: &str,
+    votes: IndexMap<u32, Vec<Selection>>,
+    voter_credential: &VoterCredential,
+    seed: [u8; 32],
+) -> Result<Signed<VoteTransaction>, CastVoteError> {
+    if election.get_ballot(ballot_id).is_none() {
+        return Err(CastVoteError::BallotDoesNotExist(ballot_id.to_string()));
+    }
+
+    let encryption_key = store.get_encryption_key(election.id)?;
+
+    let anonymous_key = PublicKey::from(&voter_credential.secret_key);
+
+    let plaintext: Vec<PlaintextVote> = votes
+        .into_iter()
+        .map(|(contest_index, mut selections)| {
+            for selection in selections.iter_mut() {
+                selection.weight = voter_credential.weight;
+            }
+            PlaintextVote {
+                contest_index,
+                selections,
+            }
+        })
+        .collect();
+    let (encrypted_votes, encryption_proof, vote_validity_proof) =
+        encrypt_vote_with_validity_proof(
+            election,
+            &encryption_key.encryption_key,
+            &anonymous_key,
+            &plaintext,
+            seed,
+            &mut rand::rngs::OsRng {},
+        )?;
+
+    let mut vote = VoteTransaction {
+        id: VoteTransaction::build_id(election.id, &anonymous_key),
+        election: election.id,
+        ballot_id: ballot_id.to_string(),
+        encrypted_votes,
+        anonymous_key,
+        authentication: vec![],
+        weight: voter_credential.weight,
+        encryption_proof,
+        vote_validity_proof: Some(vote_validity_proof),
+        created_at: None,
+    };
+
+    let auth_package = AuthPackage::new(election.id, vote.ballot_id.clone(), anonymous_key)
+        .with_weight(vote.weight);
+    for authenticator in &election.authenticators {
+        let secret = match voter_credential.authn_secrets.get(&authenticator.id) {
+            Some(secret) => secret,
+            None => continue,
+        };
+        let authn_public = authenticator
+            .public_keys
+            .get(ballot_id)
+            .ok_or(CastVoteError::MissingAuthenticatorKey(authenticator.id))?
+            .as_ref();
+
+        let (blinded, unblinder) = auth_package.blind(authn_public);
+        let authentication = authenticator.authenticate(secret, &blinded);
+        vote.authentication
+            .push(authentication.unblind(authn_public, unblinder));
+    }
+
+    if vote.authentication.len() < election.authenticators_threshold as usize {
+        return Err(CastVoteError::NotEnoughAuthentications(
+            election.authenticators_threshold as usize,
+            vote.authentication.len(),
+        ));
+    }
+
+    let vote = Signed::sign(&voter_credential.secret_key, vote)?;
+    vote.validate(store)?;
+
+    Ok(vote)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vote_signed_by_anonymous_key() {
+        let election_id = ElectionTransaction::build_id([1; 15]);
+        let (vote, voter_secret) = VoteTransaction::new(election_id, "TEST".to_string(), vec![]);
+
+        // The signature proves the voter controls the anonymous identity the vote claims
+        assert_eq!(vote.public(), Some(vote.anonymous_key));
+        let (other_secret, _) = generate_keypair();
+        assert!(matches!(
+            Signed::sign(&other_secret, vote.clone()),
+            Err(Error::MismatchedPublicKeys)
+        ));
+
+        // A signature by any other key doesn't verify
+        let (other_vote, other_secret) =
+            VoteTransaction::new(election_id, "TEST".to_string(), vec![]);
+        let other_sig = Signed::sign(&other_secret, other_vote).unwrap().sig;
+        let forged = Signed {
+            tx: vote.clone(),
+            sig: other_sig,
+        };
+        assert!(forged.verify_signature().is_err());
+        Signed::sign(&voter_secret, vote)
+            .unwrap()
+            .verify_signature()
+            .unwrap();
+    }
+
+    #[test]
+    fn test_authentication_mismatch() {
+        let txs = crate::tests::test_election_2();
+
+        let vote: Signed<VoteTransaction> = txs
+            .iter()
+            .find(|tx| tx.transaction_type() == TransactionType::Vote)
+            .cloned()
+            .unwrap()
+            .into();
+
+        // Everything up to (but not including) voting
+        let store = MemStore::from(
+            txs.into_iter()
+                .filter(|tx| tx.transaction_type() as u8 <= TransactionType::EncryptionKey as u8)
+                .collect::<Vec<_>>(),
+        );
+        vote.validate(&store).unwrap();
+
+        // Replay the authentication onto a different anonymous key
+        let (anonymous_secret, anonymous_key) = generate_keypair();
+        let mut replayed = vote.tx.clone();
+        replayed.anonymous_key = anonymous_key;
+        replayed.id = VoteTransaction::build_id(replayed.election, &anonymous_key);
+        let replayed = Signed::sign(&anonymous_secret, replayed).unwrap();
+
+        assert!(matches!(
+            replayed.validate(&store),
+            Err(ValidationError::AuthenticationMismatch)
+        ));
+    }
+}
@@ -2,22 +2,101 @@ use crate::*;
 use ed25519_dalek::PublicKey;
 
 /// Transaction 7: VotingEnd
+///
+/// Commits to the exact set of votes that were cast when voting ended, so that no vote can be inserted afterwards
+/// without every decryption failing to validate.
 #[derive(Serialize, Deserialize, Clone)]
 pub struct VotingEndTransaction {
     pub id: Identifier,
     pub election: Identifier,
     #[serde(with = "EdPublicKeyHex")]
     pub authority_public_key: PublicKey,
+
+    /// Commitment to the votes that were cast.
+    ///
+    /// VotingEnd transactions created before the commitment was introduced don't have one. Without it, votes
+    /// inserted after voting ended can't be detected, so `build` always sets it.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vote_commitment: Option<VoteCommitment>,
+
+    /// Optional creation time, in seconds since the UNIX epoch
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<u64>,
 }
 
 impl VotingEndTransaction {
-    /// Create a new DecryptionTransaction with the decrypted vote
-    pub fn new(election: Identifier, authority_public_key: PublicKey) -> Self {
+    /// Create a new VotingEndTransaction, committing to `votes`, which must be every vote cast in the election
+    pub fn build(
+        election: &Signed<ElectionTransaction>,
+        votes: &[Signed<VoteTransaction>],
+    ) -> Self {
+        let vote_ids: Vec<Identifier> = votes.iter().map(|vote| vote.id()).collect();
         VotingEndTransaction {
-            id: Identifier::new(election, TransactionType::VotingEnd, None),
-            election: election,
-            authority_public_key,
+            id: Identifier::new(election.id, TransactionType::VotingEnd, None),
+            election: election.id,
+            authority_public_key: election.authority_public,
+            vote_commitment: Some(VoteCommitment {
+                vote_count: vote_ids.len() as u64,
+                vote_root: vote_root(&vote_ids),
+            }),
+            created_at: None,
+        }
+    }
+
+    /// Check that the votes in the store are exactly the votes this transaction commits to
+    ///
+    /// A transaction without a vote commitment commits to nothing, so this always passes.
+    pub fn verify_vote_commitment<S: Store>(&self, store: &S) -> Result<(), ValidationError> {
+        let commitment = match &self.vote_commitment {
+            Some(commitment) => commitment,
+            None => return Ok(()),
+        };
+
+        let vote_ids: Vec<Identifier> = store
+            .get_multiple(self.election, TransactionType::Vote)
+            .iter()
+            .map(|vote| vote.id())
+            .collect();
+
+        if vote_ids.len() as u64 != commitment.vote_count {
+            return Err(ValidationError::VoteCountMismatch(
+                commitment.vote_count,
+                vote_ids.len() as u64,
+            ));
         }
+        if vote_root(&vote_ids) != commitment.vote_root {
+            return Err(ValidationError::VoteRootMismatch);
+        }
+
+        Ok(())
+    }
+}
+
+/// The votes a `VotingEndTransaction` commits to
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VoteCommitment {
+    /// The number of vote transactions in the election, including challenged votes
+    pub vote_count: u64,
+
+    /// The merkle root of the IDs of every vote transaction in the election (see `vote_root`)
+    #[serde(with = "hex_serde")]
+    pub vote_root: [u8; 32],
+}
+
+/// The merkle root of a set of vote IDs.
+///
+/// The IDs are sorted, so the root doesn't depend on the order the votes are given in. Each leaf is the SHA256 hash
+/// of `0x00` followed by the 32 byte ID, and each parent the SHA256 hash of `0x01` followed by its two children. A
+/// node without a sibling is carried up to the next level unchanged. The root of no votes is all zeros.
+pub fn vote_root(vote_ids: &[Identifier]) -> [u8; 32] {
+    MerkleAuditTree::from_vote_ids(vote_ids).root()
+}
+
+impl AuthorityTransaction for VotingEndTransaction {
+    fn set_authority_public(&mut self, public: PublicKey) {
+        self.authority_public_key = public;
     }
 }
 
@@ -46,6 +125,8 @@ impl CryptoBallotTransaction for VotingEndTransaction {
     ///
     /// The validation does the following:
     ///  - Validates that this transaction has been signed by a valid election authority
+    ///  - Validates that no vote was created after this transaction
+    ///  - Validates that the vote commitment, if present, commits to the votes that were cast
     fn validate_tx<S: Store>(&self, store: &S) -> Result<(), ValidationError> {
         let election = store.get_election(self.election)?;
 
@@ -54,6 +135,18 @@ impl CryptoBallotTransaction for VotingEndTransaction {
             return Err(ValidationError::AuthorityPublicKeyMismatch);
         }
 
+        // Every vote was cast before voting ended, so a vote claiming to be created later has a false timestamp
+        if let Some(voting_end) = self.created_at {
+            for tx in store.get_multiple(self.election, TransactionType::Vote) {
+                let vote: Signed<VoteTransaction> = tx.into();
+                if matches!(vote.created_at, Some(created_at) if created_at > voting_end) {
+                    return Err(ValidationError::VoteAfterVotingEnd(vote.id()));
+                }
+            }
+        }
+
+        self.verify_vote_commitment(store)?;
+
         Ok(())
     }
 }
@@ -0,0 +1,91 @@
+use cryptoballot::*;
+
+pub fn command_audit(matches: &clap::ArgMatches, uri: &str) {
+    let transactions: Vec<SignedTransaction> = match matches.value_of("input") {
+        Some(filename) => {
+            let filename = crate::expand(filename);
+            let file_bytes = std::fs::read(&filename).unwrap_or_else(|e| {
+                eprintln!("cryptoballot audit: unable to read {}: {}", filename, e);
+                std::process::exit(1);
+            });
+            serde_json::from_slice(&file_bytes).unwrap_or_else(|e| {
+                eprintln!("cryptoballot audit: unable to parse {}: {}", filename, e);
+                std::process::exit(1);
+            })
+        }
+        None => {
+            // Unwrap OK - ELECTION-ID is required unless --input is given
+            let election_id = crate::expand(matches.value_of("ELECTION-ID").unwrap());
+            if election_id.len() < 15 {
+                eprintln!("cryptoballot audit: invalid election-id");
+                std::process::exit(1);
+            }
+            crate::rest::get_transactions_by_prefix(uri, &election_id[0..15]).unwrap_or_else(|e| {
+                eprintln!("cryptoballot audit: unable to fetch transactions: {}", e);
+                std::process::exit(1);
+            })
+        }
+    };
+
+    if let Some(vote_id) = matches.value_of("audit-proof") {
+        print_inclusion_proof(&transactions, vote_id);
+        return;
+    }
+
+    let certified: Option<TallyTransaction> = matches.value_of("certified").map(|filename| {
+        let filename = crate::expand(filename);
+        let file_bytes = std::fs::read(&filename).unwrap_or_else(|e| {
+            eprintln!("cryptoballot audit: unable to read {}: {}", filename, e);
+            std::process::exit(1);
+        });
+        serde_json::from_slice(&file_bytes).unwrap_or_else(|e| {
+            eprintln!("cryptoballot audit: invalid certified result: {}", e);
+            std::process::exit(1);
+        })
+    });
+
+    let report = audit_election(&transactions, certified.as_ref());
+
+    // The structured report goes to stdout so it can be redirected to a file, the summary to stderr
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&report)
+            .expect("cryptoballot: unexpected error serializing JSON")
+    );
+    eprint!("{}", report.render_summary());
+
+    if !report.passed() {
+        std::process::exit(1);
+    }
+}
+
+// Print a proof that a vote is included in the votes committed to when voting ended
+fn print_inclusion_proof(transactions: &[SignedTransaction], vote_id: &str) {
+    let vote_id: Identifier = vote_id.parse().unwrap_or_else(|e| {
+        eprintln!("cryptoballot audit: invalid vote id: {}", e);
+        std::process::exit(1);
+    });
+
+    let votes: Vec<Signed<VoteTransaction>> = transactions
+        .iter()
+        .filter(|tx| tx.transaction_type() == TransactionType::Vote)
+        .filter(|tx| tx.id().election_id == vote_id.election_id)
+        .map(|tx| tx.clone().into())
+        .collect();
+
+    let tree = MerkleAuditTree::from_votes(&votes);
+    let proof = tree.inclusion_proof(vote_id).unwrap_or_else(|| {
+        eprintln!("cryptoballot audit: vote {} not found", vote_id);
+        std::process::exit(1);
+    });
+
+    let output = serde_json::json!({
+        "vote_root": hex::encode(tree.root()),
+        "proof": proof,
+    });
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&output)
+            .expect("cryptoballot: unexpected error serializing JSON")
+    );
+}
@@ -0,0 +1,51 @@
+use cryptoballot::*;
+
+pub fn command_check_trustees(matches: &clap::ArgMatches, uri: &str) {
+    // Unwrap OK - ELECTION-ID is required
+    let election_id = matches.value_of("ELECTION-ID").unwrap();
+    let election_id = Identifier::new_from_str_id(election_id, TransactionType::Election, None)
+        .unwrap_or_else(|| {
+            eprintln!(
+                "cryptoballot check-trustees: invalid election-id: {}",
+                election_id
+            );
+            std::process::exit(1);
+        });
+
+    let transactions = match matches.value_of("store") {
+        Some(store_path) => crate::load_transactions("check-trustees", &crate::expand(store_path)),
+        None => crate::rest::get_transactions_by_prefix(uri, &election_id.election_id_string())
+            .unwrap_or_else(|e| {
+                eprintln!(
+                    "cryptoballot check-trustees: unable to fetch transactions: {}",
+                    e
+                );
+                std::process::exit(1);
+            }),
+    };
+    let mut store = MemStore::default();
+    for tx in transactions {
+        store.set(tx);
+    }
+
+    let election = store.get_election(election_id).unwrap_or_else(|e| {
+        eprintln!("cryptoballot check-trustees: {}", e);
+        std::process::exit(1);
+    });
+
+    let pending = pending_partial_decryptions(&store, election_id);
+    for trustee in election.trustees.iter() {
+        if pending.contains(&trustee.id) {
+            println!("trustee {} ({}): pending", trustee.index, trustee.id);
+        }
+    }
+    if pending.is_empty() {
+        println!("all trustees have posted their partial decryptions");
+    }
+
+    // Exit with 2, distinct from errors, so that monitoring can alert on overdue trustees
+    if partial_decryptions_overdue(&store, election_id, &SystemClock) {
+        eprintln!("cryptoballot check-trustees: decryption deadline has passed");
+        std::process::exit(2);
+    }
+}
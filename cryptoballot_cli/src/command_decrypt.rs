@@ -0,0 +1,121 @@
+use cryptoballot::*;
+use ed25519_dalek::SecretKey;
+
+pub fn command_decrypt(matches: &clap::ArgMatches, secret_key: Option<&SecretKey>) {
+    // Unwraps OK - all args are required
+    let store_path = crate::expand(matches.value_of("store").unwrap());
+    let vote_id: Identifier = matches
+        .value_of("vote")
+        .unwrap()
+        .parse()
+        .unwrap_or_else(|e| {
+            eprintln!("cryptoballot decrypt: invalid vote id: {}", e);
+            std::process::exit(1);
+        });
+
+    let mut store = MemStore::default();
+    for tx in crate::load_transactions("decrypt", &store_path) {
+        store.set(tx);
+    }
+
+    let vote = store.get_vote(vote_id).unwrap_or_else(|e| {
+        eprintln!("cryptoballot decrypt: {}", e);
+        std::process::exit(1);
+    });
+    let election = store.get_election(vote.election).unwrap_or_else(|e| {
+        eprintln!("cryptoballot decrypt: {}", e);
+        std::process::exit(1);
+    });
+    if election.mix_config.is_some() {
+        eprintln!("cryptoballot decrypt: election uses a mixnet, votes must be decrypted from mix transactions");
+        std::process::exit(1);
+    }
+
+    let pubkeys = store
+        .get_decryption_public_keys(election.id)
+        .unwrap_or_else(|e| {
+            eprintln!("cryptoballot decrypt: {}", e);
+            std::process::exit(1);
+        });
+
+    // The decryption isn't made by any one party, so it may be signed by anyone
+    let secret_key = match secret_key {
+        Some(secret_key) => SecretKey::from_bytes(secret_key.as_bytes()).unwrap(),
+        None => generate_keypair().0,
+    };
+
+    for encrypted_vote in vote.encrypted_votes.iter() {
+        let mut partials = Vec::with_capacity(election.trustees.len());
+        for trustee in election.trustees.iter() {
+            let partial_id = PartialDecryptionTransaction::build_id(
+                election.id,
+                vote.id,
+                encrypted_vote.contest_index,
+                0,
+                trustee.index,
+            );
+            if let Some(partial) = store.find_partial_decryption(partial_id) {
+                partials.push(partial);
+            }
+        }
+
+        let required_shares = election.trustees_threshold as usize;
+        if partials.len() < required_shares {
+            let e = ValidationError::NotEnoughShares(required_shares, partials.len());
+            eprintln!(
+                "cryptoballot decrypt: contest {}: {}",
+                encrypted_vote.contest_index, e
+            );
+            std::process::exit(1);
+        }
+
+        let trustees: Vec<u8> = partials
+            .iter()
+            .map(|partial| partial.trustee_index)
+            .collect();
+        let decrypted_vote = decrypt_vote(
+            &encrypted_vote.selections,
+            election.trustees_threshold,
+            &election.trustees,
+            &pubkeys,
+            &partials,
+        );
+        let decryption = match decrypted_vote {
+            Ok(decrypted_vote) => DecryptionTransaction::new(
+                election.id,
+                vote.id,
+                encrypted_vote.contest_index,
+                0,
+                trustees,
+                decrypted_vote,
+            ),
+            Err(ValidationError::VoteDecodingError(_)) => DecryptionTransaction::new_spoiled(
+                election.id,
+                vote.id,
+                encrypted_vote.contest_index,
+                0,
+                trustees,
+            ),
+            Err(e) => {
+                eprintln!(
+                    "cryptoballot decrypt: contest {}: {}",
+                    encrypted_vote.contest_index, e
+                );
+                std::process::exit(1);
+            }
+        };
+
+        let decryption: SignedTransaction = Signed::sign(&secret_key, decryption).unwrap().into();
+        decryption.validate(&store).unwrap_or_else(|e| {
+            eprintln!(
+                "cryptoballot decrypt: contest {}: {}",
+                encrypted_vote.contest_index, e
+            );
+            std::process::exit(1);
+        });
+
+        // Serialize it and print it
+        let tx_json = serde_json::to_string_pretty(&decryption).unwrap();
+        println!("{}", tx_json);
+    }
+}
@@ -1,3 +1,4 @@
+use crate::output::{csv_field, format_transaction, OutputFormat, TallyFormat};
 use cryptoballot::*;
 use std::str;
 use tallystick::plurality::DefaultPluralityTally;
@@ -11,7 +12,7 @@ pub fn command_e2e(matches: &clap::ArgMatches, uri: &str) {
     }
     let prefix = &election_id[0..15];
 
-    let mut store = MemStore::default();
+    let store = MemStore::default();
 
     let transactions = crate::rest::get_transactions_by_prefix(uri, &prefix).unwrap();
 
@@ -22,28 +23,67 @@ pub fn command_e2e(matches: &clap::ArgMatches, uri: &str) {
 
     let first_transaction = &transactions[0];
     if first_transaction.transaction_type() != TransactionType::Election {
-        eprint!("Frist transaction must be an election transaction");
+        eprint!(
+            "First transaction must be an {} transaction, found {}",
+            TransactionType::Election.name(),
+            first_transaction.type_name()
+        );
         std::process::exit(1)
     }
     let election_id = first_transaction.id();
+    let election: ElectionTransaction = first_transaction.clone().into();
 
-    for tx in transactions {
-        match tx.validate(&store) {
-            Ok(()) => store.set(tx),
-            Err(e) => {
-                eprint!("Failed to validate transaction {}: {}", tx.id(), e);
-                std::process::exit(1)
-            }
-        }
+    let result = validate_log_with_progress(&transactions, &store, |current, total| {
+        eprint!("\r> Validating transaction {} of {}", current, total);
+    });
+    eprintln!();
+
+    if let Err((index, e)) = result {
+        let tx = &transactions[index];
+        eprint!(
+            "Failed to validate {} transaction {}: {}",
+            tx.type_name(),
+            tx.id(),
+            e
+        );
+        std::process::exit(1)
     }
 
-    println!("> Election verified OK");
+    // Keep stdout for the tally alone when it's being printed in a machine-readable format
+    let tally_format = TallyFormat::from_matches(matches);
+    let status = |line: String| match tally_format {
+        TallyFormat::Text => println!("{}", line),
+        _ => eprintln!("{}", line),
+    };
+
+    status("> Election verified OK".to_string());
+
+    if let Some(description) = &election.signing_authority_description {
+        status(format!("> Authorized by: {}", description));
+    }
+    if let Some(url) = &election.signing_authority_url {
+        status(format!("> Authority URL: {}", url));
+    }
+    for warning in election.validation_warnings() {
+        eprintln!("{}", warning);
+    }
 
     if matches.is_present("print-votes") {
         println!("Votes:");
         let votes = store.get_multiple(election_id, TransactionType::Decryption);
         for vote in votes {
+            if matches.is_present("format") {
+                let format = OutputFormat::from_matches(matches, OutputFormat::Json);
+                println!("{}", format_transaction(&vote, format));
+                continue;
+            }
+
             let vote: DecryptionTransaction = vote.into();
+            if vote.spoiled {
+                println!("  spoiled ballot {}", vote.upstream_id);
+                println!("");
+                continue;
+            }
             let vote = vote.decrypted_vote;
 
             for selection in vote {
@@ -55,39 +95,192 @@ pub fn command_e2e(matches: &clap::ArgMatches, uri: &str) {
     }
 
     if matches.is_present("print-tally") {
-        println!("Tally:");
-
         // TODO: Use a real tally / ballot / contest system
         let mut tally = DefaultPluralityTally::new(1);
+        let mut spoiled = 0;
 
         let votes = store.get_multiple(election_id, TransactionType::Decryption);
         for vote in votes {
             let vote: DecryptionTransaction = vote.into();
+            if vote.spoiled {
+                spoiled += 1;
+                continue;
+            }
             let vote = vote.decrypted_vote;
-            tally.add(vote[0].selection.clone());
+            let weight = vote_weight(&vote).unwrap_or_else(|e| {
+                eprintln!("cryptoballot e2e: {}", e);
+                std::process::exit(1);
+            });
+            tally.add_weighted(vote[0].selection.clone(), weight);
         }
 
-        for (candidate, num_votes) in tally.totals().iter() {
-            println!("  {} got {} votes", candidate, num_votes);
+        let totals = tally.totals();
+        match tally_format {
+            TallyFormat::Text => {
+                println!("Tally:");
+                for (candidate, num_votes) in totals.iter() {
+                    println!("  {} got {} votes", candidate, num_votes);
+                }
+                if spoiled > 0 {
+                    println!("  {} spoiled ballots", spoiled);
+                }
+            }
+            TallyFormat::Json => {
+                let totals: Vec<_> = totals
+                    .iter()
+                    .map(|(candidate, num_votes)| {
+                        serde_json::json!({ "candidate": candidate, "votes": num_votes })
+                    })
+                    .collect();
+                let output = serde_json::json!({ "tally": totals, "spoiled": spoiled });
+                println!("{}", serde_json::to_string_pretty(&output).unwrap());
+            }
+            TallyFormat::Csv => {
+                println!("candidate,votes");
+                for (candidate, num_votes) in totals.iter() {
+                    println!("{},{}", csv_field(candidate), num_votes);
+                }
+            }
+        }
+    }
+
+    if matches.is_present("tally-referendum") {
+        println!("Referendums:");
+
+        let decryptions: Vec<DecryptionTransaction> = store
+            .get_multiple(election_id, TransactionType::Decryption)
+            .into_iter()
+            .map(|tx| tx.into())
+            .collect();
+
+        for contest in election.contests.iter() {
+            let question = match &contest.question {
+                Some(question) if question.ballot_type == ReferendumType::YesNo => question,
+                _ => continue,
+            };
+
+            let votes: Vec<DecryptionTransaction> = decryptions
+                .iter()
+                .filter(|decryption| decryption.contest_index == contest.index)
+                .cloned()
+                .collect();
+            let (yes, no) = tally_referendum(&votes).unwrap_or_else(|e| {
+                eprintln!("cryptoballot e2e: {}", e);
+                std::process::exit(1);
+            });
+
+            println!("  {}", question.question);
+            println!("    Yes: {}", yes);
+            println!("    No:  {}", no);
         }
     }
 
     if matches.is_present("print-results") {
-        println!("Results:");
+        if tally_format == TallyFormat::Text {
+            println!("Results:");
+        }
 
-        // TODO: Use a real tally / ballot / contest system
-        let mut tally = DefaultPluralityTally::new(1);
+        let tie_break = match matches.value_of("tie-break") {
+            None => TieBreak::Report,
+            Some("random") => {
+                // Print the seed so the draw can be reproduced with --tie-break random:<seed>
+                let seed = rand::random();
+                status(format!("  Breaking ties at random with seed {}", seed));
+                TieBreak::Random(seed)
+            }
+            Some(strategy) => strategy.parse().unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }),
+        };
 
-        let votes = store.get_multiple(election_id, TransactionType::Decryption);
-        for vote in votes {
-            let vote: DecryptionTransaction = vote.into();
-            let vote = vote.decrypted_vote;
+        let results = tally_election(&store, election_id).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        });
+        if tally_format == TallyFormat::Text {
+            println!("  Ballots cast: {}", results.total_ballots);
+        }
+        if tally_format == TallyFormat::Csv {
+            println!("contest,candidate,votes,winner");
+        }
+
+        let mut contests_json = Vec::with_capacity(election.contests.len());
+        for contest in election.contests.iter() {
+            // Indexing OK - tally_election tallies every contest
+            let resolved = results.contests[&contest.id]
+                .resolve_winners(contest.num_winners, tie_break)
+                .unwrap_or_else(|e| {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                });
 
-            // For now, assume it's a string
-            tally.add(vote[0].selection.clone());
+            let totals = &results.contests[&contest.id].totals;
+            let spoiled_ballots = &results.contests[&contest.id].spoiled_ballots;
+            match tally_format {
+                TallyFormat::Text => {}
+                TallyFormat::Json => {
+                    let totals: Vec<_> = totals
+                        .iter()
+                        .map(|(candidate, num_votes)| {
+                            serde_json::json!({ "candidate": candidate, "votes": num_votes })
+                        })
+                        .collect();
+                    let spoiled: serde_json::Map<_, _> = spoiled_ballots
+                        .iter()
+                        .map(|(vote_id, reason)| (vote_id.to_string(), reason.to_string().into()))
+                        .collect();
+                    contests_json.push(serde_json::json!({
+                        "contest": contest.id,
+                        "totals": totals,
+                        "winners": resolved.winners,
+                        "tied": resolved.tied,
+                        "tied_places": resolved.tied_places,
+                        "spoiled_ballots": spoiled,
+                    }));
+                    continue;
+                }
+                TallyFormat::Csv => {
+                    for (candidate, num_votes) in totals.iter() {
+                        println!(
+                            "{},{},{},{}",
+                            csv_field(&contest.id),
+                            csv_field(candidate),
+                            num_votes,
+                            resolved.winners.contains(candidate)
+                        );
+                    }
+                    continue;
+                }
+            }
+
+            if election.contests.len() > 1 {
+                println!("  Contest {}:", contest.id);
+            }
+            for (vote_id, reason) in spoiled_ballots.iter() {
+                println!("  Spoiled ballot {}: {}", vote_id, reason);
+            }
+
+            match resolved.winners.len() {
+                0 => {}
+                1 => println!("  The winner is {}", resolved.winners[0]),
+                _ => println!("  The winners are {}", resolved.winners.join(", ")),
+            }
+            if !resolved.tied.is_empty() {
+                println!(
+                    "  Tie between {} for {} remaining place(s)",
+                    resolved.tied.join(", "),
+                    resolved.tied_places
+                );
+            }
         }
 
-        let winners = tally.winners().into_unranked();
-        println!("  The winner is {}", winners[0]);
+        if tally_format == TallyFormat::Json {
+            let output = serde_json::json!({
+                "total_ballots": results.total_ballots,
+                "contests": contests_json,
+            });
+            println!("{}", serde_json::to_string_pretty(&output).unwrap());
+        }
     }
 }
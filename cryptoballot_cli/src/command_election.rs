@@ -1,4 +1,5 @@
 use cryptoballot::indexmap::IndexMap;
+use cryptoballot::uuid::Uuid;
 use cryptoballot::Ballot;
 use cryptoballot::Contest;
 use cryptoballot::ContestType;
@@ -46,6 +47,7 @@ pub fn command_election_generate(uri: &str, secret_key: &SecretKey, post: bool)
         write_in: true,
         num_winners: 1,
         candidates: vec![],
+        question: None,
         properties: IndexMap::new(),
     };
 
@@ -54,6 +56,7 @@ pub fn command_election_generate(uri: &str, secret_key: &SecretKey, post: bool)
     election.authenticators_threshold = 0;
 
     let trustee = Trustee {
+        id: Uuid::new_v4(),
         index: 1,
         public_key,
         num_trustees: 1,
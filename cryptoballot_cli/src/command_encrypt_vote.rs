@@ -0,0 +1,145 @@
+use cryptoballot::*;
+use ed25519_dalek::PublicKey;
+use ed25519_dalek::SecretKey;
+use rand::Rng;
+use std::io::Read;
+
+pub fn command_encrypt_vote(matches: &clap::ArgMatches, secret_key: Option<&SecretKey>) {
+    let mut rng = rand::thread_rng();
+
+    // Unwrap OK - election is required
+    let filename = crate::expand(matches.value_of("election").unwrap());
+    let file_bytes = std::fs::read(&filename).unwrap_or_else(|e| {
+        eprintln!(
+            "cryptoballot encrypt-vote: unable to read {}: {}",
+            &filename, e
+        );
+        std::process::exit(1);
+    });
+
+    // The election file is either the election's transactions, or a single transaction
+    let txs: Vec<SignedTransaction> = serde_json::from_slice(&file_bytes)
+        .or_else(|_| serde_json::from_slice(&file_bytes).map(|tx| vec![tx]))
+        .unwrap_or_else(|e| {
+            eprintln!(
+                "cryptoballot encrypt-vote: unable to parse {}: {}",
+                &filename, e
+            );
+            std::process::exit(1);
+        });
+
+    // The encryption key is generated by the trustees, so it comes from the encryption_key transaction
+    let encryption_key_tx: EncryptionKeyTransaction = txs
+        .iter()
+        .find(|tx| tx.transaction_type() == TransactionType::EncryptionKey)
+        .cloned()
+        .unwrap_or_else(|| {
+            eprintln!(
+                "cryptoballot encrypt-vote: {} does not contain an encryption_key transaction",
+                &filename
+            );
+            std::process::exit(1);
+        })
+        .into();
+    let election: Option<ElectionTransaction> = txs
+        .into_iter()
+        .find(|tx| tx.transaction_type() == TransactionType::Election)
+        .map(|tx| tx.into());
+
+    // Default to the election's first ballot and that ballot's first contest
+    let ballot = election.as_ref().and_then(|e| e.ballots.first());
+    let ballot_id = match matches.value_of("ballot-id") {
+        Some(ballot_id) => ballot_id.to_string(),
+        None => match ballot {
+            Some(ballot) => ballot.id.clone(),
+            None => {
+                eprintln!("cryptoballot encrypt-vote: no ballot found, use --ballot-id");
+                std::process::exit(1);
+            }
+        },
+    };
+    let contest_index = match matches.value_of("contest") {
+        Some(contest) => contest.parse().unwrap_or_else(|e| {
+            eprintln!(
+                "cryptoballot encrypt-vote: invalid contest index {}: {}",
+                contest, e
+            );
+            std::process::exit(1);
+        }),
+        None => ballot
+            .and_then(|ballot| ballot.contests.first().cloned())
+            .unwrap_or(0),
+    };
+
+    // Read the plaintext from stdin if it's not given
+    let plaintext = match matches.value_of("ballot") {
+        Some(plaintext) if plaintext != "-" => plaintext.to_string(),
+        _ => {
+            let mut plaintext = String::new();
+            std::io::stdin()
+                .read_to_string(&mut plaintext)
+                .unwrap_or_else(|e| {
+                    eprintln!("cryptoballot encrypt-vote: unable to read stdin: {}", e);
+                    std::process::exit(1);
+                });
+            plaintext.trim_end().to_string()
+        }
+    };
+
+    let selection = Selection {
+        write_in: true,
+        score: 0,
+        selection: plaintext,
+        weight: None,
+    };
+
+    // The vote must be signed with its anonymous key, so print a new one for the caller to sign with.
+    // The encryption proofs are bound to the anonymous key, so it's needed before encrypting.
+    let public_key: PublicKey = match secret_key {
+        Some(secret_key) => secret_key.into(),
+        None => {
+            let (secret_key, public_key) = generate_keypair();
+            eprintln!(
+                "anonymous secret key: {}",
+                hex::encode(secret_key.to_bytes())
+            );
+            public_key
+        }
+    };
+
+    // The seed is needed to challenge the vote, and must be kept secret otherwise
+    let election_id = encryption_key_tx.election;
+    let seed: [u8; 32] = rng.gen();
+    eprintln!("challenge seed: {}", hex::encode(seed));
+    let plaintext = vec![PlaintextVote {
+        contest_index,
+        selections: vec![selection],
+    }];
+    let (encrypted_votes, encryption_proof) = encrypt_vote_with_proof(
+        &encryption_key_tx.encryption_key,
+        election_id,
+        &public_key,
+        &plaintext,
+        seed,
+        &mut rng,
+    )
+    .unwrap_or_else(|e| {
+        eprintln!("cryptoballot encrypt-vote: unable to encrypt vote: {}", e);
+        std::process::exit(1);
+    });
+
+    let vote = VoteTransaction {
+        id: VoteTransaction::build_id(election_id, &public_key),
+        election: election_id,
+        ballot_id,
+        encrypted_votes,
+        anonymous_key: public_key,
+        authentication: vec![],
+        weight: None,
+        encryption_proof,
+        vote_validity_proof: None,
+        created_at: None,
+    };
+
+    println!("{}", serde_json::to_string_pretty(&vote).unwrap());
+}
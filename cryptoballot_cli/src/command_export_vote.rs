@@ -0,0 +1,50 @@
+use cryptoballot::*;
+use std::path::Path;
+
+pub fn command_export_vote(matches: &clap::ArgMatches) {
+    // Unwraps OK - all args are required
+    let store_path = crate::expand(matches.value_of("store").unwrap());
+    let vote_id: Identifier = matches
+        .value_of("vote")
+        .unwrap()
+        .parse()
+        .unwrap_or_else(|e| {
+            eprintln!("cryptoballot export-vote: invalid vote id: {}", e);
+            std::process::exit(1);
+        });
+    let out = crate::expand(matches.value_of("out").unwrap());
+    let out = Path::new(&out);
+
+    let mut store = MemStore::default();
+    for tx in crate::load_transactions("export-vote", &store_path) {
+        store.set(tx);
+    }
+
+    let bundle = vote_bundle(&store, vote_id).unwrap_or_else(|e| {
+        eprintln!("cryptoballot export-vote: {}", e);
+        std::process::exit(1);
+    });
+
+    // Never append to an existing log, or the bundle would no longer be just this vote's transactions
+    if out.exists() {
+        eprintln!("cryptoballot export-vote: {} already exists", out.display());
+        std::process::exit(1);
+    }
+    let mut log = TransactionLog::open(out).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    let num_transactions = bundle.len();
+    for tx in bundle {
+        log.set(tx).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        });
+    }
+
+    eprintln!(
+        "cryptoballot export-vote: wrote {} transactions to {}",
+        num_transactions,
+        out.display()
+    );
+}
@@ -0,0 +1,274 @@
+use cryptoballot::indexmap::IndexMap;
+use cryptoballot::*;
+use dialoguer::{Confirm, Input};
+use ed25519_dalek::PublicKey;
+use ed25519_dalek::SecretKey;
+use std::fs::OpenOptions;
+use std::io::prelude::*;
+use std::path::Path;
+
+pub fn command_generate_election(matches: &clap::ArgMatches, secret_key: Option<&SecretKey>) {
+    let non_interactive = matches.is_present("non-interactive");
+
+    // Unwrap OK - secrets-dir has a default
+    let secrets_dir = crate::expand(matches.value_of("secrets-dir").unwrap());
+    std::fs::create_dir_all(&secrets_dir).unwrap_or_else(|e| {
+        eprintln!(
+            "cryptoballot generate-election: cannot create directory {}: {}",
+            &secrets_dir, e
+        );
+        std::process::exit(1);
+    });
+    let secrets_dir = Path::new(&secrets_dir);
+
+    let title: String = prompt(matches, "title", "Election title", None, non_interactive);
+    let num_trustees: u8 = prompt(
+        matches,
+        "trustees",
+        "Number of trustees",
+        Some(3),
+        non_interactive,
+    );
+    let threshold: u8 = prompt(
+        matches,
+        "threshold",
+        "Trustees needed to decrypt",
+        Some(num_trustees / 2 + 1),
+        non_interactive,
+    );
+    let keysize: usize = prompt(
+        matches,
+        "authn-keysize",
+        "Authenticator RSA key size",
+        Some(4096),
+        non_interactive,
+    );
+    let ballot_ids: Vec<String> = match matches.values_of("ballot") {
+        Some(ballot_ids) => ballot_ids.map(|id| id.to_string()).collect(),
+        None => {
+            let ballot_ids: String = prompt(
+                matches,
+                "ballot",
+                "Ballot IDs (comma separated)",
+                Some("BALLOT1".to_string()),
+                non_interactive,
+            );
+            ballot_ids
+                .split(',')
+                .map(|id| id.trim().to_string())
+                .filter(|id| !id.is_empty())
+                .collect()
+        }
+    };
+    let mix = matches.is_present("mix")
+        || (!non_interactive
+            && Confirm::new()
+                .with_prompt("Shuffle votes with a mixnet before decryption?")
+                .default(false)
+                .interact()
+                .unwrap_or_else(|e| prompt_failed(e)));
+    let mix_timeout: u64 = if mix {
+        prompt(
+            matches,
+            "mix-timeout",
+            "Mixnet timeout in seconds",
+            Some(3600),
+            non_interactive,
+        )
+    } else {
+        0
+    };
+
+    if num_trustees == 0 {
+        eprintln!("cryptoballot generate-election: there must be at least one trustee");
+        std::process::exit(1);
+    }
+    if threshold == 0 || threshold > num_trustees {
+        eprintln!(
+            "cryptoballot generate-election: threshold must be between 1 and the number of trustees"
+        );
+        std::process::exit(1);
+    }
+    if ballot_ids.is_empty() {
+        eprintln!("cryptoballot generate-election: there must be at least one ballot");
+        std::process::exit(1);
+    }
+    if keysize < 2048 {
+        eprintln!("cryptoballot: WARNING: Using insecure keysize for authn")
+    }
+
+    // Use the provided authority key, or generate one and keep its secret with the others
+    let authority_secret = match secret_key {
+        Some(secret_key) => SecretKey::from_bytes(secret_key.as_bytes()).unwrap(),
+        None => {
+            let (secret, _) = generate_keypair();
+            write_secret(
+                &secrets_dir.join("authority.key"),
+                hex::encode(secret.to_bytes()).as_bytes(),
+            );
+            secret
+        }
+    };
+    let authority_public: PublicKey = (&authority_secret).into();
+
+    let mut election = ElectionTransaction::new(authority_public);
+    election
+        .properties
+        .insert("title".to_string(), serde_json::Value::String(title));
+
+    // Every ballot holds a single write-in plurality contest, to be edited before the election is posted
+    election.contests = vec![Contest {
+        id: "CONTEST1".to_string(),
+        index: 0,
+        contest_type: ContestType::Plurality,
+        write_in: true,
+        num_winners: 1,
+        candidates: vec![],
+        question: None,
+        properties: IndexMap::new(),
+    }];
+    election.ballots = ballot_ids
+        .iter()
+        .map(|id| Ballot {
+            id: id.clone(),
+            contests: vec![0],
+            properties: IndexMap::new(),
+        })
+        .collect();
+
+    let (authn, authn_secrets) = Authenticator::new(keysize, &ballot_ids).unwrap_or_else(|e| {
+        eprintln!(
+            "cryptoballot generate-election: unable to generate authenticator: {}",
+            e
+        );
+        std::process::exit(1);
+    });
+    write_secret(
+        &secrets_dir.join("authenticator.json"),
+        serde_json::to_string_pretty(&authn_secrets)
+            .unwrap()
+            .as_bytes(),
+    );
+    election.authenticators = vec![authn];
+    election.authenticators_threshold = 1;
+
+    let mut trustees = Vec::with_capacity(num_trustees as usize);
+    for index in 1..=num_trustees {
+        let (trustee, secret) = Trustee::new(index, num_trustees as usize, threshold);
+        write_secret(
+            &secrets_dir.join(format!("trustee_{}.key", index)),
+            hex::encode(secret.to_bytes()).as_bytes(),
+        );
+        trustees.push((trustee, secret));
+    }
+    election.trustees = trustees
+        .iter()
+        .map(|(trustee, _)| trustee.clone())
+        .collect();
+    election.trustees_threshold = threshold;
+
+    if mix {
+        election.mix_config = Some(MixConfig {
+            timeout_secs: mix_timeout,
+            batch_size: None,
+        });
+    }
+
+    let election_id = election.id;
+    let election_tx: SignedTransaction = Signed::sign(&authority_secret, election).unwrap().into();
+    let mut txs = vec![election_tx];
+
+    // Each trustee's keygen commitment is the first round of key generation, and only needs the trustee's own secret
+    for (trustee, secret) in trustees.iter() {
+        let commitment_tx = KeyGenCommitmentTransaction::new(
+            election_id,
+            trustee.index,
+            trustee.public_key,
+            trustee.x25519_public_key(secret, election_id),
+            trustee.keygen_commitment(secret, election_id),
+        );
+        txs.push(Signed::sign(secret, commitment_tx).unwrap().into());
+    }
+
+    println!("{}", serde_json::to_string_pretty(&txs).unwrap());
+    eprintln!(
+        "cryptoballot generate-election: secrets written to {}",
+        secrets_dir.display()
+    );
+}
+
+// Take a value from its flag, falling back to prompting for it unless running non-interactively
+fn prompt<T>(
+    matches: &clap::ArgMatches,
+    name: &str,
+    prompt: &str,
+    default: Option<T>,
+    non_interactive: bool,
+) -> T
+where
+    T: Clone + std::fmt::Display + std::str::FromStr,
+    T::Err: std::fmt::Display + std::fmt::Debug,
+{
+    if let Some(value) = matches.value_of(name) {
+        return value.parse().unwrap_or_else(|_| {
+            eprintln!(
+                "cryptoballot generate-election: invalid {}: {}",
+                name, value
+            );
+            std::process::exit(1);
+        });
+    }
+
+    if non_interactive {
+        return default.unwrap_or_else(|| {
+            eprintln!(
+                "cryptoballot generate-election: --{} is required with --non-interactive",
+                name
+            );
+            std::process::exit(1);
+        });
+    }
+
+    let mut input = Input::<T>::new();
+    input.with_prompt(prompt);
+    if let Some(default) = default {
+        input.default(default);
+    }
+    input.interact_text().unwrap_or_else(|e| prompt_failed(e))
+}
+
+fn prompt_failed<T>(e: std::io::Error) -> T {
+    eprintln!(
+        "cryptoballot generate-election: unable to read input: {}",
+        e
+    );
+    std::process::exit(1);
+}
+
+// Secrets should only be readable by the current user, and never overwrite an existing secret
+fn write_secret(location: &Path, secret: &[u8]) {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options.open(location).unwrap_or_else(|e| {
+        eprintln!(
+            "cryptoballot generate-election: cannot create file {}: {}",
+            location.display(),
+            e
+        );
+        std::process::exit(1);
+    });
+    file.write_all(secret).unwrap_or_else(|e| {
+        eprintln!(
+            "cryptoballot generate-election: unable to write secret to {}: {}",
+            location.display(),
+            e
+        );
+        std::process::exit(1);
+    });
+}
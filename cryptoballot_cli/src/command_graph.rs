@@ -0,0 +1,31 @@
+use cryptoballot::*;
+
+pub fn command_graph(matches: &clap::ArgMatches, uri: &str) {
+    // Unwrap OK - ELECTION-ID is required
+    let election_id_str = crate::expand(matches.value_of("ELECTION-ID").unwrap());
+    let election_id =
+        Identifier::new_from_str_id(&election_id_str, TransactionType::Election, None)
+            .unwrap_or_else(|| {
+                eprintln!("cryptoballot graph: invalid election-id");
+                std::process::exit(1);
+            });
+
+    let transactions = crate::rest::get_transactions_by_prefix(uri, &election_id_str[0..15])
+        .unwrap_or_else(|e| {
+            eprintln!("cryptoballot graph: unable to fetch transactions: {}", e);
+            std::process::exit(1);
+        });
+    let store = MemStore::from(transactions);
+    let dot = export_dot(&store, election_id);
+
+    match matches.value_of("FILE") {
+        Some(filename) => {
+            let filename = crate::expand(filename);
+            std::fs::write(&filename, dot).unwrap_or_else(|e| {
+                eprintln!("cryptoballot graph: unable to write {}: {}", filename, e);
+                std::process::exit(1);
+            });
+        }
+        None => print!("{}", dot),
+    }
+}
@@ -0,0 +1,214 @@
+use crate::keyfile::{secret_key_from_pem, secret_key_to_pem, EncryptedKeyShares, KeyShares};
+use cryptoballot::x25519_dalek as x25519;
+use cryptoballot::*;
+use dialoguer::Password;
+use ed25519_dalek::PublicKey;
+use std::fs::OpenOptions;
+use std::io::prelude::*;
+use std::path::Path;
+
+pub fn command_export_keys(matches: &clap::ArgMatches) {
+    // Unwraps OK - all args are required
+    let store_path = crate::expand(matches.value_of("store").unwrap());
+    let secret_location = crate::expand(matches.value_of("secret").unwrap());
+    let trustee_index = parse_trustee_index("export-keys", matches);
+    let output_dir = crate::expand(matches.value_of("output-dir").unwrap());
+
+    let secret_key = crate::command_trustee::read_secret_key(&secret_location);
+    let public_key: PublicKey = (&secret_key).into();
+
+    let mut store = MemStore::default();
+    for tx in crate::load_transactions("export-keys", &store_path) {
+        store.set(tx);
+    }
+
+    // Find the election this trustee belongs to
+    let mut elections = store.list_elections().into_iter().filter_map(|id| {
+        let election = store.get_election(id).ok()?;
+        match election.get_trustee(trustee_index) {
+            Some(trustee) if trustee.public_key == public_key => Some(election),
+            _ => None,
+        }
+    });
+    let election = match (elections.next(), elections.next()) {
+        (Some(election), None) => election,
+        (None, _) => {
+            eprintln!(
+                "cryptoballot export-keys: no election has trustee {} with this secret key",
+                trustee_index
+            );
+            std::process::exit(1);
+        }
+        (Some(_), Some(_)) => {
+            eprintln!("cryptoballot export-keys: more than one election has this trustee");
+            std::process::exit(1);
+        }
+    };
+    let trustee = election
+        .get_full_trustees()
+        .into_iter()
+        .find(|trustee| trustee.index == trustee_index)
+        .unwrap();
+
+    let x25519_public_keys: Vec<(u8, x25519::PublicKey)> = store
+        .get_multiple(election.id, TransactionType::KeyGenCommitment)
+        .into_iter()
+        .map(|tx| {
+            let tx: KeyGenCommitmentTransaction = tx.into();
+            (tx.trustee_index, tx.x25519_public_key)
+        })
+        .collect();
+
+    let mut shares: Vec<(u8, EncryptedShare)> = Vec::new();
+    for tx in store.get_multiple(election.id, TransactionType::KeyGenShare) {
+        let tx: KeyGenShareTransaction = tx.into();
+        if let Some(share) = tx.shares.get(&trustee.index) {
+            shares.push((tx.trustee_index, share.clone()));
+        }
+    }
+    let shares = trustee
+        .decrypt_key_shares(&secret_key, &shares, &x25519_public_keys, election.id)
+        .unwrap_or_else(|e| {
+            eprintln!("cryptoballot export-keys: {}", e);
+            std::process::exit(1);
+        });
+    let shares = KeyShares::new(election.id, trustee.index, &shares);
+
+    let passphrase = passphrase(true);
+    let mut rng = rand::thread_rng();
+    let encrypted = EncryptedKeyShares::encrypt(&mut rng, &passphrase, &shares);
+
+    let output_dir = Path::new(&output_dir);
+    let (key_path, shares_path) = key_paths(output_dir, trustee_index);
+    write_secret_file(&key_path, secret_key_to_pem(&secret_key).as_bytes());
+    write_secret_file(
+        &shares_path,
+        serde_json::to_string_pretty(&encrypted).unwrap().as_bytes(),
+    );
+
+    eprintln!(
+        "cryptoballot export-keys: wrote {} and {}",
+        key_path.display(),
+        shares_path.display()
+    );
+}
+
+pub fn command_import_keys(matches: &clap::ArgMatches) {
+    // Unwraps OK - these args are required
+    let trustee_index = parse_trustee_index("import-keys", matches);
+    let input_dir = crate::expand(matches.value_of("input-dir").unwrap());
+    let secret_location = matches.value_of("secret").map(crate::expand);
+
+    let (key_path, shares_path) = key_paths(Path::new(&input_dir), trustee_index);
+    let pem = std::fs::read_to_string(&key_path).unwrap_or_else(|e| {
+        eprintln!(
+            "cryptoballot import-keys: unable to read {}: {}",
+            key_path.display(),
+            e
+        );
+        std::process::exit(1);
+    });
+    let secret_key = secret_key_from_pem(&pem).unwrap_or_else(|e| {
+        eprintln!("cryptoballot import-keys: {}: {}", key_path.display(), e);
+        std::process::exit(1);
+    });
+
+    let encrypted = std::fs::read(&shares_path).unwrap_or_else(|e| {
+        eprintln!(
+            "cryptoballot import-keys: unable to read {}: {}",
+            shares_path.display(),
+            e
+        );
+        std::process::exit(1);
+    });
+    let encrypted: EncryptedKeyShares = serde_json::from_slice(&encrypted).unwrap_or_else(|e| {
+        eprintln!("cryptoballot import-keys: {}: {}", shares_path.display(), e);
+        std::process::exit(1);
+    });
+    let shares = encrypted
+        .decrypt(&passphrase(false))
+        .and_then(|shares| shares.scalars().map(|_| shares))
+        .unwrap_or_else(|e| {
+            eprintln!("cryptoballot import-keys: {}: {}", shares_path.display(), e);
+            std::process::exit(1);
+        });
+    if shares.trustee_index != trustee_index {
+        eprintln!(
+            "cryptoballot import-keys: {} holds the shares of trustee {}",
+            shares_path.display(),
+            shares.trustee_index
+        );
+        std::process::exit(1);
+    }
+
+    // Write the secret key in the format used by `trustee partial-decrypt --secret`
+    if let Some(secret_location) = secret_location {
+        let secret = hex::encode(secret_key.to_bytes());
+        write_secret_file(Path::new(&secret_location), secret.as_bytes());
+    }
+
+    let public_key: PublicKey = (&secret_key).into();
+    let summary = serde_json::json!({
+        "election_id": shares.election_id,
+        "trustee_index": shares.trustee_index,
+        "public_key": hex::encode(public_key.to_bytes()),
+        "shares": shares.shares.len(),
+    });
+    println!("{}", serde_json::to_string_pretty(&summary).unwrap());
+}
+
+fn parse_trustee_index(command: &str, matches: &clap::ArgMatches) -> u8 {
+    let index = matches.value_of("trustee-index").unwrap();
+    match index.parse() {
+        Ok(index) if index > 0 => index,
+        _ => {
+            eprintln!("cryptoballot {}: invalid trustee-index: {}", command, index);
+            std::process::exit(1);
+        }
+    }
+}
+
+// The PEM secret key and encrypted shares files for a trustee
+fn key_paths(dir: &Path, trustee_index: u8) -> (std::path::PathBuf, std::path::PathBuf) {
+    (
+        dir.join(format!("trustee-{}.pem", trustee_index)),
+        dir.join(format!("trustee-{}-shares.json", trustee_index)),
+    )
+}
+
+// Read the passphrase from CRYPTOBALLOT_PASSPHRASE, or prompt for it
+fn passphrase(confirm: bool) -> String {
+    if let Ok(passphrase) = std::env::var("CRYPTOBALLOT_PASSPHRASE") {
+        return passphrase;
+    }
+
+    let mut prompt = Password::new();
+    prompt.with_prompt("Passphrase");
+    if confirm {
+        prompt.with_confirmation("Repeat passphrase", "Passphrases do not match");
+    }
+    prompt.interact().unwrap_or_else(|e| {
+        eprintln!("cryptoballot: unable to read passphrase: {}", e);
+        std::process::exit(1);
+    })
+}
+
+// Secret files should only be readable by the current user, and are never overwritten
+fn write_secret_file(path: &Path, contents: &[u8]) {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options.open(path).unwrap_or_else(|e| {
+        eprintln!("cryptoballot: cannot create file {}: {}", path.display(), e);
+        std::process::exit(1);
+    });
+    file.write_all(contents).unwrap_or_else(|e| {
+        eprintln!("cryptoballot: unable to write {}: {}", path.display(), e);
+        std::process::exit(1);
+    });
+}
@@ -0,0 +1,16 @@
+use cryptoballot::*;
+
+pub fn command_list_elections(matches: &clap::ArgMatches) {
+    // Unwrap OK - store is required
+    let path = crate::expand(matches.value_of("store").unwrap());
+
+    // Transactions are only listed, not validated, so they are stored as-is
+    let mut store = MemStore::default();
+    for tx in crate::load_transactions("list-elections", &path) {
+        store.set(tx);
+    }
+
+    for election_id in store.list_elections() {
+        println!("{}", election_id);
+    }
+}
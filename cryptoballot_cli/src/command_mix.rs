@@ -0,0 +1,114 @@
+use cryptoballot::*;
+
+pub fn command_mix(matches: &clap::ArgMatches) {
+    // Unwraps OK - election-id and STORE are required
+    let store_path = crate::expand(matches.value_of("STORE").unwrap());
+    let election_id = matches.value_of("election-id").unwrap();
+    let election_id = Identifier::new_from_str_id(election_id, TransactionType::Election, None)
+        .unwrap_or_else(|| {
+            eprintln!("cryptoballot mix: invalid election-id: {}", election_id);
+            std::process::exit(1);
+        });
+
+    let mut store = MemStore::default();
+    for tx in crate::load_transactions("mix", &store_path) {
+        store.set(tx);
+    }
+
+    let election = store.get_election(election_id).unwrap_or_else(|e| {
+        eprintln!("cryptoballot mix: {}", e);
+        std::process::exit(1);
+    });
+    if election.mix_config.is_none() {
+        eprintln!("cryptoballot mix: election does not use a mixnet");
+        std::process::exit(1);
+    }
+    let encryption_key = store.get_encryption_key(election_id).unwrap_or_else(|e| {
+        eprintln!("cryptoballot mix: {}", e);
+        std::process::exit(1);
+    });
+
+    // Votes are shuffled once by each of the first `trustees_threshold` trustees, defaulting to all of them
+    let num_shuffles = match matches.value_of("num-shuffles") {
+        Some(num_shuffles) => num_shuffles.parse().unwrap_or_else(|_| {
+            eprintln!("cryptoballot mix: invalid num-shuffles: {}", num_shuffles);
+            std::process::exit(1);
+        }),
+        None => election.trustees_threshold,
+    };
+    if num_shuffles == 0 || num_shuffles > election.trustees_threshold {
+        eprintln!(
+            "cryptoballot mix: num-shuffles must be between 1 and {}",
+            election.trustees_threshold
+        );
+        std::process::exit(1);
+    }
+
+    let votes = store.get_cast_votes(election_id);
+    let vote_ids: Vec<Identifier> = votes.iter().map(|vote| vote.id()).collect();
+
+    let mut rng = rand::thread_rng();
+    let mut mixes: Vec<Transaction> = Vec::new();
+    for contest in election.contests.iter() {
+        let ciphertexts: Vec<_> = votes
+            .iter()
+            .filter_map(|vote| vote.get_contest(contest.index))
+            .map(|encrypted_vote| encrypted_vote.selections.clone())
+            .collect();
+        if ciphertexts.is_empty() {
+            continue;
+        }
+
+        let mut builder = MixTransactionBuilder::new(election_id, ciphertexts, 0)
+            .with_contest(contest.index, 0)
+            .with_vote_ids(vote_ids.clone());
+        let mut prev_mix: Option<MixTransaction> = None;
+
+        // Each mix is made on behalf of the trustee who will sign it, the trustee at index mix_index + 1
+        for mix_index in 0..num_shuffles {
+            let trustee = election.get_trustee(mix_index + 1).unwrap_or_else(|| {
+                eprintln!(
+                    "cryptoballot mix: election has no trustee {}",
+                    mix_index + 1
+                );
+                std::process::exit(1);
+            });
+            let mix = builder
+                .build(&mut rng, trustee, &encryption_key.encryption_key)
+                .unwrap_or_else(|e| {
+                    eprintln!("cryptoballot mix: contest {}: {}", contest.index, e);
+                    std::process::exit(1);
+                });
+
+            // Later mixes can't be fully validated until the mix before them is signed and posted, so just check
+            // their proof against the previous mix's output
+            let valid = match &prev_mix {
+                None => mix.validate_tx(&store).map_err(|e| e.to_string()),
+                Some(prev_mix) => {
+                    if mix.verify_shuffle_proof(
+                        &prev_mix.mixed_ciphertexts,
+                        &encryption_key.encryption_key,
+                    ) {
+                        Ok(())
+                    } else {
+                        Err(ValidationError::ShuffleVerificationFailed.to_string())
+                    }
+                }
+            };
+            if let Err(e) = valid {
+                eprintln!(
+                    "cryptoballot mix: contest {} mix {}: {}",
+                    contest.index, mix_index, e
+                );
+                std::process::exit(1);
+            }
+
+            builder = MixTransactionBuilder::after(&mix);
+            prev_mix = Some(mix.clone());
+            mixes.push(Transaction::Mix(mix));
+        }
+    }
+
+    // Print the unsigned mix transactions, for each trustee to sign and post
+    println!("{}", serde_json::to_string_pretty(&mixes).unwrap());
+}
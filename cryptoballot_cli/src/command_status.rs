@@ -0,0 +1,25 @@
+use cryptoballot::*;
+
+pub fn command_status(matches: &clap::ArgMatches, uri: &str) {
+    // Unwrap OK - ELECTION-ID is required
+    let election_id_str = crate::expand(matches.value_of("ELECTION-ID").unwrap());
+    let election_id =
+        Identifier::new_from_str_id(&election_id_str, TransactionType::Election, None)
+            .unwrap_or_else(|| {
+                eprintln!("cryptoballot status: invalid election-id");
+                std::process::exit(1);
+            });
+
+    let transactions = crate::rest::get_transactions_by_prefix(uri, &election_id_str[0..15])
+        .unwrap_or_else(|e| {
+            eprintln!("cryptoballot status: unable to fetch transactions: {}", e);
+            std::process::exit(1);
+        });
+    let store = MemStore::from(transactions);
+
+    let summary = summarize_election(&store, election_id).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    print!("{}", summary);
+}
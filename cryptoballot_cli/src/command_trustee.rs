@@ -1,25 +1,58 @@
 use super::expand;
-use cryptoballot::Trustee;
-use std::fs::File;
+use cryptoballot::cryptid::threshold::KeygenCommitment;
+use cryptoballot::x25519_dalek as x25519;
+use cryptoballot::*;
+use ed25519_dalek::PublicKey;
+use ed25519_dalek::SecretKey;
+use std::fs::OpenOptions;
 use std::io::prelude::*;
 
-pub fn command_trustee(matches: &clap::ArgMatches) {
+pub fn command_trustee(matches: &clap::ArgMatches, uri: &str) {
     // Subcommands
     if let Some(matches) = matches.subcommand_matches("generate") {
         command_trustee_generate(matches);
         std::process::exit(0);
     }
+    if let Some(matches) = matches.subcommand_matches("partial-decrypt") {
+        command_trustee_partial_decrypt(matches, uri);
+        std::process::exit(0);
+    }
+    if let Some(matches) = matches.subcommand_matches("reshare") {
+        command_trustee_reshare(matches, uri);
+        std::process::exit(0);
+    }
 }
 
 pub fn command_trustee_generate(matches: &clap::ArgMatches) {
-    // Unwraps are OK, both these args are required
+    // Unwraps are OK, these args are required or have defaults
     let secret_location = expand(matches.value_of("secret").unwrap());
+    let index: u8 = parse_arg(matches, "index");
+    let num_trustees: usize = parse_arg(matches, "num-trustees");
+    let threshold: u8 = parse_arg(matches, "threshold");
 
-    let (trustee, secret) = Trustee::new(1, 1, 1);
+    if index == 0 || index as usize > num_trustees {
+        eprintln!("cryptoballot trustee generate: index must be between 1 and num-trustees");
+        std::process::exit(1);
+    }
+    if threshold == 0 || threshold as usize > num_trustees {
+        eprintln!("cryptoballot trustee generate: threshold must be between 1 and num-trustees");
+        std::process::exit(1);
+    }
+
+    let (trustee, secret) = Trustee::new(index, num_trustees, threshold);
 
     let secret = hex::encode(secret.to_bytes());
 
-    let mut file = File::create(&secret_location).unwrap_or_else(|e| {
+    // The secret key file should only be readable by the current user
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options.open(&secret_location).unwrap_or_else(|e| {
         eprintln!(
             "cryptoballot generate: cannot create file {}: {}",
             &secret_location, e
@@ -39,3 +72,266 @@ pub fn command_trustee_generate(matches: &clap::ArgMatches) {
 
     println!("{}", trustee);
 }
+
+pub fn command_trustee_partial_decrypt(matches: &clap::ArgMatches, uri: &str) {
+    // Unwraps are OK, both these args are required
+    let vote_location = expand(matches.value_of("vote").unwrap());
+    let secret_location = expand(matches.value_of("secret").unwrap());
+    let post = matches.is_present("post");
+
+    let secret_key = read_secret_key(&secret_location);
+    let public_key: PublicKey = (&secret_key).into();
+
+    let vote_tx = read_transaction(&vote_location);
+    if vote_tx.transaction_type() != TransactionType::Vote {
+        eprintln!(
+            "cryptoballot trustee partial-decrypt: expected a {} transaction, found {}",
+            TransactionType::Vote.name(),
+            vote_tx.type_name()
+        );
+        std::process::exit(1);
+    }
+    let vote_tx: VoteTransaction = vote_tx.into();
+
+    // Fetch everything needed for the decryption from the election
+    let prefix = &vote_tx.election.to_string()[0..15];
+    let transactions = crate::rest::get_transactions_by_prefix(uri, prefix).unwrap_or_else(|e| {
+        eprintln!("cryptoballot trustee partial-decrypt: {}", e);
+        std::process::exit(1);
+    });
+    let store = MemStore::from(transactions);
+
+    let election = store.get_election(vote_tx.election).unwrap_or_else(|e| {
+        eprintln!("cryptoballot trustee partial-decrypt: {}", e);
+        std::process::exit(1);
+    });
+    if election.mix_config.is_some() {
+        eprintln!("cryptoballot trustee partial-decrypt: election uses a mixnet, votes must be decrypted from mix transactions");
+        std::process::exit(1);
+    }
+
+    let trustee = election
+        .get_full_trustees()
+        .into_iter()
+        .find(|trustee| trustee.public_key == public_key)
+        .unwrap_or_else(|| {
+            eprintln!("cryptoballot trustee partial-decrypt: secret key does not belong to a trustee of this election");
+            std::process::exit(1);
+        });
+
+    let commit_txs: Vec<KeyGenCommitmentTransaction> = store
+        .get_multiple(election.id, TransactionType::KeyGenCommitment)
+        .into_iter()
+        .map(|tx| tx.into())
+        .collect();
+
+    let commitments: Vec<(u8, KeygenCommitment)> = commit_txs
+        .iter()
+        .map(|tx| (tx.trustee_index, tx.commitment.clone()))
+        .collect();
+
+    let x25519_public_keys: Vec<(u8, x25519::PublicKey)> = commit_txs
+        .into_iter()
+        .map(|tx| (tx.trustee_index, tx.x25519_public_key))
+        .collect();
+
+    // Get all Shares shared with this trustee
+    let mut shares: Vec<(u8, EncryptedShare)> = Vec::new();
+    for tx in store.get_multiple(election.id, TransactionType::KeyGenShare) {
+        let tx: KeyGenShareTransaction = tx.into();
+        match tx.shares.get(&trustee.index) {
+            Some(share) => shares.push((tx.trustee_index, share.clone())),
+            None => {
+                eprintln!(
+                    "cryptoballot trustee partial-decrypt: trustee {} did not provide a share for trustee {}",
+                    tx.trustee_index, trustee.index
+                );
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let mut rng = rand::thread_rng();
+
+    // Refresh the shares with the re-shares of any revoked trustees' shares
+    let reshares: Vec<TrusteeReshareTransaction> = store
+        .get_trustee_reshares(election.id)
+        .into_iter()
+        .map(|tx| tx.tx)
+        .collect();
+    let (commitments, shares) = trustee
+        .apply_reshares(
+            &mut rng,
+            &secret_key,
+            &x25519_public_keys,
+            &commitments,
+            &shares,
+            &reshares,
+            election.id,
+        )
+        .unwrap_or_else(|e| {
+            eprintln!("cryptoballot trustee partial-decrypt: {}", e);
+            std::process::exit(1);
+        });
+
+    for encrypted_vote in vote_tx.encrypted_votes.iter() {
+        let mut decrypt_shares = Vec::with_capacity(encrypted_vote.selections.len());
+        for ciphertext in encrypted_vote.selections.iter() {
+            let partial_decrypt = trustee
+                .partial_decrypt(
+                    &mut rng,
+                    &secret_key,
+                    &x25519_public_keys,
+                    &commitments,
+                    &shares,
+                    ciphertext,
+                    election.id,
+                )
+                .unwrap_or_else(|e| {
+                    eprintln!("cryptoballot trustee partial-decrypt: {}", e);
+                    std::process::exit(1);
+                });
+            decrypt_shares.push(partial_decrypt);
+        }
+
+        let partial_decrypt_tx = PartialDecryptionTransaction::new(
+            election.id,
+            vote_tx.id,
+            0,
+            trustee.index,
+            encrypted_vote.contest_index,
+            public_key,
+            decrypt_shares,
+        );
+
+        let partial_decrypt_tx = Signed::sign(&secret_key, partial_decrypt_tx).unwrap();
+        let partial_decrypt_tx: SignedTransaction = partial_decrypt_tx.into();
+
+        // Serialize it and print it
+        let tx_json = serde_json::to_string_pretty(&partial_decrypt_tx).unwrap();
+        println!("{}", tx_json);
+
+        if post {
+            let _res = crate::rest::post_transaction(uri, partial_decrypt_tx, Some(&secret_key));
+        }
+    }
+}
+
+pub fn command_trustee_reshare(matches: &clap::ArgMatches, uri: &str) {
+    // Unwraps are OK, these args are required
+    let election_id_str = expand(matches.value_of("ELECTION-ID").unwrap());
+    let secret_location = expand(matches.value_of("secret").unwrap());
+    let revoked_index: u8 = parse_arg(matches, "revoked");
+    let post = matches.is_present("post");
+
+    let secret_key = read_secret_key(&secret_location);
+    let public_key: PublicKey = (&secret_key).into();
+
+    let election_id =
+        Identifier::new_from_str_id(&election_id_str, TransactionType::Election, None)
+            .unwrap_or_else(|| {
+                eprintln!("cryptoballot trustee reshare: invalid election id");
+                std::process::exit(1);
+            });
+
+    // Fetch the keygen commitments and revocations from the election
+    let transactions = crate::rest::get_transactions_by_prefix(uri, &election_id_str[0..15])
+        .unwrap_or_else(|e| {
+            eprintln!("cryptoballot trustee reshare: {}", e);
+            std::process::exit(1);
+        });
+    let store = MemStore::from(transactions);
+
+    let election = store.get_election(election_id).unwrap_or_else(|e| {
+        eprintln!("cryptoballot trustee reshare: {}", e);
+        std::process::exit(1);
+    });
+    if !store.is_trustee_revoked(election.id, revoked_index) {
+        eprintln!(
+            "cryptoballot trustee reshare: {}",
+            ValidationError::TrusteeNotRevoked(revoked_index)
+        );
+        std::process::exit(1);
+    }
+
+    let trustee = election
+        .get_full_trustees()
+        .into_iter()
+        .find(|trustee| trustee.public_key == public_key)
+        .unwrap_or_else(|| {
+            eprintln!("cryptoballot trustee reshare: secret key does not belong to a trustee of this election");
+            std::process::exit(1);
+        });
+
+    // Send a share to every trustee that hasn't been revoked, including this one
+    let x25519_public_keys: Vec<(u8, x25519::PublicKey)> = store
+        .get_multiple(election.id, TransactionType::KeyGenCommitment)
+        .into_iter()
+        .map(|tx| -> KeyGenCommitmentTransaction { tx.into() })
+        .filter(|tx| !store.is_trustee_revoked(election.id, tx.trustee_index))
+        .map(|tx| (tx.trustee_index, tx.x25519_public_key))
+        .collect();
+
+    let mut rng = rand::thread_rng();
+    let (commitment, shares) =
+        trustee.generate_reshares(&mut rng, &secret_key, &x25519_public_keys, election.id);
+
+    let reshare_tx = TrusteeReshareTransaction::new(
+        election.id,
+        trustee.index,
+        public_key,
+        revoked_index,
+        commitment,
+        shares,
+    );
+    let reshare_tx = Signed::sign(&secret_key, reshare_tx).unwrap();
+    let reshare_tx: SignedTransaction = reshare_tx.into();
+
+    // Serialize it and print it
+    let tx_json = serde_json::to_string_pretty(&reshare_tx).unwrap();
+    println!("{}", tx_json);
+
+    if post {
+        let _res = crate::rest::post_transaction(uri, reshare_tx, Some(&secret_key));
+    }
+}
+
+fn parse_arg<T: std::str::FromStr>(matches: &clap::ArgMatches, name: &str) -> T {
+    let value = matches.value_of(name).unwrap();
+    value.parse().unwrap_or_else(|_| {
+        eprintln!("cryptoballot trustee: invalid {}: {}", name, value);
+        std::process::exit(1);
+    })
+}
+
+pub(crate) fn read_secret_key(location: &str) -> SecretKey {
+    let secret = std::fs::read_to_string(location).unwrap_or_else(|e| {
+        eprintln!("cryptoballot trustee: unable to read {}: {}", location, e);
+        std::process::exit(1);
+    });
+    let bytes = hex::decode(secret.trim()).unwrap_or_else(|e| {
+        eprintln!("Invalid secret key: {}", e);
+        std::process::exit(1);
+    });
+    SecretKey::from_bytes(&bytes).unwrap_or_else(|e| {
+        eprintln!("Invalid secret key: {}", e);
+        std::process::exit(1);
+    })
+}
+
+fn read_transaction(location: &str) -> SignedTransaction {
+    let file_bytes = std::fs::read(location).unwrap_or_else(|e| {
+        eprintln!("cryptoballot trustee: unable to read {}: {}", location, e);
+        std::process::exit(1);
+    });
+
+    let tx = match String::from_utf8(file_bytes.clone()) {
+        Ok(json_string) => serde_json::from_str(json_string.trim()).map_err(Error::from),
+        Err(_) => SignedTransaction::from_bytes(&file_bytes),
+    };
+
+    tx.unwrap_or_else(|e| {
+        eprintln!("cryptoballot trustee: unable to read {}: {}", location, e);
+        std::process::exit(1);
+    })
+}
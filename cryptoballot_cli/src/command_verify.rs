@@ -0,0 +1,77 @@
+use cryptoballot::*;
+
+pub fn command_verify(matches: &clap::ArgMatches, uri: &str) {
+    let (store, election_id) = match matches.value_of("input") {
+        Some(path) => {
+            let path = crate::expand(path);
+            let store = MemStore::from(crate::load_transactions("verify", &path));
+
+            // A bundle or log holding a single election doesn't need to be told which one
+            let election_id = match matches.value_of("ELECTION-ID") {
+                Some(election_id) => parse_election_id(&crate::expand(election_id)),
+                None => {
+                    let elections = store.list_elections();
+                    if elections.len() != 1 {
+                        eprintln!(
+                            "cryptoballot verify: {} holds {} elections, please give an election-id",
+                            path,
+                            elections.len()
+                        );
+                        std::process::exit(1);
+                    }
+                    elections[0]
+                }
+            };
+            (store, election_id)
+        }
+        None => {
+            // Unwrap OK - ELECTION-ID is required unless --input is given
+            let election_id_str = crate::expand(matches.value_of("ELECTION-ID").unwrap());
+            let election_id = parse_election_id(&election_id_str);
+
+            let transactions =
+                crate::rest::get_transactions_by_prefix(uri, &election_id_str[0..15])
+                    .unwrap_or_else(|e| {
+                        eprintln!("cryptoballot verify: unable to fetch transactions: {}", e);
+                        std::process::exit(1);
+                    });
+            (MemStore::from(transactions), election_id)
+        }
+    };
+
+    let mut ok = true;
+    for (id, e) in store.validate_all() {
+        eprintln!(
+            "cryptoballot verify: {} {}: {}",
+            id.transaction_type.name(),
+            id,
+            e
+        );
+        ok = false;
+    }
+
+    if matches.is_present("integrity") {
+        let report = verify_election_integrity(&store, election_id).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        });
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&report)
+                .expect("cryptoballot: unexpected error serializing JSON")
+        );
+        ok = ok && report.is_consistent();
+    }
+
+    if !ok {
+        std::process::exit(1);
+    }
+    eprintln!("cryptoballot verify: OK");
+}
+
+fn parse_election_id(election_id: &str) -> Identifier {
+    Identifier::new_from_str_id(election_id, TransactionType::Election, None).unwrap_or_else(|| {
+        eprintln!("cryptoballot verify: invalid election-id");
+        std::process::exit(1);
+    })
+}
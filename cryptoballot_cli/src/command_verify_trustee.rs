@@ -0,0 +1,81 @@
+use cryptoballot::uuid::Uuid;
+use cryptoballot::*;
+use ed25519_dalek::PublicKey;
+
+pub fn command_verify_trustee(matches: &clap::ArgMatches) {
+    // Unwraps OK - all args are required
+    let trustee_id: Uuid = matches
+        .value_of("trustee-id")
+        .unwrap()
+        .parse()
+        .unwrap_or_else(|e| {
+            eprintln!("cryptoballot verify-trustee: invalid trustee-id: {}", e);
+            std::process::exit(1);
+        });
+    let public_key = hex::decode(matches.value_of("public-key").unwrap()).unwrap_or_else(|e| {
+        eprintln!("cryptoballot verify-trustee: invalid public-key: {}", e);
+        std::process::exit(1);
+    });
+    let public_key = PublicKey::from_bytes(&public_key).unwrap_or_else(|e| {
+        eprintln!("cryptoballot verify-trustee: invalid public-key: {}", e);
+        std::process::exit(1);
+    });
+    let path = crate::expand(matches.value_of("ELECTION-FILE").unwrap());
+
+    // The trustee's partial decryptions are verified directly, so the transactions are stored as-is
+    let mut store = MemStore::default();
+    for tx in crate::load_transactions("verify-trustee", &path) {
+        store.set(tx);
+    }
+
+    let mut found = false;
+    let mut complete = true;
+    for election_id in store.list_elections() {
+        // Unwrap OK - the election was just listed
+        let election = store.get_election(election_id).unwrap();
+        let trustee = match election.get_trustee_by_id(trustee_id) {
+            Some(trustee) => trustee,
+            None => continue,
+        };
+        if trustee.public_key != public_key {
+            eprintln!(
+                "cryptoballot verify-trustee: trustee {} has a different public key in election {}",
+                trustee_id, election_id
+            );
+            std::process::exit(1);
+        }
+        found = true;
+
+        // Unwrap OK - the election was just found
+        let report = verify_trustee_participation(&store, election_id, trustee.index).unwrap();
+        println!("Election {}, trustee {}", election_id, trustee.index);
+        for target in report.accepted.iter() {
+            println!("  ok       {}", target);
+        }
+        for target in report.missing.iter() {
+            println!("  missing  {}", target);
+        }
+        for (target, reason) in report.failed.iter() {
+            println!("  failed   {}: {}", target, reason);
+        }
+        println!(
+            "  {} accepted, {} missing, {} failed",
+            report.accepted.len(),
+            report.missing.len(),
+            report.failed.len()
+        );
+
+        complete &= report.is_complete();
+    }
+
+    if !found {
+        eprintln!(
+            "cryptoballot verify-trustee: trustee {} is not in any election in {}",
+            trustee_id, path
+        );
+        std::process::exit(1);
+    }
+    if !complete {
+        std::process::exit(1);
+    }
+}
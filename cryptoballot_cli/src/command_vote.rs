@@ -1,6 +1,7 @@
 use cryptoballot::*;
 use ed25519_dalek::PublicKey;
 use ed25519_dalek::SecretKey;
+use rand::Rng;
 
 pub fn command_vote(matches: &clap::ArgMatches, uri: &str, secret_key: Option<&SecretKey>) {
     // Subcommands
@@ -34,6 +35,7 @@ pub fn command_vote_generate(
         write_in: true,
         score: 0,
         selection: secret_vote,
+        weight: None,
     };
 
     // Get the encryption-key
@@ -54,22 +56,36 @@ pub fn command_vote_generate(
 
     // Encrypt the secret vote
     // TODO: Real error not expect
-    let encrypted_selections =
-        cryptoballot::encrypt_vote(&encryption_key_tx.encryption_key, vec![selection], &mut rng)
-            .expect("Error encrypting vote");
+    // The seed is needed to challenge the vote, and must be kept secret otherwise
+    let election_id = encryption_key_tx.election;
+    let seed: [u8; 32] = rng.gen();
+    eprintln!("challenge seed: {}", hex::encode(seed));
+    let plaintext = vec![PlaintextVote {
+        contest_index: 0,
+        selections: vec![selection],
+    }];
+    let (encrypted_votes, encryption_proof) = cryptoballot::encrypt_vote_with_proof(
+        &encryption_key_tx.encryption_key,
+        election_id,
+        &public_key,
+        &plaintext,
+        seed,
+        &mut rng,
+    )
+    .expect("Error encrypting vote");
 
     // Generate an empty vote transaction
-    let election_id = encryption_key_tx.election;
     let vote = VoteTransaction {
         id: VoteTransaction::build_id(election_id, &public_key),
         election: election_id,
         ballot_id: "BALLOT1".to_string(),
-        encrypted_votes: vec![EncryptedVote {
-            contest_index: 0,
-            selections: encrypted_selections,
-        }],
+        encrypted_votes,
         anonymous_key: public_key,
         authentication: vec![],
+        weight: None,
+        encryption_proof,
+        vote_validity_proof: None,
+        created_at: None,
     };
 
     // TODO: Normally we would do blind authentication here, but this is just for testing for now so skip
@@ -1,6 +1,9 @@
+use cryptoballot::MemStore;
 use cryptoballot::Signed;
 use cryptoballot::SignedTransaction;
+use cryptoballot::Store;
 use cryptoballot::TransactionType;
+use cryptoballot::VoteTransaction;
 use cryptoballot::VotingEndTransaction;
 use ed25519_dalek::PublicKey;
 use ed25519_dalek::SecretKey;
@@ -30,16 +33,43 @@ pub fn command_voting_end_generate(
 ) {
     let public_key: PublicKey = (secret_key).into();
 
-    let election_id = crate::expand(matches.value_of("ELECTION-ID").unwrap());
-    let election_id =
-        cryptoballot::Identifier::new_from_str_id(&election_id, TransactionType::Election, None)
-            .unwrap_or_else(|| {
-                // TODO: Replace with real error
-                panic!("Invalid election-id");
-            });
+    let election_id_str = crate::expand(matches.value_of("ELECTION-ID").unwrap());
+    let election_id = cryptoballot::Identifier::new_from_str_id(
+        &election_id_str,
+        TransactionType::Election,
+        None,
+    )
+    .unwrap_or_else(|| {
+        // TODO: Replace with real error
+        panic!("Invalid election-id");
+    });
+
+    // Commit to every vote cast so far
+    let transactions = crate::rest::get_transactions_by_prefix(uri, &election_id_str[0..15])
+        .unwrap_or_else(|e| {
+            eprintln!(
+                "cryptoballot voting_end: unable to fetch transactions: {}",
+                e
+            );
+            std::process::exit(1);
+        });
+    let store = MemStore::from(transactions);
+    let election = store.get_election(election_id).unwrap_or_else(|e| {
+        eprintln!("cryptoballot voting_end: {}", e);
+        std::process::exit(1);
+    });
+    if election.authority_public != public_key {
+        eprintln!("cryptoballot voting_end: secret key is not the election authority's key");
+        std::process::exit(1);
+    }
+    let votes: Vec<Signed<VoteTransaction>> = store
+        .get_multiple(election_id, TransactionType::Vote)
+        .into_iter()
+        .map(|tx| tx.into())
+        .collect();
 
     // Create a voting-end transaction
-    let voting_end_tx = VotingEndTransaction::new(election_id, public_key);
+    let voting_end_tx = VotingEndTransaction::build(&election, &votes);
 
     //  Turn it into a signed transaction
     let voting_end_tx = Signed::sign(&secret_key, voting_end_tx).unwrap();
@@ -3,14 +3,29 @@ use clap::{App, Arg, SubCommand};
 use cryptoballot::*;
 use ed25519_dalek::SecretKey;
 
+mod command_audit;
 mod command_authn;
+mod command_check_trustees;
+mod command_decrypt;
 mod command_e2e;
 mod command_election;
+mod command_encrypt_vote;
+mod command_export_vote;
+mod command_generate_election;
+mod command_graph;
 mod command_keygen;
+mod command_keys;
+mod command_list_elections;
+mod command_mix;
 mod command_post_transaction;
+mod command_status;
 mod command_trustee;
+mod command_verify;
+mod command_verify_trustee;
 mod command_vote;
 mod command_voting_end;
+mod keyfile;
+mod output;
 mod rest;
 
 fn main() {
@@ -61,8 +76,10 @@ fn main() {
                 .arg(
                     Arg::with_name("pretty")
                         .long("pretty")
-                        .help("Pretty print JSON"),
-                ),
+                        .conflicts_with("format")
+                        .help("Pretty print JSON (same as --format=pretty-json)"),
+                )
+                .arg(output::format_arg()),
         )
         .subcommand(
             SubCommand::with_name("post")
@@ -101,6 +118,9 @@ fn main() {
                         .long("print-votes")
                         .help("Print all the recorded votes"),
                 )
+                .arg(output::format_arg().help(
+                    "Print votes as full decryption transactions in the given format (with --print-votes)",
+                ))
                 .arg(
                     Arg::with_name("print-tally")
                         .long("print-tally")
@@ -110,6 +130,377 @@ fn main() {
                     Arg::with_name("print-results")
                         .long("print-results")
                         .help("Print the election results"),
+                )
+                .arg(output::tally_format_arg().help(
+                    "Print the tally and results (with --print-tally or --print-results) as text (default), json or csv",
+                ))
+                .arg(
+                    Arg::with_name("tally-referendum")
+                        .long("tally-referendum")
+                        .help("Print the yes and no counts for each yes / no referendum question"),
+                )
+                .arg(
+                    Arg::with_name("tie-break")
+                        .long("tie-break")
+                        .takes_value(true)
+                        .help("How to break a tie for the last winning place (with --print-results): report (default), lexicographic, random, random:<seed>, or error"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("audit")
+                .about("Audit an election, producing a JSON report and a human-readable summary")
+                .setting(AppSettings::ArgRequiredElseHelp)
+                .arg(
+                    Arg::with_name("ELECTION-ID")
+                        .index(1)
+                        .required_unless("input")
+                        .help("Election ID"),
+                )
+                .arg(
+                    Arg::with_name("input")
+                        .long("input")
+                        .takes_value(true)
+                        .help("Audit the transactions in a JSON file instead of fetching them"),
+                )
+                .arg(
+                    Arg::with_name("certified")
+                        .long("certified")
+                        .takes_value(true)
+                        .help("Certified results (a tally transaction in JSON) to check against a re-tally"),
+                )
+                .arg(
+                    Arg::with_name("audit-proof")
+                        .long("audit-proof")
+                        .takes_value(true)
+                        .value_name("VOTE-ID")
+                        .help("Instead of auditing, print a merkle proof in JSON that the vote is included in the election's votes"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("check-trustees")
+                .about("List trustees who have not yet posted their partial decryptions, exiting with 2 if the decryption deadline has passed")
+                .setting(AppSettings::ArgRequiredElseHelp)
+                .arg(
+                    Arg::with_name("ELECTION-ID")
+                        .index(1)
+                        .required(true)
+                        .help("Election ID"),
+                )
+                .arg(
+                    Arg::with_name("store")
+                        .long("store")
+                        .takes_value(true)
+                        .help("Read transactions from a transaction log, a JSON file of transactions, or a directory of either, instead of fetching them"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("decrypt")
+                .about("Combine trustees' partial decryptions of a vote, printing the signed decryption transactions in JSON")
+                .setting(AppSettings::ArgRequiredElseHelp)
+                .arg(
+                    Arg::with_name("store")
+                        .long("store")
+                        .takes_value(true)
+                        .required(true)
+                        .help("A transaction log, a JSON file of transactions, or a directory of either"),
+                )
+                .arg(
+                    Arg::with_name("vote")
+                        .long("vote")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Vote transaction ID"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("encrypt-vote")
+                .about("Encrypt a plaintext vote, printing an unsigned vote transaction in JSON")
+                .setting(AppSettings::ArgRequiredElseHelp)
+                .arg(
+                    Arg::with_name("election")
+                        .long("election")
+                        .takes_value(true)
+                        .required(true)
+                        .help("JSON file with the election's transactions, including its encryption_key transaction"),
+                )
+                .arg(
+                    Arg::with_name("ballot")
+                        .long("ballot")
+                        .takes_value(true)
+                        .help("Plaintext vote, read from stdin if omitted or -"),
+                )
+                .arg(
+                    Arg::with_name("ballot-id")
+                        .long("ballot-id")
+                        .takes_value(true)
+                        .help("Ballot to vote, defaults to the election's first ballot"),
+                )
+                .arg(
+                    Arg::with_name("contest")
+                        .long("contest")
+                        .takes_value(true)
+                        .help("Contest index to vote in, defaults to the ballot's first contest"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("export-keys")
+                .about("Export a trustee's secret key as PEM and its key shares encrypted with a passphrase")
+                .setting(AppSettings::ArgRequiredElseHelp)
+                .arg(
+                    Arg::with_name("store")
+                        .long("store")
+                        .takes_value(true)
+                        .required(true)
+                        .help("A transaction log, a JSON file of transactions, or a directory of either"),
+                )
+                .arg(
+                    Arg::with_name("secret")
+                        .long("secret")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Trustee secret key file, hex encoded"),
+                )
+                .arg(
+                    Arg::with_name("trustee-index")
+                        .long("trustee-index")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Index of the trustee in the election"),
+                )
+                .arg(
+                    Arg::with_name("output-dir")
+                        .long("output-dir")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Directory to write trustee-<n>.pem and trustee-<n>-shares.json to. The passphrase is read from CRYPTOBALLOT_PASSPHRASE if set."),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("export-vote")
+                .about("Export the transactions needed to verify a single vote's decryption")
+                .setting(AppSettings::ArgRequiredElseHelp)
+                .arg(
+                    Arg::with_name("store")
+                        .long("store")
+                        .takes_value(true)
+                        .required(true)
+                        .help("A transaction log, a JSON file of transactions, or a directory of either"),
+                )
+                .arg(
+                    Arg::with_name("vote")
+                        .long("vote")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Vote transaction ID"),
+                )
+                .arg(
+                    Arg::with_name("out")
+                        .long("out")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Transaction log to write the bundle to, which must not already exist"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("generate-election")
+                .about("Set up a new election, printing its signed transactions in JSON")
+                .arg(
+                    Arg::with_name("non-interactive")
+                        .long("non-interactive")
+                        .help("Never prompt, using defaults for anything not given by a flag"),
+                )
+                .arg(
+                    Arg::with_name("title")
+                        .long("title")
+                        .takes_value(true)
+                        .help("Election title"),
+                )
+                .arg(
+                    Arg::with_name("trustees")
+                        .long("trustees")
+                        .takes_value(true)
+                        .help("Number of trustees"),
+                )
+                .arg(
+                    Arg::with_name("threshold")
+                        .long("threshold")
+                        .takes_value(true)
+                        .help("Number of trustees needed to decrypt votes"),
+                )
+                .arg(
+                    Arg::with_name("authn-keysize")
+                        .long("authn-keysize")
+                        .takes_value(true)
+                        .help("Length of the authenticator's RSA keys, anything less than 2048 is insecure"),
+                )
+                .arg(
+                    Arg::with_name("ballot")
+                        .long("ballot")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .help("Ballot ID, may be given more than once"),
+                )
+                .arg(
+                    Arg::with_name("mix")
+                        .long("mix")
+                        .help("Shuffle votes with a mixnet before decryption"),
+                )
+                .arg(
+                    Arg::with_name("mix-timeout")
+                        .long("mix-timeout")
+                        .takes_value(true)
+                        .help("Mixnet timeout in seconds"),
+                )
+                .arg(
+                    Arg::with_name("secrets-dir")
+                        .long("secrets-dir")
+                        .takes_value(true)
+                        .default_value(".")
+                        .help("Directory to write the trustee, authenticator and authority secrets to"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("graph")
+                .about("Export an election's transaction dependency graph")
+                .setting(AppSettings::ArgRequiredElseHelp)
+                .arg(
+                    Arg::with_name("ELECTION-ID")
+                        .index(1)
+                        .required(true)
+                        .help("Election ID"),
+                )
+                .arg(
+                    Arg::with_name("FILE")
+                        .index(2)
+                        .help("File to write the graph to, defaults to stdout"),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .long("output")
+                        .takes_value(true)
+                        .possible_values(&["dot"])
+                        .default_value("dot")
+                        .help("Output format"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("import-keys")
+                .about("Read a trustee's keys written by export-keys, printing a summary in JSON")
+                .setting(AppSettings::ArgRequiredElseHelp)
+                .arg(
+                    Arg::with_name("trustee-index")
+                        .long("trustee-index")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Index of the trustee in the election"),
+                )
+                .arg(
+                    Arg::with_name("input-dir")
+                        .long("input-dir")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Directory containing trustee-<n>.pem and trustee-<n>-shares.json. The passphrase is read from CRYPTOBALLOT_PASSPHRASE if set."),
+                )
+                .arg(
+                    Arg::with_name("secret")
+                        .long("secret")
+                        .takes_value(true)
+                        .help("Write the secret key, hex encoded, to this file"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("list-elections")
+                .about("List the elections in a local store")
+                .setting(AppSettings::ArgRequiredElseHelp)
+                .arg(
+                    Arg::with_name("store")
+                        .long("store")
+                        .takes_value(true)
+                        .required(true)
+                        .help("A transaction log, a JSON file of transactions, or a directory of either"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("mix")
+                .about("Shuffle an election's votes, printing the unsigned mix transactions in JSON for each trustee to sign")
+                .setting(AppSettings::ArgRequiredElseHelp)
+                .arg(
+                    Arg::with_name("election-id")
+                        .long("election-id")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Election ID in hex"),
+                )
+                .arg(
+                    Arg::with_name("num-shuffles")
+                        .long("num-shuffles")
+                        .takes_value(true)
+                        .help("Number of successive shuffles, by default the election's trustees_threshold"),
+                )
+                .arg(
+                    Arg::with_name("STORE")
+                        .index(1)
+                        .required(true)
+                        .help("A transaction log, a JSON file of transactions, or a directory of either"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("status")
+                .about("Summarize an election's progress")
+                .setting(AppSettings::ArgRequiredElseHelp)
+                .arg(
+                    Arg::with_name("ELECTION-ID")
+                        .index(1)
+                        .required(true)
+                        .help("Election ID"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("verify")
+                .about("Validate every transaction in an election")
+                .setting(AppSettings::ArgRequiredElseHelp)
+                .arg(
+                    Arg::with_name("ELECTION-ID")
+                        .index(1)
+                        .required_unless("input")
+                        .help("Election ID"),
+                )
+                .arg(
+                    Arg::with_name("input")
+                        .long("input")
+                        .takes_value(true)
+                        .help("Verify the transactions in a transaction log, JSON file or directory instead of fetching them"),
+                )
+                .arg(
+                    Arg::with_name("integrity")
+                        .long("integrity")
+                        .help("Also check cross-transaction invariants, printing an integrity report in JSON"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("verify-trustee")
+                .about("Check that a trustee's partial decryptions were all posted and verify")
+                .setting(AppSettings::ArgRequiredElseHelp)
+                .arg(
+                    Arg::with_name("trustee-id")
+                        .long("trustee-id")
+                        .takes_value(true)
+                        .required(true)
+                        .help("The trustee's UUID"),
+                )
+                .arg(
+                    Arg::with_name("public-key")
+                        .long("public-key")
+                        .takes_value(true)
+                        .required(true)
+                        .help("The trustee's public key, hex encoded"),
+                )
+                .arg(
+                    Arg::with_name("ELECTION-FILE")
+                        .index(1)
+                        .required(true)
+                        .help("A transaction log, a JSON file of transactions, or a directory of either"),
                 ),
         )
         .subcommand(
@@ -119,12 +510,90 @@ fn main() {
                 .subcommand(
                     SubCommand::with_name("generate")
                         .about("Generate new trustee")
+                        .alias("new")
                         .arg(
                             Arg::with_name("secret")
                                 .long("secret")
                                 .help("File location to write secret key")
                                 .takes_value(true)
                                 .required(true), // TODO: allow PEM format with password
+                        )
+                        .arg(
+                            Arg::with_name("index")
+                                .long("index")
+                                .help("Trustee index, starting at 1")
+                                .takes_value(true)
+                                .default_value("1"),
+                        )
+                        .arg(
+                            Arg::with_name("num-trustees")
+                                .long("num-trustees")
+                                .help("Total number of trustees in the election")
+                                .takes_value(true)
+                                .default_value("1"),
+                        )
+                        .arg(
+                            Arg::with_name("threshold")
+                                .long("threshold")
+                                .help("Number of trustees needed to decrypt votes")
+                                .takes_value(true)
+                                .default_value("1"),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("partial-decrypt")
+                        .about("Partially decrypt a vote, producing a partial_decryption transaction")
+                        .arg(
+                            Arg::with_name("vote")
+                                .long("vote")
+                                .help("Vote transaction file in JSON or CBOR format")
+                                .takes_value(true)
+                                .required(true),
+                        )
+                        .arg(
+                            Arg::with_name("secret")
+                                .long("secret")
+                                .help("Trustee secret key file location in hex format")
+                                .takes_value(true)
+                                .required(true),
+                        )
+                        .arg(
+                            Arg::with_name("post")
+                                .long("post")
+                                .help("Post the transaction")
+                                .takes_value(false)
+                                .required(false),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("reshare")
+                        .about("Re-share a revoked trustee's key share, producing a trustee_reshare transaction")
+                        .arg(
+                            Arg::with_name("ELECTION-ID")
+                                .index(1)
+                                .required(true)
+                                .help("Election ID"),
+                        )
+                        .arg(
+                            Arg::with_name("revoked")
+                                .long("revoked")
+                                .help("Index of the revoked trustee")
+                                .takes_value(true)
+                                .required(true),
+                        )
+                        .arg(
+                            Arg::with_name("secret")
+                                .long("secret")
+                                .help("Trustee secret key file location in hex format")
+                                .takes_value(true)
+                                .required(true),
+                        )
+                        .arg(
+                            Arg::with_name("post")
+                                .long("post")
+                                .help("Post the transaction")
+                                .takes_value(false)
+                                .required(false),
                         ),
                 ),
         )
@@ -274,8 +743,64 @@ fn main() {
         command_e2e::command_e2e(matches, &uri);
         std::process::exit(0);
     }
+    if let Some(matches) = matches.subcommand_matches("audit") {
+        command_audit::command_audit(matches, &uri);
+        std::process::exit(0);
+    }
+    if let Some(matches) = matches.subcommand_matches("check-trustees") {
+        command_check_trustees::command_check_trustees(matches, &uri);
+        std::process::exit(0);
+    }
+    if let Some(matches) = matches.subcommand_matches("decrypt") {
+        command_decrypt::command_decrypt(matches, secret_key.as_ref());
+        std::process::exit(0);
+    }
+    if let Some(matches) = matches.subcommand_matches("encrypt-vote") {
+        command_encrypt_vote::command_encrypt_vote(matches, secret_key.as_ref());
+        std::process::exit(0);
+    }
+    if let Some(matches) = matches.subcommand_matches("export-keys") {
+        command_keys::command_export_keys(matches);
+        std::process::exit(0);
+    }
+    if let Some(matches) = matches.subcommand_matches("export-vote") {
+        command_export_vote::command_export_vote(matches);
+        std::process::exit(0);
+    }
+    if let Some(matches) = matches.subcommand_matches("generate-election") {
+        command_generate_election::command_generate_election(matches, secret_key.as_ref());
+        std::process::exit(0);
+    }
+    if let Some(matches) = matches.subcommand_matches("graph") {
+        command_graph::command_graph(matches, &uri);
+        std::process::exit(0);
+    }
+    if let Some(matches) = matches.subcommand_matches("import-keys") {
+        command_keys::command_import_keys(matches);
+        std::process::exit(0);
+    }
+    if let Some(matches) = matches.subcommand_matches("list-elections") {
+        command_list_elections::command_list_elections(matches);
+        std::process::exit(0);
+    }
+    if let Some(matches) = matches.subcommand_matches("mix") {
+        command_mix::command_mix(matches);
+        std::process::exit(0);
+    }
+    if let Some(matches) = matches.subcommand_matches("status") {
+        command_status::command_status(matches, &uri);
+        std::process::exit(0);
+    }
+    if let Some(matches) = matches.subcommand_matches("verify-trustee") {
+        command_verify_trustee::command_verify_trustee(matches);
+        std::process::exit(0);
+    }
+    if let Some(matches) = matches.subcommand_matches("verify") {
+        command_verify::command_verify(matches, &uri);
+        std::process::exit(0);
+    }
     if let Some(matches) = matches.subcommand_matches("trustee") {
-        command_trustee::command_trustee(matches);
+        command_trustee::command_trustee(matches, &uri);
         std::process::exit(0);
     }
     if let Some(matches) = matches.subcommand_matches("authn") {
@@ -338,13 +863,13 @@ fn command_get_transaction(matches: &clap::ArgMatches, uri: &str) {
     // TODO: remove unwrap
     let tx = rest::get_transaction(uri, id).unwrap();
 
-    let json_tx = if matches.is_present("pretty") {
-        serde_json::to_string_pretty(&tx).unwrap()
+    let format = if matches.is_present("pretty") {
+        output::OutputFormat::PrettyJson
     } else {
-        serde_json::to_string(&tx).unwrap()
+        output::OutputFormat::from_matches(matches, output::OutputFormat::Json)
     };
 
-    println!("{}", json_tx);
+    println!("{}", output::format_transaction(&tx, format));
 }
 
 fn command_tally(matches: &clap::ArgMatches, uri: &str) {
@@ -389,3 +914,73 @@ pub fn expand(filename: &str) -> String {
         })
         .into_owned()
 }
+
+// Load every transaction in a transaction log, a JSON array of transactions, a single transaction in any format,
+// or a directory of any of these
+pub fn load_transactions(command: &str, path: &str) -> Vec<SignedTransaction> {
+    let path = std::path::Path::new(path);
+    if !path.is_dir() {
+        return load_file(command, path);
+    }
+
+    let entries = std::fs::read_dir(path).unwrap_or_else(|e| {
+        eprintln!(
+            "cryptoballot {}: unable to read {}: {}",
+            command,
+            path.display(),
+            e
+        );
+        std::process::exit(1);
+    });
+    let mut transactions = Vec::new();
+    for entry in entries {
+        let entry = entry.unwrap_or_else(|e| {
+            eprintln!(
+                "cryptoballot {}: unable to read {}: {}",
+                command,
+                path.display(),
+                e
+            );
+            std::process::exit(1);
+        });
+        if entry.path().is_file() {
+            transactions.extend(load_file(command, &entry.path()));
+        }
+    }
+    transactions
+}
+
+fn load_file(command: &str, path: &std::path::Path) -> Vec<SignedTransaction> {
+    let file_bytes = std::fs::read(path).unwrap_or_else(|e| {
+        eprintln!(
+            "cryptoballot {}: unable to read {}: {}",
+            command,
+            path.display(),
+            e
+        );
+        std::process::exit(1);
+    });
+
+    if file_bytes.starts_with(&TRANSACTION_LOG_MAGIC) {
+        let log = TransactionLog::open(path).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        });
+        log.range(Identifier::first(), Identifier::last())
+    } else if let Ok(txs) = serde_json::from_slice::<Vec<SignedTransaction>>(&file_bytes) {
+        txs
+    } else if let Ok(tx) = serde_json::from_slice::<SignedTransaction>(&file_bytes) {
+        vec![tx]
+    } else {
+        let tx = SignedTransaction::from_bytes(&file_bytes).unwrap_or_else(|e| {
+            eprintln!(
+                "cryptoballot {}: unable to parse {}: {}",
+                command,
+                path.display(),
+                e
+            );
+            std::process::exit(1);
+        });
+        vec![tx]
+    }
+}
@@ -0,0 +1,165 @@
+use cryptoballot::SignedTransaction;
+use std::str::FromStr;
+
+/// Output format for printing transactions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Compact JSON on a single line
+    Json,
+
+    /// Indented JSON
+    PrettyJson,
+
+    /// Lowercase hex of the CBOR-packed transaction
+    CborHex,
+}
+
+impl OutputFormat {
+    pub const VALUES: &'static [&'static str] = &["json", "pretty-json", "cbor-hex"];
+
+    /// Get the output format from the `--format` argument, falling back to `default`
+    pub fn from_matches(matches: &clap::ArgMatches, default: OutputFormat) -> Self {
+        match matches.value_of("format") {
+            // Unwrap OK - clap restricts the possible values
+            Some(format) => format.parse().unwrap(),
+            None => default,
+        }
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(OutputFormat::Json),
+            "pretty-json" => Ok(OutputFormat::PrettyJson),
+            "cbor-hex" => Ok(OutputFormat::CborHex),
+            _ => Err(format!("unknown output format {}", s)),
+        }
+    }
+}
+
+/// Format a transaction for output
+pub fn format_transaction(tx: &SignedTransaction, fmt: OutputFormat) -> String {
+    match fmt {
+        OutputFormat::Json => {
+            serde_json::to_string(tx).expect("cryptoballot: unexpected error serializing JSON")
+        }
+        OutputFormat::PrettyJson => serde_json::to_string_pretty(tx)
+            .expect("cryptoballot: unexpected error serializing JSON"),
+        OutputFormat::CborHex => hex::encode(tx.as_bytes()),
+    }
+}
+
+/// The `--format` argument shared by subcommands that print transactions
+pub fn format_arg<'a, 'b>() -> clap::Arg<'a, 'b> {
+    clap::Arg::with_name("format")
+        .long("format")
+        .takes_value(true)
+        .possible_values(OutputFormat::VALUES)
+        .help("Transaction output format")
+}
+
+/// Output format for printing tallies and election results
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TallyFormat {
+    /// Human-readable lines
+    Text,
+
+    /// Indented JSON
+    Json,
+
+    /// CSV with a header row
+    Csv,
+}
+
+impl TallyFormat {
+    pub const VALUES: &'static [&'static str] = &["text", "json", "csv"];
+
+    /// Get the output format from the `--tally-format` argument, defaulting to text
+    pub fn from_matches(matches: &clap::ArgMatches) -> Self {
+        match matches.value_of("tally-format") {
+            // Unwrap OK - clap restricts the possible values
+            Some(format) => format.parse().unwrap(),
+            None => TallyFormat::Text,
+        }
+    }
+}
+
+impl FromStr for TallyFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(TallyFormat::Text),
+            "json" => Ok(TallyFormat::Json),
+            "csv" => Ok(TallyFormat::Csv),
+            _ => Err(format!("unknown tally format {}", s)),
+        }
+    }
+}
+
+/// The `--tally-format` argument for subcommands that print tallies or results
+pub fn tally_format_arg<'a, 'b>() -> clap::Arg<'a, 'b> {
+    clap::Arg::with_name("tally-format")
+        .long("tally-format")
+        .takes_value(true)
+        .possible_values(TallyFormat::VALUES)
+        .help("Tally and results output format")
+}
+
+/// Quote a CSV field if it contains a comma, quote or line break (RFC 4180)
+pub fn csv_field(field: &str) -> String {
+    if field.contains(|c: char| matches!(c, ',' | '"' | '\n' | '\r')) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_transaction_all_formats() {
+        let file_bytes =
+            std::fs::read("../test_elections/test_election_1/test_election_1.json").unwrap();
+        let txs: Vec<SignedTransaction> = serde_json::from_slice(&file_bytes).unwrap();
+        let tx = &txs[0];
+
+        let json = format_transaction(tx, OutputFormat::Json);
+        assert!(!json.contains('\n'));
+        let parsed: SignedTransaction = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.id(), tx.id());
+
+        let pretty = format_transaction(tx, OutputFormat::PrettyJson);
+        assert!(pretty.contains('\n'));
+        let parsed: SignedTransaction = serde_json::from_str(&pretty).unwrap();
+        assert_eq!(parsed.id(), tx.id());
+
+        let cbor_hex = format_transaction(tx, OutputFormat::CborHex);
+        assert_eq!(cbor_hex, cbor_hex.to_lowercase());
+        let parsed = SignedTransaction::from_bytes(&hex::decode(&cbor_hex).unwrap()).unwrap();
+        assert_eq!(parsed.id(), tx.id());
+
+        for value in OutputFormat::VALUES {
+            assert!(value.parse::<OutputFormat>().is_ok());
+        }
+    }
+
+    #[test]
+    fn csv_field_quoting() {
+        assert_eq!(csv_field("Barak Obama"), "Barak Obama");
+        assert_eq!(csv_field("Obama, Barak"), "\"Obama, Barak\"");
+        assert_eq!(
+            csv_field("Barak \"Barry\" Obama"),
+            "\"Barak \"\"Barry\"\" Obama\""
+        );
+
+        for value in TallyFormat::VALUES {
+            assert!(value.parse::<TallyFormat>().is_ok());
+        }
+    }
+}
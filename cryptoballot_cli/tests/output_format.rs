@@ -0,0 +1,156 @@
+use cryptoballot::SignedTransaction;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::process::Command;
+
+const TEST_ELECTION: &str = "../test_elections/test_election_2/test_election_2.json";
+
+// Serve the test election's transactions over the REST endpoints the CLI reads from
+fn serve_test_election() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let uri = format!("http://{}", listener.local_addr().unwrap());
+
+    let file_bytes = std::fs::read(TEST_ELECTION).unwrap();
+    let txs: Vec<SignedTransaction> = serde_json::from_slice(&file_bytes).unwrap();
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut request_line = String::new();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            reader.read_line(&mut request_line).unwrap();
+            loop {
+                let mut header = String::new();
+                reader.read_line(&mut header).unwrap();
+                if header.trim().is_empty() {
+                    break;
+                }
+            }
+
+            let path = request_line.split_whitespace().nth(1).unwrap_or("");
+            let body = if path.contains("/transactions?") {
+                serde_json::to_string(&txs).unwrap()
+            } else {
+                let id = path.rsplit("id=").next().unwrap();
+                let tx = txs.iter().find(|tx| tx.id().to_string() == id).unwrap();
+                serde_json::to_string(tx).unwrap()
+            };
+
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+                 Connection: close\r\n\r\n{}",
+                body.len(),
+                body
+            )
+            .unwrap();
+        }
+    });
+
+    uri
+}
+
+fn cryptoballot(uri: &str, args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_cryptoballot"))
+        .env("CRYPTOBALLOT_URI", uri)
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "cryptoballot {:?} failed: {}",
+        args,
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    String::from_utf8(output.stdout).unwrap()
+}
+
+fn election() -> SignedTransaction {
+    let file_bytes = std::fs::read(TEST_ELECTION).unwrap();
+    let txs: Vec<SignedTransaction> = serde_json::from_slice(&file_bytes).unwrap();
+    txs[0].clone()
+}
+
+#[test]
+fn get_format_json() {
+    let uri = serve_test_election();
+    let election = election();
+    let id = election.id().to_string();
+
+    let stdout = cryptoballot(&uri, &["get", &id, "--format", "json"]);
+    assert_eq!(stdout.lines().count(), 1);
+    let parsed: SignedTransaction = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(parsed.id(), election.id());
+
+    // JSON is the default
+    assert_eq!(cryptoballot(&uri, &["get", &id]), stdout);
+}
+
+#[test]
+fn get_format_pretty_json() {
+    let uri = serve_test_election();
+    let election = election();
+    let id = election.id().to_string();
+
+    let stdout = cryptoballot(&uri, &["get", &id, "--format", "pretty-json"]);
+    assert!(stdout.lines().count() > 1);
+    let parsed: SignedTransaction = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(parsed.id(), election.id());
+
+    assert_eq!(cryptoballot(&uri, &["get", &id, "--pretty"]), stdout);
+}
+
+#[test]
+fn get_format_cbor_hex() {
+    let uri = serve_test_election();
+    let election = election();
+    let id = election.id().to_string();
+
+    let stdout = cryptoballot(&uri, &["get", &id, "--format", "cbor-hex"]);
+    let cbor_hex = stdout.trim_end();
+    assert_eq!(cbor_hex, cbor_hex.to_lowercase());
+    let parsed = SignedTransaction::from_bytes(&hex::decode(cbor_hex).unwrap()).unwrap();
+    assert_eq!(parsed.id(), election.id());
+}
+
+#[test]
+fn get_format_unknown() {
+    let uri = serve_test_election();
+    let id = election().id().to_string();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_cryptoballot"))
+        .env("CRYPTOBALLOT_URI", &uri)
+        .args(&["get", &id, "--format", "yaml"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+}
+
+#[test]
+fn e2e_print_votes_format() {
+    let uri = serve_test_election();
+    let id = election().id().to_string();
+
+    for format in &["json", "pretty-json", "cbor-hex"] {
+        let stdout = cryptoballot(&uri, &["e2e", &id, "--print-votes", "--format", format]);
+        let votes = stdout.splitn(2, "Votes:\n").nth(1).unwrap();
+
+        let decryptions: Vec<SignedTransaction> = match *format {
+            "cbor-hex" => votes
+                .lines()
+                .map(|line| SignedTransaction::from_bytes(&hex::decode(line).unwrap()).unwrap())
+                .collect(),
+            _ => serde_json::Deserializer::from_str(votes)
+                .into_iter()
+                .map(|tx| tx.unwrap())
+                .collect(),
+        };
+
+        assert_eq!(decryptions.len(), 1);
+        assert_eq!(
+            decryptions[0].transaction_type(),
+            cryptoballot::TransactionType::Decryption
+        );
+    }
+}
@@ -95,6 +95,11 @@ impl<T: Access> cryptoballot::Store for TransactionSchema<T> {
         }
     }
 
+    // Avoid decoding the transaction just to check that it exists
+    fn contains(&self, id: Identifier) -> bool {
+        self.transactions.contains(&id.to_string())
+    }
+
     fn range(&self, start: Identifier, end_inclusive: Identifier) -> Vec<SignedTransaction> {
         let mut results = Vec::new();
 
@@ -30,6 +30,14 @@ pub fn generate_transactions<S: Store>(
             process_partial_decryption(store, incoming_tx.clone().into())
         }
 
+        TransactionType::PartialDecryptionBatch => {
+            process_partial_decryption_batch(store, incoming_tx.clone().into())
+        }
+
+        TransactionType::TrusteeRevocation => {
+            process_trustee_revocation(store, incoming_tx.clone().into())
+        }
+
         _ => Ok(vec![]),
     }
 }
@@ -198,9 +206,9 @@ fn process_keygen_public_key<S: Store>(
     if election_tx.authority_public == public_key {
         // Get all public key transactions
         let pk_txs: Vec<KeyGenPublicKeyTransaction> = store
-            .get_multiple(election_tx.id, TransactionType::KeyGenPublicKey)
+            .get_current_keygen_public_keys(election_tx.id)
             .into_iter()
-            .map(|tx| tx.into())
+            .map(|tx| tx.tx)
             .collect();
 
         if election_tx.trustees.len() == pk_txs.len() {
@@ -239,15 +247,13 @@ fn process_voting_end<S: Store>(
                 //       In this situation, we go to the "back of the line" to wait our turn again
 
                 // Get the EncryptionKey Transaction
-                let encryption_key_tx = EncryptionKeyTransaction::build_id(election_tx.id);
-                let encryption_key_tx: EncryptionKeyTransaction =
-                    store.get_transaction(encryption_key_tx).unwrap().into();
+                let encryption_key_tx = store.get_encryption_key(election_tx.id).unwrap();
 
-                // Get all vote transactions
+                // Get all vote transactions, other than challenged votes
                 let vote_txs: Vec<VoteTransaction> = store
-                    .get_multiple(election_tx.id, TransactionType::Vote)
+                    .get_cast_votes(election_tx.id)
                     .into_iter()
-                    .map(|tx| tx.into())
+                    .map(|tx| tx.tx)
                     .collect();
 
                 // Produce contest -> votes map
@@ -344,9 +350,7 @@ fn process_mix<S: Store>(
 
             if trustee.index == mix_tx.mix_index + 2 {
                 // Get the EncryptionKey Transaction
-                let encryption_key_tx = EncryptionKeyTransaction::build_id(election_tx.id);
-                let encryption_key_tx: EncryptionKeyTransaction =
-                    store.get_transaction(encryption_key_tx).unwrap().into();
+                let encryption_key_tx = store.get_encryption_key(election_tx.id).unwrap();
 
                 let vote_ids = mix_tx.vote_ids;
                 let ciphertexts = mix_tx.mixed_ciphertexts;
@@ -384,6 +388,18 @@ fn process_mix<S: Store>(
     Ok(vec![])
 }
 
+// On PartialDecryptionBatch transaction, check each of the batched partials as if it had been posted on its own
+fn process_partial_decryption_batch<S: Store>(
+    store: &S,
+    batch_tx: PartialDecryptionBatchTransaction,
+) -> Result<Vec<SignedTransaction>, Error> {
+    let mut decryptions = Vec::new();
+    for partial_tx in batch_tx.partial_decryptions() {
+        decryptions.extend(process_partial_decryption(store, partial_tx)?);
+    }
+    Ok(decryptions)
+}
+
 // On PartialDecrytion transaction, check if we have enough partials for a full decryption transaction
 // TODO: This needs to be batched, likely goes in a different function, also snouldn't happen on the consensus thread
 fn process_partial_decryption<S: Store>(
@@ -397,39 +413,32 @@ fn process_partial_decryption<S: Store>(
     let election_tx = store.get_election(partial_tx.election_id)?.tx;
 
     if let Some(_trustee) = trustee_from_election(&election_tx, &public_key) {
-        // Get partials
-        // TODO: Use Identifier::start and Identifier::end with a mask
-        let mut start = election_tx.id().clone();
-        start.transaction_type = TransactionType::PartialDecryption;
-        let mut unique_info = partial_tx.id.unique_info;
-        unique_info[15] = 0;
-        start.unique_info = unique_info;
-
-        let mut end = start.clone();
-        end.unique_info[15] = 255;
-
+        // Get partials, whether they were posted on their own or in a batch
         // TODO: Need some way of partitioning the work between trustee nodes,
         //       while at the same time allowing them to pick up eachother's slack
         //       Alternatively, just do it all with no coordination and let consensus sort it out
-        let partial_txs = store.range(start, end);
+        let partial_txs: Vec<PartialDecryptionTransaction> = election_tx
+            .trustees
+            .iter()
+            .filter_map(|trustee| {
+                let partial_id = PartialDecryptionTransaction::build_id(
+                    election_tx.id,
+                    partial_tx.upstream_id,
+                    partial_tx.contest_index,
+                    partial_tx.upstream_index,
+                    trustee.index,
+                );
+                store.find_partial_decryption(partial_id)
+            })
+            .collect();
 
         if partial_txs.len() >= election_tx.trustees_threshold as usize {
-            let partial_txs: Vec<PartialDecryptionTransaction> =
-                partial_txs.into_iter().map(|tx| tx.into()).collect();
-
             // Get upstream encrypted selections
             let ciphertexts = match partial_tx.upstream_id.transaction_type {
                 TransactionType::Vote => {
                     let vote = store.get_vote(partial_tx.upstream_id)?.tx;
-                    let mut ciphertexts = None;
-                    for encrypted_vote in vote.encrypted_votes {
-                        if encrypted_vote.contest_index == partial_tx.contest_index {
-                            ciphertexts = Some(encrypted_vote.selections);
-                            break;
-                        }
-                    }
-                    match ciphertexts {
-                        Some(ct) => ct,
+                    match vote.get_contest(partial_tx.contest_index) {
+                        Some(encrypted_vote) => encrypted_vote.selections.clone(),
                         None => return Err(Error::CannotFindContet(partial_tx.contest_index)),
                     }
                 }
@@ -441,10 +450,8 @@ fn process_partial_decryption<S: Store>(
                 _ => return Err(Error::UnexpectedTransactionType),
             };
 
-            // Get public key transactions
-            let pubkeys = store.get_multiple(election_tx.id, TransactionType::KeyGenPublicKey);
-            let pubkeys: Vec<KeyGenPublicKeyTransaction> =
-                pubkeys.into_iter().map(|tx| tx.into()).collect();
+            // Get public key transactions, refreshed by any re-shares
+            let pubkeys = store.get_decryption_public_keys(election_tx.id)?;
 
             // Fully decrypt the vote
             let decrypted = decrypt_vote(
@@ -453,19 +460,29 @@ fn process_partial_decryption<S: Store>(
                 &election_tx.get_full_trustees(),
                 &pubkeys,
                 &partial_txs,
-            )?;
+            );
 
             let trustee_indexs = partial_txs.iter().map(|tx| tx.trustee_index).collect();
 
-            // Create a vote decryption transaction
-            let decrypted_tx = DecryptionTransaction::new(
-                election_tx.id,
-                partial_tx.upstream_id,
-                partial_tx.contest_index,
-                partial_tx.upstream_index,
-                trustee_indexs,
-                decrypted,
-            );
+            // Create a vote decryption transaction, recording a vote that can't be decoded as spoiled
+            let decrypted_tx = match decrypted {
+                Ok(decrypted) => DecryptionTransaction::new(
+                    election_tx.id,
+                    partial_tx.upstream_id,
+                    partial_tx.contest_index,
+                    partial_tx.upstream_index,
+                    trustee_indexs,
+                    decrypted,
+                ),
+                Err(ValidationError::VoteDecodingError(_)) => DecryptionTransaction::new_spoiled(
+                    election_tx.id,
+                    partial_tx.upstream_id,
+                    partial_tx.contest_index,
+                    partial_tx.upstream_index,
+                    trustee_indexs,
+                ),
+                Err(e) => return Err(e.into()),
+            };
 
             let decrypted_tx = Signed::sign(&secret_key, decrypted_tx)?.into();
             return Ok(vec![decrypted_tx]);
@@ -475,6 +492,54 @@ fn process_partial_decryption<S: Store>(
     Ok(vec![])
 }
 
+// On trustee_revocation transaction, re-share the revoked trustee's key share (if we are a remaining trustee)
+fn process_trustee_revocation<S: Store>(
+    store: &S,
+    revocation_tx: TrusteeRevocationTransaction,
+) -> Result<Vec<SignedTransaction>, Error> {
+    let public_key = crate::public_key();
+    let secret_key = crate::secret_key();
+
+    // Get the election_tx
+    let election_tx = store.get_election(revocation_tx.election)?.tx;
+
+    if let Some(trustee) = trustee_from_election(&election_tx, &public_key) {
+        let is_revoked = |trustee_index: u8| {
+            trustee_index == revocation_tx.trustee_index
+                || store.is_trustee_revoked(election_tx.id, trustee_index)
+        };
+        if is_revoked(trustee.index) {
+            return Ok(vec![]);
+        }
+
+        // Send a share to every trustee that hasn't been revoked, including ourselves
+        let x25519_public_keys: Vec<(u8, x25519::PublicKey)> = store
+            .get_multiple(election_tx.id, TransactionType::KeyGenCommitment)
+            .into_iter()
+            .map(|tx| -> KeyGenCommitmentTransaction { tx.into() })
+            .filter(|tx| !is_revoked(tx.trustee_index))
+            .map(|tx| (tx.trustee_index, tx.x25519_public_key))
+            .collect();
+
+        let mut rng: StdRng = SeedableRng::from_entropy();
+        let (commitment, shares) =
+            trustee.generate_reshares(&mut rng, &secret_key, &x25519_public_keys, election_tx.id);
+
+        let reshare_tx = TrusteeReshareTransaction::new(
+            election_tx.id,
+            trustee.index,
+            trustee.public_key,
+            revocation_tx.trustee_index,
+            commitment,
+            shares,
+        );
+        let reshare_tx = Signed::sign(&secret_key, reshare_tx)?;
+        return Ok(vec![reshare_tx.into()]);
+    }
+
+    Ok(vec![])
+}
+
 // TODO: Switch to batching
 fn produce_partials<S: Store>(
     store: &S,
@@ -486,6 +551,11 @@ fn produce_partials<S: Store>(
     let public_key = crate::public_key();
     let secret_key = crate::secret_key();
 
+    // A revoked trustee's partial decryptions would be rejected
+    if store.is_trustee_revoked(election_tx.id, trustee.index) {
+        return Ok(vec![]);
+    }
+
     // If there's no mix config, produce partial decryptions for every vote
     let mut rng = rand::thread_rng();
 
@@ -522,6 +592,22 @@ fn produce_partials<S: Store>(
         })
         .collect();
 
+    // Refresh them with the re-shares of any revoked trustees' shares
+    let reshares: Vec<TrusteeReshareTransaction> = store
+        .get_trustee_reshares(election_tx.id)
+        .into_iter()
+        .map(|tx| tx.tx)
+        .collect();
+    let (commitments, shares) = trustee.apply_reshares(
+        &mut rng,
+        &secret_key,
+        &x25519_public_keys,
+        &commitments,
+        &shares,
+        &reshares,
+        election_tx.id,
+    )?;
+
     // Produce partial decryptions
     let mut parial_txs = Vec::new();
 
@@ -557,10 +643,10 @@ fn produce_partials<S: Store>(
             }
         }
         None => {
-            let vote_txs = store.get_multiple(election_tx.id, TransactionType::Vote);
+            let vote_txs = store.get_cast_votes(election_tx.id);
 
             for vote_tx in vote_txs {
-                let vote_tx: VoteTransaction = vote_tx.into();
+                let vote_tx: VoteTransaction = vote_tx.tx;
 
                 for encrypted_vote in vote_tx.encrypted_votes {
                     let mut decrypt_shares = Vec::with_capacity(encrypted_vote.selections.len());
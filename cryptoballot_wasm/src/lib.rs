@@ -0,0 +1,285 @@
+//! WebAssembly bindings for encrypting, authenticating and casting CryptoBallot votes from a browser.
+//!
+//! Every function takes and returns JSON strings, using the same serialization as the rest of CryptoBallot, so
+//! transactions can be passed straight between the REST API and these bindings. Errors are thrown as strings.
+//!
+//! Casting a vote from JavaScript:
+//!  1. `generate_keypair()` to create the vote's anonymous key. Keep the secret key private - anyone who knows it can
+//!     link the vote to the voter.
+//!  2. `encrypt_vote()` to encrypt every contest on the ballot, with proofs of correct encryption and of the
+//!     validity of closed plurality contests, using the election and its encryption_key transaction.
+//!  3. `blind_auth_packages()`, sending each blinded package to its authenticator, then `authenticate_voter()` to
+//!     unblind and check the signatures they return.
+//!  4. `create_vote()` to build and sign the vote transaction, ready to be posted.
+//!
+//! For a weighted vote, pass the weight the authenticators certify to `encrypt_vote`, `blind_auth_packages`,
+//! `authenticate_voter` and `create_vote`. Pass `undefined` for an unweighted vote.
+//!
+//! `validate_transaction()` checks a transaction against the election's transactions before it is posted.
+//!
+//! Build with `wasm-pack build --target web` for browsers, or `--target nodejs` for Node.js.
+
+use cryptoballot::*;
+use ed25519_dalek::{PublicKey, SecretKey};
+use rand::Rng;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use uuid::Uuid;
+use wasm_bindgen::prelude::*;
+
+/// A new ed25519 keypair, hex encoded
+#[derive(Serialize, Deserialize)]
+pub struct Keypair {
+    pub secret_key: String,
+    pub public_key: String,
+}
+
+/// Every contest on a ballot, encrypted by `encrypt_vote`.
+///
+/// `encrypted_votes`, `encryption_proof` and `vote_validity_proof` go in the vote transaction. `seed` is the hex
+/// encoded seed the vote was encrypted with: keep it secret, unless challenging the vote to check it was encrypted
+/// honestly.
+#[derive(Serialize, Deserialize)]
+pub struct EncryptedBallot {
+    pub encrypted_votes: Vec<EncryptedVote>,
+    pub encryption_proof: EncryptionProof,
+    pub vote_validity_proof: VoteValidityProof,
+    pub seed: String,
+}
+
+/// An auth package blinded for a single authenticator.
+///
+/// `blinded` is sent to the authenticator. `unblinder` must be kept by the voter, and is needed to unblind the
+/// authenticator's signature.
+#[derive(Serialize, Deserialize)]
+pub struct BlindedAuthPackage {
+    pub authenticator: Uuid,
+    pub blinded: String,
+    pub unblinder: String,
+}
+
+/// Generate a new anonymous keypair for a vote, returned as a JSON `Keypair`
+#[wasm_bindgen]
+pub fn generate_keypair() -> String {
+    let (secret_key, public_key) = cryptoballot::generate_keypair();
+    to_json(&Keypair {
+        secret_key: hex::encode(secret_key.to_bytes()),
+        public_key: hex::encode(public_key.to_bytes()),
+    })
+}
+
+/// Encrypt the selections for every contest on the ballot, proving each ciphertext is a correct encryption.
+///
+/// `election_tx` and `encryption_key_tx` are the election's signed election and encryption_key transactions,
+/// `anonymous_key` is the hex encoded anonymous public key the vote will be signed with, and `votes` is a JSON array of
+/// `PlaintextVote`, one per contest. Every selection is given `weight`. Each closed plurality contest is also proven to
+/// select distinct candidates it declares (see `VoteValidityProof`). Returns a JSON `EncryptedBallot`.
+#[wasm_bindgen]
+pub fn encrypt_vote(
+    election_tx: &str,
+    encryption_key_tx: &str,
+    anonymous_key: &str,
+    votes: &str,
+    weight: Option<u32>,
+) -> Result<String, JsValue> {
+    let election = election_from_json(election_tx)?;
+    let encryption_key_tx = match from_json("encryption_key transaction", encryption_key_tx)? {
+        SignedTransaction::EncryptionKey(tx) => tx.tx,
+        tx => return Err(unexpected_type(TransactionType::EncryptionKey, tx)),
+    };
+    let anonymous_key = public_key_from_hex(anonymous_key)?;
+    let mut votes: Vec<PlaintextVote> = from_json("votes", votes)?;
+    for selection in votes.iter_mut().flat_map(|vote| vote.selections.iter_mut()) {
+        selection.weight = weight;
+    }
+
+    let mut rng = rand::rngs::OsRng {};
+    let seed: [u8; 32] = rng.gen();
+    let (encrypted_votes, encryption_proof, vote_validity_proof) =
+        encrypt_vote_with_validity_proof(
+            &election,
+            &encryption_key_tx.encryption_key,
+            &anonymous_key,
+            &votes,
+            seed,
+            &mut rng,
+        )
+        .map_err(js_error)?;
+
+    Ok(to_json(&EncryptedBallot {
+        encrypted_votes,
+        encryption_proof,
+        vote_validity_proof,
+        seed: hex::encode(seed),
+    }))
+}
+
+/// Blind the voter's auth package, certifying `weight`, for every authenticator in the election.
+///
+/// Returns a JSON array of `BlindedAuthPackage`, one per authenticator.
+#[wasm_bindgen]
+pub fn blind_auth_packages(
+    election_tx: &str,
+    ballot_id: &str,
+    anonymous_key: &str,
+    weight: Option<u32>,
+) -> Result<String, JsValue> {
+    let election = election_from_json(election_tx)?;
+    let anonymous_key = public_key_from_hex(anonymous_key)?;
+    let package =
+        AuthPackage::new(election.id, ballot_id.to_string(), anonymous_key).with_weight(weight);
+
+    let mut blinded_packages = Vec::with_capacity(election.authenticators.len());
+    for authenticator in election.authenticators.iter() {
+        let authn_public = authenticator_key(authenticator, ballot_id)?;
+        let (blinded, unblinder) = package.blind(authn_public.as_ref());
+        blinded_packages.push(BlindedAuthPackage {
+            authenticator: authenticator.id,
+            blinded: hex::encode(blinded),
+            unblinder: hex::encode(unblinder),
+        });
+    }
+
+    Ok(to_json(&blinded_packages))
+}
+
+/// Unblind the signatures returned by the authenticators, checking that each authenticates the voter's anonymous key.
+///
+/// `blinded_packages` is the result of `blind_auth_packages`, and `authentications` is a JSON array of the
+/// `Authentication` returned by each authenticator. `weight` must be the weight the packages were blinded with.
+/// Returns a JSON array of unblinded `Authentication`, ready to be added to the vote.
+#[wasm_bindgen]
+pub fn authenticate_voter(
+    election_tx: &str,
+    ballot_id: &str,
+    anonymous_key: &str,
+    blinded_packages: &str,
+    authentications: &str,
+    weight: Option<u32>,
+) -> Result<String, JsValue> {
+    let election = election_from_json(election_tx)?;
+    let anonymous_key = public_key_from_hex(anonymous_key)?;
+    let blinded_packages: Vec<BlindedAuthPackage> =
+        from_json("blinded auth packages", blinded_packages)?;
+    let authentications: Vec<Authentication> = from_json("authentications", authentications)?;
+    let package =
+        AuthPackage::new(election.id, ballot_id.to_string(), anonymous_key).with_weight(weight);
+
+    let mut unblinded = Vec::with_capacity(authentications.len());
+    for authentication in authentications {
+        let authenticator = election
+            .get_authenticator(authentication.authenticator)
+            .ok_or_else(|| js_error(ValidationError::AuthDoesNotExist))?;
+        let blinded_package = blinded_packages
+            .iter()
+            .find(|blinded| blinded.authenticator == authenticator.id)
+            .ok_or_else(|| {
+                JsValue::from_str(&format!(
+                    "cryptoballot: no blinded auth package for authenticator {}",
+                    authenticator.id
+                ))
+            })?;
+        let unblinder = hex::decode(&blinded_package.unblinder).map_err(js_error)?;
+        let authn_public = authenticator_key(authenticator, ballot_id)?;
+
+        let authentication = authentication.unblind(authn_public.as_ref(), unblinder);
+        authenticator
+            .verify_package(&package, &authentication.signature)
+            .map_err(js_error)?;
+        unblinded.push(authentication);
+    }
+
+    Ok(to_json(&unblinded))
+}
+
+/// Create and sign a vote transaction.
+///
+/// `encrypted_ballot` is the JSON `EncryptedBallot` returned by `encrypt_vote`, `authentications` is a JSON array of
+/// unblinded `Authentication` (see `authenticate_voter`), `secret_key` is the hex encoded anonymous secret key, and
+/// `weight` is the weight the vote was encrypted and authenticated with. Returns the JSON signed vote transaction.
+#[wasm_bindgen]
+pub fn create_vote(
+    election_id: &str,
+    ballot_id: &str,
+    encrypted_ballot: &str,
+    authentications: &str,
+    secret_key: &str,
+    weight: Option<u32>,
+) -> Result<String, JsValue> {
+    let election_id: Identifier = election_id.parse().map_err(js_error)?;
+    let encrypted_ballot: EncryptedBallot = from_json("encrypted ballot", encrypted_ballot)?;
+    let authentication: Vec<Authentication> = from_json("authentications", authentications)?;
+    let secret_key = hex::decode(secret_key).map_err(js_error)?;
+    let secret_key = SecretKey::from_bytes(&secret_key).map_err(js_error)?;
+    let anonymous_key: PublicKey = (&secret_key).into();
+
+    let vote = VoteTransaction {
+        id: VoteTransaction::build_id(election_id, &anonymous_key),
+        election: election_id,
+        ballot_id: ballot_id.to_string(),
+        encrypted_votes: encrypted_ballot.encrypted_votes,
+        anonymous_key,
+        authentication,
+        weight,
+        encryption_proof: encrypted_ballot.encryption_proof,
+        vote_validity_proof: Some(encrypted_ballot.vote_validity_proof),
+        created_at: None,
+    };
+    let vote: SignedTransaction = Signed::sign(&secret_key, vote).map_err(js_error)?.into();
+
+    Ok(to_json(&vote))
+}
+
+/// Validate a signed transaction against a JSON array of the election's transactions
+#[wasm_bindgen]
+pub fn validate_transaction(transactions: &str, tx: &str) -> Result<(), JsValue> {
+    let transactions: Vec<SignedTransaction> = from_json("transactions", transactions)?;
+    let tx: SignedTransaction = from_json("transaction", tx)?;
+
+    let store = MemStore::from(transactions);
+    tx.validate(&store).map_err(js_error)
+}
+
+fn election_from_json(election_tx: &str) -> Result<ElectionTransaction, JsValue> {
+    match from_json("election transaction", election_tx)? {
+        SignedTransaction::Election(tx) => Ok(tx.tx),
+        tx => Err(unexpected_type(TransactionType::Election, tx)),
+    }
+}
+
+fn authenticator_key<'a>(
+    authenticator: &'a Authenticator,
+    ballot_id: &str,
+) -> Result<&'a AuthPublicKey, JsValue> {
+    authenticator
+        .public_keys
+        .get(ballot_id)
+        .ok_or_else(|| js_error(CastVoteError::MissingAuthenticatorKey(authenticator.id)))
+}
+
+fn public_key_from_hex(public_key: &str) -> Result<PublicKey, JsValue> {
+    let bytes = hex::decode(public_key).map_err(js_error)?;
+    PublicKey::from_bytes(&bytes).map_err(js_error)
+}
+
+fn from_json<T: DeserializeOwned>(name: &str, json: &str) -> Result<T, JsValue> {
+    serde_json::from_str(json)
+        .map_err(|e| JsValue::from_str(&format!("cryptoballot: invalid {}: {}", name, e)))
+}
+
+fn to_json<T: Serialize>(value: &T) -> String {
+    serde_json::to_string(value).expect("cryptoballot: unexpected error serializing to JSON")
+}
+
+fn unexpected_type(expected: TransactionType, tx: SignedTransaction) -> JsValue {
+    JsValue::from_str(&format!(
+        "cryptoballot: expected {} transaction, got {}",
+        expected.name(),
+        tx.transaction_type().name()
+    ))
+}
+
+fn js_error<E: Display>(e: E) -> JsValue {
+    JsValue::from_str(&e.to_string())
+}
@@ -0,0 +1,98 @@
+// Create and validate a vote entirely from JavaScript, using the Node.js build of the WebAssembly bindings.
+//
+// Run with `npm test` from the cryptoballot_wasm directory, which builds the bindings with wasm-pack first.
+
+const assert = require("assert");
+const fs = require("fs");
+const path = require("path");
+const cryptoballot = require("../../pkg/cryptoballot_wasm.js");
+
+const electionFile = path.join(
+  __dirname,
+  "../../../test_elections/test_election_1/test_election_1.json"
+);
+const transactions = JSON.parse(fs.readFileSync(electionFile, "utf8"));
+
+// The election as it stood while voting was open
+const firstVote = transactions.findIndex((tx) => tx.type === "vote");
+const openElection = transactions.slice(0, firstVote);
+const electionTx = openElection.find((tx) => tx.type === "election");
+const encryptionKeyTx = openElection.find((tx) => tx.type === "encryption_key");
+const ballotId = electionTx.tx.ballots[0].id;
+
+const keypair = JSON.parse(cryptoballot.generate_keypair());
+assert.strictEqual(keypair.secret_key.length, 64);
+assert.strictEqual(keypair.public_key.length, 64);
+
+const votes = [
+  { contest_index: 0, selections: [{ write_in: true, score: 0, selection: "Barak Obama" }] },
+];
+const encryptedBallot = JSON.parse(
+  cryptoballot.encrypt_vote(
+    JSON.stringify(electionTx),
+    JSON.stringify(encryptionKeyTx),
+    keypair.public_key,
+    JSON.stringify(votes),
+    undefined
+  )
+);
+assert.strictEqual(encryptedBallot.encrypted_votes.length, 1);
+assert.strictEqual(encryptedBallot.encrypted_votes[0].contest_index, 0);
+assert.strictEqual(encryptedBallot.encrypted_votes[0].selections.length, 1);
+
+// Each ciphertext comes with a proof that it was encrypted correctly
+assert.strictEqual(encryptedBallot.encryption_proof.length, 1);
+assert.strictEqual(encryptedBallot.encryption_proof[0].length, 1);
+assert.strictEqual(encryptedBallot.seed.length, 64);
+
+// The contest takes write-ins, so there is no fixed set of valid selections to prove
+assert.deepStrictEqual(encryptedBallot.vote_validity_proof, []);
+
+// One blinded package for each of the election's authenticators
+const blindedPackages = JSON.parse(
+  cryptoballot.blind_auth_packages(
+    JSON.stringify(electionTx),
+    ballotId,
+    keypair.public_key,
+    undefined
+  )
+);
+assert.strictEqual(blindedPackages.length, electionTx.tx.authenticators.length);
+assert.strictEqual(blindedPackages[0].authenticator, electionTx.tx.authenticators[0].id);
+
+// The authenticator secrets for the test election aren't available, so the vote is cast without authentications
+const voteTx = cryptoballot.create_vote(
+  electionTx.tx.id,
+  ballotId,
+  JSON.stringify(encryptedBallot),
+  "[]",
+  keypair.secret_key,
+  undefined
+);
+const vote = JSON.parse(voteTx);
+assert.strictEqual(vote.type, "vote");
+assert.strictEqual(vote.tx.anonymous_key, keypair.public_key);
+assert.deepStrictEqual(vote.tx.encryption_proof, encryptedBallot.encryption_proof);
+
+cryptoballot.validate_transaction(JSON.stringify(openElection), voteTx);
+
+// Once voting has ended the same vote is refused
+assert.throws(
+  () => cryptoballot.validate_transaction(JSON.stringify(transactions), voteTx),
+  /voting has ended/i
+);
+
+// Wrong transaction types are reported rather than panicking
+assert.throws(
+  () =>
+    cryptoballot.encrypt_vote(
+      JSON.stringify(electionTx),
+      JSON.stringify(electionTx),
+      keypair.public_key,
+      JSON.stringify(votes),
+      undefined
+    ),
+  /expected encryption_key transaction/
+);
+
+console.log("cryptoballot_wasm: all tests passed");